@@ -0,0 +1,107 @@
+//! Trains a small MLP on MNIST until it reaches a target accuracy on the test split.
+//!
+//! The decompressed IDX files, such as `train-images-idx3-ubyte`, must be in a local
+//! directory, given as the first argument and `data/mnist` by default. The target accuracy
+//! is the second argument, 0.95 by default.
+//!
+//! cargo run --release --example mnist_mlp -- data/mnist 0.95
+use std::env;
+use std::process;
+use tensor_compute::autograd::no_grad;
+use tensor_compute::data::{load_mnist, DataLoader, Dataset, TensorDataset};
+use tensor_compute::loss::{cross_entropy, Reduction};
+use tensor_compute::nn::{Linear, Module};
+use tensor_compute::optim::{Optimizer, Sgd};
+use tensor_compute::{GpuStore, RawTensor, Tensor};
+
+const MAX_EPOCHS: usize = 10;
+
+struct Mlp {
+    hidden: Linear,
+    output: Linear,
+}
+
+impl Mlp {
+    fn new() -> Self {
+        Self {
+            hidden: Linear::new(28 * 28, 128),
+            output: Linear::new(128, 10),
+        }
+    }
+
+    fn forward(&self, images: &Tensor) -> Tensor {
+        self.output.forward(&self.hidden.forward(images).tanh())
+    }
+
+    fn parameters(&self) -> Vec<Tensor> {
+        let mut parameters = self.hidden.parameters();
+        parameters.extend(self.output.parameters());
+        parameters
+    }
+}
+
+/// Flattens a batch of images of shape `[batch, 28, 28]` and reads back its labels
+fn unpack(batch: Vec<RawTensor>) -> (Tensor, Vec<usize>) {
+    let mut images = batch[0].clone();
+    let batch_size = images.shape()[0];
+    images.reshape(vec![batch_size, 28 * 28]);
+    let labels = batch[1].to_vec().into_iter().map(|label| label as usize).collect();
+    (Tensor::from_raw_tensor(images), labels)
+}
+
+fn accuracy(model: &Mlp, test_set: TensorDataset) -> f32 {
+    let nb_samples = test_set.len();
+    let mut correct = 0;
+    for batch in &mut DataLoader::new(test_set, 1000) {
+        let (images, labels) = unpack(batch);
+        let logits = no_grad(|| model.forward(&images)).to_vec();
+        for (scores, label) in logits.chunks(10).zip(labels) {
+            let predicted = (0..10).fold(0, |best, class| if scores[class] > scores[best] { class } else { best });
+            correct += (predicted == label) as usize;
+        }
+    }
+    correct as f32 / nb_samples as f32
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let dir = args.get(1).map(String::as_str).unwrap_or("data/mnist");
+    let target_accuracy: f32 = args.get(2).map_or(0.95, |target| target.parse().expect("Invalid target accuracy"));
+    let load = |train| {
+        load_mnist(dir, train).unwrap_or_else(|error| {
+            eprintln!("Could not load MNIST from {}: {}", dir, error);
+            process::exit(1);
+        })
+    };
+    println!("Running in {:?}", GpuStore::get_default().info());
+
+    let mut train_loader = DataLoader::new(load(true), 64);
+    train_loader.shuffle = true;
+    let model = Mlp::new();
+    let mut optimizer = Sgd::new(model.parameters(), 0.1);
+    optimizer.momentum = 0.9;
+    for epoch in 1..=MAX_EPOCHS {
+        let mut total_loss = 0.;
+        for batch in &mut train_loader {
+            let (images, labels) = unpack(batch);
+            optimizer.zero_grad();
+            let loss = cross_entropy(&model.forward(&images), &labels, Reduction::Mean);
+            loss.backward();
+            optimizer.step();
+            total_loss += loss.to_f32();
+        }
+        let accuracy = accuracy(&model, load(false));
+        println!(
+            "Epoch {}: mean loss {:.4}, test accuracy {:.2}%",
+            epoch,
+            total_loss / train_loader.len() as f32,
+            accuracy * 100.
+        );
+        if accuracy >= target_accuracy {
+            println!("Reached the target accuracy of {:.2}%", target_accuracy * 100.);
+            return;
+        }
+    }
+    eprintln!("Did not reach the target accuracy of {:.2}% in {} epochs", target_accuracy * 100., MAX_EPOCHS);
+    process::exit(1);
+}
@@ -1,38 +1,267 @@
 
 use super::RawTensor;
 use std::sync::{RwLock, Arc, RwLockReadGuard, RwLockWriteGuard};
-use crate::autograd::ops::{set_matmul_grad, set_exp_grad, set_sum_grad};
+use std::collections::{HashMap, HashSet};
+use std::cell::Cell;
+use crate::autograd::ops::{matmul_grad, exp_grad, sum_grad, add_grad, dot_mul_grad, transpose_grad, expand_grad, reshape_grad, broadcast_to_grad, sum_dim_grad, mul_scalar_grad, pointwise_loss_grad, class_loss_grad, im2col_grad, col2im_grad, max_pool2d_grad, avg_pool2d_grad, adaptive_avg_pool2d_grad, normalize_grad, affine_grad, dropout_grad, index_select_grad, gather_grad, scatter_grad, embedding_grad, sigmoid_grad, tanh_grad, cat_grad, narrow_grad, masked_softmax_grad};
+use crate::tensors::broadcast_shapes;
+use crate::{NormGroups, PointwiseLoss, Window2d};
 
 mod ops;
+mod gradcheck;
+mod functional;
+mod dot;
+mod conv;
+mod normalization;
+mod indexing;
+pub use gradcheck::{numerical_grad, gradcheck};
+pub use functional::{grad, vjp, jvp, jacobian, hessian};
 type Shared<T> = Arc<RwLock<T>>;
+type TensorId = *const RwLock<VariableData>;
+type HookFn = dyn Fn(&RawTensor) -> Option<RawTensor> + Send + Sync;
+
+thread_local! {
+    /// Whether Tensor Ops created in this thread record their parent Op
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Restores the previous value of [`GRAD_ENABLED`] when dropped, even if a panic happens
+struct GradModeGuard {
+    previous: bool,
+}
+
+impl Drop for GradModeGuard {
+    fn drop(&mut self) {
+        let previous = self.previous;
+        GRAD_ENABLED.with(|enabled| enabled.set(previous));
+    }
+}
+
+fn with_grad_enabled<T, F: FnOnce() -> T>(grad_enabled: bool, f: F) -> T {
+    let _guard = GradModeGuard {
+        previous: GRAD_ENABLED.with(|enabled| enabled.replace(grad_enabled)),
+    };
+    f()
+}
+
+fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(|enabled| enabled.get())
+}
+
+/// Runs `f` without recording the Ops it does, so the Tensors it creates are leaves and can
+/// not be back propagated through.
+pub fn no_grad<T, F: FnOnce() -> T>(f: F) -> T {
+    with_grad_enabled(false, f)
+}
 
 #[derive(Debug)]
 enum Op{
     MatMul(Tensor, Tensor),
     Exp(Tensor),
+    Sigmoid(Tensor),
+    Tanh(Tensor),
     Sum(Tensor),
+    Add(Tensor, Tensor),
+    DotMul(Tensor, Tensor),
+    Transpose(Tensor),
+    Expand(Tensor),
+    Reshape(Tensor),
+    BroadcastTo(Tensor),
+    SumDim(Tensor),
+    MulScalar(Tensor, f32),
+    /// The input, the target, which is not differentiated, and the loss kind
+    PointwiseLoss(Tensor, RawTensor, PointwiseLoss),
+    /// The scores, the target classes and whether the log softmax is applied to the scores
+    ClassLoss(Tensor, RawTensor, bool),
+    Im2Col(Tensor, Window2d),
+    Col2Im(Tensor, Window2d),
+    /// The input, the index of the maximum of each window and the window
+    MaxPool2d(Tensor, RawTensor, Window2d),
+    /// The input, the window and whether the padding is counted in the averages
+    AvgPool2d(Tensor, Window2d, bool),
+    AdaptiveAvgPool2d(Tensor),
+    /// The input, the mean and variance of each group, the groups, the epsilon and whether
+    /// the statistics were computed from the input
+    Normalize(Tensor, RawTensor, RawTensor, NormGroups, f32, bool),
+    /// The input, the optional weight and bias and their groups
+    Affine(Tensor, Option<Tensor>, Option<Tensor>, NormGroups),
+    /// The input and the scaled mask it was multiplied by
+    Dropout(Tensor, RawTensor),
+    /// The input, the indices and the dimension they select from
+    IndexSelect(Tensor, RawTensor, usize),
+    /// The input, the index and the dimension
+    Gather(Tensor, RawTensor, usize),
+    /// The input, the index, the source, the dimension and whether the source is added
+    Scatter(Tensor, RawTensor, Tensor, usize, bool),
+    /// The indices and the weight
+    Embedding(RawTensor, Tensor),
+    /// The concatenated Tensors and the dimension
+    Cat(Vec<Tensor>, usize),
+    /// The input, the dimension and the first entry taken along it
+    Narrow(Tensor, usize, usize),
+    /// The scores, the output and the scale of the scores
+    MaskedSoftmax(Tensor, RawTensor, f32),
 }
 
 impl Op{
-    pub fn propagate_grad(&self, child_grad: &RawTensor){
+    /// The kind of this Op, used when describing the graph
+    fn name(&self) -> &'static str{
+        match self{
+            Op::MatMul(..) => "MatMul",
+            Op::Exp(_) => "Exp",
+            Op::Sigmoid(_) => "Sigmoid",
+            Op::Tanh(_) => "Tanh",
+            Op::Sum(_) => "Sum",
+            Op::Add(..) => "Add",
+            Op::DotMul(..) => "DotMul",
+            Op::Transpose(_) => "Transpose",
+            Op::Expand(_) => "Expand",
+            Op::Reshape(_) => "Reshape",
+            Op::BroadcastTo(_) => "BroadcastTo",
+            Op::SumDim(_) => "SumDim",
+            Op::MulScalar(..) => "MulScalar",
+            Op::PointwiseLoss(..) => "PointwiseLoss",
+            Op::ClassLoss(..) => "ClassLoss",
+            Op::Im2Col(..) => "Im2Col",
+            Op::Col2Im(..) => "Col2Im",
+            Op::MaxPool2d(..) => "MaxPool2d",
+            Op::AvgPool2d(..) => "AvgPool2d",
+            Op::AdaptiveAvgPool2d(_) => "AdaptiveAvgPool2d",
+            Op::Normalize(..) => "Normalize",
+            Op::Affine(..) => "Affine",
+            Op::Dropout(..) => "Dropout",
+            Op::IndexSelect(..) => "IndexSelect",
+            Op::Gather(..) => "Gather",
+            Op::Scatter(..) => "Scatter",
+            Op::Embedding(..) => "Embedding",
+            Op::Cat(..) => "Cat",
+            Op::Narrow(..) => "Narrow",
+            Op::MaskedSoftmax(..) => "MaskedSoftmax",
+        }
+    }
+
+    /// The Tensors this Op was computed from
+    fn inputs(&self) -> Vec<&Tensor>{
+        match self{
+            Op::MatMul(left, right) => vec![left, right],
+            Op::Exp(input) => vec![input],
+            Op::Sigmoid(input) => vec![input],
+            Op::Tanh(input) => vec![input],
+            Op::Sum(input) => vec![input],
+            Op::Add(left, right) => vec![left, right],
+            Op::DotMul(left, right) => vec![left, right],
+            Op::Transpose(input) => vec![input],
+            Op::Expand(input) => vec![input],
+            Op::Reshape(input) => vec![input],
+            Op::BroadcastTo(input) => vec![input],
+            Op::SumDim(input) => vec![input],
+            Op::MulScalar(input, _) => vec![input],
+            Op::PointwiseLoss(input, ..) => vec![input],
+            Op::ClassLoss(input, ..) => vec![input],
+            Op::Im2Col(input, _) => vec![input],
+            Op::Col2Im(input, ..) => vec![input],
+            Op::MaxPool2d(input, ..) => vec![input],
+            Op::AvgPool2d(input, ..) => vec![input],
+            Op::AdaptiveAvgPool2d(input) => vec![input],
+            Op::Normalize(input, ..) => vec![input],
+            Op::Dropout(input, _) => vec![input],
+            Op::IndexSelect(input, ..) => vec![input],
+            Op::Gather(input, ..) => vec![input],
+            Op::Scatter(input, _, source, ..) => vec![input, source],
+            Op::Embedding(_, weight) => vec![weight],
+            Op::Cat(inputs, _) => inputs.iter().collect(),
+            Op::Narrow(input, ..) => vec![input],
+            Op::MaskedSoftmax(input, ..) => vec![input],
+            Op::Affine(input, weight, bias, _) => {
+                let mut inputs = vec![input];
+                inputs.extend(weight.iter());
+                inputs.extend(bias.iter());
+                inputs
+            }
+        }
+    }
+
+    /// Computes the gradient of each one of the [`Op::inputs`], given the gradient of this Op
+    /// output. The gradients are computed using Tensor Ops, so they are differentiable
+    /// themselves if gradient recording is enabled.
+    fn backward(&self, child_grad: &Tensor) -> Vec<Tensor>{
         match self{
             Op::MatMul(left, right) => {
-                set_matmul_grad(left, right, child_grad);
-                left.backward();
-                right.backward();
+                let (left_grad, right_grad) = matmul_grad(left, right, child_grad);
+                vec![left_grad, right_grad]
+            }
+            Op::Exp(input) => vec![exp_grad(input, child_grad)],
+            Op::Sigmoid(input) => vec![sigmoid_grad(input, child_grad)],
+            Op::Tanh(input) => vec![tanh_grad(input, child_grad)],
+            Op::Sum(input) => vec![sum_grad(input, child_grad)],
+            Op::Add(left, right) => {
+                let (left_grad, right_grad) = add_grad(left, right, child_grad);
+                vec![left_grad, right_grad]
+            }
+            Op::DotMul(left, right) => {
+                let (left_grad, right_grad) = dot_mul_grad(left, right, child_grad);
+                vec![left_grad, right_grad]
+            }
+            Op::Transpose(input) => vec![transpose_grad(input, child_grad)],
+            Op::Expand(input) => vec![expand_grad(input, child_grad)],
+            Op::Reshape(input) => vec![reshape_grad(input, child_grad)],
+            Op::BroadcastTo(input) => vec![broadcast_to_grad(input, child_grad)],
+            Op::SumDim(input) => vec![sum_dim_grad(input, child_grad)],
+            Op::MulScalar(input, scalar) => vec![mul_scalar_grad(input, *scalar, child_grad)],
+            Op::PointwiseLoss(input, target, loss) => vec![pointwise_loss_grad(input, target, *loss, child_grad)],
+            Op::ClassLoss(input, targets, log_softmax) => {
+                vec![class_loss_grad(input, targets, *log_softmax, child_grad)]
             }
-            Op::Exp(input) => {
-                set_exp_grad(input, child_grad);
-                input.backward();
+            Op::Im2Col(input, window) => vec![im2col_grad(input, window, child_grad)],
+            Op::Col2Im(_, window) => vec![col2im_grad(window, child_grad)],
+            Op::MaxPool2d(input, indices, window) => vec![max_pool2d_grad(input, indices, window, child_grad)],
+            Op::AvgPool2d(input, window, count_include_pad) => {
+                vec![avg_pool2d_grad(input, window, *count_include_pad, child_grad)]
             }
-            Op::Sum(input) => {
-                set_sum_grad(input, child_grad);
-                input.backward();
+            Op::AdaptiveAvgPool2d(input) => vec![adaptive_avg_pool2d_grad(input, child_grad)],
+            Op::Normalize(input, mean, var, groups, eps, batch_stats) => {
+                vec![normalize_grad(input, mean, var, groups, *eps, *batch_stats, child_grad)]
             }
+            Op::Affine(input, weight, bias, groups) => {
+                affine_grad(input, weight.as_ref(), bias.as_ref(), groups, child_grad)
+            }
+            Op::Dropout(_, mask) => vec![dropout_grad(mask, child_grad)],
+            Op::IndexSelect(input, indices, dim) => vec![index_select_grad(input, indices, *dim, child_grad)],
+            Op::Gather(input, index, dim) => vec![gather_grad(input, index, *dim, child_grad)],
+            Op::Scatter(_, index, source, dim, add) => {
+                let (input_grad, source_grad) = scatter_grad(index, source, *dim, *add, child_grad);
+                vec![input_grad, source_grad]
+            }
+            Op::Embedding(indices, weight) => vec![embedding_grad(indices, weight, child_grad)],
+            Op::Cat(inputs, dim) => cat_grad(inputs, *dim, child_grad),
+            Op::Narrow(input, dim, start) => vec![narrow_grad(input, *dim, *start, child_grad)],
+            Op::MaskedSoftmax(_, output, scale) => vec![masked_softmax_grad(output, *scale, child_grad)],
         }
     }
 }
 
+/// Options controlling a [`Tensor::backward_with`] call
+#[derive(Debug, Clone, Default)]
+pub struct BackwardOptions {
+    /// If false (the default), the graph used to compute the gradients is released after
+    /// the backward pass, so the intermediate Tensors it references can be freed.
+    pub retain_graph: bool,
+    /// If true, the gradient computation is itself recorded, so the resulting gradients can
+    /// be back propagated through, for example to compute second derivatives.
+    /// Implies `retain_graph`.
+    pub create_graph: bool,
+}
+
+/// A function called with the gradient of a Tensor during back propagation, see
+/// [`Tensor::register_hook`]
+struct GradHook(Box<HookFn>);
+
+impl std::fmt::Debug for GradHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("GradHook")
+    }
+}
+
 #[derive(Debug)]
 pub struct Tensor {
     inner: Shared<VariableData>
@@ -42,7 +271,12 @@ pub struct Tensor {
 pub struct VariableData{
     parent_op: Option<Op>,
     tensor: RawTensor,
-    grad: Option<RawTensor>
+    grad: Option<Tensor>,
+    /// Set when a backward pass freed the `parent_op` of this (non leaf) Tensor
+    graph_released: bool,
+    /// If true, this Tensor keeps its gradient after backward even if it is not a leaf
+    retains_grad: bool,
+    hooks: Vec<GradHook>,
 }
 
 impl Tensor {
@@ -58,6 +292,21 @@ impl Tensor {
         self.inner.read().expect("Error acquiring read lock").tensor.to_vec()
     }
 
+    /// Assumes the tensor only has one element, panics otherwise
+    pub fn to_f32(&self) -> f32{
+        self.read_lock().tensor.to_f32()
+    }
+
+    /// Returns the shape of the Tensor
+    pub fn shape(&self) -> Vec<usize>{
+        Vec::from(self.read_lock().tensor.shape().clone())
+    }
+
+    /// Return the number of elements in this tensor
+    pub fn numel(&self) -> usize{
+        self.read_lock().tensor.numel()
+    }
+
     /// Creates a read lock on it, panicking if that was not successful
     pub fn read_lock(&self) -> RwLockReadGuard<VariableData>{
         self.inner.read().expect("Error acquiring read lock")
@@ -68,83 +317,436 @@ impl Tensor {
         self.inner.write().expect("Error acquiring write lock")
     }
 
-    // pub fn shape(&self) -> &[usize]{
-    //
-    // }
+    /// Returns a copy of the data of this Tensor, without its graph
+    pub fn raw_tensor(&self) -> RawTensor{
+        self.read_lock().tensor.clone()
+    }
+
+    /// Replaces the data of this Tensor, keeping its gradient and graph, for example to load
+    /// saved parameters. The data must have the shape of this Tensor.
+    pub fn set_data(&self, data: RawTensor){
+        let mut inner = self.write_lock();
+        assert_eq!(inner.tensor.shape(), data.shape(), "The new data must have the shape of the Tensor");
+        inner.tensor = data;
+    }
+
+    /// Returns a copy of the gradient accumulated in this Tensor, if any
+    pub fn grad(&self) -> Option<RawTensor>{
+        self.read_lock().grad.as_ref().map(|grad| grad.read_lock().tensor.clone())
+    }
+
+    /// Returns the gradient accumulated in this Tensor, if any, without copying it.
+    /// If it was computed by a [`Tensor::backward_with`] call with `create_graph` set,
+    /// it can itself be back propagated through.
+    pub fn grad_tensor(&self) -> Option<Tensor>{
+        self.read_lock().grad.as_ref().map(Tensor::shallow_clone)
+    }
+
+    /// Clears the gradient accumulated in this Tensor. Since gradients are accumulated by
+    /// [`Tensor::backward`], this should be called between optimization steps.
+    pub fn zero_grad(&self){
+        self.write_lock().grad = None;
+    }
+
+    /// Calls `f` with the data of this Tensor, which can be modified in place, and its
+    /// gradient. Returns false, without calling `f`, if there is no gradient.
+    pub(crate) fn update_with_grad<F: FnOnce(&mut RawTensor, &RawTensor)>(&self, f: F) -> bool{
+        let grad = match self.grad_tensor() {
+            Some(grad) => grad,
+            None => return false,
+        };
+        let grad = grad.read_lock();
+        f(&mut self.write_lock().tensor, &grad.tensor);
+        true
+    }
+
+    /// Returns true if this Tensor was not created by an Op, for example by
+    /// [`Tensor::from_data_and_shape`]
+    pub fn is_leaf(&self) -> bool{
+        let inner = self.read_lock();
+        inner.parent_op.is_none() && !inner.graph_released
+    }
+
+    /// Makes this Tensor keep the gradient computed by [`Tensor::backward`] even if it is not
+    /// a leaf, which is useful for debugging. By default only leaves keep their gradient.
+    pub fn retain_grad(&self){
+        self.write_lock().retains_grad = true;
+    }
+
+    /// Registers a function to be called with the gradient of this Tensor during back
+    /// propagation, once all the gradients flowing into it were accumulated. If it returns a
+    /// new gradient, that one is used instead, both for this Tensor and the ones it was computed
+    /// from. Hooks run in the order they were registered.
+    ///
+    /// A replaced gradient is not differentiable, even with `create_graph`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::Tensor;
+    /// let x = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    /// x.register_hook(|grad| Some(grad.mul_scalar(0.5)));
+    /// x.sum().backward();
+    /// assert_eq!(x.grad().unwrap().to_vec(), &[0.5, 0.5, 0.5, 0.5]);
+    /// ```
+    pub fn register_hook<F>(&self, hook: F)
+    where
+        F: Fn(&RawTensor) -> Option<RawTensor> + Send + Sync + 'static,
+    {
+        self.write_lock().hooks.push(GradHook(Box::new(hook)));
+    }
+
+    /// Passes `grad` through the hooks registered in this Tensor
+    fn run_hooks(&self, grad: Tensor) -> Tensor{
+        let read_guard = self.read_lock();
+        let mut grad = grad;
+        for hook in &read_guard.hooks{
+            let new_grad = hook.0(&grad.read_lock().tensor);
+            if let Some(new_grad) = new_grad{
+                grad = Tensor::from_raw_tensor(new_grad);
+            }
+        }
+        grad
+    }
 
     /// Manually sets the gradient of this Tensor.
     /// This function deep clones the input tensor
     pub fn set_grad(&mut self, grad: Tensor){
         let tensor = grad.write_lock().tensor.clone();
-        self.write_lock().grad = Some(tensor);
+        self.write_lock().grad = Some(Tensor::from_raw_tensor(tensor));
     }
 
     /// Creates a new Tensor from the provided data and shape
     pub fn from_data_and_shape(data: Vec<f32>, shape: Vec<usize>) -> Self{
+        Self::from_raw_tensor(RawTensor::from_data_and_shape(data, shape))
+    }
+
+    /// Returns a new leaf Tensor with a copy of this Tensor data. Gradients do not flow from
+    /// the returned Tensor back into `self`.
+    pub fn detach(&self) -> Self{
+        Self::from_raw_tensor(self.read_lock().tensor.clone())
+    }
+
+    /// Creates a new leaf Tensor backed by the given [`RawTensor`]
+    pub fn from_raw_tensor(tensor: RawTensor) -> Self{
         Self{
             inner: Arc::new(RwLock::new(VariableData{
                 parent_op: None,
-                tensor: RawTensor::from_data_and_shape(data, shape),
-                grad: None
+                tensor,
+                grad: None,
+                graph_released: false,
+                retains_grad: false,
+                hooks: vec![],
+            }))
+        }
+    }
+
+    /// Wraps the result of an Op, recording the Op unless inside [`no_grad`]
+    fn from_op(tensor: RawTensor, op: Op) -> Self{
+        Self{
+            inner: Arc::new(RwLock::new(VariableData{
+                parent_op: if is_grad_enabled() { Some(op) } else { None },
+                tensor,
+                grad: None,
+                graph_released: false,
+                retains_grad: false,
+                hooks: vec![],
             }))
         }
     }
 
-    /// Simple Batch Matrix Multiplication. Requires both inputs to have rank 3 and same batch size
+    /// Batch Matrix Multiplication. Requires both inputs to have rank 3 or 4, their batch
+    /// dimensions being broadcast, see [`RawTensor::matmul`]
     pub fn matmul(&self, other_var: &Tensor) -> Self{
         let inner = self.read_lock();
         let self_tensor = &inner.tensor;
         let other = other_var.read_lock();
         let other_tensor = &other.tensor;
         let dot_mul_res = self_tensor.matmul(&other_tensor);
-        Tensor {
-            inner: Arc::new(RwLock::new(VariableData{
-                parent_op: Some(Op::MatMul(self.shallow_clone(), other_var.shallow_clone())),
-                tensor: dot_mul_res,
-                grad: None
-            }))
-        }
+        Tensor::from_op(dot_mul_res, Op::MatMul(self.shallow_clone(), other_var.shallow_clone()))
     }
 
     pub fn exp(&self) -> Self{
         let inner = self.read_lock();
         let res = inner.tensor.exp();
-        Tensor {
-            inner: Arc::new(RwLock::new(VariableData{
-                parent_op: Some(Op::Exp(self.shallow_clone())),
-                tensor: res,
-                grad: None
-            }))
-        }
+        Tensor::from_op(res, Op::Exp(self.shallow_clone()))
+    }
+
+    /// Applies the logistic function to each element, see [`RawTensor::sigmoid`]
+    pub fn sigmoid(&self) -> Self{
+        let res = self.read_lock().tensor.sigmoid();
+        Tensor::from_op(res, Op::Sigmoid(self.shallow_clone()))
+    }
+
+    /// Applies the hyperbolic tangent to each element, see [`RawTensor::tanh`]
+    pub fn tanh(&self) -> Self{
+        let res = self.read_lock().tensor.tanh();
+        Tensor::from_op(res, Op::Tanh(self.shallow_clone()))
+    }
+
+    /// Softmax over the last dimension, see [`RawTensor::softmax`]
+    pub fn softmax(&self) -> Self{
+        self.masked_softmax(None, false, 1.)
+    }
+
+    /// Softmax of scaled and masked scores, see [`RawTensor::masked_softmax`]. The mask is a
+    /// constant, so it is not back propagated through.
+    pub fn masked_softmax(&self, mask: Option<&RawTensor>, causal: bool, scale: f32) -> Self{
+        let res = self.read_lock().tensor.masked_softmax(mask, causal, scale);
+        Tensor::from_op(res.clone(), Op::MaskedSoftmax(self.shallow_clone(), res, scale))
     }
 
     pub fn sum(&self) -> Self{
         let inner = self.read_lock();
         let res = inner.tensor.sum();
-        Tensor {
-            inner: Arc::new(RwLock::new(VariableData{
-                parent_op: Some(Op::Sum(self.shallow_clone())),
-                tensor: res,
-                grad: None
-            }))
+        Tensor::from_op(res, Op::Sum(self.shallow_clone()))
+    }
+
+    /// Element wise addition. If the shapes differ, both Tensors are first broadcasted into a
+    /// common shape, see [`Tensor::broadcast_to`].
+    pub fn add(&self, other_var: &Tensor) -> Self{
+        let (self_shape, other_shape) = (self.shape(), other_var.shape());
+        if self_shape != other_shape {
+            let shape: Vec<usize> = broadcast_shapes(&self_shape, &other_shape)
+                .unwrap_or_else(|e| panic!("Can't add Tensors: {}", e))
+                .into();
+            return self.broadcast_to(shape.clone()).add(&other_var.broadcast_to(shape));
         }
+        let res = self.read_lock().tensor.add(&other_var.read_lock().tensor);
+        Tensor::from_op(res, Op::Add(self.shallow_clone(), other_var.shallow_clone()))
+    }
+
+    /// Element wise multiplication, both Tensors must have the same shape
+    pub fn dot_mul(&self, other_var: &Tensor) -> Self{
+        let res = self.read_lock().tensor.dot_mul(&other_var.read_lock().tensor);
+        Tensor::from_op(res, Op::DotMul(self.shallow_clone(), other_var.shallow_clone()))
+    }
+
+    /// Swaps the last two dimensions, see [`RawTensor::transpose`]
+    pub fn transpose(&self) -> Self{
+        let res = self.read_lock().tensor.transpose();
+        Tensor::from_op(res, Op::Transpose(self.shallow_clone()))
+    }
+
+    /// Creates a Tensor of the given shape with all elements equal to this one.
+    /// Only Tensors with a single element can be expanded for now.
+    pub fn expand(&self, shape: Vec<usize>) -> Self{
+        let value = self.to_f32();
+        let mut res = RawTensor::zeros(shape);
+        res.fill_with(value);
+        Tensor::from_op(res, Op::Expand(self.shallow_clone()))
     }
 
-    /// Back propagates the gradients from itself into parent Tensors
+    /// Returns a Tensor with the same data and the given shape, which must have the same number
+    /// of elements
+    pub fn reshape(&self, shape: Vec<usize>) -> Self{
+        let mut res = self.read_lock().tensor.clone();
+        res.reshape(shape);
+        Tensor::from_op(res, Op::Reshape(self.shallow_clone()))
+    }
+
+    /// Broadcasts this Tensor into the given shape, see [`RawTensor::broadcast_to`].
+    /// Returns a shallow clone if the shape is already the requested one.
+    pub fn broadcast_to(&self, shape: Vec<usize>) -> Self{
+        if self.shape() == shape {
+            return self.shallow_clone();
+        }
+        let res = self.read_lock().tensor.broadcast_to(shape);
+        Tensor::from_op(res, Op::BroadcastTo(self.shallow_clone()))
+    }
+
+    /// Sums the elements along the dimension `dim`, which is kept with size 1
+    pub fn sum_dim(&self, dim: usize) -> Self{
+        let res = self.read_lock().tensor.sum_dim(dim);
+        Tensor::from_op(res, Op::SumDim(self.shallow_clone()))
+    }
+
+    /// Multiplies every element by `scalar`
+    pub fn mul_scalar(&self, scalar: f32) -> Self{
+        let res = self.read_lock().tensor.mul_scalar(scalar);
+        Tensor::from_op(res, Op::MulScalar(self.shallow_clone(), scalar))
+    }
+
+    /// Zeroes each element with probability `p` and scales the others by `1 / (1 - p)` if
+    /// `training` is set, see [`RawTensor::dropout`]. The same mask is applied to the gradient.
+    pub fn dropout(&self, p: f32, training: bool) -> Self{
+        if !training || p == 0. {
+            return self.shallow_clone();
+        }
+        let mask = RawTensor::dropout_mask(self.shape(), p);
+        let res = self.read_lock().tensor.dot_mul(&mask);
+        Tensor::from_op(res, Op::Dropout(self.shallow_clone(), mask))
+    }
+
+    /// Returns the loss of each element given the target, see [`crate::loss`].
+    /// The target is not differentiated.
+    pub(crate) fn pointwise_loss(&self, target: &Tensor, loss: PointwiseLoss) -> Self{
+        let target = target.read_lock().tensor.clone();
+        let res = self.read_lock().tensor.pointwise_loss(&target, loss);
+        Tensor::from_op(res, Op::PointwiseLoss(self.shallow_clone(), target, loss))
+    }
+
+    /// Returns the loss of each sample given its target class, see [`crate::loss`]
+    pub(crate) fn class_loss(&self, targets: RawTensor, log_softmax: bool) -> Self{
+        let res = self.read_lock().tensor.class_loss(&targets, log_softmax);
+        Tensor::from_op(res, Op::ClassLoss(self.shallow_clone(), targets, log_softmax))
+    }
+
+    /// Back propagates the gradients from itself into parent Tensors.
+    ///
+    /// The graph is released afterwards, see [`Tensor::backward_with`] in order to keep it.
     pub fn backward(&self){
-        let default_grad = RawTensor::from_data_and_shape(vec![1.], vec![1]);
-        let read_guard = self.read_lock();
-        let self_grad = if self.read_lock().tensor.numel() == 1 && self.read_lock().grad.is_none(){
-            &default_grad
-        }else{
-            read_guard.grad.as_ref().expect("Can't call backwards without grad")
+        self.backward_with(BackwardOptions::default());
+    }
+
+    /// Back propagates the gradients from itself into parent Tensors.
+    ///
+    /// The gradients are accumulated into the leaves, and into the Tensors which called
+    /// [`Tensor::retain_grad`]. Every Tensor of the graph is visited only once, after all the
+    /// gradients flowing into it were accumulated. Unless `options.retain_graph` is set, the
+    /// Ops of the graph are dropped along the way, which frees the intermediate Tensors they
+    /// kept alive. Going backwards through a released graph panics.
+    pub fn backward_with(&self, options: BackwardOptions){
+        let self_grad = match self.grad_tensor(){
+            Some(grad) => grad,
+            None if self.numel() == 1 => Tensor::from_data_and_shape(vec![1.], vec![1]),
+            None => panic!("Can't call backwards without grad"),
+        };
+        let release_graph = !options.retain_graph && !options.create_graph;
+        let grads = propagate(self, self_grad, &HashSet::new(), options.create_graph, release_graph);
+        for (node, grad) in grads{
+            let keeps_grad = node.is_leaf() || node.read_lock().retains_grad;
+            if keeps_grad && !node.inner_ptr_eq(self){
+                with_grad_enabled(options.create_graph, || node.accumulate_grad(grad));
+            }
+        }
+    }
+
+    /// Adds `grad` to the gradient of this Tensor
+    fn accumulate_grad(&self, grad: Tensor){
+        let existing = self.grad_tensor();
+        let new_grad = match existing{
+            Some(existing) => existing.add(&grad),
+            None => grad,
         };
-        if let Some(parent_op) = &read_guard.parent_op{
-            parent_op.propagate_grad(&self_grad)
+        self.write_lock().grad = Some(new_grad);
+    }
+
+    /// Drops the Op which created this Tensor, and with it the references to its inputs
+    fn release_graph(&self){
+        let mut write_guard = self.write_lock();
+        if write_guard.parent_op.take().is_some(){
+            write_guard.graph_released = true;
+        }
+    }
+
+    fn inner_ptr_eq(&self, other: &Tensor) -> bool{
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Identifies this Tensor, shallow clones have the same id
+    fn id(&self) -> TensorId{
+        Arc::as_ptr(&self.inner)
+    }
+
+    /// Returns the Tensors of the graph which ends in `self`, ordered so that every Tensor comes
+    /// before the ones it was computed from. The graph is not explored past the Tensors
+    /// in `stop_at`.
+    fn topological_order(&self, stop_at: &HashSet<TensorId>) -> Vec<Tensor>{
+        // Post-order depth first search with an explicit stack, so long graphs such as
+        // unrolled recurrent networks can't overflow the call stack. Each entry holds a
+        // Tensor and whether its inputs were already pushed.
+        let mut visited = HashSet::new();
+        let mut order = vec![];
+        let mut stack = vec![(self.shallow_clone(), false)];
+        while let Some((tensor, expanded)) = stack.pop(){
+            if expanded{
+                order.push(tensor);
+                continue;
+            }
+            if !visited.insert(tensor.id()){
+                continue;
+            }
+            if stop_at.contains(&tensor.id()){
+                order.push(tensor);
+                continue;
+            }
+            let inputs: Vec<Tensor> = {
+                let inner = tensor.read_lock();
+                assert!(
+                    !inner.graph_released,
+                    "Trying to backward through the graph a second time, but it was already released. \
+                    Use `retain_graph: true` in the first backward call."
+                );
+                inner.parent_op.iter().flat_map(|op| op.inputs()).map(|input| input.shallow_clone()).collect()
+            };
+            stack.push((tensor, true));
+            // Reversed, so the inputs are visited in order
+            for input in inputs.into_iter().rev(){
+                if !visited.contains(&input.id()){
+                    stack.push((input, false));
+                }
+            }
         }
+        order.reverse();
+        order
     }
 }
 
+/// Back propagates `output_grad` from `output`, returning every Tensor of the graph together
+/// with the gradient that reached it, in topological order. Nothing is written into the
+/// Tensors `grad`, and the graph is not explored past the Tensors in `stop_at`.
+fn propagate(
+    output: &Tensor,
+    output_grad: Tensor,
+    stop_at: &HashSet<TensorId>,
+    create_graph: bool,
+    release_graph: bool,
+) -> Vec<(Tensor, Tensor)>{
+    let mut pending_grads: HashMap<TensorId, Tensor> = HashMap::new();
+    pending_grads.insert(output.id(), output_grad);
+    let mut grads = vec![];
+    for node in output.topological_order(stop_at){
+        let node_grad = pending_grads
+            .remove(&node.id())
+            .expect("Visited a Tensor before all its children");
+        let node_grad = node.run_hooks(node_grad);
+        if !stop_at.contains(&node.id()){
+            let read_guard = node.read_lock();
+            if let Some(parent_op) = &read_guard.parent_op{
+                with_grad_enabled(create_graph, || {
+                    let input_grads = parent_op.backward(&node_grad);
+                    for (input, input_grad) in parent_op.inputs().into_iter().zip(input_grads){
+                        let accumulated = match pending_grads.remove(&input.id()){
+                            Some(existing) => existing.add(&input_grad),
+                            None => input_grad,
+                        };
+                        pending_grads.insert(input.id(), accumulated);
+                    }
+                });
+            }
+            drop(read_guard);
+            if release_graph{
+                node.release_graph();
+            }
+        }
+        grads.push((node, node_grad));
+    }
+    grads
+}
+
+/// Computes the gradient of `output` with respect to each one of `inputs`, back propagating
+/// `output_grad` without releasing the graph or touching the Tensors `grad`.
+/// Inputs which `output` does not depend on get `None`.
+fn grads_of(output: &Tensor, output_grad: Tensor, inputs: &[Tensor], create_graph: bool) -> Vec<Option<Tensor>>{
+    let input_ids: HashSet<TensorId> = inputs.iter().map(Tensor::id).collect();
+    let mut grads: HashMap<TensorId, Tensor> = propagate(output, output_grad, &input_ids, create_graph, false)
+        .into_iter()
+        .map(|(node, grad)| (node.id(), grad))
+        .collect();
+    inputs.iter().map(|input| grads.remove(&input.id())).collect()
+}
+
 #[test]
 fn matmul_grad_works(){
     let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
@@ -163,7 +765,10 @@ fn exp_grad_works(){
     let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
     let sum = left.exp().sum();
     sum.backward();
-    assert_eq!(left.read_lock().grad.as_ref().unwrap().to_vec(), &[1., 2., 3., 4.]);
+    let grad = left.read_lock().grad.as_ref().unwrap().to_vec();
+    for (grad, input) in grad.iter().zip(&[1f32, 2., 3., 4.]){
+        assert!((grad - input.exp()).abs() < 0.01, "{} != {}", grad, input.exp());
+    }
 }
 
 // #[test]
@@ -190,4 +795,160 @@ fn sum_grad_works(){
     c.set_grad(c_grad);
     c.backward();
     assert_eq!(left.read_lock().grad.as_ref().unwrap().to_vec(), &[2., 2., 2., 2.]);
-}
\ No newline at end of file
+}
+
+#[test]
+fn zero_grad_clears_grad(){
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    left.sum().backward();
+    assert_eq!(left.grad().unwrap().to_vec(), &[1., 1., 1., 1.]);
+    left.zero_grad();
+    assert!(left.grad().is_none());
+    left.sum().backward();
+    assert_eq!(left.grad().unwrap().to_vec(), &[1., 1., 1., 1.]);
+}
+
+#[test]
+fn grad_accumulates_once_per_path(){
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let right = Tensor::from_data_and_shape(vec![1., 1., 1., 1.], vec![1, 2, 2]);
+    let squared = left.matmul(&right);
+    let res = squared.matmul(&right).sum();
+    res.backward();
+    assert_eq!(right.grad().unwrap().to_vec(), &[18., 18., 22., 22.]);
+}
+
+#[test]
+fn backward_releases_graph(){
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let exp = left.exp();
+    let sum = exp.sum();
+    sum.backward();
+    assert!(exp.read_lock().parent_op.is_none());
+    assert!(!exp.is_leaf());
+    assert!(left.is_leaf());
+}
+
+#[test]
+#[should_panic(expected = "backward through the graph a second time")]
+fn backward_twice_on_released_graph_panics(){
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let sum = left.exp().sum();
+    sum.backward();
+    sum.backward();
+}
+
+#[test]
+fn backward_twice_with_retained_graph(){
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let sum = left.sum();
+    sum.backward_with(BackwardOptions{ retain_graph: true, ..Default::default() });
+    sum.backward();
+    assert_eq!(left.grad().unwrap().to_vec(), &[2., 2., 2., 2.]);
+}
+
+#[test]
+fn first_order_grads_match_numerical(){
+    let input = RawTensor::from_data_and_shape(vec![0.1, -0.2, 0.3, 0.4], vec![1, 2, 2]);
+    let other = Tensor::from_data_and_shape(vec![1., -1., 2., 0.5], vec![1, 2, 2]);
+    assert!(gradcheck(|x| x.exp().sum(), &input, 1e-2, 1e-2));
+    assert!(gradcheck(|x| x.matmul(&other).dot_mul(x).sum(), &input, 1e-2, 1e-2));
+    assert!(gradcheck(|x| x.transpose().add(x).exp().sum(), &input, 1e-2, 1e-2));
+}
+
+#[test]
+fn hessian_vector_product_matches_numerical(){
+    // f(x) = sum(exp(x) * x), so the gradient is exp(x) * (1 + x)
+    let data = vec![0.1, -0.2, 0.3, 0.4];
+    let vector = Tensor::from_data_and_shape(vec![1., -1., 0.5, 2.], vec![1, 2, 2]);
+    let input = Tensor::from_data_and_shape(data.clone(), vec![1, 2, 2]);
+    input.exp().dot_mul(&input).sum().backward_with(BackwardOptions{ create_graph: true, ..Default::default() });
+    let first_grad = input.grad_tensor().unwrap();
+    input.zero_grad();
+    first_grad.dot_mul(&vector).sum().backward();
+    let hvp = input.grad().unwrap().to_vec();
+
+    let grad_dot_vector = |x: &Tensor| {
+        x.exp().dot_mul(x).sum().backward();
+        x.grad_tensor().unwrap().dot_mul(&vector).sum()
+    };
+    let raw_input = RawTensor::from_data_and_shape(data, vec![1, 2, 2]);
+    let expected = numerical_grad(grad_dot_vector, &raw_input, 1e-2).to_vec();
+    for (actual, expected) in hvp.iter().zip(expected.iter()){
+        assert!((actual - expected).abs() < 1e-2, "{} != {}", actual, expected);
+    }
+}
+
+#[test]
+fn no_grad_does_not_record_ops(){
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let sum = no_grad(|| left.exp().sum());
+    assert!(sum.is_leaf());
+    assert!(!left.exp().sum().is_leaf());
+}
+
+#[test]
+fn only_leaves_and_retained_keep_grad(){
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let exp = left.exp();
+    let retained = exp.transpose();
+    retained.retain_grad();
+    retained.sum().backward();
+    assert!(exp.grad().is_none());
+    assert_eq!(retained.grad().unwrap().to_vec(), &[1., 1., 1., 1.]);
+    assert!(left.grad().is_some());
+}
+
+#[test]
+fn hooks_observe_and_replace_grads(){
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let calls = Arc::new(AtomicUsize::new(0));
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let transposed = left.transpose();
+    let hook_calls = calls.clone();
+    transposed.register_hook(move |grad| {
+        hook_calls.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(grad.to_vec(), &[1., 1., 1., 1.]);
+        None
+    });
+    transposed.register_hook(|grad| Some(grad.mul_scalar(3.)));
+    transposed.sum().backward();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(left.grad().unwrap().to_vec(), &[3., 3., 3., 3.]);
+}
+
+#[test]
+fn broadcast_add_grad_sums_over_broadcasted_dims(){
+    let input = Tensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+    let bias = Tensor::from_data_and_shape(vec![1., 2., 3.], vec![3]);
+    let out = input.add(&bias);
+    assert_eq!(out.to_vec(), &[2., 4., 6., 5., 7., 9.]);
+    out.dot_mul(&out).sum().backward();
+    assert_eq!(input.grad().unwrap().to_vec(), &[4., 8., 12., 10., 14., 18.]);
+    assert_eq!(bias.grad().unwrap().to_vec(), &[14., 22., 30.]);
+}
+
+#[test]
+fn dropout_reuses_mask_in_backward(){
+    let input = Tensor::from_data_and_shape(vec![1.; 1000], vec![10, 100]);
+    let output = input.dropout(0.5, true);
+    output.sum().backward();
+    let kept = output.to_vec();
+    // Kept elements are scaled by 2 and have gradient 2, dropped ones are zero in both
+    assert_eq!(input.grad().unwrap().to_vec(), kept);
+    let kept_count = kept.iter().filter(|&&value| value == 2.).count();
+    assert_eq!(kept_count + kept.iter().filter(|&&value| value == 0.).count(), 1000);
+    assert!((400..600).contains(&kept_count));
+    assert_eq!(input.dropout(0.5, false).to_vec(), vec![1.; 1000]);
+}
+
+#[test]
+fn backward_through_long_graphs(){
+    let input = Tensor::from_data_and_shape(vec![1.], vec![1]);
+    let mut output = input.shallow_clone();
+    for _ in 0..20_000{
+        output = output.mul_scalar(1.);
+    }
+    output.backward();
+    assert_eq!(input.grad().unwrap().to_vec(), &[1.]);
+}
@@ -0,0 +1,242 @@
+//! Convolution and pooling Ops. Convolutions are composed of [`Tensor::im2col`] or
+//! [`Tensor::col2im`] and [`Tensor::matmul`], so their gradients come from the rules of those Ops.
+use crate::autograd::{Op, Tensor};
+use crate::tensors::{Conv2dGeometry, ConvTranspose2dGeometry};
+use crate::{Conv1dParams, Conv2dParams, ConvTranspose1dParams, ConvTranspose2dParams, Window2d};
+use std::collections::VecDeque;
+
+impl Tensor {
+    /// Copies each window of this `[batch, channels, height, width]` Tensor into a column,
+    /// see [`crate::RawTensor::im2col`]
+    pub fn im2col(&self, window: &Window2d) -> Self {
+        let res = self.read_lock().tensor.im2col(window);
+        Tensor::from_op(res, Op::Im2Col(self.shallow_clone(), *window))
+    }
+
+    /// Adds the columns back into an image, see [`crate::RawTensor::col2im`]
+    pub fn col2im(&self, window: &Window2d, image_size: (usize, usize, usize)) -> Self {
+        let res = self.read_lock().tensor.col2im(window, image_size);
+        Tensor::from_op(res, Op::Col2Im(self.shallow_clone(), *window))
+    }
+
+    /// 2D convolution of this `[batch, in_channels, height, width]` Tensor with a weight of
+    /// shape `[out_channels, in_channels / groups, kernel_h, kernel_w]` and an optional bias
+    /// of shape `[out_channels]`, see [`crate::GpuTensor::conv2d`]
+    pub fn conv2d(&self, weight: &Tensor, bias: Option<&Tensor>, params: &Conv2dParams) -> Self {
+        let geometry = Conv2dGeometry::new(
+            &VecDeque::from(self.shape()),
+            &VecDeque::from(weight.shape()),
+            params,
+        );
+        let columns = self.im2col(&geometry.window).reshape(geometry.grouped_columns_shape());
+        // The matmul broadcasts the kernels over the samples, and sums their gradients
+        let weight = weight.reshape(geometry.grouped_weight_shape());
+        let output = weight.matmul(&columns).reshape(geometry.output_shape());
+        match bias {
+            Some(bias) => {
+                assert_eq!(bias.shape(), vec![geometry.out_channels], "The bias must have shape [out_channels]");
+                output.add(&bias.reshape(vec![geometry.out_channels, 1, 1]))
+            }
+            None => output,
+        }
+    }
+
+    /// 1D convolution of this `[batch, in_channels, length]` Tensor with a weight of shape
+    /// `[out_channels, in_channels / groups, kernel]`, see [`crate::GpuTensor::conv1d`]
+    pub fn conv1d(&self, weight: &Tensor, bias: Option<&Tensor>, params: &Conv1dParams) -> Self {
+        let output = self.with_single_row().conv2d(&weight.with_single_row(), bias, &params.to_2d());
+        output.without_row()
+    }
+
+    /// Transposed 2D convolution of this `[batch, in_channels, height, width]` Tensor with a
+    /// weight of shape `[in_channels, out_channels / groups, kernel_h, kernel_w]`, see
+    /// [`crate::GpuTensor::conv_transpose2d`]
+    pub fn conv_transpose2d(&self, weight: &Tensor, bias: Option<&Tensor>, params: &ConvTranspose2dParams) -> Self {
+        let geometry = ConvTranspose2dGeometry::new(
+            &VecDeque::from(self.shape()),
+            &VecDeque::from(weight.shape()),
+            params,
+        );
+        let input = self.reshape(geometry.grouped_input_shape());
+        let group_in_channels = geometry.in_channels / geometry.groups;
+        let weight = weight
+            .reshape(vec![geometry.groups, group_in_channels, geometry.group_rows])
+            .transpose();
+        let columns = weight.matmul(&input).reshape(geometry.columns_shape());
+        let output = columns.col2im(&geometry.window, geometry.image_size());
+        match bias {
+            Some(bias) => {
+                assert_eq!(bias.shape(), vec![geometry.out_channels], "The bias must have shape [out_channels]");
+                output.add(&bias.reshape(vec![geometry.out_channels, 1, 1]))
+            }
+            None => output,
+        }
+    }
+
+    /// Transposed 1D convolution of this `[batch, in_channels, length]` Tensor with a weight of
+    /// shape `[in_channels, out_channels / groups, kernel]`, see
+    /// [`crate::GpuTensor::conv_transpose1d`]
+    pub fn conv_transpose1d(&self, weight: &Tensor, bias: Option<&Tensor>, params: &ConvTranspose1dParams) -> Self {
+        let output = self
+            .with_single_row()
+            .conv_transpose2d(&weight.with_single_row(), bias, &params.to_2d());
+        output.without_row()
+    }
+
+    /// Reshapes a `[batch, channels, length]` Tensor into `[batch, channels, 1, length]`
+    fn with_single_row(&self) -> Self {
+        let shape = self.shape();
+        assert_eq!(shape.len(), 3, "Expected a [batch, channels, length] Tensor, got shape {:?}", shape);
+        self.reshape(vec![shape[0], shape[1], 1, shape[2]])
+    }
+
+    /// Removes the single row of a `[batch, channels, 1, length]` Tensor
+    fn without_row(&self) -> Self {
+        let shape = self.shape();
+        self.reshape(vec![shape[0], shape[1], shape[3]])
+    }
+
+    /// Returns the maximum of each window, see [`crate::RawTensor::max_pool2d`]
+    pub fn max_pool2d(&self, window: &Window2d) -> Self {
+        let (res, indices) = self.read_lock().tensor.max_pool2d(window);
+        Tensor::from_op(res, Op::MaxPool2d(self.shallow_clone(), indices, *window))
+    }
+
+    /// Returns the average of each window, see [`crate::RawTensor::avg_pool2d`]
+    pub fn avg_pool2d(&self, window: &Window2d, count_include_pad: bool) -> Self {
+        let res = self.read_lock().tensor.avg_pool2d(window, count_include_pad);
+        Tensor::from_op(res, Op::AvgPool2d(self.shallow_clone(), *window, count_include_pad))
+    }
+
+    /// Averages windows chosen to produce the given output size, see
+    /// [`crate::RawTensor::adaptive_avg_pool2d`]
+    pub fn adaptive_avg_pool2d(&self, output_size: (usize, usize)) -> Self {
+        let res = self.read_lock().tensor.adaptive_avg_pool2d(output_size);
+        Tensor::from_op(res, Op::AdaptiveAvgPool2d(self.shallow_clone()))
+    }
+}
+
+#[cfg(test)]
+fn random(shape: Vec<usize>) -> crate::RawTensor {
+    crate::RawTensor::rand(shape).sub_scalar(0.5)
+}
+
+#[test]
+fn conv2d_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let params = Conv2dParams {
+        stride: (2, 1),
+        padding: (1, 1),
+        dilation: (1, 2),
+        groups: 2,
+    };
+    let input = random(vec![2, 4, 5, 5]);
+    let weight = random(vec![4, 2, 2, 2]);
+    let bias = Tensor::from_raw_tensor(random(vec![4]));
+    let weight_tensor = Tensor::from_raw_tensor(weight.clone());
+    assert!(gradcheck(
+        |x| x.conv2d(&weight_tensor, Some(&bias), &params).dot_mul(&x.conv2d(&weight_tensor, None, &params)).sum(),
+        &input,
+        1e-2,
+        1e-2
+    ));
+    let input_tensor = Tensor::from_raw_tensor(input);
+    assert!(gradcheck(
+        |w| input_tensor.conv2d(w, Some(&bias), &params).exp().sum(),
+        &weight,
+        1e-2,
+        1e-2
+    ));
+}
+
+#[test]
+fn conv2d_matches_cpu_reference() {
+    let params = Conv2dParams {
+        padding: (1, 0),
+        ..Conv2dParams::default()
+    };
+    let input = random(vec![1, 3, 4, 4]);
+    let weight = random(vec![2, 3, 3, 3]);
+    let output = Tensor::from_raw_tensor(input.clone()).conv2d(&Tensor::from_raw_tensor(weight.clone()), None, &params);
+    let expected = input.to_cpu().conv2d(&weight.to_cpu(), None, &params);
+    for (actual, expected) in output.to_vec().iter().zip(expected.as_contiguous_vec()) {
+        assert!((actual - expected).abs() < 1e-4, "{} != {}", actual, expected);
+    }
+}
+
+#[test]
+fn conv_transpose2d_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let params = ConvTranspose2dParams {
+        stride: (2, 1),
+        padding: (1, 0),
+        output_padding: (1, 0),
+        dilation: (1, 2),
+        groups: 2,
+    };
+    let input = random(vec![2, 4, 3, 3]);
+    let weight = random(vec![4, 1, 2, 2]);
+    let bias = Tensor::from_raw_tensor(random(vec![2]));
+    let weight_tensor = Tensor::from_raw_tensor(weight.clone());
+    assert!(gradcheck(
+        |x| x.conv_transpose2d(&weight_tensor, Some(&bias), &params).exp().sum(),
+        &input,
+        1e-2,
+        1e-2
+    ));
+    let input_tensor = Tensor::from_raw_tensor(input);
+    assert!(gradcheck(
+        |w| input_tensor.conv_transpose2d(w, None, &params).exp().sum(),
+        &weight,
+        1e-2,
+        1e-2
+    ));
+}
+
+#[test]
+fn conv1d_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let params = Conv1dParams {
+        stride: 2,
+        padding: 1,
+        ..Conv1dParams::default()
+    };
+    let transpose_params = ConvTranspose1dParams {
+        stride: 2,
+        output_padding: 1,
+        ..ConvTranspose1dParams::default()
+    };
+    let input = random(vec![1, 2, 7]);
+    let weight = Tensor::from_raw_tensor(random(vec![3, 2, 3]));
+    let transpose_weight = Tensor::from_raw_tensor(random(vec![3, 2, 2]));
+    assert!(gradcheck(
+        |x| x.conv1d(&weight, None, &params).conv_transpose1d(&transpose_weight, None, &transpose_params).exp().sum(),
+        &input,
+        1e-2,
+        1e-2
+    ));
+}
+
+#[test]
+fn pooling_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let window = Window2d {
+        kernel_size: (2, 3),
+        stride: (2, 1),
+        padding: (1, 1),
+        dilation: (1, 1),
+    };
+    let input = random(vec![1, 2, 4, 5]);
+    // The squares make the gradients depend on the pooled values
+    assert!(gradcheck(|x| { let y = x.max_pool2d(&window); y.dot_mul(&y).sum() }, &input, 1e-3, 1e-2));
+    assert!(gradcheck(|x| { let y = x.avg_pool2d(&window, false); y.dot_mul(&y).sum() }, &input, 1e-2, 1e-2));
+    assert!(gradcheck(|x| { let y = x.adaptive_avg_pool2d((3, 2)); y.dot_mul(&y).sum() }, &input, 1e-2, 1e-2));
+}
+
+#[test]
+#[should_panic(expected = "higher order gradients")]
+fn pooling_grads_are_not_differentiable() {
+    use crate::autograd::BackwardOptions;
+    let input = Tensor::from_raw_tensor(random(vec![1, 2, 4, 4]));
+    input.adaptive_avg_pool2d((2, 2)).sum().backward_with(BackwardOptions { create_graph: true, ..Default::default() });
+}
@@ -0,0 +1,111 @@
+use crate::autograd::{Tensor, TensorId};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Accumulates the DOT statements while walking the graph, giving each Tensor a stable name
+struct DotWriter {
+    names: HashMap<TensorId, String>,
+    statements: Vec<String>,
+    /// The Tensors which were named but whose node was not written yet
+    pending: Vec<Tensor>,
+}
+
+impl DotWriter {
+    /// Returns the name of the node representing `tensor`, scheduling the node to be written
+    /// the first time it is seen
+    fn name(&mut self, tensor: &Tensor) -> String {
+        if let Some(name) = self.names.get(&tensor.id()) {
+            return name.clone();
+        }
+        let tensor_name = format!("tensor_{}", self.names.len());
+        self.names.insert(tensor.id(), tensor_name.clone());
+        self.pending.push(tensor.shallow_clone());
+        tensor_name
+    }
+
+    /// Writes the nodes of the graph ending in `root` and of the Ops which created them. The
+    /// graph is walked with an explicit stack, since long graphs such as unrolled recurrent
+    /// networks would overflow the call stack.
+    fn visit(&mut self, root: &Tensor) {
+        self.name(root);
+        while let Some(tensor) = self.pending.pop() {
+            let tensor_name = self.names[&tensor.id()].clone();
+            let inner = tensor.read_lock();
+            let leaf = inner.parent_op.is_none() && !inner.graph_released;
+            let mut label = format!(
+                "shape: {:?}\\nleaf: {}\\ngrad: {}",
+                Vec::from(inner.tensor.shape().clone()),
+                leaf,
+                if inner.grad.is_some() { "populated" } else { "none" },
+            );
+            if inner.graph_released {
+                label.push_str("\\ngraph released");
+            }
+            self.statements.push(format!("{} [shape=box, label=\"{}\"];", tensor_name, label));
+            if let Some(parent_op) = &inner.parent_op {
+                let op_name = format!("op_{}", tensor_name);
+                self.statements.push(format!("{} [shape=ellipse, label=\"{}\"];", op_name, parent_op.name()));
+                self.statements.push(format!("{} -> {};", op_name, tensor_name));
+                for input in parent_op.inputs() {
+                    let input_name = self.name(input);
+                    self.statements.push(format!("{} -> {};", input_name, op_name));
+                }
+            }
+        }
+    }
+}
+
+impl Tensor {
+    /// Returns a [Graphviz](https://graphviz.org/) DOT document describing the graph which
+    /// ends in this Tensor. Tensors are drawn as boxes labelled with their shape, whether they
+    /// are leaves and whether their gradient is populated. Ops are drawn as ellipses labelled
+    /// with their kind. The Tensors data is not included.
+    ///
+    /// The output can be rendered with `dot -Tpng graph.dot -o graph.png`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::Tensor;
+    /// let x = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    /// let dot = x.exp().sum().to_dot();
+    /// assert!(dot.starts_with("digraph"));
+    /// assert!(dot.contains("label=\"Exp\""));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut writer = DotWriter {
+            names: HashMap::new(),
+            statements: vec![],
+            pending: vec![],
+        };
+        writer.visit(self);
+        let mut dot = String::from("digraph {\n");
+        for statement in writer.statements {
+            writeln!(dot, "    {}", statement).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[test]
+fn dot_describes_graph() {
+    let left = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let right = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let sum = left.matmul(&right).add(&left).sum();
+    let dot = sum.to_dot();
+    assert!(dot.starts_with("digraph {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("tensor_0 [shape=box, label=\"shape: [1]\\nleaf: false\\ngrad: none\"];"));
+    assert!(dot.contains("op_tensor_0 [shape=ellipse, label=\"Sum\"];"));
+    assert!(dot.contains("label=\"MatMul\""));
+    assert!(dot.contains("label=\"Add\""));
+    // left is used twice but appears only once
+    assert_eq!(dot.matches("leaf: true").count(), 2);
+
+    sum.backward();
+    let dot = sum.to_dot();
+    assert!(dot.contains("leaf: false\\ngrad: none\\ngraph released"));
+    assert!(!dot.contains("shape=ellipse"));
+    assert!(left.to_dot().contains("leaf: true\\ngrad: populated"));
+}
@@ -0,0 +1,207 @@
+//! Functional versions of the autograd API. Instead of accumulating gradients into the `grad`
+//! of the Tensors, these functions differentiate a function at the given inputs and return
+//! the results as new Tensors.
+use crate::autograd::{grads_of, with_grad_enabled, Tensor};
+use crate::RawTensor;
+
+fn ones(shape: Vec<usize>) -> Tensor {
+    let mut ones = RawTensor::zeros(shape);
+    ones.fill_with(1.);
+    Tensor::from_raw_tensor(ones)
+}
+
+fn one_hot(shape: Vec<usize>, hot_idx: usize) -> Tensor {
+    let numel = shape.iter().product();
+    let mut data = vec![0.; numel];
+    data[hot_idx] = 1.;
+    Tensor::from_data_and_shape(data, shape)
+}
+
+fn zeros_like(tensor: &Tensor) -> Tensor {
+    Tensor::from_raw_tensor(RawTensor::zeros(tensor.shape()))
+}
+
+/// Computes the gradient of the single element Tensor returned by `f` with respect to each
+/// one of its inputs. The gradients are recorded if `create_graph` is set.
+fn grads_of_scalar_fn<F: Fn(&[Tensor]) -> Tensor>(f: F, inputs: &[Tensor], create_graph: bool) -> Vec<Tensor> {
+    with_grad_enabled(true, || {
+        let output = f(inputs);
+        assert_eq!(
+            output.numel(),
+            1,
+            "The function must return a single element Tensor, use vjp otherwise"
+        );
+        let output_grad = ones(output.shape());
+        grads_of(&output, output_grad, inputs, create_graph)
+            .into_iter()
+            .zip(inputs)
+            .map(|(grad, input)| grad.unwrap_or_else(|| zeros_like(input)))
+            .collect()
+    })
+}
+
+/// Returns the gradient of `f`, which must return a single element Tensor, with respect to each
+/// one of the `inputs`. The `grad` of the Tensors involved is not changed.
+///
+/// # Examples
+///
+/// ```
+/// use tensor_compute::{autograd, Tensor};
+/// let x = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+/// let grads = autograd::grad(|inputs| inputs[0].dot_mul(&inputs[0]).sum(), &[x.shallow_clone()]);
+/// assert_eq!(grads[0].to_vec(), &[2., 4., 6., 8.]);
+/// assert!(x.grad().is_none());
+/// ```
+pub fn grad<F: Fn(&[Tensor]) -> Tensor>(f: F, inputs: &[Tensor]) -> Vec<Tensor> {
+    grads_of_scalar_fn(f, inputs, false)
+        .iter()
+        .map(Tensor::detach)
+        .collect()
+}
+
+/// Returns the output of `f` and the product of `vector` with the Jacobian of `f` at `inputs`,
+/// one Tensor per input. `vector` must have the same shape as the output of `f`.
+pub fn vjp<F: Fn(&[Tensor]) -> Tensor>(f: F, inputs: &[Tensor], vector: &Tensor) -> (Tensor, Vec<Tensor>) {
+    with_grad_enabled(true, || {
+        let output = f(inputs);
+        assert_eq!(output.shape(), vector.shape(), "vector must have the same shape as the output");
+        let products = grads_of(&output, vector.detach(), inputs, false)
+            .into_iter()
+            .zip(inputs)
+            .map(|(grad, input)| match grad {
+                Some(grad) => grad.detach(),
+                None => zeros_like(input),
+            })
+            .collect();
+        (output.detach(), products)
+    })
+}
+
+/// Returns the output of `f` and the product of the Jacobian of `f` at `inputs` with the
+/// `tangents`, which must have the same shapes as the `inputs`.
+///
+/// This uses the "double backward" trick: the vector-Jacobian product is linear in the vector,
+/// so back propagating through it, seeded with the tangents, yields the Jacobian-vector product.
+pub fn jvp<F: Fn(&[Tensor]) -> Tensor>(f: F, inputs: &[Tensor], tangents: &[Tensor]) -> (Tensor, Tensor) {
+    assert_eq!(inputs.len(), tangents.len(), "There must be one tangent per input");
+    with_grad_enabled(true, || {
+        let output = f(inputs);
+        let vector = zeros_like(&output);
+        let vjps = grads_of(&output, vector.shallow_clone(), inputs, true);
+        let mut vjp_dot_tangents: Option<Tensor> = None;
+        for (vjp, tangent) in vjps.iter().zip(tangents) {
+            if let Some(vjp) = vjp {
+                let term = vjp.dot_mul(tangent).sum();
+                vjp_dot_tangents = Some(match vjp_dot_tangents {
+                    Some(acc) => acc.add(&term),
+                    None => term,
+                });
+            }
+        }
+        let product = vjp_dot_tangents
+            .and_then(|vjp_dot_tangents| {
+                let output_grad = ones(vjp_dot_tangents.shape());
+                grads_of(&vjp_dot_tangents, output_grad, &[vector.shallow_clone()], false).remove(0)
+            })
+            .map(|product| product.detach())
+            .unwrap_or_else(|| zeros_like(&output));
+        (output.detach(), product)
+    })
+}
+
+/// Returns the Jacobian of `f` at `input`. The result has shape `output_shape ++ input_shape`,
+/// so with `f` mapping a `[2]` Tensor into a `[3]` one, the Jacobian has shape `[3, 2]`.
+///
+/// One back propagation is done for each element of the output.
+pub fn jacobian<F: Fn(&Tensor) -> Tensor>(f: F, input: &Tensor) -> Tensor {
+    with_grad_enabled(true, || {
+        let output = f(input);
+        let output_shape = output.shape();
+        let input_numel = input.numel();
+        let mut data = Vec::with_capacity(output.numel() * input_numel);
+        for output_idx in 0..output.numel() {
+            let output_grad = one_hot(output_shape.clone(), output_idx);
+            match grads_of(&output, output_grad, &[input.shallow_clone()], false).remove(0) {
+                Some(row) => data.extend(row.to_vec()),
+                None => data.extend(std::iter::repeat_n(0., input_numel)),
+            }
+        }
+        let mut shape = output_shape;
+        shape.extend(input.shape());
+        Tensor::from_data_and_shape(data, shape)
+    })
+}
+
+/// Returns the Hessian of `f`, which must return a single element Tensor, at `input`.
+/// The result has shape `input_shape ++ input_shape`.
+pub fn hessian<F: Fn(&Tensor) -> Tensor>(f: F, input: &Tensor) -> Tensor {
+    jacobian(
+        |input| grads_of_scalar_fn(|inputs| f(&inputs[0]), &[input.shallow_clone()], true).remove(0),
+        input,
+    )
+}
+
+#[test]
+fn grad_does_not_touch_leaf_grads() {
+    let x = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let y = Tensor::from_data_and_shape(vec![2., 0., 1., 1.], vec![1, 2, 2]);
+    let grads = grad(|inputs| inputs[0].dot_mul(&inputs[1]).sum(), &[x.shallow_clone(), y.shallow_clone()]);
+    assert_eq!(grads[0].to_vec(), &[2., 0., 1., 1.]);
+    assert_eq!(grads[1].to_vec(), &[1., 2., 3., 4.]);
+    assert!(x.grad().is_none());
+    assert!(y.grad().is_none());
+}
+
+#[test]
+fn vjp_of_matmul() {
+    let x = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let w = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let vector = Tensor::from_data_and_shape(vec![1., 1., 1., 1.], vec![1, 2, 2]);
+    let (output, products) = vjp(|inputs| inputs[0].matmul(&w), &[x.shallow_clone()], &vector);
+    assert_eq!(output.to_vec(), &[7., 10., 15., 22.]);
+    assert_eq!(products[0].to_vec(), &[3., 7., 3., 7.]);
+}
+
+#[test]
+fn jvp_matches_jacobian() {
+    let x = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let w = Tensor::from_data_and_shape(vec![1., -2., 0.5, 4.], vec![1, 2, 2]);
+    let tangent = Tensor::from_data_and_shape(vec![1., 0., -1., 2.], vec![1, 2, 2]);
+    let (_, product) = jvp(|inputs| inputs[0].matmul(&w), &[x.shallow_clone()], &[tangent.shallow_clone()]);
+    // (J t) = t @ w since f is linear in x
+    let expected = tangent.matmul(&w).to_vec();
+    for (actual, expected) in product.to_vec().iter().zip(expected.iter()) {
+        assert!((actual - expected).abs() < 1e-4, "{} != {}", actual, expected);
+    }
+    // d(x @ w)[0, i, j] / dx[0, i2, k] is w[k, j] if i == i2, zero otherwise
+    let jacobian = jacobian(|x| x.matmul(&w), &x);
+    assert_eq!(jacobian.shape(), vec![1, 2, 2, 1, 2, 2]);
+    let (jacobian, w) = (jacobian.to_vec(), w.to_vec());
+    for i in 0..2 {
+        for j in 0..2 {
+            for i2 in 0..2 {
+                for k in 0..2 {
+                    let expected = if i == i2 { w[k * 2 + j] } else { 0. };
+                    assert_eq!(jacobian[(i * 2 + j) * 4 + i2 * 2 + k], expected, "J[0,{},{},0,{},{}]", i, j, i2, k);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn hessian_of_exp_times_x() {
+    // f(x) = sum(exp(x) * x), the Hessian is diagonal with elements exp(x) * (2 + x)
+    let data = vec![0.1f32, -0.2, 0.3, 0.4];
+    let x = Tensor::from_data_and_shape(data.clone(), vec![1, 2, 2]);
+    let hessian = hessian(|x| x.exp().dot_mul(x).sum(), &x);
+    assert_eq!(hessian.shape(), vec![1, 2, 2, 1, 2, 2]);
+    let hessian = hessian.to_vec();
+    for row in 0..4 {
+        for col in 0..4 {
+            let expected = if row == col { data[row].exp() * (2. + data[row]) } else { 0. };
+            let actual = hessian[row * 4 + col];
+            assert!((actual - expected).abs() < 1e-3, "{} != {}", actual, expected);
+        }
+    }
+}
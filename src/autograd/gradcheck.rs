@@ -0,0 +1,34 @@
+use crate::autograd::Tensor;
+use crate::RawTensor;
+
+/// Computes the gradient of `f` at `input` using central finite differences: for each element
+/// `f` is evaluated with the element shifted by `eps` and by `-eps`.
+/// `f` must return a Tensor with a single element.
+pub fn numerical_grad<F: Fn(&Tensor) -> Tensor>(f: F, input: &RawTensor, eps: f32) -> RawTensor{
+    let shape = Vec::from(input.shape().clone());
+    let data = input.to_vec();
+    let mut grad = Vec::with_capacity(data.len());
+    for i in 0..data.len(){
+        let mut plus = data.clone();
+        plus[i] += eps;
+        let mut minus = data.clone();
+        minus[i] -= eps;
+        let f_plus = f(&Tensor::from_data_and_shape(plus, shape.clone())).to_f32();
+        let f_minus = f(&Tensor::from_data_and_shape(minus, shape.clone())).to_f32();
+        grad.push((f_plus - f_minus) / (2. * eps));
+    }
+    RawTensor::from_data_and_shape(grad, shape)
+}
+
+/// Returns true if the gradient of `f` at `input` computed by [`Tensor::backward`] matches
+/// the one computed by [`numerical_grad`]. Each element must be within `tolerance`, relative
+/// to the numerical gradient magnitude if it is bigger than 1.
+pub fn gradcheck<F: Fn(&Tensor) -> Tensor>(f: F, input: &RawTensor, eps: f32, tolerance: f32) -> bool{
+    let leaf = Tensor::from_raw_tensor(input.clone());
+    f(&leaf).backward();
+    let analytical = leaf.grad().expect("Input did not receive a gradient").to_vec();
+    let numerical = numerical_grad(f, input, eps).to_vec();
+    analytical.iter()
+        .zip(numerical.iter())
+        .all(|(analytical, numerical)| (analytical - numerical).abs() <= tolerance * numerical.abs().max(1.))
+}
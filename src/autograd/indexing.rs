@@ -0,0 +1,116 @@
+//! Integer indexed Ops and slicing. The indices are constants, only the indexed Tensors are
+//! differentiated.
+use crate::autograd::{Op, Tensor};
+use crate::RawTensor;
+
+impl Tensor {
+    /// Selects entries along `dim` given one dimensional indices, see
+    /// [`RawTensor::index_select`]
+    pub fn index_select(&self, dim: usize, indices: &RawTensor) -> Self {
+        let res = self.read_lock().tensor.index_select(dim, indices);
+        Tensor::from_op(res, Op::IndexSelect(self.shallow_clone(), indices.clone(), dim))
+    }
+
+    /// Gathers values along `dim`, see [`RawTensor::gather`]
+    pub fn gather(&self, dim: usize, index: &RawTensor) -> Self {
+        let res = self.read_lock().tensor.gather(dim, index);
+        Tensor::from_op(res, Op::Gather(self.shallow_clone(), index.clone(), dim))
+    }
+
+    /// Writes `source` along `dim` at the positions given by `index`, see
+    /// [`RawTensor::scatter`]
+    pub fn scatter(&self, dim: usize, index: &RawTensor, source: &Tensor) -> Self {
+        self.scatter_impl(dim, index, source, false)
+    }
+
+    /// Adds `source` along `dim` at the positions given by `index`, see
+    /// [`RawTensor::scatter_add`]
+    pub fn scatter_add(&self, dim: usize, index: &RawTensor, source: &Tensor) -> Self {
+        self.scatter_impl(dim, index, source, true)
+    }
+
+    fn scatter_impl(&self, dim: usize, index: &RawTensor, source: &Tensor, add: bool) -> Self {
+        let res = {
+            let (input, source) = (self.read_lock(), source.read_lock());
+            if add {
+                input.tensor.scatter_add(dim, index, &source.tensor)
+            } else {
+                input.tensor.scatter(dim, index, &source.tensor)
+            }
+        };
+        Tensor::from_op(res, Op::Scatter(self.shallow_clone(), index.clone(), source.shallow_clone(), dim, add))
+    }
+
+    /// Concatenates the Tensors along `dim`, see [`RawTensor::cat`]
+    pub fn cat(tensors: &[Tensor], dim: usize) -> Self {
+        let res = {
+            let locks: Vec<_> = tensors.iter().map(|tensor| tensor.read_lock()).collect();
+            let raw_tensors: Vec<&RawTensor> = locks.iter().map(|lock| &lock.tensor).collect();
+            RawTensor::cat(&raw_tensors, dim)
+        };
+        let inputs = tensors.iter().map(|tensor| tensor.shallow_clone()).collect();
+        Tensor::from_op(res, Op::Cat(inputs, dim))
+    }
+
+    /// Returns the entries `[start, start + len)` along `dim`, see [`RawTensor::narrow`]
+    pub fn narrow(&self, dim: usize, start: usize, len: usize) -> Self {
+        let res = self.read_lock().tensor.narrow(dim, start, len);
+        Tensor::from_op(res, Op::Narrow(self.shallow_clone(), dim, start))
+    }
+
+    /// Looks up the rows of `weight`, with shape `[num_embeddings, embedding_dim]`, given by
+    /// `indices`, see [`RawTensor::embedding`]
+    pub fn embedding(indices: &RawTensor, weight: &Tensor) -> Self {
+        let res = indices.embedding(&weight.read_lock().tensor);
+        Tensor::from_op(res, Op::Embedding(indices.clone(), weight.shallow_clone()))
+    }
+}
+
+#[cfg(test)]
+fn random(shape: Vec<usize>) -> RawTensor {
+    RawTensor::rand(shape).sub_scalar(0.5)
+}
+
+#[test]
+fn index_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let scale = Tensor::from_raw_tensor(random(vec![2, 4]));
+    let indices = RawTensor::from_data_1d(vec![2., 0., 2., 1.]);
+    assert!(gradcheck(|x| x.index_select(1, &indices).dot_mul(&scale).sum(), &random(vec![2, 3]), 1e-2, 1e-2));
+    let index = RawTensor::from_data_and_shape(vec![0., 0., 2., 1., 1., 1., 2., 0.], vec![2, 4]);
+    assert!(gradcheck(|x| x.gather(1, &index).dot_mul(&scale).sum(), &random(vec![2, 3]), 1e-2, 1e-2));
+    let weight = random(vec![3, 2]);
+    let embedding_indices = RawTensor::from_data_and_shape(vec![2., 0., 2., 1.], vec![2, 2]);
+    let embedding_scale = Tensor::from_raw_tensor(random(vec![2, 2, 2]));
+    assert!(gradcheck(
+        |w| Tensor::embedding(&embedding_indices, w).dot_mul(&embedding_scale).sum(),
+        &weight,
+        1e-2,
+        1e-2
+    ));
+}
+
+#[test]
+fn scatter_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let scale = Tensor::from_raw_tensor(random(vec![2, 3]));
+    // Both sources of the second row are written to the same element
+    let index = RawTensor::from_data_and_shape(vec![0., 2., 1., 1.], vec![2, 2]);
+    let source = random(vec![2, 2]);
+    let source_tensor = Tensor::from_raw_tensor(source.clone());
+    assert!(gradcheck(|x| x.scatter_add(1, &index, &source_tensor).dot_mul(&scale).sum(), &random(vec![2, 3]), 1e-2, 1e-2));
+    assert!(gradcheck(|x| x.scatter(1, &index, &source_tensor).dot_mul(&scale).sum(), &random(vec![2, 3]), 1e-2, 1e-2));
+    let input = Tensor::from_raw_tensor(random(vec![2, 3]));
+    assert!(gradcheck(|s| input.scatter_add(1, &index, s).dot_mul(&scale).sum(), &source, 1e-2, 1e-2));
+}
+
+#[test]
+fn cat_and_narrow_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let scale = Tensor::from_raw_tensor(random(vec![2, 5]));
+    let other = Tensor::from_raw_tensor(random(vec![2, 2]));
+    assert!(gradcheck(|x| Tensor::cat(&[other.shallow_clone(), x.shallow_clone()], 1).dot_mul(&scale).sum(), &random(vec![2, 3]), 1e-2, 1e-2));
+    let narrow_scale = Tensor::from_raw_tensor(random(vec![2, 2]));
+    assert!(gradcheck(|x| x.narrow(1, 1, 2).dot_mul(&narrow_scale).sum(), &random(vec![2, 5]), 1e-2, 1e-2));
+    assert!(gradcheck(|x| x.narrow(0, 0, 2).sigmoid().tanh().sum(), &random(vec![3, 2]), 1e-2, 1e-2));
+}
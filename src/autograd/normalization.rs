@@ -0,0 +1,141 @@
+//! Normalization Ops, split into the normalization itself and the optional affine
+//! transformation, each with a fused backward kernel.
+use crate::autograd::{Op, Tensor};
+use crate::tensors::update_running_stats;
+use crate::{NormGroups, RawTensor};
+use std::collections::VecDeque;
+
+impl Tensor {
+    /// Layer normalization over the trailing `normalized_shape` dimensions, see
+    /// [`RawTensor::layer_norm`]
+    pub fn layer_norm(&self, normalized_shape: &[usize], weight: Option<&Tensor>, bias: Option<&Tensor>, eps: f32) -> Self {
+        let (stats_groups, affine_groups) = NormGroups::layer_norm(&VecDeque::from(self.shape()), normalized_shape);
+        self.normalize_with_batch_stats(&stats_groups, eps)
+            .optional_affine(weight, bias, &affine_groups)
+    }
+
+    /// Group normalization of a `[batch, channels, *]` Tensor, see [`RawTensor::group_norm`]
+    pub fn group_norm(&self, num_groups: usize, weight: Option<&Tensor>, bias: Option<&Tensor>, eps: f32) -> Self {
+        let (stats_groups, affine_groups) = NormGroups::group_norm(&VecDeque::from(self.shape()), num_groups);
+        self.normalize_with_batch_stats(&stats_groups, eps)
+            .optional_affine(weight, bias, &affine_groups)
+    }
+
+    /// Batch normalization of a `[batch, channels, *]` Tensor, see [`RawTensor::batch_norm`].
+    /// In evaluation mode the running statistics are constants, so they are not back
+    /// propagated through.
+    pub fn batch_norm(
+        &self,
+        running_stats: Option<(&mut RawTensor, &mut RawTensor)>,
+        weight: Option<&Tensor>,
+        bias: Option<&Tensor>,
+        training: bool,
+        momentum: f32,
+        eps: f32,
+    ) -> Self {
+        let groups = NormGroups::batch_norm(&VecDeque::from(self.shape()));
+        let normalized = if training {
+            let (mean, var) = self.read_lock().tensor.norm_stats(&groups);
+            if let Some((running_mean, running_var)) = running_stats {
+                update_running_stats(running_mean, running_var, &mean, &var, momentum, groups.len(self.numel()));
+            }
+            self.normalize_with(mean, var, &groups, eps, true)
+        } else {
+            let (running_mean, running_var) =
+                running_stats.expect("Batch normalization needs the running statistics in evaluation mode");
+            self.normalize_with(running_mean.clone(), running_var.clone(), &groups, eps, false)
+        };
+        normalized.optional_affine(weight, bias, &groups)
+    }
+
+    fn normalize_with_batch_stats(&self, groups: &NormGroups, eps: f32) -> Self {
+        let (mean, var) = self.read_lock().tensor.norm_stats(groups);
+        self.normalize_with(mean, var, groups, eps, true)
+    }
+
+    fn normalize_with(&self, mean: RawTensor, var: RawTensor, groups: &NormGroups, eps: f32, batch_stats: bool) -> Self {
+        let res = self.read_lock().tensor.normalize(&mean, &var, groups, eps);
+        Tensor::from_op(res, Op::Normalize(self.shallow_clone(), mean, var, *groups, eps, batch_stats))
+    }
+
+    /// Applies the affine transformation of a normalization, a missing weight being ones and a
+    /// missing bias zeros
+    fn optional_affine(&self, weight: Option<&Tensor>, bias: Option<&Tensor>, groups: &NormGroups) -> Self {
+        if weight.is_none() && bias.is_none() {
+            return self.shallow_clone();
+        }
+        let weight_data = weight.map(Tensor::read_lock);
+        let bias_data = bias.map(Tensor::read_lock);
+        let res = self.read_lock().tensor.affine_with_defaults(
+            weight_data.as_ref().map(|weight| &weight.tensor),
+            bias_data.as_ref().map(|bias| &bias.tensor),
+            groups,
+        );
+        let op = Op::Affine(
+            self.shallow_clone(),
+            weight.map(|weight| weight.shallow_clone()),
+            bias.map(|bias| bias.shallow_clone()),
+            *groups,
+        );
+        Tensor::from_op(res, op)
+    }
+}
+
+#[cfg(test)]
+fn random(shape: Vec<usize>) -> RawTensor {
+    RawTensor::rand(shape).sub_scalar(0.5)
+}
+
+#[test]
+fn layer_norm_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let weight_data = random(vec![2, 3]);
+    let weight = Tensor::from_raw_tensor(weight_data.clone());
+    let bias = Tensor::from_raw_tensor(random(vec![2, 3]));
+    let scale = Tensor::from_raw_tensor(random(vec![4, 2, 3]));
+    let input = random(vec![4, 2, 3]);
+    // Multiplying by a random Tensor, since the sum of normalized values is always zero
+    assert!(gradcheck(
+        |x| x.layer_norm(&[2, 3], Some(&weight), Some(&bias), 1e-5).dot_mul(&scale).sum(),
+        &input,
+        1e-2,
+        1e-2
+    ));
+    let input_tensor = Tensor::from_raw_tensor(input);
+    assert!(gradcheck(
+        |w| input_tensor.layer_norm(&[2, 3], Some(w), None, 1e-5).dot_mul(&scale).sum(),
+        &weight_data,
+        1e-2,
+        1e-2
+    ));
+}
+
+#[test]
+fn group_and_batch_norm_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    let scale = Tensor::from_raw_tensor(random(vec![3, 4, 2, 2]));
+    let input = random(vec![3, 4, 2, 2]);
+    assert!(gradcheck(|x| x.group_norm(2, None, None, 1e-5).dot_mul(&scale).sum(), &input, 1e-2, 1e-2));
+    let bias = Tensor::from_raw_tensor(random(vec![4]));
+    assert!(gradcheck(
+        |x| x.batch_norm(None, None, Some(&bias), true, 0.1, 1e-5).dot_mul(&scale).sum(),
+        &input,
+        1e-2,
+        1e-2
+    ));
+    let mut running_mean = random(vec![4]);
+    let mut running_var = RawTensor::filled(vec![4], 4.);
+    let weight = Tensor::from_raw_tensor(random(vec![4]));
+    let input = Tensor::from_raw_tensor(input);
+    input
+        .batch_norm(Some((&mut running_mean, &mut running_var)), Some(&weight), None, false, 0.1, 0.)
+        .sum()
+        .backward();
+    // In evaluation mode the gradient of each element is the weight of its channel over the
+    // running standard deviation
+    let weight = weight.to_vec();
+    for (index, grad) in input.grad().unwrap().to_vec().iter().enumerate() {
+        let channel = (index / 4) % 4;
+        assert!((grad - weight[channel] / 2.).abs() < 1e-5, "{} != {}", grad, weight[channel] / 2.);
+    }
+}
@@ -1,47 +1,243 @@
-use crate::autograd::Tensor;
-use crate::RawTensor;
-
-pub fn set_matmul_grad(left: &Tensor, right: &Tensor, child_grad: &RawTensor){
-    let right_t = right.read_lock().tensor.transpose();
-    let new_left_grad = child_grad.matmul(&right_t);
-    let new_grad = if let Some(existing) = &left.write_lock().grad{
-        existing.add(&new_left_grad)
-    }else{
-        new_left_grad
+use crate::autograd::{is_grad_enabled, Tensor};
+use crate::{NormGroups, PointwiseLoss, RawTensor, Window2d};
+
+/// Wraps a gradient computed by a single fused kernel. Those gradients are not differentiable
+/// themselves, so they can not be part of the graph built by a backward pass with
+/// `create_graph` set.
+fn kernel_grad(grad: RawTensor) -> Tensor{
+    assert!(!is_grad_enabled(), "This Op does not support higher order gradients, backward can not create a graph through it");
+    Tensor::from_raw_tensor(grad)
+}
+
+/// The gradient of an input whose batch dimensions were broadcast is summed over them
+pub fn matmul_grad(left: &Tensor, right: &Tensor, child_grad: &Tensor) -> (Tensor, Tensor){
+    let unbroadcast = |input: &Tensor, grad: Tensor| {
+        if grad.shape() == input.shape() { grad } else { broadcast_to_grad(input, &grad) }
     };
-    left.write_lock().grad = Some(new_grad);
+    let left_grad = child_grad.matmul(&right.transpose());
+    let right_grad = left.transpose().matmul(child_grad);
+    (unbroadcast(left, left_grad), unbroadcast(right, right_grad))
+}
+
+pub fn exp_grad(original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    original_input.exp().dot_mul(child_grad)
+}
+
+/// Uses `sigmoid'(x) = sigmoid(x) sigmoid(-x)`, which is differentiable with the same Ops
+pub fn sigmoid_grad(original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    let negated = original_input.mul_scalar(-1.);
+    child_grad.dot_mul(&original_input.sigmoid()).dot_mul(&negated.sigmoid())
+}
 
+/// Uses `tanh(x) = 2 sigmoid(2x) - 1`, so `tanh'(x) = 4 sigmoid(2x) sigmoid(-2x)`
+pub fn tanh_grad(original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    let doubled = original_input.mul_scalar(2.);
+    sigmoid_grad(&doubled, child_grad).mul_scalar(4.)
+}
+
+pub fn sum_grad(original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    child_grad.expand(original_input.shape())
+}
+
+pub fn add_grad(_left: &Tensor, _right: &Tensor, child_grad: &Tensor) -> (Tensor, Tensor){
+    (child_grad.shallow_clone(), child_grad.shallow_clone())
+}
+
+pub fn dot_mul_grad(left: &Tensor, right: &Tensor, child_grad: &Tensor) -> (Tensor, Tensor){
+    (child_grad.dot_mul(right), child_grad.dot_mul(left))
+}
+
+pub fn transpose_grad(_original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    child_grad.transpose()
+}
+
+pub fn expand_grad(_original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    child_grad.sum()
+}
+
+pub fn reshape_grad(original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    child_grad.reshape(original_input.shape())
+}
+
+/// Sums the gradient over the dimensions which were created or stretched by the broadcast
+pub fn broadcast_to_grad(original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    let input_shape = original_input.shape();
+    let grad_shape = child_grad.shape();
+    let new_dims = grad_shape.len() - input_shape.len();
+    let mut grad = child_grad.shallow_clone();
+    for (dim, size) in grad_shape.iter().enumerate() {
+        let broadcasted = dim < new_dims || (input_shape[dim - new_dims] == 1 && *size != 1);
+        if broadcasted {
+            grad = grad.sum_dim(dim);
+        }
+    }
+    grad.reshape(input_shape)
+}
+
+pub fn sum_dim_grad(original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    child_grad.broadcast_to(original_input.shape())
+}
+
+pub fn mul_scalar_grad(_original_input: &Tensor, scalar: f32, child_grad: &Tensor) -> Tensor{
+    child_grad.mul_scalar(scalar)
+}
+
+pub fn pointwise_loss_grad(original_input: &Tensor, target: &RawTensor, loss: PointwiseLoss, child_grad: &Tensor) -> Tensor{
+    let input = original_input.read_lock();
+    let grad = child_grad.read_lock();
+    kernel_grad(input.tensor.pointwise_loss_backward(target, &grad.tensor, loss))
+}
 
-    let left_t = left.read_lock().tensor.transpose();
-    let new_right_grad = left_t.matmul(&child_grad);
-    let new_grad = if let Some(existing) = &right.write_lock().grad{
-        existing.add(&new_right_grad)
-    }else{
-        new_right_grad
+pub fn class_loss_grad(original_input: &Tensor, targets: &RawTensor, log_softmax: bool, child_grad: &Tensor) -> Tensor{
+    let input = original_input.read_lock();
+    let grad = child_grad.read_lock();
+    kernel_grad(input.tensor.class_loss_backward(targets, &grad.tensor, log_softmax))
+}
+
+pub fn im2col_grad(original_input: &Tensor, window: &Window2d, child_grad: &Tensor) -> Tensor{
+    let shape = original_input.shape();
+    child_grad.col2im(window, (shape[1], shape[2], shape[3]))
+}
+
+pub fn col2im_grad(window: &Window2d, child_grad: &Tensor) -> Tensor{
+    child_grad.im2col(window)
+}
+
+/// Input `(height, width)` of a pooling Op
+fn pooled_input_size(original_input: &Tensor) -> (usize, usize){
+    let shape = original_input.shape();
+    (shape[2], shape[3])
+}
+
+pub fn max_pool2d_grad(original_input: &Tensor, indices: &RawTensor, window: &Window2d, child_grad: &Tensor) -> Tensor{
+    let grad = child_grad.read_lock();
+    let input_size = pooled_input_size(original_input);
+    kernel_grad(grad.tensor.max_pool2d_backward(indices, window, input_size))
+}
+
+pub fn avg_pool2d_grad(original_input: &Tensor, window: &Window2d, count_include_pad: bool, child_grad: &Tensor) -> Tensor{
+    let grad = child_grad.read_lock();
+    let input_size = pooled_input_size(original_input);
+    kernel_grad(grad.tensor.avg_pool2d_backward(window, count_include_pad, input_size))
+}
+
+pub fn adaptive_avg_pool2d_grad(original_input: &Tensor, child_grad: &Tensor) -> Tensor{
+    let grad = child_grad.read_lock();
+    kernel_grad(grad.tensor.adaptive_avg_pool2d_backward(pooled_input_size(original_input)))
+}
+
+/// The normalized values are recomputed instead of being kept alive by the graph
+pub fn normalize_grad(
+    original_input: &Tensor,
+    mean: &RawTensor,
+    var: &RawTensor,
+    groups: &NormGroups,
+    eps: f32,
+    batch_stats: bool,
+    child_grad: &Tensor,
+) -> Tensor{
+    let input = original_input.read_lock();
+    let grad = child_grad.read_lock();
+    let normalized = input.tensor.normalize(mean, var, groups, eps);
+    kernel_grad(normalized.normalize_backward(&grad.tensor, var, groups, eps, batch_stats))
+}
+
+/// Returns the gradients of the input followed by the ones of the weight and bias, if present
+pub fn affine_grad(
+    original_input: &Tensor,
+    weight: Option<&Tensor>,
+    bias: Option<&Tensor>,
+    groups: &NormGroups,
+    child_grad: &Tensor,
+) -> Vec<Tensor>{
+    let input = original_input.read_lock();
+    let grad = child_grad.read_lock();
+    let input_grad = match weight {
+        Some(weight) => {
+            let zeros = RawTensor::zeros(vec![groups.groups]);
+            grad.tensor.affine(&weight.read_lock().tensor, &zeros, groups)
+        }
+        None => grad.tensor.clone(),
     };
-    right.write_lock().grad = Some(new_grad);
+    let mut grads = vec![kernel_grad(input_grad)];
+    let (mut weight_grad, mut bias_grad) = input.tensor.affine_backward(&grad.tensor, groups);
+    if let Some(weight) = weight {
+        weight_grad.reshape(weight.shape());
+        grads.push(kernel_grad(weight_grad));
+    }
+    if let Some(bias) = bias {
+        bias_grad.reshape(bias.shape());
+        grads.push(kernel_grad(bias_grad));
+    }
+    grads
+}
+
+/// The same mask used in the forward pass zeroes and scales the gradient
+pub fn dropout_grad(mask: &RawTensor, child_grad: &Tensor) -> Tensor{
+    child_grad.dot_mul(&Tensor::from_raw_tensor(mask.clone()))
 }
 
+pub fn index_select_grad(original_input: &Tensor, indices: &RawTensor, dim: usize, child_grad: &Tensor) -> Tensor{
+    let grad = child_grad.read_lock();
+    let zeros = RawTensor::zeros(original_input.shape());
+    kernel_grad(zeros.index_add(dim, indices, &grad.tensor))
+}
+
+pub fn gather_grad(original_input: &Tensor, index: &RawTensor, dim: usize, child_grad: &Tensor) -> Tensor{
+    let zeros = Tensor::from_raw_tensor(RawTensor::zeros(original_input.shape()));
+    zeros.scatter_add(dim, index, child_grad)
+}
 
-pub fn set_exp_grad(original_input: &Tensor, child_grad: &RawTensor){
-    let mut write_lock = original_input.write_lock();
-    let op_grad = write_lock.tensor.dot_mul(child_grad);
-    let new_grad = if let Some(existing) = &write_lock.grad{
-        existing.add(&op_grad)
-    }else{
-        op_grad
+/// The gradient of the input is zero where it was overwritten, unless the source was added.
+/// Each source element receives the gradient of the element it was scattered to, including the
+/// sources overwritten by a later one with the same index.
+pub fn scatter_grad(index: &RawTensor, source: &Tensor, dim: usize, add: bool, child_grad: &Tensor) -> (Tensor, Tensor){
+    let input_grad = if add {
+        child_grad.shallow_clone()
+    } else {
+        let zeros = Tensor::from_raw_tensor(RawTensor::zeros(source.shape()));
+        child_grad.scatter(dim, index, &zeros)
     };
-    write_lock.grad = Some(new_grad);
+    (input_grad, child_grad.gather(dim, index))
 }
 
-pub fn set_sum_grad(original_input: &Tensor, child_grad: &RawTensor){
-    let mut write_lock = original_input.write_lock();
-    let mut op_grad = RawTensor::zeros_like(&write_lock.tensor);
-    op_grad.fill_with(child_grad.to_f32());
-    let new_grad = if let Some(existing) = &write_lock.grad{
-        existing.add(&op_grad)
-    }else{
-        op_grad
+pub fn embedding_grad(indices: &RawTensor, weight: &Tensor, child_grad: &Tensor) -> Tensor{
+    let grad = child_grad.read_lock();
+    kernel_grad(indices.embedding_backward(&grad.tensor, weight.shape()[0]))
+}
+
+pub fn cat_grad(inputs: &[Tensor], dim: usize, child_grad: &Tensor) -> Vec<Tensor>{
+    let mut start = 0;
+    inputs.iter().map(|input| {
+        let len = input.shape()[dim];
+        let grad = child_grad.narrow(dim, start, len);
+        start += len;
+        grad
+    }).collect()
+}
+
+/// Pads the gradient with zeros along `dim` back to the shape of the input
+pub fn narrow_grad(original_input: &Tensor, dim: usize, start: usize, child_grad: &Tensor) -> Tensor{
+    let input_shape = original_input.shape();
+    let len = child_grad.shape()[dim];
+    let zeros = |len: usize| {
+        let mut shape = input_shape.clone();
+        shape[dim] = len;
+        Tensor::from_raw_tensor(RawTensor::zeros(shape))
     };
-    write_lock.grad = Some(new_grad);
-}
\ No newline at end of file
+    let mut pieces = Vec::with_capacity(3);
+    if start > 0 {
+        pieces.push(zeros(start));
+    }
+    pieces.push(child_grad.shallow_clone());
+    if start + len < input_shape[dim] {
+        pieces.push(zeros(input_shape[dim] - start - len));
+    }
+    Tensor::cat(&pieces, dim)
+}
+
+/// Computed from the output of the softmax, see [`RawTensor::masked_softmax_backward`]
+pub fn masked_softmax_grad(output: &RawTensor, scale: f32, child_grad: &Tensor) -> Tensor{
+    let grad = child_grad.read_lock();
+    kernel_grad(output.masked_softmax_backward(&grad.tensor, scale))
+}
@@ -0,0 +1,204 @@
+//! Checkpoints of the state of Modules and Optimizers, used to resume training.
+//!
+//! A [`StateDict`] maps names to Tensors, such as the parameters of a Module or the moments of
+//! Adam, and to scalars, such as the learning rates. It is returned by
+//! [`crate::nn::Module::state_dict`] and [`crate::optim::Optimizer::state_dict`], and can be
+//! saved to a safetensors file, the scalars being written in its metadata.
+//!
+//! # Examples
+//!
+//! ```
+//! use tensor_compute::nn::{Linear, Module};
+//! let model = Linear::new(3, 2);
+//! let mut restored = Linear::new(3, 2);
+//! let report = restored.load_state_dict(&model.state_dict(), true);
+//! assert!(report.is_clean());
+//! assert_eq!(restored.weight.to_vec(), model.weight.to_vec());
+//! ```
+use crate::serialization::safetensors;
+use crate::{RawTensor, ShapeStrideTrait};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Named Tensors and scalars describing the state of a Module or an Optimizer
+#[derive(Default)]
+pub struct StateDict {
+    pub tensors: HashMap<String, RawTensor>,
+    pub scalars: HashMap<String, f64>,
+}
+
+impl StateDict {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the entries of `other` with their names prefixed by `prefix` and a dot, so the
+    /// states of a model and of its optimizer can be saved to the same file
+    pub fn extend_prefixed(&mut self, prefix: &str, other: StateDict) {
+        let name = |key: String| format!("{}.{}", prefix, key);
+        self.tensors.extend(other.tensors.into_iter().map(|(key, tensor)| (name(key), tensor)));
+        self.scalars.extend(other.scalars.into_iter().map(|(key, value)| (name(key), value)));
+    }
+
+    /// Returns the entries whose names start with `prefix` and a dot, without that prefix
+    pub fn strip_prefix(&self, prefix: &str) -> StateDict {
+        let prefix = format!("{}.", prefix);
+        let strip = |key: &String| key.strip_prefix(&prefix).map(str::to_string);
+        StateDict {
+            tensors: self
+                .tensors
+                .iter()
+                .filter_map(|(key, tensor)| strip(key).map(|key| (key, tensor.clone())))
+                .collect(),
+            scalars: self
+                .scalars
+                .iter()
+                .filter_map(|(key, value)| strip(key).map(|key| (key, *value)))
+                .collect(),
+        }
+    }
+
+    /// Saves the state to a safetensors file, with the scalars in its metadata
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let tensors = self.tensors.iter().map(|(name, tensor)| (name.clone(), tensor.to_cpu())).collect();
+        // The shortest representation which parses back to the same value
+        let metadata = self.scalars.iter().map(|(name, value)| (name.clone(), value.to_string())).collect();
+        fs::write(path, safetensors::serialize_with_metadata(&tensors, &metadata))
+    }
+
+    /// Loads a state saved by [`StateDict::save`]. Metadata which are not numbers are ignored,
+    /// so any safetensors file can be loaded.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<StateDict> {
+        let (tensors, metadata) = safetensors::deserialize_with_metadata(&fs::read(path)?)?;
+        let tensors = tensors
+            .into_iter()
+            .map(|(name, tensor)| {
+                let shape = tensor.shape().iter().copied().collect();
+                (name, RawTensor::from_data_and_shape(tensor.as_contiguous_vec(), shape))
+            })
+            .collect();
+        let scalars = metadata
+            .into_iter()
+            .filter_map(|(name, value)| value.parse().ok().map(|value| (name, value)))
+            .collect();
+        Ok(StateDict { tensors, scalars })
+    }
+}
+
+/// A Tensor of a [`StateDict`] whose shape differs from the one it is loaded into
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeMismatch {
+    pub key: String,
+    pub expected: Vec<usize>,
+    pub found: Vec<usize>,
+}
+
+/// The differences between a [`StateDict`] and the state it was loaded into. Entries which
+/// mismatch are skipped when loading non strictly.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    /// Entries of the state which are not in the StateDict, and were left unchanged
+    pub missing_keys: Vec<String>,
+    /// Entries of the StateDict which don't belong to the state
+    pub unexpected_keys: Vec<String>,
+    pub shape_mismatches: Vec<ShapeMismatch>,
+}
+
+impl LoadReport {
+    /// Compares the `expected` Tensor names and shapes to the ones `found` in a StateDict.
+    /// The keys are sorted, so reports can be compared.
+    pub fn compare<'a, E, F>(expected: E, found: F) -> Self
+    where
+        E: IntoIterator<Item = (String, Vec<usize>)>,
+        F: IntoIterator<Item = (&'a String, Vec<usize>)>,
+    {
+        let mut expected: HashMap<String, Vec<usize>> = expected.into_iter().collect();
+        let mut report = LoadReport::default();
+        for (key, found) in found {
+            match expected.remove(key) {
+                Some(expected) if expected != found => report.shape_mismatches.push(ShapeMismatch {
+                    key: key.clone(),
+                    expected,
+                    found,
+                }),
+                Some(_) => {}
+                None => report.unexpected_keys.push(key.clone()),
+            }
+        }
+        report.missing_keys = expected.into_keys().collect();
+        report.sort();
+        report
+    }
+
+    /// Adds the entries of `other`
+    pub fn merge(&mut self, other: LoadReport) {
+        self.missing_keys.extend(other.missing_keys);
+        self.unexpected_keys.extend(other.unexpected_keys);
+        self.shape_mismatches.extend(other.shape_mismatches);
+        self.sort();
+    }
+
+    pub(crate) fn sort(&mut self) {
+        self.missing_keys.sort();
+        self.unexpected_keys.sort();
+        self.shape_mismatches.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+
+    /// Whether the StateDict matched the state exactly
+    pub fn is_clean(&self) -> bool {
+        self.missing_keys.is_empty() && self.unexpected_keys.is_empty() && self.shape_mismatches.is_empty()
+    }
+
+    /// Panics with the report if loading is strict and it is not clean
+    pub(crate) fn check_strict(&self, strict: bool) {
+        assert!(!strict || self.is_clean(), "Error loading the state dict: {}", self);
+    }
+}
+
+impl Display for LoadReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_clean() {
+            return f.write_str("all keys matched");
+        }
+        let mut parts = Vec::new();
+        if !self.missing_keys.is_empty() {
+            parts.push(format!("missing keys {:?}", self.missing_keys));
+        }
+        if !self.unexpected_keys.is_empty() {
+            parts.push(format!("unexpected keys {:?}", self.unexpected_keys));
+        }
+        for mismatch in &self.shape_mismatches {
+            parts.push(format!(
+                "shape mismatch for {}: expected {:?}, found {:?}",
+                mismatch.key,
+                mismatch.expected,
+                mismatch.found
+            ));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+#[test]
+fn load_report_lists_differences() {
+    let expected = vec![
+        ("weight".to_string(), vec![2, 3]),
+        ("bias".to_string(), vec![2]),
+        ("running_mean".to_string(), vec![2]),
+    ];
+    let (weight, extra) = ("weight".to_string(), "extra".to_string());
+    let found = vec![(&weight, vec![3, 2]), (&extra, vec![1])];
+    let report = LoadReport::compare(expected, found);
+    assert_eq!(report.missing_keys, vec!["bias", "running_mean"]);
+    assert_eq!(report.unexpected_keys, vec!["extra"]);
+    assert_eq!(report.shape_mismatches[0].found, vec![3, 2]);
+    assert!(!report.is_clean());
+    assert_eq!(
+        report.to_string(),
+        "missing keys [\"bias\", \"running_mean\"], unexpected keys [\"extra\"], \
+         shape mismatch for weight: expected [2, 3], found [3, 2]"
+    );
+}
@@ -0,0 +1,74 @@
+use crate::data::TensorDataset;
+use crate::serialization::json::invalid_data;
+use crate::{CpuTensor, ShapeStrideTrait};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Loads the training or test split of MNIST, or of a dataset distributed in the same files
+/// such as Fashion-MNIST, from a directory holding the decompressed IDX files, for example
+/// `train-images-idx3-ubyte` and `train-labels-idx1-ubyte`, or `t10k-images-idx3-ubyte` and
+/// `t10k-labels-idx1-ubyte` for the test split. The names with a dot before `idx`, such as
+/// `train-images.idx3-ubyte`, are also found.
+///
+/// The samples are the images, of shape `[28, 28]` and scaled to `[0, 1]`, and their labels,
+/// of shape `[1]`.
+pub fn load_mnist<P: AsRef<Path>>(dir: P, train: bool) -> io::Result<TensorDataset> {
+    let split = if train { "train" } else { "t10k" };
+    let images = read_idx_file(dir.as_ref(), &format!("{}-images", split), "idx3-ubyte")?;
+    let labels = read_idx_file(dir.as_ref(), &format!("{}-labels", split), "idx1-ubyte")?;
+    if images.shape().len() != 3 || labels.shape().len() != 1 || images.shape()[0] != labels.shape()[0] {
+        return invalid_data(format!(
+            "Expected MNIST images of shape [samples, rows, columns] and labels of shape [samples], got {:?} and {:?}",
+            images.shape(),
+            labels.shape()
+        ));
+    }
+    let scaled = images.as_contiguous_vec().into_iter().map(|pixel| pixel / 255.).collect();
+    let images = CpuTensor::from_data_and_shape(scaled, images.shape().iter().copied().collect());
+    Ok(TensorDataset::new(vec![images, labels]))
+}
+
+fn read_idx_file(dir: &Path, name: &str, extension: &str) -> io::Result<CpuTensor> {
+    let candidates: Vec<PathBuf> = ['-', '.']
+        .iter()
+        .map(|separator| dir.join(format!("{}{}{}", name, separator, extension)))
+        .collect();
+    match candidates.iter().find(|path| path.is_file()) {
+        Some(path) => CpuTensor::from_idx(BufReader::new(File::open(path)?)),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Neither {} nor {} exist", candidates[0].display(), candidates[1].display()),
+        )),
+    }
+}
+
+/// Loads a numeric CSV file, see [`CpuTensor::from_csv`], into a Dataset whose samples are
+/// the rows. With a `target_column`, the samples are the other columns and the target, of
+/// shape `[1]`, and otherwise the whole row.
+pub fn load_csv<P: AsRef<Path>>(path: P, has_header: bool, target_column: Option<usize>) -> io::Result<TensorDataset> {
+    let table = CpuTensor::from_csv(BufReader::new(File::open(path)?), has_header)?;
+    let (rows, columns) = (table.shape()[0], table.shape()[1]);
+    let target_column = match target_column {
+        Some(target_column) if target_column < columns && columns > 1 => target_column,
+        Some(target_column) => {
+            return invalid_data(format!(
+                "Can't use the column {} of a CSV with {} columns as the target",
+                target_column, columns
+            ))
+        }
+        None => return Ok(TensorDataset::new(vec![table])),
+    };
+    let data = table.as_contiguous_vec();
+    let mut features = Vec::with_capacity(rows * (columns - 1));
+    let mut targets = Vec::with_capacity(rows);
+    for row in data.chunks(columns) {
+        features.extend_from_slice(&row[..target_column]);
+        features.extend_from_slice(&row[target_column + 1..]);
+        targets.push(row[target_column]);
+    }
+    Ok(TensorDataset::new(vec![
+        CpuTensor::from_data_and_shape(features, vec![rows, columns - 1]),
+        CpuTensor::from_data_and_shape(targets, vec![rows]),
+    ]))
+}
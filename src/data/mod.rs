@@ -0,0 +1,309 @@
+//! Feeding training data to models. A [`Dataset`] gives access to its samples by index, and a
+//! [`DataLoader`] iterates over it in batches, optionally shuffled, stacking the samples into
+//! [`RawTensor`]s.
+//!
+//! The samples are loaded and stacked on the CPU by a background thread which prepares the
+//! next batches while the current one is used, so loading overlaps with the GPU compute.
+//!
+//! MNIST and numeric CSV files can be loaded as Datasets with [`load_mnist`] and [`load_csv`].
+//!
+//! # Examples
+//!
+//! ```
+//! use tensor_compute::data::{DataLoader, TensorDataset};
+//! use tensor_compute::CpuTensor;
+//! let inputs = CpuTensor::from_data_and_shape((0..10).map(|x| x as f32).collect(), vec![5, 2]);
+//! let labels = CpuTensor::from_data_and_shape(vec![0., 1., 0., 1., 1.], vec![5]);
+//! let mut loader = DataLoader::new(TensorDataset::new(vec![inputs, labels]), 2);
+//! loader.shuffle = true;
+//! assert_eq!(loader.len(), 3);
+//! for batch in &mut loader {
+//!     let (inputs, labels) = (&batch[0], &batch[1]);
+//!     assert_eq!(inputs.shape()[1], 2);
+//!     assert_eq!(labels.shape()[0], inputs.shape()[0]);
+//! }
+//! ```
+use crate::random::{self, Generator};
+use crate::{CpuTensor, RawTensor, ShapeStrideTrait};
+use std::panic;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+mod datasets;
+pub use datasets::{load_csv, load_mnist};
+
+#[cfg(test)]
+mod tests;
+
+/// A collection of samples accessed by index. Each sample is made of one or more fields, for
+/// example an input and its label, and every sample has the same number of fields with the
+/// same shapes, so they can be stacked by [`collate`].
+pub trait Dataset {
+    /// The number of samples
+    fn len(&self) -> usize;
+
+    /// Returns the fields of the sample at `index`, which is smaller than [`Dataset::len`]
+    fn get(&self, index: usize) -> Vec<CpuTensor>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A Dataset whose samples are the slices along the first dimension of some Tensors, which
+/// must all have the same first dimension. The samples of one dimensional Tensors, such as
+/// labels, have shape `[1]`.
+pub struct TensorDataset {
+    fields: Vec<(Vec<f32>, Vec<usize>)>,
+    len: usize,
+}
+
+impl TensorDataset {
+    pub fn new(tensors: Vec<CpuTensor>) -> Self {
+        assert!(!tensors.is_empty(), "A TensorDataset needs at least one Tensor");
+        let len = tensors[0].shape()[0];
+        let fields = tensors
+            .iter()
+            .map(|tensor| {
+                assert_eq!(
+                    tensor.shape()[0],
+                    len,
+                    "All the Tensors of a TensorDataset must have the same first dimension"
+                );
+                let sample_shape: Vec<usize> = match tensor.shape().len() {
+                    1 => vec![1],
+                    _ => tensor.shape().iter().skip(1).copied().collect(),
+                };
+                (tensor.as_contiguous_vec(), sample_shape)
+            })
+            .collect();
+        Self { fields, len }
+    }
+}
+
+impl Dataset for TensorDataset {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Vec<CpuTensor> {
+        assert!(index < self.len, "Index {} is out of bounds for a Dataset of length {}", index, self.len);
+        self.fields
+            .iter()
+            .map(|(data, sample_shape)| {
+                let sample_numel: usize = sample_shape.iter().product();
+                let sample = data[index * sample_numel..(index + 1) * sample_numel].to_vec();
+                CpuTensor::from_data_and_shape(sample, sample_shape.clone())
+            })
+            .collect()
+    }
+}
+
+/// Stacks each field of the samples into a Tensor whose first dimension is the number of
+/// samples. Panics if the samples don't have the same fields shapes.
+///
+/// # Examples
+///
+/// ```
+/// use tensor_compute::data::collate;
+/// use tensor_compute::{CpuTensor, ShapeStrideTrait};
+/// let first = vec![CpuTensor::from_data_and_shape(vec![1., 2.], vec![2])];
+/// let second = vec![CpuTensor::from_data_and_shape(vec![3., 4.], vec![2])];
+/// let batch = collate(vec![first, second]);
+/// assert_eq!(batch[0].shape(), &[2, 2]);
+/// assert_eq!(batch[0].as_contiguous_vec(), vec![1., 2., 3., 4.]);
+/// ```
+pub fn collate(samples: Vec<Vec<CpuTensor>>) -> Vec<CpuTensor> {
+    assert!(!samples.is_empty(), "Can't collate an empty batch");
+    let nb_fields = samples[0].len();
+    (0..nb_fields)
+        .map(|field| {
+            let field_shape = samples[0][field].shape().clone();
+            let mut data = Vec::with_capacity(samples.len() * samples[0][field].numel());
+            for sample in &samples {
+                assert_eq!(sample.len(), nb_fields, "All the samples of a batch must have the same number of fields");
+                assert_eq!(
+                    sample[field].shape(),
+                    &field_shape,
+                    "All the samples of a batch must have the same shape for the field {}",
+                    field
+                );
+                data.extend(sample[field].as_contiguous_vec());
+            }
+            let mut shape = vec![samples.len()];
+            shape.extend(field_shape);
+            CpuTensor::from_data_and_shape(data, shape)
+        })
+        .collect()
+}
+
+/// Iterates over a [`Dataset`] in batches of stacked samples, see [`collate`].
+///
+/// Each call to [`DataLoader::iter`] is an epoch. When shuffling, each epoch visits the samples
+/// in a new order drawn from the generator of the DataLoader, so seeding it makes the order
+/// reproducible.
+pub struct DataLoader<D> {
+    dataset: Arc<D>,
+    batch_size: usize,
+    /// Whether each epoch visits the samples in a random order
+    pub shuffle: bool,
+    /// Whether to drop the last batch if it is smaller than the batch size
+    pub drop_last: bool,
+    /// The generator drawing the order of the samples when shuffling
+    pub generator: Generator,
+    /// The number of batches prepared in advance by a background thread. With 0, the
+    /// batches are loaded by the iterating thread when needed.
+    pub prefetch: usize,
+}
+
+impl<D: Dataset + Send + Sync + 'static> DataLoader<D> {
+    /// Creates a DataLoader which does not shuffle, keeps the last batch and prefetches
+    /// two batches
+    pub fn new(dataset: D, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "The batch size must be positive");
+        Self {
+            dataset: Arc::new(dataset),
+            batch_size,
+            shuffle: false,
+            drop_last: false,
+            generator: Generator::default(),
+            prefetch: 2,
+        }
+    }
+
+    pub fn dataset(&self) -> &D {
+        &self.dataset
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// The number of batches of an epoch
+    pub fn len(&self) -> usize {
+        if self.drop_last {
+            self.dataset.len() / self.batch_size
+        } else {
+            self.dataset.len().div_ceil(self.batch_size)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Starts an epoch, returning an iterator over its batches. Each batch holds one Tensor per
+    /// field of the samples.
+    pub fn iter(&mut self) -> Batches {
+        Batches {
+            cpu_batches: self.cpu_batches(),
+        }
+    }
+
+    /// Same as [`DataLoader::iter`], but the batches stay on the CPU
+    pub(crate) fn cpu_batches(&mut self) -> CpuBatches {
+        let mut order: Vec<usize> = if self.shuffle {
+            let (seed, offset) = self.generator.advance();
+            random::permutation(self.dataset.len(), seed, offset)
+        } else {
+            (0..self.dataset.len()).collect()
+        };
+        order.truncate(self.len() * self.batch_size);
+        let batches: Vec<Vec<usize>> = order.chunks(self.batch_size).map(|batch| batch.to_vec()).collect();
+        let dataset = Arc::clone(&self.dataset);
+        let load = move |indices: &[usize]| collate(indices.iter().map(|&index| dataset.get(index)).collect());
+        if self.prefetch == 0 {
+            return CpuBatches::Sequential {
+                batches: batches.into_iter(),
+                load: Box::new(load),
+            };
+        }
+        let (sender, receiver) = mpsc::sync_channel(self.prefetch);
+        let worker = thread::spawn(move || {
+            for indices in batches {
+                // The receiver is gone when the iteration stopped early
+                if sender.send(load(&indices)).is_err() {
+                    return;
+                }
+            }
+        });
+        CpuBatches::Prefetched {
+            receiver: Some(receiver),
+            worker: Some(worker),
+        }
+    }
+}
+
+impl<D: Dataset + Send + Sync + 'static> IntoIterator for &mut DataLoader<D> {
+    type Item = Vec<RawTensor>;
+    type IntoIter = Batches;
+
+    fn into_iter(self) -> Batches {
+        self.iter()
+    }
+}
+
+/// The batches of an epoch, uploaded to the GPU, see [`DataLoader::iter`]
+pub struct Batches {
+    cpu_batches: CpuBatches,
+}
+
+impl Iterator for Batches {
+    type Item = Vec<RawTensor>;
+
+    fn next(&mut self) -> Option<Vec<RawTensor>> {
+        let batch = self.cpu_batches.next()?;
+        Some(batch.iter().map(RawTensor::from_cpu).collect())
+    }
+}
+
+/// Loads and collates the samples of a batch given their indices
+type LoadBatch = Box<dyn Fn(&[usize]) -> Vec<CpuTensor>>;
+
+/// The collated batches of an epoch, either loaded when needed or received from the thread
+/// loading them in advance
+pub(crate) enum CpuBatches {
+    Sequential {
+        batches: std::vec::IntoIter<Vec<usize>>,
+        load: LoadBatch,
+    },
+    Prefetched {
+        receiver: Option<Receiver<Vec<CpuTensor>>>,
+        worker: Option<JoinHandle<()>>,
+    },
+}
+
+impl Iterator for CpuBatches {
+    type Item = Vec<CpuTensor>;
+
+    fn next(&mut self) -> Option<Vec<CpuTensor>> {
+        match self {
+            CpuBatches::Sequential { batches, load } => batches.next().map(|indices| load(&indices)),
+            CpuBatches::Prefetched { receiver, worker } => {
+                if let Some(batch) = receiver.as_ref().and_then(|receiver| receiver.recv().ok()) {
+                    return Some(batch);
+                }
+                // The worker is done, either because the epoch is over or because loading
+                // a sample panicked, in which case the panic is propagated
+                *receiver = None;
+                if let Some(Err(payload)) = worker.take().map(JoinHandle::join) {
+                    panic::resume_unwind(payload);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl Drop for CpuBatches {
+    fn drop(&mut self) {
+        if let CpuBatches::Prefetched { receiver, worker } = self {
+            // Dropping the receiver first unblocks the worker if it is waiting to send
+            *receiver = None;
+            if let Some(worker) = worker.take() {
+                let _ = worker.join();
+            }
+        }
+    }
+}
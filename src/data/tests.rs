@@ -0,0 +1,189 @@
+use crate::data::{collate, load_csv, load_mnist, DataLoader, Dataset, TensorDataset};
+use crate::random::Generator;
+use crate::{CpuTensor, ShapeStrideTrait};
+use std::path::PathBuf;
+use std::{env, fs, io, process};
+
+/// Samples made of their index and its square, which panics at `panic_at`
+struct Squares {
+    len: usize,
+    panic_at: Option<usize>,
+}
+
+impl Dataset for Squares {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Vec<CpuTensor> {
+        assert_ne!(Some(index), self.panic_at, "Failed to load sample {}", index);
+        let x = index as f32;
+        vec![
+            CpuTensor::from_data_and_shape(vec![x], vec![1]),
+            CpuTensor::from_data_and_shape(vec![x, x * x], vec![1, 2]),
+        ]
+    }
+}
+
+fn squares(len: usize) -> Squares {
+    Squares { len, panic_at: None }
+}
+
+/// The first field of each batch, which holds the sample indices
+fn epoch_indices(loader: &mut DataLoader<Squares>) -> Vec<Vec<f32>> {
+    loader.cpu_batches().map(|batch| batch[0].as_contiguous_vec()).collect()
+}
+
+#[test]
+fn tensor_dataset_slices_the_first_dimension() {
+    let inputs = CpuTensor::from_data_and_shape((0..12).map(|x| x as f32).collect(), vec![3, 2, 2]);
+    let labels = CpuTensor::from_data_and_shape(vec![7., 8., 9.], vec![3]);
+    let dataset = TensorDataset::new(vec![inputs, labels]);
+    assert_eq!(dataset.len(), 3);
+    let sample = dataset.get(1);
+    assert_eq!(sample[0].shape(), &[2, 2]);
+    assert_eq!(sample[0].as_contiguous_vec(), vec![4., 5., 6., 7.]);
+    assert_eq!(sample[1].shape(), &[1]);
+    assert_eq!(sample[1].as_contiguous_vec(), vec![8.]);
+}
+
+#[test]
+#[should_panic(expected = "same first dimension")]
+fn tensor_dataset_checks_the_first_dimension() {
+    TensorDataset::new(vec![CpuTensor::from_data_and_shape(vec![0.; 4], vec![2, 2]), CpuTensor::from_data_and_shape(vec![0.; 3], vec![3])]);
+}
+
+#[test]
+fn collate_stacks_each_field() {
+    let batch = collate(vec![squares(4).get(2), squares(4).get(3)]);
+    assert_eq!(batch[0].shape(), &[2, 1]);
+    assert_eq!(batch[0].as_contiguous_vec(), vec![2., 3.]);
+    assert_eq!(batch[1].shape(), &[2, 1, 2]);
+    assert_eq!(batch[1].as_contiguous_vec(), vec![2., 4., 3., 9.]);
+}
+
+#[test]
+#[should_panic(expected = "same shape for the field 0")]
+fn collate_checks_the_shapes() {
+    let first = vec![CpuTensor::from_data_and_shape(vec![1.], vec![1])];
+    let second = vec![CpuTensor::from_data_and_shape(vec![1., 2.], vec![2])];
+    collate(vec![first, second]);
+}
+
+#[test]
+fn batches_keep_the_order_without_shuffling() {
+    for &prefetch in &[0, 1, 3] {
+        let mut loader = DataLoader::new(squares(7), 3);
+        loader.prefetch = prefetch;
+        assert_eq!(loader.len(), 3);
+        assert_eq!(epoch_indices(&mut loader), vec![vec![0., 1., 2.], vec![3., 4., 5.], vec![6.]]);
+        loader.drop_last = true;
+        assert_eq!(loader.len(), 2);
+        assert_eq!(epoch_indices(&mut loader), vec![vec![0., 1., 2.], vec![3., 4., 5.]]);
+    }
+}
+
+#[test]
+fn shuffling_is_reproducible_and_changes_every_epoch() {
+    let mut loader = DataLoader::new(squares(10), 4);
+    loader.shuffle = true;
+    loader.generator = Generator::new(5);
+    let first_epoch = epoch_indices(&mut loader);
+    let second_epoch = epoch_indices(&mut loader);
+    assert_ne!(first_epoch, second_epoch);
+    for epoch in &[&first_epoch, &second_epoch] {
+        assert_eq!(epoch.iter().map(Vec::len).collect::<Vec<usize>>(), vec![4, 4, 2]);
+        let mut indices: Vec<f32> = epoch.concat();
+        indices.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(indices, (0..10).map(|x| x as f32).collect::<Vec<f32>>());
+    }
+
+    loader.generator = Generator::new(5);
+    loader.prefetch = 0;
+    assert_eq!(epoch_indices(&mut loader), first_epoch);
+}
+
+#[test]
+fn stopping_an_epoch_early_stops_the_prefetching() {
+    let mut loader = DataLoader::new(squares(100), 2);
+    loader.prefetch = 1;
+    let mut batches = loader.cpu_batches();
+    assert_eq!(batches.next().unwrap()[0].as_contiguous_vec(), vec![0., 1.]);
+    // Joins the worker, which would hang if it kept waiting to send
+    drop(batches);
+}
+
+#[test]
+#[should_panic(expected = "Failed to load sample 5")]
+fn prefetching_propagates_panics() {
+    let mut loader = DataLoader::new(Squares { len: 8, panic_at: Some(5) }, 2);
+    for _ in loader.cpu_batches() {}
+}
+
+#[test]
+fn batches_are_uploaded() {
+    let mut loader = DataLoader::new(squares(5), 2);
+    let batches: Vec<_> = loader.iter().collect();
+    assert_eq!(batches.len(), 3);
+    assert_eq!(batches[2][0].shape(), &[1, 1]);
+    assert_eq!(batches[1][1].to_vec(), vec![2., 4., 3., 9.]);
+}
+
+/// A directory in the system temporary directory, removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("tensor_compute_{}_{}", name, process::id()));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn write_idx(path: PathBuf, shape: &[u32], data: &[u8]) {
+    let mut bytes = vec![0, 0, 0x08, shape.len() as u8];
+    for dim in shape {
+        bytes.extend_from_slice(&dim.to_be_bytes());
+    }
+    bytes.extend_from_slice(data);
+    fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn loads_mnist_files() {
+    let dir = TempDir::new("mnist");
+    write_idx(dir.0.join("t10k-images-idx3-ubyte"), &[2, 2, 2], &[0, 51, 102, 255, 255, 0, 0, 0]);
+    write_idx(dir.0.join("t10k-labels.idx1-ubyte"), &[2], &[7, 3]);
+    let dataset = load_mnist(&dir.0, false).unwrap();
+    assert_eq!(dataset.len(), 2);
+    let sample = dataset.get(0);
+    assert_eq!(sample[0].shape(), &[2, 2]);
+    assert_eq!(sample[0].as_contiguous_vec(), vec![0., 0.2, 0.4, 1.]);
+    assert_eq!(dataset.get(1)[1].as_contiguous_vec(), vec![3.]);
+
+    let error = load_mnist(&dir.0, true).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    write_idx(dir.0.join("train-images-idx3-ubyte"), &[2, 1, 1], &[0, 1]);
+    write_idx(dir.0.join("train-labels-idx1-ubyte"), &[3], &[0, 1, 2]);
+    assert_eq!(load_mnist(&dir.0, true).err().unwrap().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn loads_csv_files_with_a_target_column() {
+    let dir = TempDir::new("csv");
+    let path = dir.0.join("table.csv");
+    fs::write(&path, "x,label,y\n1,0,2\n3,1,4\n").unwrap();
+    let dataset = load_csv(&path, true, Some(1)).unwrap();
+    assert_eq!(dataset.len(), 2);
+    let sample = dataset.get(1);
+    assert_eq!(sample[0].as_contiguous_vec(), vec![3., 4.]);
+    assert_eq!(sample[1].as_contiguous_vec(), vec![1.]);
+    assert_eq!(load_csv(&path, true, None).unwrap().get(0)[0].as_contiguous_vec(), vec![1., 0., 2.]);
+    assert!(load_csv(&path, true, Some(3)).is_err());
+}
@@ -1,4 +1,6 @@
 use crate::gpu_internals::{GpuInfo, GpuInstance};
+use crate::random::Generator;
+use std::sync::Mutex;
 
 /// Should be used for querying the available GPUs and instantiating a [GpuInstance] in
 /// order to be able to interact with them. Does not need to be kept alive after a [GpuInstance].
@@ -50,6 +52,7 @@ impl GpuFactory {
             device,
             queue,
             info: gpu_info.clone(),
+            rng: Mutex::new(Generator::default()),
         }
     }
 }
@@ -1,3 +1,5 @@
+use crate::random::{Generator, GeneratorState};
+use std::sync::Mutex;
 use wgpu::{AdapterInfo, Device, Queue};
 
 pub mod gpu_buffers;
@@ -12,6 +14,7 @@ pub struct GpuInstance {
     device: Device,
     queue: Queue,
     info: AdapterInfo,
+    rng: Mutex<Generator>,
 }
 
 impl GpuInstance {
@@ -26,4 +29,24 @@ impl GpuInstance {
     pub fn info(&self) -> &AdapterInfo {
         &self.info
     }
+
+    /// Seeds the random number generator of this GPU, resetting its offset
+    pub fn manual_seed(&self, seed: u64) {
+        self.rng.lock().unwrap().manual_seed(seed);
+    }
+
+    /// Returns the state of the random number generator of this GPU, which can be restored
+    /// with [`GpuInstance::set_rng_state`] to replay the following random Ops
+    pub fn rng_state(&self) -> GeneratorState {
+        self.rng.lock().unwrap().get_state()
+    }
+
+    pub fn set_rng_state(&self, state: GeneratorState) {
+        self.rng.lock().unwrap().set_state(state);
+    }
+
+    /// Returns the seed and offset to be used by the next random Op on this GPU
+    pub(crate) fn next_rng_state(&self) -> (u64, u64) {
+        self.rng.lock().unwrap().advance()
+    }
 }
@@ -5,10 +5,20 @@
 //! supporting either `Vulkan`, `Metal` or `DX12`. It can run even on a *Raspberry Pi 4 GPU*.
 //!
 //! The API entry point is the [`Tensor`] structure.
-mod autograd;
+pub mod autograd;
+pub mod checkpoint;
+pub mod data;
+pub mod nn;
+pub mod onnx;
+pub mod optim;
+pub mod loss;
+pub mod random;
+pub mod serialization;
 mod tensors;
 pub use tensors::*;
 pub use gpu_store::*;
+pub use autograd::{Tensor, BackwardOptions};
+pub use random::{manual_seed, Distribution};
 
 mod gpu_internals;
 mod gpu_store;
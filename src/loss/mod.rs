@@ -0,0 +1,110 @@
+//! Loss functions. Each loss is computed by a fused kernel and has a dedicated backward
+//! kernel, which is faster and more numerically stable than composing it from simpler Ops.
+//!
+//! Targets are never differentiated. The gradients of the losses are not differentiable
+//! themselves, so they can't be used with `create_graph`.
+//!
+//! # Examples
+//!
+//! ```
+//! use tensor_compute::loss::{cross_entropy, Reduction};
+//! use tensor_compute::Tensor;
+//! let logits = Tensor::from_data_and_shape(vec![0., 0., 0., 0.], vec![2, 2]);
+//! let loss = cross_entropy(&logits, &[0, 1], Reduction::Mean);
+//! assert!((loss.to_f32() - 2f32.ln()).abs() < 1e-6);
+//! loss.backward();
+//! assert_eq!(logits.grad().unwrap().to_vec(), &[-0.25, 0.25, 0.25, -0.25]);
+//! ```
+use crate::{PointwiseLoss, RawTensor, Tensor};
+
+#[cfg(test)]
+mod tests;
+
+/// How the loss of each element, or sample, is reduced into the returned loss
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Reduction {
+    /// The losses are returned as they are
+    None,
+    /// The average of the losses
+    #[default]
+    Mean,
+    /// The sum of the losses
+    Sum,
+}
+
+fn reduce(losses: Tensor, reduction: Reduction) -> Tensor {
+    match reduction {
+        Reduction::None => losses,
+        Reduction::Mean => {
+            let numel = losses.numel();
+            losses.sum().mul_scalar(1. / numel as f32)
+        }
+        Reduction::Sum => losses.sum(),
+    }
+}
+
+fn pointwise_loss(input: &Tensor, target: &Tensor, loss: PointwiseLoss, reduction: Reduction) -> Tensor {
+    assert_eq!(
+        input.shape(),
+        target.shape(),
+        "The target must have the same shape as the input"
+    );
+    reduce(input.pointwise_loss(target, loss), reduction)
+}
+
+/// Checks the scores have shape `[samples, classes]` and every target is a valid class,
+/// returning the targets as a Tensor
+fn class_targets(input: &Tensor, targets: &[usize]) -> RawTensor {
+    let shape = input.shape();
+    assert_eq!(shape.len(), 2, "The input must have shape [samples, classes], got {:?}", shape);
+    assert_eq!(shape[0], targets.len(), "There must be one target class per sample");
+    let classes = shape[1];
+    let data = targets
+        .iter()
+        .map(|&class| {
+            assert!(class < classes, "Target class {} is out of range for {} classes", class, classes);
+            class as f32
+        })
+        .collect();
+    RawTensor::from_data_and_shape(data, vec![targets.len()])
+}
+
+/// Mean squared error between each element of `input` and `target`: `(x - t)^2`
+pub fn mse_loss(input: &Tensor, target: &Tensor, reduction: Reduction) -> Tensor {
+    pointwise_loss(input, target, PointwiseLoss::SquaredError, reduction)
+}
+
+/// Mean absolute error between each element of `input` and `target`: `|x - t|`
+pub fn l1_loss(input: &Tensor, target: &Tensor, reduction: Reduction) -> Tensor {
+    pointwise_loss(input, target, PointwiseLoss::AbsoluteError, reduction)
+}
+
+/// Squared error for differences smaller than `delta` and absolute error, scaled by `delta`,
+/// otherwise. It is less sensitive to outliers than [`mse_loss`].
+pub fn huber_loss(input: &Tensor, target: &Tensor, delta: f32, reduction: Reduction) -> Tensor {
+    assert!(delta > 0., "delta must be positive");
+    pointwise_loss(input, target, PointwiseLoss::Huber { delta }, reduction)
+}
+
+/// Binary cross entropy between the probabilities given by `sigmoid(input)` and the `target`
+/// probabilities. Computing it from the logits is numerically stable, unlike applying the
+/// sigmoid first.
+pub fn binary_cross_entropy_with_logits(input: &Tensor, target: &Tensor, reduction: Reduction) -> Tensor {
+    pointwise_loss(input, target, PointwiseLoss::BinaryCrossEntropyWithLogits, reduction)
+}
+
+/// Negative log likelihood of the target classes. The input has shape `[samples, classes]`
+/// and holds log probabilities, `targets` has the class index of each sample.
+/// The mean is taken over the samples.
+pub fn nll_loss(input: &Tensor, targets: &[usize], reduction: Reduction) -> Tensor {
+    let targets = class_targets(input, targets);
+    reduce(input.class_loss(targets, false), reduction)
+}
+
+/// Cross entropy between the softmax of the logits in `input`, with shape
+/// `[samples, classes]`, and the target classes. Same as applying the log softmax followed by
+/// [`nll_loss`], but fused into a single stable kernel.
+pub fn cross_entropy(input: &Tensor, targets: &[usize], reduction: Reduction) -> Tensor {
+    let targets = class_targets(input, targets);
+    reduce(input.class_loss(targets, true), reduction)
+}
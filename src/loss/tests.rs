@@ -0,0 +1,76 @@
+use crate::autograd::gradcheck;
+use crate::loss::{
+    binary_cross_entropy_with_logits, cross_entropy, huber_loss, l1_loss, mse_loss, nll_loss, Reduction,
+};
+use crate::{RawTensor, Tensor};
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn pointwise_losses_reduce() {
+    let input = Tensor::from_data_and_shape(vec![0., 1., 4., 2.], vec![2, 2]);
+    let target = Tensor::from_data_and_shape(vec![1., 1., 1., 1.], vec![2, 2]);
+    assert_close(&mse_loss(&input, &target, Reduction::None).to_vec(), &[1., 0., 9., 1.]);
+    assert_close(&mse_loss(&input, &target, Reduction::Sum).to_vec(), &[11.]);
+    assert_close(&mse_loss(&input, &target, Reduction::Mean).to_vec(), &[2.75]);
+    assert_close(&l1_loss(&input, &target, Reduction::Mean).to_vec(), &[1.25]);
+    assert_close(&huber_loss(&input, &target, 1., Reduction::Sum).to_vec(), &[3.5]);
+}
+
+#[test]
+fn mse_backward_works() {
+    let input = Tensor::from_data_and_shape(vec![0., 1., 4., 2.], vec![2, 2]);
+    let target = Tensor::from_data_and_shape(vec![1., 1., 1., 1.], vec![2, 2]);
+    mse_loss(&input, &target, Reduction::Mean).backward();
+    assert_close(&input.grad().unwrap().to_vec(), &[-0.5, 0., 1.5, 0.5]);
+    assert!(target.grad().is_none());
+}
+
+#[test]
+fn pointwise_grads_match_numerical() {
+    let input = RawTensor::from_data_and_shape(vec![-2., -0.3, 0.4, 1.7], vec![4]);
+    let target = Tensor::from_data_and_shape(vec![0., 1., 0.2, 1.], vec![4]);
+    assert!(gradcheck(|x| huber_loss(x, &target, 1., Reduction::Sum), &input, 1e-2, 1e-2));
+    assert!(gradcheck(
+        |x| binary_cross_entropy_with_logits(x, &target, Reduction::Mean),
+        &input,
+        1e-2,
+        1e-2
+    ));
+}
+
+#[test]
+fn cross_entropy_matches_log_softmax_nll() {
+    let logits = vec![1., 2., 3., -1., 0., 4.];
+    let input = Tensor::from_data_and_shape(logits.clone(), vec![2, 3]);
+    let losses = cross_entropy(&input, &[2, 0], Reduction::None).to_vec();
+    let log_probs: Vec<f32> = logits
+        .chunks(3)
+        .flat_map(|row| {
+            let log_sum_exp = row.iter().map(|x| x.exp()).sum::<f32>().ln();
+            row.iter().map(move |x| x - log_sum_exp).collect::<Vec<f32>>()
+        })
+        .collect();
+    assert_close(&losses, &[-log_probs[2], -log_probs[3]]);
+    let log_probs = Tensor::from_data_and_shape(log_probs, vec![2, 3]);
+    assert_close(&nll_loss(&log_probs, &[2, 0], Reduction::None).to_vec(), &losses);
+}
+
+#[test]
+fn cross_entropy_grads_match_numerical() {
+    let input = RawTensor::from_data_and_shape(vec![1., 2., 3., -1., 0., 4.], vec![2, 3]);
+    assert!(gradcheck(|x| cross_entropy(x, &[1, 0], Reduction::Mean), &input, 1e-2, 1e-2));
+    assert!(gradcheck(|x| nll_loss(x, &[1, 0], Reduction::Sum), &input, 1e-2, 1e-2));
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn cross_entropy_checks_classes() {
+    let input = Tensor::from_data_and_shape(vec![0., 0.], vec![1, 2]);
+    cross_entropy(&input, &[2], Reduction::Mean);
+}
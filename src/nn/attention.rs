@@ -0,0 +1,203 @@
+//! Attention layers. The scores are normalized by the fused masked softmax kernel, see
+//! [`RawTensor::masked_softmax`], and the heads are split and merged with reshapes and
+//! transposes of the last two dimensions, so everything is differentiated by autograd.
+use crate::nn::init::xavier_uniform;
+use crate::nn::linear::linear;
+use crate::nn::{prefixed, Linear, Module};
+use crate::{RawTensor, Tensor};
+
+/// Computes `softmax(q k^T / sqrt(dim) + mask) v` for queries of shape `[*, queries, dim]`, keys
+/// of shape `[*, keys, dim]` and values of shape `[*, keys, value_dim]`, the leading dimensions
+/// being the same for the three of them. The output has shape `[*, queries, value_dim]`.
+///
+/// The mask is added to the scaled scores, so keys are masked out with `-inf`, and its shape
+/// must be a suffix of the scores shape `[*, queries, keys]`. When `causal` is set, query `i`
+/// only attends to the keys up to `i`. Queries whose keys are all masked out output zeros.
+///
+/// # Examples
+///
+/// ```
+/// use tensor_compute::nn::scaled_dot_product_attention;
+/// use tensor_compute::Tensor;
+/// let query = Tensor::from_data_and_shape(vec![1., 0., 0., 1.], vec![1, 2, 2]);
+/// let value = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+/// let output = scaled_dot_product_attention(&query, &query, &value, None, true);
+/// assert_eq!(output.shape(), vec![1, 2, 2]);
+/// // The first query can only attend to the first key
+/// assert_eq!(&output.to_vec()[..2], &[1., 2.]);
+/// ```
+pub fn scaled_dot_product_attention(query: &Tensor, key: &Tensor, value: &Tensor, mask: Option<&RawTensor>, causal: bool) -> Tensor {
+    let (query_shape, key_shape, value_shape) = (query.shape(), key.shape(), value.shape());
+    let rank = query_shape.len();
+    assert!(rank >= 2, "The queries must have shape [*, queries, dim], got {:?}", query_shape);
+    let valid = key_shape.len() == rank
+        && value_shape.len() == rank
+        && key_shape[..rank - 2] == query_shape[..rank - 2]
+        && value_shape[..rank - 2] == query_shape[..rank - 2]
+        && key_shape[rank - 1] == query_shape[rank - 1]
+        && value_shape[rank - 2] == key_shape[rank - 2];
+    assert!(
+        valid,
+        "Incompatible shapes for the queries {:?}, the keys {:?} and the values {:?}",
+        query_shape,
+        key_shape,
+        value_shape
+    );
+    let batch = query_shape[..rank - 2].iter().product();
+    let (queries, dim) = (query_shape[rank - 2], query_shape[rank - 1]);
+    let (keys, value_dim) = (key_shape[rank - 2], value_shape[rank - 1]);
+    // The matmul kernel needs rank 3 inputs
+    let as_batch = |tensor: &Tensor, len: usize, dim: usize| {
+        if rank == 3 {
+            tensor.shallow_clone()
+        } else {
+            tensor.reshape(vec![batch, len, dim])
+        }
+    };
+    let scores = as_batch(query, queries, dim).matmul(&as_batch(key, keys, dim).transpose());
+    let mut scores_shape = query_shape.clone();
+    scores_shape[rank - 1] = keys;
+    let scores = if rank == 3 { scores } else { scores.reshape(scores_shape) };
+    let weights = scores.masked_softmax(mask, causal, 1. / (dim as f32).sqrt());
+    let output = as_batch(&weights, queries, keys).matmul(&as_batch(value, keys, value_dim));
+    if rank == 3 {
+        output
+    } else {
+        let mut output_shape = query_shape;
+        output_shape[rank - 1] = value_dim;
+        output.reshape(output_shape)
+    }
+}
+
+/// Multi-head attention over `[batch, len, embed_dim]` sequences. The queries, keys and values
+/// are projected and split into `num_heads` heads of size `embed_dim / num_heads`, each head
+/// attends with [`scaled_dot_product_attention`], and the merged heads go through the output
+/// projection.
+///
+/// The parameters have the names used by PyTorch: the query, key and value projections are
+/// stacked in the rows of `in_proj_weight`, initialized with [`xavier_uniform`], and
+/// `in_proj_bias`, initialized with zeros, like the bias of `out_proj`.
+pub struct MultiheadAttention {
+    pub in_proj_weight: Tensor,
+    pub in_proj_bias: Option<Tensor>,
+    pub out_proj: Linear,
+    num_heads: usize,
+    training: bool,
+}
+
+impl MultiheadAttention {
+    /// Creates a MultiheadAttention layer with biases
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::nn::{Module, MultiheadAttention};
+    /// use tensor_compute::{RawTensor, Tensor};
+    /// let attention = MultiheadAttention::new(8, 2);
+    /// let input = Tensor::from_raw_tensor(RawTensor::rand(vec![3, 5, 8]));
+    /// assert_eq!(attention.forward(&input).shape(), vec![3, 5, 8]);
+    /// ```
+    pub fn new(embed_dim: usize, num_heads: usize) -> Self {
+        Self::new_with_bias(embed_dim, num_heads, true)
+    }
+
+    /// Creates a MultiheadAttention layer, with biases if `bias` is true
+    pub fn new_with_bias(embed_dim: usize, num_heads: usize, bias: bool) -> Self {
+        assert!(
+            num_heads > 0 && embed_dim > 0 && embed_dim.is_multiple_of(num_heads),
+            "The embedding dimension {} must be split evenly into {} heads",
+            embed_dim,
+            num_heads
+        );
+        let mut out_proj = Linear::new_with_bias(embed_dim, embed_dim, bias);
+        out_proj.bias = out_proj.bias.map(|_| Tensor::from_raw_tensor(RawTensor::zeros(vec![embed_dim])));
+        Self {
+            in_proj_weight: Tensor::from_raw_tensor(xavier_uniform(vec![3 * embed_dim, embed_dim], 1., None)),
+            in_proj_bias: if bias { Some(Tensor::from_raw_tensor(RawTensor::zeros(vec![3 * embed_dim]))) } else { None },
+            out_proj,
+            num_heads,
+            training: true,
+        }
+    }
+
+    pub fn embed_dim(&self) -> usize {
+        self.in_proj_weight.shape()[1]
+    }
+
+    pub fn num_heads(&self) -> usize {
+        self.num_heads
+    }
+
+    pub fn head_dim(&self) -> usize {
+        self.embed_dim() / self.num_heads
+    }
+
+    /// Attends from `query`, of shape `[batch, queries, embed_dim]`, to `key` and `value`, of
+    /// shape `[batch, keys, embed_dim]`. The mask has shape `[queries, keys]`, shared by all
+    /// the heads, or `[batch * num_heads, queries, keys]`, see [`scaled_dot_product_attention`].
+    pub fn forward_attention(&self, query: &Tensor, key: &Tensor, value: &Tensor, mask: Option<&RawTensor>, causal: bool) -> Tensor {
+        let embed_dim = self.embed_dim();
+        for input in &[query, key, value] {
+            let shape = input.shape();
+            assert!(
+                shape.len() == 3 && shape[2] == embed_dim,
+                "MultiheadAttention expected inputs of shape [batch, len, {}], got {:?}",
+                embed_dim,
+                shape
+            );
+        }
+        assert_eq!(key.shape(), value.shape(), "The keys and the values must have the same shape");
+        assert_eq!(query.shape()[0], key.shape()[0], "The queries and the keys must have the same batch size");
+        let project = |input: &Tensor, index: usize| {
+            let weight = self.in_proj_weight.narrow(0, index * embed_dim, embed_dim);
+            let bias = self.in_proj_bias.as_ref().map(|bias| bias.narrow(0, index * embed_dim, embed_dim));
+            self.split_heads(&linear(input, &weight, bias.as_ref()))
+        };
+        let heads = scaled_dot_product_attention(&project(query, 0), &project(key, 1), &project(value, 2), mask, causal);
+        self.out_proj.forward(&self.merge_heads(&heads))
+    }
+
+    /// `[batch, len, embed_dim]` to `[batch * num_heads, len, head_dim]`. The transpose moves
+    /// the embedding dimension before the sequence, where it can be reshaped into the heads.
+    fn split_heads(&self, input: &Tensor) -> Tensor {
+        let shape = input.shape();
+        input
+            .transpose()
+            .reshape(vec![shape[0] * self.num_heads, self.head_dim(), shape[1]])
+            .transpose()
+    }
+
+    /// The inverse of [`MultiheadAttention::split_heads`]
+    fn merge_heads(&self, heads: &Tensor) -> Tensor {
+        let shape = heads.shape();
+        heads
+            .transpose()
+            .reshape(vec![shape[0] / self.num_heads, self.embed_dim(), shape[1]])
+            .transpose()
+    }
+}
+
+impl Module for MultiheadAttention {
+    /// Self-attention of a `[batch, len, embed_dim]` input, without mask
+    fn forward(&self, input: &Tensor) -> Tensor {
+        self.forward_attention(input, input, input, None, false)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        let mut parameters = vec![("in_proj_weight".to_string(), self.in_proj_weight.shallow_clone())];
+        if let Some(bias) = &self.in_proj_bias {
+            parameters.push(("in_proj_bias".to_string(), bias.shallow_clone()));
+        }
+        parameters.extend(prefixed("out_proj", self.out_proj.named_parameters()));
+        parameters
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+        self.out_proj.set_training(training);
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
@@ -0,0 +1,185 @@
+use crate::nn::{prefixed, Module};
+use crate::{RawTensor, Tensor};
+
+/// Chains Modules, feeding the output of each one into the next. The parameters of each child
+/// are prefixed by its index, for example `0.weight`.
+///
+/// # Examples
+///
+/// ```
+/// use tensor_compute::nn::{Linear, Module, Sequential};
+/// use tensor_compute::Tensor;
+/// let model = Sequential::new(vec![Box::new(Linear::new(4, 8)), Box::new(Linear::new(8, 2))]);
+/// let input = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 4]);
+/// assert_eq!(model.forward(&input).shape(), vec![1, 2]);
+/// assert_eq!(model.named_parameters()[2].0, "1.weight");
+/// ```
+pub struct Sequential {
+    modules: ModuleList,
+}
+
+impl Sequential {
+    pub fn new(modules: Vec<Box<dyn Module>>) -> Self {
+        let mut list = ModuleList::new();
+        for module in modules {
+            list.push_boxed(module);
+        }
+        Self { modules: list }
+    }
+
+    /// Appends a Module to the end of the chain
+    pub fn push<M: Module + 'static>(&mut self, module: M) {
+        self.modules.push(module);
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&dyn Module> {
+        self.modules.get(idx)
+    }
+}
+
+impl Module for Sequential {
+    /// Returns a shallow clone of the input if there are no Modules
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let mut output = input.shallow_clone();
+        for module in self.modules.iter() {
+            output = module.forward(&output);
+        }
+        output
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        self.modules.named_parameters()
+    }
+
+    fn named_buffers(&self) -> Vec<(String, RawTensor)> {
+        self.modules.named_buffers()
+    }
+
+    fn set_buffer(&mut self, name: &str, value: RawTensor) {
+        self.modules.set_buffer(name, value);
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.modules.set_training(training);
+    }
+
+    fn is_training(&self) -> bool {
+        self.modules.is_training()
+    }
+}
+
+/// Holds Modules in a list, exposing their parameters and buffers through [`Module`] so it can
+/// be nested in other containers. Unlike [`Sequential`], it does not define how the Modules are
+/// connected, which is left to the Module containing it.
+pub struct ModuleList {
+    modules: Vec<Box<dyn Module>>,
+    training: bool,
+}
+
+impl Default for ModuleList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleList {
+    pub fn new() -> Self {
+        Self {
+            modules: vec![],
+            training: true,
+        }
+    }
+
+    /// Appends a Module, which is set to the training mode of the list
+    pub fn push<M: Module + 'static>(&mut self, module: M) {
+        self.push_boxed(Box::new(module));
+    }
+
+    /// Same as [`ModuleList::push`] for an already boxed Module
+    pub fn push_boxed(&mut self, mut module: Box<dyn Module>) {
+        module.set_training(self.training);
+        self.modules.push(module);
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&dyn Module> {
+        self.modules.get(idx).map(|module| module.as_ref())
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut Box<dyn Module>> {
+        self.modules.get_mut(idx)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Module> {
+        self.modules.iter().map(|module| module.as_ref())
+    }
+}
+
+impl Module for ModuleList {
+    /// Panics, since the list does not define how its Modules are connected. The Module
+    /// containing it calls the forward of each child instead.
+    fn forward(&self, _input: &Tensor) -> Tensor {
+        panic!("A ModuleList has no forward, call the forward of its Modules instead");
+    }
+
+    /// The parameters of each Module, prefixed by its index
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, module)| prefixed(&idx.to_string(), module.named_parameters()))
+            .collect()
+    }
+
+    /// The buffers of each Module, prefixed by its index
+    fn named_buffers(&self) -> Vec<(String, RawTensor)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, module)| {
+                module
+                    .named_buffers()
+                    .into_iter()
+                    .map(move |(name, buffer)| (format!("{}.{}", idx, name), buffer))
+            })
+            .collect()
+    }
+
+    /// Replaces the buffer `name` of the Module given by its index prefix
+    fn set_buffer(&mut self, name: &str, value: RawTensor) {
+        let module = name
+            .split_once('.')
+            .and_then(|(idx, name)| idx.parse::<usize>().ok().map(|idx| (idx, name)))
+            .and_then(|(idx, name)| self.modules.get_mut(idx).map(|module| (module, name)));
+        match module {
+            Some((module, name)) => module.set_buffer(name, value),
+            None => panic!("The ModuleList has no buffer named {}", name),
+        }
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+        for module in &mut self.modules {
+            module.set_training(training);
+        }
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
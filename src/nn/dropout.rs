@@ -0,0 +1,45 @@
+use crate::nn::Module;
+use crate::Tensor;
+
+/// Zeroes each element of the input with probability `p` during training, scaling the others
+/// by `1 / (1 - p)`, see [`Tensor::dropout`]. In evaluation mode it is the identity.
+pub struct Dropout {
+    pub p: f32,
+    training: bool,
+}
+
+impl Dropout {
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::nn::{Dropout, Module};
+    /// use tensor_compute::Tensor;
+    /// let mut dropout = Dropout::new(0.5);
+    /// let input = Tensor::from_data_and_shape(vec![1.; 4], vec![2, 2]);
+    /// assert!(dropout.forward(&input).to_vec().iter().all(|&x| x == 0. || x == 2.));
+    /// dropout.eval();
+    /// assert_eq!(dropout.forward(&input).to_vec(), &[1.; 4]);
+    /// ```
+    pub fn new(p: f32) -> Self {
+        assert!((0. ..=1.).contains(&p), "The dropout probability must be in [0, 1], got {}", p);
+        Self { p, training: true }
+    }
+}
+
+impl Module for Dropout {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        input.dropout(self.p, self.training)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        vec![]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
@@ -0,0 +1,58 @@
+use crate::nn::Module;
+use crate::{RawTensor, Tensor};
+
+/// A lookup table of `num_embeddings` vectors of size `embedding_dim`, see
+/// [`Tensor::embedding`]. The input holds the indices, which are not differentiated, and the
+/// output has shape `[*input_shape, embedding_dim]`. The weight is initialized from `N(0, 1)`.
+pub struct Embedding {
+    pub weight: Tensor,
+    training: bool,
+}
+
+impl Embedding {
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::nn::{Embedding, Module};
+    /// use tensor_compute::Tensor;
+    /// let embedding = Embedding::new(10, 3);
+    /// let indices = Tensor::from_data_and_shape(vec![1., 4., 4., 9.], vec![2, 2]);
+    /// assert_eq!(embedding.forward(&indices).shape(), vec![2, 2, 3]);
+    /// ```
+    pub fn new(num_embeddings: usize, embedding_dim: usize) -> Self {
+        assert!(
+            num_embeddings > 0 && embedding_dim > 0,
+            "Embeddings need at least one vector of one dimension"
+        );
+        Self {
+            weight: Tensor::from_raw_tensor(RawTensor::randn(vec![num_embeddings, embedding_dim])),
+            training: true,
+        }
+    }
+
+    pub fn num_embeddings(&self) -> usize {
+        self.weight.shape()[0]
+    }
+
+    pub fn embedding_dim(&self) -> usize {
+        self.weight.shape()[1]
+    }
+}
+
+impl Module for Embedding {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        Tensor::embedding(&input.raw_tensor(), &self.weight)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        vec![("weight".to_string(), self.weight.shallow_clone())]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
@@ -0,0 +1,54 @@
+//! Weight initializers returning freshly sampled Tensors. The fans are computed as in most
+//! frameworks: for a weight of shape `[out, in, k...]`, `fan_in = in * prod(k)` and
+//! `fan_out = out * prod(k)`.
+use crate::random::Generator;
+use crate::RawTensor;
+
+/// Which fan preserves the variance in [`kaiming_normal`]: `FanIn` keeps it in the forward
+/// pass, `FanOut` in the backward pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanMode {
+    FanIn,
+    FanOut,
+}
+
+/// Returns the `(fan_in, fan_out)` of a weight of the given shape, which must have at least
+/// two dimensions
+pub fn fans(shape: &[usize]) -> (usize, usize) {
+    assert!(
+        shape.len() >= 2,
+        "Fans need a weight with at least two dimensions, got the shape {:?}",
+        shape
+    );
+    let receptive_field: usize = shape[2..].iter().product();
+    (shape[1] * receptive_field, shape[0] * receptive_field)
+}
+
+/// Samples from `U(-a, a)` with `a = gain * sqrt(6 / (fan_in + fan_out))`, see "Understanding
+/// the difficulty of training deep feedforward neural networks" by Glorot and Bengio.
+///
+/// # Examples
+///
+/// ```
+/// use tensor_compute::nn::init::xavier_uniform;
+/// use tensor_compute::random::Generator;
+/// let weight = xavier_uniform(vec![4, 2], 1., Some(&mut Generator::new(0)));
+/// assert!(weight.to_vec().iter().all(|value| value.abs() < 1.));
+/// ```
+pub fn xavier_uniform(shape: Vec<usize>, gain: f32, generator: Option<&mut Generator>) -> RawTensor {
+    let (fan_in, fan_out) = fans(&shape);
+    let bound = gain * (6. / (fan_in + fan_out) as f32).sqrt();
+    RawTensor::uniform(shape, -bound, bound, generator)
+}
+
+/// Samples from `N(0, std²)` with `std = gain / sqrt(fan)`, where `gain = sqrt(2 / (1 + a²))`
+/// suits a leaky ReLU of negative slope `a`, see "Delving deep into rectifiers" by He et al.
+pub fn kaiming_normal(shape: Vec<usize>, negative_slope: f32, mode: FanMode, generator: Option<&mut Generator>) -> RawTensor {
+    let (fan_in, fan_out) = fans(&shape);
+    let fan = match mode {
+        FanMode::FanIn => fan_in,
+        FanMode::FanOut => fan_out,
+    };
+    let gain = (2. / (1. + negative_slope * negative_slope)).sqrt();
+    RawTensor::normal(shape, 0., gain / (fan as f32).sqrt(), generator)
+}
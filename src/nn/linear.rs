@@ -0,0 +1,104 @@
+use crate::nn::Module;
+use crate::{RawTensor, Tensor};
+
+/// Applies a linear transformation to the last dimension of the input: `y = x W^T + b`.
+///
+/// The weight has shape `[out_features, in_features]` and the bias `[out_features]`. Both are
+/// initialized from `U(-k, k)` with `k = 1 / sqrt(in_features)`.
+pub struct Linear {
+    pub weight: Tensor,
+    pub bias: Option<Tensor>,
+    in_features: usize,
+    out_features: usize,
+    training: bool,
+}
+
+impl Linear {
+    /// Creates a Linear layer with bias
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::nn::{Linear, Module};
+    /// use tensor_compute::Tensor;
+    /// let linear = Linear::new(3, 2);
+    /// let input = Tensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+    /// assert_eq!(linear.forward(&input).shape(), vec![2, 2]);
+    /// ```
+    pub fn new(in_features: usize, out_features: usize) -> Self {
+        Self::new_with_bias(in_features, out_features, true)
+    }
+
+    /// Creates a Linear layer, with a bias if `bias` is true
+    pub fn new_with_bias(in_features: usize, out_features: usize, bias: bool) -> Self {
+        assert!(
+            in_features > 0 && out_features > 0,
+            "Linear layers need at least one input and one output feature"
+        );
+        let bound = 1. / (in_features as f32).sqrt();
+        let uniform = |shape: Vec<usize>| {
+            Tensor::from_raw_tensor(RawTensor::rand(shape).mul_scalar(2. * bound).sub_scalar(bound))
+        };
+        Self {
+            weight: uniform(vec![out_features, in_features]),
+            bias: if bias { Some(uniform(vec![out_features])) } else { None },
+            in_features,
+            out_features,
+            training: true,
+        }
+    }
+
+    pub fn in_features(&self) -> usize {
+        self.in_features
+    }
+
+    pub fn out_features(&self) -> usize {
+        self.out_features
+    }
+}
+
+impl Module for Linear {
+    /// The input must have shape `[*, in_features]`, the output has shape `[*, out_features]`
+    fn forward(&self, input: &Tensor) -> Tensor {
+        linear(input, &self.weight, self.bias.as_ref())
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        let mut parameters = vec![("weight".to_string(), self.weight.shallow_clone())];
+        if let Some(bias) = &self.bias {
+            parameters.push(("bias".to_string(), bias.shallow_clone()));
+        }
+        parameters
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
+
+/// Computes `x W^T + b` over the last dimension of `input`, `weight` having shape
+/// `[out_features, in_features]`
+pub(crate) fn linear(input: &Tensor, weight: &Tensor, bias: Option<&Tensor>) -> Tensor {
+    let (out_features, in_features) = (weight.shape()[0], weight.shape()[1]);
+    let mut shape = input.shape();
+    assert_eq!(
+        shape.last(),
+        Some(&in_features),
+        "Linear layer expected {} input features, but got an input of shape {:?}",
+        in_features,
+        shape
+    );
+    let rows = input.numel() / in_features;
+    let weight = weight.reshape(vec![1, out_features, in_features]).transpose();
+    let output = input.reshape(vec![1, rows, in_features]).matmul(&weight);
+    *shape.last_mut().unwrap() = out_features;
+    let output = output.reshape(shape);
+    match bias {
+        Some(bias) => output.add(bias),
+        None => output,
+    }
+}
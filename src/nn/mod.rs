@@ -0,0 +1,129 @@
+//! Building blocks for Neural Networks. Layers implement the [`Module`] trait, which gives
+//! access to their parameters, and can be composed using [`Sequential`] and [`ModuleList`].
+use crate::checkpoint::{LoadReport, StateDict};
+use crate::{RawTensor, Tensor};
+
+mod attention;
+mod containers;
+mod dropout;
+mod embedding;
+pub mod init;
+mod linear;
+mod normalization;
+mod recurrent;
+pub use attention::{scaled_dot_product_attention, MultiheadAttention};
+pub use containers::{ModuleList, Sequential};
+pub use dropout::Dropout;
+pub use embedding::Embedding;
+pub use linear::Linear;
+pub use normalization::{BatchNorm, GroupNorm, LayerNorm};
+pub use recurrent::{GRUCell, LSTMCell, RNNCell, Recurrent, RecurrentCell, GRU, LSTM, RNN};
+
+#[cfg(test)]
+mod tests;
+
+/// A layer or composition of layers of a Neural Network
+pub trait Module {
+    /// Computes the output of the Module for the given input, recording the Ops so the
+    /// parameters gradients can be computed
+    fn forward(&self, input: &Tensor) -> Tensor;
+
+    /// Returns the parameters of the Module and of its children. Names of children parameters
+    /// are prefixed by the child name and a dot, for example `0.weight`.
+    fn named_parameters(&self) -> Vec<(String, Tensor)>;
+
+    /// Returns the parameters of the Module and of its children, in the same order as
+    /// [`Module::named_parameters`]. The returned Tensors share their data with the Module.
+    fn parameters(&self) -> Vec<Tensor> {
+        self.named_parameters()
+            .into_iter()
+            .map(|(_name, parameter)| parameter)
+            .collect()
+    }
+
+    /// Sets the Module and its children into training or evaluation mode. Some Modules,
+    /// such as Dropout, behave differently in each mode.
+    fn set_training(&mut self, training: bool);
+
+    /// Whether the Module is in training mode, which is the default
+    fn is_training(&self) -> bool;
+
+    /// Same as `set_training(true)`
+    fn train(&mut self) {
+        self.set_training(true);
+    }
+
+    /// Same as `set_training(false)`
+    fn eval(&mut self) {
+        self.set_training(false);
+    }
+
+    /// Returns copies of the state of the Module and of its children which is not made of
+    /// parameters, such as the running statistics of [`BatchNorm`]. They are named like the
+    /// parameters.
+    fn named_buffers(&self) -> Vec<(String, RawTensor)> {
+        vec![]
+    }
+
+    /// Replaces the buffer `name` returned by [`Module::named_buffers`]. Panics if there is
+    /// no such buffer.
+    fn set_buffer(&mut self, name: &str, _value: RawTensor) {
+        panic!("The Module has no buffer named {}", name);
+    }
+
+    /// Returns copies of the parameters and buffers, which can be saved to resume training
+    fn state_dict(&self) -> StateDict {
+        let mut state_dict = StateDict::new();
+        for (name, parameter) in self.named_parameters() {
+            state_dict.tensors.insert(name, parameter.raw_tensor());
+        }
+        state_dict.tensors.extend(self.named_buffers());
+        state_dict
+    }
+
+    /// Copies the parameters and buffers of `state_dict` into the Module. The parameters keep
+    /// sharing their data with the optimizers holding them.
+    ///
+    /// Returns the keys which are missing or unexpected and the shape mismatches. When
+    /// `strict` is set, panics if there are any, before changing anything. Otherwise the
+    /// matching entries are loaded and the others are left unchanged.
+    fn load_state_dict(&mut self, state_dict: &StateDict, strict: bool) -> LoadReport {
+        let parameters = self.named_parameters();
+        let buffers = self.named_buffers();
+        let expected = parameters
+            .iter()
+            .map(|(name, parameter)| (name.clone(), parameter.shape()))
+            .chain(buffers.iter().map(|(name, buffer)| (name.clone(), buffer.shape().iter().copied().collect())));
+        let found = state_dict
+            .tensors
+            .iter()
+            .map(|(name, tensor)| (name, tensor.shape().iter().copied().collect()));
+        let report = LoadReport::compare(expected, found);
+        report.check_strict(strict);
+        let matches = |name: &String| {
+            state_dict
+                .tensors
+                .get(name)
+                .filter(|_| !report.shape_mismatches.iter().any(|mismatch| &mismatch.key == name))
+        };
+        for (name, parameter) in &parameters {
+            if let Some(value) = matches(name) {
+                parameter.set_data(value.clone());
+            }
+        }
+        for (name, _) in &buffers {
+            if let Some(value) = matches(name) {
+                self.set_buffer(name, value.clone());
+            }
+        }
+        report
+    }
+}
+
+/// Prefixes the parameters names with `prefix` and a dot
+pub(crate) fn prefixed(prefix: &str, named_parameters: Vec<(String, Tensor)>) -> Vec<(String, Tensor)> {
+    named_parameters
+        .into_iter()
+        .map(|(name, parameter)| (format!("{}.{}", prefix, name), parameter))
+        .collect()
+}
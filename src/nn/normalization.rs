@@ -0,0 +1,282 @@
+use crate::nn::Module;
+use crate::{RawTensor, Tensor};
+use std::sync::Mutex;
+
+/// The affine parameters of a normalization layer: a weight of ones and a bias of zeros
+fn affine_parameters(shape: Vec<usize>, affine: bool) -> (Option<Tensor>, Option<Tensor>) {
+    if !affine {
+        return (None, None);
+    }
+    let weight = Tensor::from_raw_tensor(RawTensor::filled(shape.clone(), 1.));
+    let bias = Tensor::from_raw_tensor(RawTensor::zeros(shape));
+    (Some(weight), Some(bias))
+}
+
+fn named_affine_parameters(weight: &Option<Tensor>, bias: &Option<Tensor>) -> Vec<(String, Tensor)> {
+    let mut parameters = vec![];
+    if let Some(weight) = weight {
+        parameters.push(("weight".to_string(), weight.shallow_clone()));
+    }
+    if let Some(bias) = bias {
+        parameters.push(("bias".to_string(), bias.shallow_clone()));
+    }
+    parameters
+}
+
+/// Batch normalization over the channels of `[batch, channels, *]` inputs, see
+/// [`Tensor::batch_norm`].
+///
+/// In training mode each channel is normalized with the statistics of the batch, which also
+/// update the running statistics. In evaluation mode the running statistics are used instead.
+pub struct BatchNorm {
+    pub weight: Option<Tensor>,
+    pub bias: Option<Tensor>,
+    pub eps: f32,
+    pub momentum: f32,
+    /// The running mean and variance, starting as zeros and ones
+    running_stats: Mutex<(RawTensor, RawTensor)>,
+    num_features: usize,
+    training: bool,
+}
+
+impl BatchNorm {
+    /// Creates a BatchNorm layer with affine parameters, `eps = 1e-5` and `momentum = 0.1`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::nn::{BatchNorm, Module};
+    /// use tensor_compute::Tensor;
+    /// let mut batch_norm = BatchNorm::new(2);
+    /// let input = Tensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![2, 2]);
+    /// assert_eq!(batch_norm.forward(&input).shape(), vec![2, 2]);
+    /// assert_eq!(batch_norm.running_mean().to_vec(), &[0.2, 0.3]);
+    /// batch_norm.eval();
+    /// batch_norm.forward(&input);
+    /// assert_eq!(batch_norm.running_mean().to_vec(), &[0.2, 0.3]);
+    /// ```
+    pub fn new(num_features: usize) -> Self {
+        Self::new_with_affine(num_features, true)
+    }
+
+    /// Creates a BatchNorm layer, with affine parameters if `affine` is true
+    pub fn new_with_affine(num_features: usize, affine: bool) -> Self {
+        assert!(num_features > 0, "BatchNorm layers need at least one feature");
+        let (weight, bias) = affine_parameters(vec![num_features], affine);
+        Self {
+            weight,
+            bias,
+            eps: 1e-5,
+            momentum: 0.1,
+            running_stats: Mutex::new((
+                RawTensor::zeros(vec![num_features]),
+                RawTensor::filled(vec![num_features], 1.),
+            )),
+            num_features,
+            training: true,
+        }
+    }
+
+    pub fn num_features(&self) -> usize {
+        self.num_features
+    }
+
+    pub fn running_mean(&self) -> RawTensor {
+        self.running_stats.lock().unwrap().0.clone()
+    }
+
+    pub fn running_var(&self) -> RawTensor {
+        self.running_stats.lock().unwrap().1.clone()
+    }
+
+    /// Replaces the running mean and variance, both of shape `[num_features]`
+    pub fn set_running_stats(&mut self, mean: RawTensor, var: RawTensor) {
+        let shape = [self.num_features];
+        assert!(
+            mean.shape() == &shape && var.shape() == &shape,
+            "The running statistics must have shape {:?}",
+            shape
+        );
+        *self.running_stats.get_mut().unwrap() = (mean, var);
+    }
+}
+
+impl Module for BatchNorm {
+    /// The input must have shape `[batch, num_features, *]`
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let shape = input.shape();
+        assert!(
+            shape.len() >= 2 && shape[1] == self.num_features,
+            "BatchNorm layer expected {} features, but got an input of shape {:?}",
+            self.num_features,
+            shape
+        );
+        let mut running_stats = self.running_stats.lock().unwrap();
+        let (running_mean, running_var) = &mut *running_stats;
+        input.batch_norm(
+            Some((running_mean, running_var)),
+            self.weight.as_ref(),
+            self.bias.as_ref(),
+            self.training,
+            self.momentum,
+            self.eps,
+        )
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        named_affine_parameters(&self.weight, &self.bias)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+
+    /// The running statistics, named `running_mean` and `running_var` as in PyTorch
+    fn named_buffers(&self) -> Vec<(String, RawTensor)> {
+        vec![
+            ("running_mean".to_string(), self.running_mean()),
+            ("running_var".to_string(), self.running_var()),
+        ]
+    }
+
+    fn set_buffer(&mut self, name: &str, value: RawTensor) {
+        let (mean, var) = match name {
+            "running_mean" => (value, self.running_var()),
+            "running_var" => (self.running_mean(), value),
+            _ => panic!("BatchNorm has no buffer named {}", name),
+        };
+        self.set_running_stats(mean, var);
+    }
+}
+
+/// Layer normalization over the trailing `normalized_shape` dimensions of the input, see
+/// [`Tensor::layer_norm`]. The affine parameters have shape `normalized_shape`.
+pub struct LayerNorm {
+    pub weight: Option<Tensor>,
+    pub bias: Option<Tensor>,
+    pub eps: f32,
+    normalized_shape: Vec<usize>,
+    training: bool,
+}
+
+impl LayerNorm {
+    /// Creates a LayerNorm layer with affine parameters and `eps = 1e-5`
+    pub fn new(normalized_shape: Vec<usize>) -> Self {
+        Self::new_with_affine(normalized_shape, true)
+    }
+
+    /// Creates a LayerNorm layer, with affine parameters if `affine` is true
+    pub fn new_with_affine(normalized_shape: Vec<usize>, affine: bool) -> Self {
+        assert!(
+            !normalized_shape.is_empty() && normalized_shape.iter().all(|&dim| dim > 0),
+            "Invalid normalized shape {:?}",
+            normalized_shape
+        );
+        let (weight, bias) = affine_parameters(normalized_shape.clone(), affine);
+        Self {
+            weight,
+            bias,
+            eps: 1e-5,
+            normalized_shape,
+            training: true,
+        }
+    }
+
+    pub fn normalized_shape(&self) -> &[usize] {
+        &self.normalized_shape
+    }
+}
+
+impl Module for LayerNorm {
+    /// The input must have shape `[*, normalized_shape]`
+    fn forward(&self, input: &Tensor) -> Tensor {
+        input.layer_norm(&self.normalized_shape, self.weight.as_ref(), self.bias.as_ref(), self.eps)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        named_affine_parameters(&self.weight, &self.bias)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
+
+/// Group normalization of `[batch, channels, *]` inputs, with the channels split into
+/// `num_groups` groups, see [`Tensor::group_norm`]. The affine parameters are per channel.
+pub struct GroupNorm {
+    pub weight: Option<Tensor>,
+    pub bias: Option<Tensor>,
+    pub eps: f32,
+    num_groups: usize,
+    num_channels: usize,
+    training: bool,
+}
+
+impl GroupNorm {
+    /// Creates a GroupNorm layer with affine parameters and `eps = 1e-5`
+    pub fn new(num_groups: usize, num_channels: usize) -> Self {
+        Self::new_with_affine(num_groups, num_channels, true)
+    }
+
+    /// Creates a GroupNorm layer, with affine parameters if `affine` is true
+    pub fn new_with_affine(num_groups: usize, num_channels: usize, affine: bool) -> Self {
+        assert!(
+            num_groups > 0 && num_channels.is_multiple_of(num_groups),
+            "The {} channels must be divisible by the {} groups",
+            num_channels,
+            num_groups
+        );
+        let (weight, bias) = affine_parameters(vec![num_channels], affine);
+        Self {
+            weight,
+            bias,
+            eps: 1e-5,
+            num_groups,
+            num_channels,
+            training: true,
+        }
+    }
+
+    pub fn num_groups(&self) -> usize {
+        self.num_groups
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+}
+
+impl Module for GroupNorm {
+    /// The input must have shape `[batch, num_channels, *]`
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let shape = input.shape();
+        assert!(
+            shape.len() >= 2 && shape[1] == self.num_channels,
+            "GroupNorm layer expected {} channels, but got an input of shape {:?}",
+            self.num_channels,
+            shape
+        );
+        input.group_norm(self.num_groups, self.weight.as_ref(), self.bias.as_ref(), self.eps)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        named_affine_parameters(&self.weight, &self.bias)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
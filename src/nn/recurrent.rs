@@ -0,0 +1,490 @@
+//! Recurrent layers. The cells compute one time step from matmuls and element-wise Ops, and
+//! [`Recurrent`] unrolls them over the sequence, so backpropagation through time is done by
+//! autograd.
+//!
+//! The gates are stacked in the rows of the weights, in the order and with the parameter
+//! names used by PyTorch, so weights can be exchanged with it.
+use crate::nn::linear::linear;
+use crate::nn::Module;
+use crate::{RawTensor, Tensor};
+
+/// The input to hidden and hidden to hidden weights and biases of a cell with `gates` gates,
+/// initialized from `U(-k, k)` with `k = 1 / sqrt(hidden_size)`
+fn init_weights(input_size: usize, hidden_size: usize, gates: usize, bias: bool) -> (Tensor, Tensor, Option<Tensor>, Option<Tensor>) {
+    assert!(
+        input_size > 0 && hidden_size > 0,
+        "Recurrent cells need at least one input and one hidden feature"
+    );
+    let bound = 1. / (hidden_size as f32).sqrt();
+    let uniform = |shape: Vec<usize>| Tensor::from_raw_tensor(RawTensor::uniform(shape, -bound, bound, None));
+    let bias = |gates: usize| if bias { Some(uniform(vec![gates * hidden_size])) } else { None };
+    (
+        uniform(vec![gates * hidden_size, input_size]),
+        uniform(vec![gates * hidden_size, hidden_size]),
+        bias(gates),
+        bias(gates),
+    )
+}
+
+fn cell_parameters(weight_ih: &Tensor, weight_hh: &Tensor, bias_ih: &Option<Tensor>, bias_hh: &Option<Tensor>) -> Vec<(String, Tensor)> {
+    let mut parameters = vec![
+        ("weight_ih".to_string(), weight_ih.shallow_clone()),
+        ("weight_hh".to_string(), weight_hh.shallow_clone()),
+    ];
+    if let Some(bias) = bias_ih {
+        parameters.push(("bias_ih".to_string(), bias.shallow_clone()));
+    }
+    if let Some(bias) = bias_hh {
+        parameters.push(("bias_hh".to_string(), bias.shallow_clone()));
+    }
+    parameters
+}
+
+/// Checks the input is `[batch, input_size]` and returns the given state, or zeros
+fn state_or_zeros(input: &Tensor, input_size: usize, hidden_size: usize, state: Option<&Tensor>) -> Tensor {
+    let shape = input.shape();
+    assert!(
+        shape.len() == 2 && shape[1] == input_size,
+        "Recurrent cell expected an input of shape [batch, {}], got {:?}",
+        input_size,
+        shape
+    );
+    match state {
+        Some(state) => {
+            assert_eq!(state.shape(), vec![shape[0], hidden_size], "The state must have shape [batch, hidden_size]");
+            state.shallow_clone()
+        }
+        None => Tensor::from_raw_tensor(RawTensor::zeros(vec![shape[0], hidden_size])),
+    }
+}
+
+/// Returns the gate `index` of the stacked gates of each sample
+fn gate(gates: &Tensor, index: usize, hidden_size: usize) -> Tensor {
+    gates.narrow(1, index * hidden_size, hidden_size)
+}
+
+/// Elman RNN cell: `h' = tanh(x W_ih^T + b_ih + h W_hh^T + b_hh)`
+pub struct RNNCell {
+    pub weight_ih: Tensor,
+    pub weight_hh: Tensor,
+    pub bias_ih: Option<Tensor>,
+    pub bias_hh: Option<Tensor>,
+    training: bool,
+}
+
+impl RNNCell {
+    /// Creates an RNN cell with biases
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::nn::RNNCell;
+    /// use tensor_compute::Tensor;
+    /// let cell = RNNCell::new(3, 2);
+    /// let input = Tensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+    /// let hidden = cell.step(&input, None);
+    /// assert_eq!(cell.step(&input, Some(&hidden)).shape(), vec![2, 2]);
+    /// ```
+    pub fn new(input_size: usize, hidden_size: usize) -> Self {
+        Self::new_with_bias(input_size, hidden_size, true)
+    }
+
+    pub fn new_with_bias(input_size: usize, hidden_size: usize, bias: bool) -> Self {
+        let (weight_ih, weight_hh, bias_ih, bias_hh) = init_weights(input_size, hidden_size, 1, bias);
+        Self {
+            weight_ih,
+            weight_hh,
+            bias_ih,
+            bias_hh,
+            training: true,
+        }
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.weight_ih.shape()[1]
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.weight_hh.shape()[1]
+    }
+
+    /// Computes the next hidden state of a `[batch, input_size]` input, the hidden state
+    /// being zero if not given
+    pub fn step(&self, input: &Tensor, hidden: Option<&Tensor>) -> Tensor {
+        let hidden = state_or_zeros(input, self.input_size(), self.hidden_size(), hidden);
+        linear(input, &self.weight_ih, self.bias_ih.as_ref())
+            .add(&linear(&hidden, &self.weight_hh, self.bias_hh.as_ref()))
+            .tanh()
+    }
+}
+
+/// Long short-term memory cell. With `gates = x W_ih^T + b_ih + h W_hh^T + b_hh` split into
+/// the input, forget, cell and output gates `i, f, g, o`:
+/// `c' = sigmoid(f) * c + sigmoid(i) * tanh(g)` and `h' = sigmoid(o) * tanh(c')`
+pub struct LSTMCell {
+    pub weight_ih: Tensor,
+    pub weight_hh: Tensor,
+    pub bias_ih: Option<Tensor>,
+    pub bias_hh: Option<Tensor>,
+    training: bool,
+}
+
+impl LSTMCell {
+    /// Creates an LSTM cell with biases
+    pub fn new(input_size: usize, hidden_size: usize) -> Self {
+        Self::new_with_bias(input_size, hidden_size, true)
+    }
+
+    pub fn new_with_bias(input_size: usize, hidden_size: usize, bias: bool) -> Self {
+        let (weight_ih, weight_hh, bias_ih, bias_hh) = init_weights(input_size, hidden_size, 4, bias);
+        Self {
+            weight_ih,
+            weight_hh,
+            bias_ih,
+            bias_hh,
+            training: true,
+        }
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.weight_ih.shape()[1]
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.weight_hh.shape()[1]
+    }
+
+    /// Computes the next hidden and cell states of a `[batch, input_size]` input, the states
+    /// being zero if not given
+    pub fn step(&self, input: &Tensor, state: Option<(&Tensor, &Tensor)>) -> (Tensor, Tensor) {
+        let hidden_size = self.hidden_size();
+        let hidden = state_or_zeros(input, self.input_size(), hidden_size, state.map(|state| state.0));
+        let cell = state_or_zeros(input, self.input_size(), hidden_size, state.map(|state| state.1));
+        let gates = linear(input, &self.weight_ih, self.bias_ih.as_ref())
+            .add(&linear(&hidden, &self.weight_hh, self.bias_hh.as_ref()));
+        let input_gate = gate(&gates, 0, hidden_size).sigmoid();
+        let forget_gate = gate(&gates, 1, hidden_size).sigmoid();
+        let cell_gate = gate(&gates, 2, hidden_size).tanh();
+        let output_gate = gate(&gates, 3, hidden_size).sigmoid();
+        let cell = forget_gate.dot_mul(&cell).add(&input_gate.dot_mul(&cell_gate));
+        let hidden = output_gate.dot_mul(&cell.tanh());
+        (hidden, cell)
+    }
+}
+
+/// Gated recurrent unit cell. With the reset, update and new gates `r, z, n`:
+/// `r = sigmoid(x W_ir^T + b_ir + h W_hr^T + b_hr)`, `z` likewise,
+/// `n = tanh(x W_in^T + b_in + r * (h W_hn^T + b_hn))` and `h' = (1 - z) * n + z * h`
+pub struct GRUCell {
+    pub weight_ih: Tensor,
+    pub weight_hh: Tensor,
+    pub bias_ih: Option<Tensor>,
+    pub bias_hh: Option<Tensor>,
+    training: bool,
+}
+
+impl GRUCell {
+    /// Creates a GRU cell with biases
+    pub fn new(input_size: usize, hidden_size: usize) -> Self {
+        Self::new_with_bias(input_size, hidden_size, true)
+    }
+
+    pub fn new_with_bias(input_size: usize, hidden_size: usize, bias: bool) -> Self {
+        let (weight_ih, weight_hh, bias_ih, bias_hh) = init_weights(input_size, hidden_size, 3, bias);
+        Self {
+            weight_ih,
+            weight_hh,
+            bias_ih,
+            bias_hh,
+            training: true,
+        }
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.weight_ih.shape()[1]
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.weight_hh.shape()[1]
+    }
+
+    /// Computes the next hidden state of a `[batch, input_size]` input, the hidden state
+    /// being zero if not given
+    pub fn step(&self, input: &Tensor, hidden: Option<&Tensor>) -> Tensor {
+        let hidden_size = self.hidden_size();
+        let hidden = state_or_zeros(input, self.input_size(), hidden_size, hidden);
+        let input_gates = linear(input, &self.weight_ih, self.bias_ih.as_ref());
+        let hidden_gates = linear(&hidden, &self.weight_hh, self.bias_hh.as_ref());
+        let gate_sum = |index: usize| gate(&input_gates, index, hidden_size).add(&gate(&hidden_gates, index, hidden_size));
+        let reset_gate = gate_sum(0).sigmoid();
+        let update_gate = gate_sum(1).sigmoid();
+        let new_gate = gate(&input_gates, 2, hidden_size)
+            .add(&reset_gate.dot_mul(&gate(&hidden_gates, 2, hidden_size)))
+            .tanh();
+        // (1 - z) * n + z * h == n + z * (h - n)
+        new_gate.add(&update_gate.dot_mul(&hidden.add(&new_gate.mul_scalar(-1.))))
+    }
+}
+
+/// A cell unrolled by [`Recurrent`]. Its state is a list of `[batch, hidden_size]` Tensors,
+/// the first one being the hidden state, which is also the output of the cell.
+pub trait RecurrentCell: Module {
+    /// The number of Tensors in the state
+    const STATE_LEN: usize;
+
+    fn new_cell(input_size: usize, hidden_size: usize, bias: bool) -> Self;
+
+    fn cell_hidden_size(&self) -> usize;
+
+    /// Computes the next state, which is zero if not given
+    fn step_state(&self, input: &Tensor, state: Option<&[Tensor]>) -> Vec<Tensor>;
+}
+
+macro_rules! impl_module_for_cell {
+    ($cell:ident) => {
+        impl Module for $cell {
+            /// Computes the next hidden state of a `[batch, input_size]` input from a zero state
+            fn forward(&self, input: &Tensor) -> Tensor {
+                self.step_state(input, None).remove(0)
+            }
+
+            fn named_parameters(&self) -> Vec<(String, Tensor)> {
+                cell_parameters(&self.weight_ih, &self.weight_hh, &self.bias_ih, &self.bias_hh)
+            }
+
+            fn set_training(&mut self, training: bool) {
+                self.training = training;
+            }
+
+            fn is_training(&self) -> bool {
+                self.training
+            }
+        }
+    };
+}
+
+impl_module_for_cell!(RNNCell);
+impl_module_for_cell!(LSTMCell);
+impl_module_for_cell!(GRUCell);
+
+impl RecurrentCell for RNNCell {
+    const STATE_LEN: usize = 1;
+
+    fn new_cell(input_size: usize, hidden_size: usize, bias: bool) -> Self {
+        Self::new_with_bias(input_size, hidden_size, bias)
+    }
+
+    fn cell_hidden_size(&self) -> usize {
+        self.hidden_size()
+    }
+
+    fn step_state(&self, input: &Tensor, state: Option<&[Tensor]>) -> Vec<Tensor> {
+        vec![self.step(input, state.map(|state| &state[0]))]
+    }
+}
+
+impl RecurrentCell for LSTMCell {
+    const STATE_LEN: usize = 2;
+
+    fn new_cell(input_size: usize, hidden_size: usize, bias: bool) -> Self {
+        Self::new_with_bias(input_size, hidden_size, bias)
+    }
+
+    fn cell_hidden_size(&self) -> usize {
+        self.hidden_size()
+    }
+
+    fn step_state(&self, input: &Tensor, state: Option<&[Tensor]>) -> Vec<Tensor> {
+        let (hidden, cell) = self.step(input, state.map(|state| (&state[0], &state[1])));
+        vec![hidden, cell]
+    }
+}
+
+impl RecurrentCell for GRUCell {
+    const STATE_LEN: usize = 1;
+
+    fn new_cell(input_size: usize, hidden_size: usize, bias: bool) -> Self {
+        Self::new_with_bias(input_size, hidden_size, bias)
+    }
+
+    fn cell_hidden_size(&self) -> usize {
+        self.hidden_size()
+    }
+
+    fn step_state(&self, input: &Tensor, state: Option<&[Tensor]>) -> Vec<Tensor> {
+        vec![self.step(input, state.map(|state| &state[0]))]
+    }
+}
+
+/// Multi-layer, optionally bidirectional, recurrent layer unrolling a [`RecurrentCell`] over
+/// a `[seq_len, batch, input_size]` input. The output has shape
+/// `[seq_len, batch, num_directions * hidden_size]`, the hidden states of both directions
+/// being concatenated, and each layer takes the output of the previous one as input.
+///
+/// The states have shape `[num_layers * num_directions, batch, hidden_size]`, ordered by layer
+/// then direction. The parameters of layer `k` are suffixed by `_lk`, and by `_lk_reverse` for
+/// the reverse direction.
+pub struct Recurrent<C: RecurrentCell> {
+    cells: Vec<C>,
+    num_layers: usize,
+    bidirectional: bool,
+    training: bool,
+}
+
+pub type RNN = Recurrent<RNNCell>;
+pub type LSTM = Recurrent<LSTMCell>;
+pub type GRU = Recurrent<GRUCell>;
+
+impl<C: RecurrentCell> Recurrent<C> {
+    /// Creates a recurrent layer with biases
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::nn::{Module, LSTM};
+    /// use tensor_compute::RawTensor;
+    /// use tensor_compute::Tensor;
+    /// let lstm = LSTM::new(3, 4, 2, true);
+    /// let input = Tensor::from_raw_tensor(RawTensor::rand(vec![5, 2, 3]));
+    /// let (output, state) = lstm.forward_with_state(&input, None);
+    /// assert_eq!(output.shape(), vec![5, 2, 8]);
+    /// // The hidden and cell states of both layers and directions
+    /// assert_eq!(state.len(), 2);
+    /// assert_eq!(state[1].shape(), vec![4, 2, 4]);
+    /// ```
+    pub fn new(input_size: usize, hidden_size: usize, num_layers: usize, bidirectional: bool) -> Self {
+        Self::new_with_bias(input_size, hidden_size, num_layers, bidirectional, true)
+    }
+
+    pub fn new_with_bias(input_size: usize, hidden_size: usize, num_layers: usize, bidirectional: bool, bias: bool) -> Self {
+        assert!(num_layers > 0, "Recurrent layers need at least one layer");
+        let num_directions = if bidirectional { 2 } else { 1 };
+        let cells = (0..num_layers * num_directions)
+            .map(|index| {
+                let layer_input_size = if index < num_directions { input_size } else { num_directions * hidden_size };
+                C::new_cell(layer_input_size, hidden_size, bias)
+            })
+            .collect();
+        Self {
+            cells,
+            num_layers,
+            bidirectional,
+            training: true,
+        }
+    }
+
+    pub fn num_layers(&self) -> usize {
+        self.num_layers
+    }
+
+    pub fn is_bidirectional(&self) -> bool {
+        self.bidirectional
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.cells[0].cell_hidden_size()
+    }
+
+    /// The cell of the given layer and direction, `1` being the reverse direction
+    pub fn cell(&self, layer: usize, direction: usize) -> &C {
+        &self.cells[layer * self.num_directions() + direction]
+    }
+
+    fn num_directions(&self) -> usize {
+        if self.bidirectional {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Runs the layer over the sequence, starting from `initial_state`, or zeros, and returns
+    /// the output and the final state
+    pub fn forward_with_state(&self, input: &Tensor, initial_state: Option<&[Tensor]>) -> (Tensor, Vec<Tensor>) {
+        let shape = input.shape();
+        assert_eq!(shape.len(), 3, "Expected a [seq_len, batch, input_size] input, got {:?}", shape);
+        let (seq_len, batch, hidden_size) = (shape[0], shape[1], self.hidden_size());
+        assert!(seq_len > 0, "The input sequence must have at least one step");
+        if let Some(state) = initial_state {
+            assert_eq!(state.len(), C::STATE_LEN, "The state must have {} Tensors", C::STATE_LEN);
+            for tensor in state {
+                assert_eq!(
+                    tensor.shape(),
+                    vec![self.cells.len(), batch, hidden_size],
+                    "The states must have shape [num_layers * num_directions, batch, hidden_size]"
+                );
+            }
+        }
+        let mut final_states: Vec<Vec<Tensor>> = (0..C::STATE_LEN).map(|_| Vec::with_capacity(self.cells.len())).collect();
+        let mut layer_input = input.shallow_clone();
+        for layer in 0..self.num_layers {
+            let input_size = layer_input.shape()[2];
+            let mut direction_outputs = Vec::with_capacity(self.num_directions());
+            for direction in 0..self.num_directions() {
+                let index = layer * self.num_directions() + direction;
+                let mut state: Option<Vec<Tensor>> = initial_state.map(|initial_state| {
+                    initial_state
+                        .iter()
+                        .map(|tensor| tensor.narrow(0, index, 1).reshape(vec![batch, hidden_size]))
+                        .collect()
+                });
+                let mut outputs: Vec<Tensor> = Vec::with_capacity(seq_len);
+                for step in 0..seq_len {
+                    let time = if direction == 0 { step } else { seq_len - 1 - step };
+                    let step_input = layer_input.narrow(0, time, 1).reshape(vec![batch, input_size]);
+                    let next_state = self.cells[index].step_state(&step_input, state.as_deref());
+                    outputs.push(next_state[0].reshape(vec![1, batch, hidden_size]));
+                    state = Some(next_state);
+                }
+                if direction == 1 {
+                    outputs.reverse();
+                }
+                direction_outputs.push(Tensor::cat(&outputs, 0));
+                for (final_state, tensor) in final_states.iter_mut().zip(state.unwrap()) {
+                    final_state.push(tensor.reshape(vec![1, batch, hidden_size]));
+                }
+            }
+            layer_input = if direction_outputs.len() == 1 {
+                direction_outputs.remove(0)
+            } else {
+                Tensor::cat(&direction_outputs, 2)
+            };
+        }
+        let final_states = final_states.iter().map(|tensors| Tensor::cat(tensors, 0)).collect();
+        (layer_input, final_states)
+    }
+}
+
+impl<C: RecurrentCell> Module for Recurrent<C> {
+    /// Runs the layer over a `[seq_len, batch, input_size]` input from a zero state, returning
+    /// the output of the last layer at each time step
+    fn forward(&self, input: &Tensor) -> Tensor {
+        self.forward_with_state(input, None).0
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor)> {
+        let num_directions = self.num_directions();
+        self.cells
+            .iter()
+            .enumerate()
+            .flat_map(|(index, cell)| {
+                let reverse = if index % num_directions == 1 { "_reverse" } else { "" };
+                let suffix = format!("_l{}{}", index / num_directions, reverse);
+                cell.named_parameters()
+                    .into_iter()
+                    .map(move |(name, parameter)| (name + &suffix, parameter))
+            })
+            .collect()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+        for cell in &mut self.cells {
+            cell.set_training(training);
+        }
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
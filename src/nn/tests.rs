@@ -0,0 +1,294 @@
+use crate::nn::{Linear, Module, ModuleList, Sequential};
+use crate::Tensor;
+
+fn linear_with(weight: Vec<f32>, bias: Vec<f32>, in_features: usize, out_features: usize) -> Linear {
+    let mut linear = Linear::new(in_features, out_features);
+    linear.weight = Tensor::from_data_and_shape(weight, vec![out_features, in_features]);
+    linear.bias = Some(Tensor::from_data_and_shape(bias, vec![out_features]));
+    linear
+}
+
+#[test]
+fn linear_forward_works() {
+    let linear = linear_with(vec![1., 0., -1., 2., 1., 0.], vec![0.5, -1.], 3, 2);
+    let input = Tensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+    let output = linear.forward(&input);
+    assert_eq!(output.shape(), vec![2, 2]);
+    assert_eq!(output.to_vec(), &[-1.5, 3., -1.5, 12.]);
+}
+
+#[test]
+fn linear_keeps_leading_dims() {
+    let linear = Linear::new(3, 5);
+    let input = Tensor::from_data_and_shape(vec![0.; 2 * 4 * 3], vec![2, 4, 3]);
+    assert_eq!(linear.forward(&input).shape(), vec![2, 4, 5]);
+}
+
+#[test]
+fn linear_init_is_bounded() {
+    let linear = Linear::new(16, 4);
+    let bound = 1. / 4.;
+    for parameter in linear.parameters() {
+        assert!(parameter.to_vec().iter().all(|v| v.abs() <= bound));
+    }
+}
+
+#[test]
+fn linear_backward_works() {
+    let linear = linear_with(vec![1., 0., -1., 2., 1., 0.], vec![0.5, -1.], 3, 2);
+    let input = Tensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+    linear.forward(&input).sum().backward();
+    // d sum(x W^T + b) / dW = sum of the input rows for every output
+    assert_eq!(linear.weight.grad().unwrap().to_vec(), &[5., 7., 9., 5., 7., 9.]);
+    assert_eq!(linear.bias.as_ref().unwrap().grad().unwrap().to_vec(), &[2., 2.]);
+}
+
+#[test]
+fn sequential_names_and_mode() {
+    let mut model = Sequential::new(vec![Box::new(Linear::new(4, 3))]);
+    model.push(Linear::new_with_bias(3, 2, false));
+    let names: Vec<String> = model.named_parameters().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, vec!["0.weight", "0.bias", "1.weight"]);
+    model.eval();
+    assert!(!model.is_training());
+    assert!(!model.get(1).unwrap().is_training());
+    model.train();
+    assert!(model.get(0).unwrap().is_training());
+}
+
+#[test]
+fn module_list_holds_parameters() {
+    let mut list = ModuleList::new();
+    list.eval();
+    list.push(Linear::new(2, 2));
+    assert!(!list.get(0).unwrap().is_training());
+    assert_eq!(list.parameters().len(), 2);
+    assert_eq!(list.named_parameters()[1].0, "0.bias");
+}
+
+#[test]
+fn normalization_layers_parameters() {
+    use crate::nn::{BatchNorm, GroupNorm, LayerNorm};
+    let names = |module: &dyn Module| -> Vec<String> {
+        module.named_parameters().into_iter().map(|(name, _)| name).collect()
+    };
+    assert_eq!(names(&BatchNorm::new(3)), vec!["weight", "bias"]);
+    assert!(names(&GroupNorm::new_with_affine(2, 4, false)).is_empty());
+    let layer_norm = LayerNorm::new(vec![2, 3]);
+    assert_eq!(layer_norm.weight.as_ref().unwrap().shape(), vec![2, 3]);
+    let input = Tensor::from_data_and_shape((0..12).map(|x| x as f32).collect(), vec![2, 2, 3]);
+    let output = layer_norm.forward(&input).to_vec();
+    // Both rows are the same up to a shift, so they are normalized to the same values
+    for (first, second) in output[..6].iter().zip(&output[6..]) {
+        assert!((first - second).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn initializers_have_the_expected_scale() {
+    use crate::nn::init::{fans, kaiming_normal, xavier_uniform, FanMode};
+    use crate::random::Generator;
+    assert_eq!(fans(&[8, 4, 3, 3]), (36, 72));
+    let mut generator = Generator::new(1);
+    let bound = (6. / (36. + 72.) as f32).sqrt();
+    let uniform = xavier_uniform(vec![8, 4, 3, 3], 1., Some(&mut generator)).to_vec();
+    assert!(uniform.iter().all(|value| value.abs() <= bound));
+    let normal = kaiming_normal(vec![64, 50, 4], 0., FanMode::FanOut, Some(&mut generator)).to_vec();
+    let variance = normal.iter().map(|value| value * value).sum::<f32>() / normal.len() as f32;
+    // std = sqrt(2 / 256)
+    assert!((variance - 2. / 256.).abs() < 1e-3, "variance {}", variance);
+}
+
+#[test]
+fn rnn_cell_step() {
+    use crate::nn::RNNCell;
+    let mut cell = RNNCell::new(1, 1);
+    cell.weight_ih = Tensor::from_data_and_shape(vec![0.5], vec![1, 1]);
+    cell.weight_hh = Tensor::from_data_and_shape(vec![-1.], vec![1, 1]);
+    cell.bias_ih = Some(Tensor::from_data_and_shape(vec![0.1], vec![1]));
+    cell.bias_hh = Some(Tensor::from_data_and_shape(vec![0.2], vec![1]));
+    let input = Tensor::from_data_and_shape(vec![2.], vec![1, 1]);
+    let hidden = Tensor::from_data_and_shape(vec![0.3], vec![1, 1]);
+    let next = cell.step(&input, Some(&hidden)).to_f32();
+    assert!((next - 1f32.tanh()).abs() < 1e-5, "{}", next);
+}
+
+#[test]
+fn bidirectional_lstm_states() {
+    use crate::nn::LSTM;
+    use crate::RawTensor;
+    let lstm = LSTM::new(2, 3, 2, true);
+    let names: Vec<String> = lstm.named_parameters().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names.len(), 16);
+    assert_eq!(names[4], "weight_ih_l0_reverse");
+    assert_eq!(lstm.cell(1, 0).weight_ih.shape(), vec![12, 6]);
+    let input = Tensor::from_raw_tensor(RawTensor::rand(vec![4, 2, 2]));
+    let (output, state) = lstm.forward_with_state(&input, None);
+    let (output, hidden) = (output.to_vec(), state[0].to_vec());
+    // The forward direction ends at the last step and the reverse one at the first step
+    let last_layer = &hidden[12..];
+    for sample in 0..2 {
+        for feature in 0..3 {
+            let forward = output[(3 * 2 + sample) * 6 + feature];
+            let reverse = output[sample * 6 + 3 + feature];
+            assert!((forward - last_layer[sample * 3 + feature]).abs() < 1e-6);
+            assert!((reverse - last_layer[6 + sample * 3 + feature]).abs() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn recurrent_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    use crate::nn::{GRU, LSTM};
+    use crate::RawTensor;
+    let input = RawTensor::rand(vec![3, 2, 2]).sub_scalar(0.5);
+    let gru = GRU::new(2, 2, 2, true);
+    let scale = Tensor::from_raw_tensor(RawTensor::rand(vec![3, 2, 4]));
+    assert!(gradcheck(|x| gru.forward(x).dot_mul(&scale).sum(), &input, 1e-2, 1e-2));
+    let lstm = LSTM::new(2, 2, 1, false);
+    let initial = Tensor::from_raw_tensor(RawTensor::rand(vec![1, 2, 2]));
+    assert!(gradcheck(
+        |x| lstm.forward_with_state(x, Some(&[initial.shallow_clone(), initial.shallow_clone()])).1[1].sum(),
+        &input,
+        1e-2,
+        1e-2
+    ));
+}
+
+#[test]
+#[should_panic(expected = "The input sequence must have at least one step")]
+fn recurrent_layers_reject_empty_sequences() {
+    use crate::nn::GRU;
+    use crate::RawTensor;
+    let gru = GRU::new(2, 2, 1, false);
+    gru.forward(&Tensor::from_raw_tensor(RawTensor::zeros(vec![0, 1, 2])));
+}
+
+#[test]
+fn attention_matches_reference() {
+    use crate::nn::scaled_dot_product_attention;
+    use crate::{CpuTensor, RawTensor, ShapeStrideTrait};
+    let query = CpuTensor::rand(vec![2, 3, 4]);
+    let key = CpuTensor::rand(vec![2, 5, 4]);
+    let value = CpuTensor::rand(vec![2, 5, 2]);
+    let mask = CpuTensor::from_data_and_shape(
+        (0..15).map(|i| if i % 4 == 1 { f32::NEG_INFINITY } else { 0.5 }).collect(),
+        vec![3, 5],
+    );
+    let to_tensor = |tensor: &CpuTensor| {
+        Tensor::from_data_and_shape(tensor.as_contiguous_vec(), tensor.shape().iter().copied().collect())
+    };
+    let mask_raw = RawTensor::from_data_and_shape(mask.as_contiguous_vec(), vec![3, 5]);
+    for &causal in &[false, true] {
+        let output = scaled_dot_product_attention(&to_tensor(&query), &to_tensor(&key), &to_tensor(&value), Some(&mask_raw), causal);
+        let expected = query.scaled_dot_product_attention(&key, &value, Some(&mask), causal);
+        assert_eq!(output.shape(), vec![2, 3, 2]);
+        for (actual, expected) in output.to_vec().iter().zip(expected.as_contiguous_vec()) {
+            assert!((actual - expected).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+}
+
+#[test]
+fn multihead_attention_splits_the_heads() {
+    use crate::nn::MultiheadAttention;
+    use crate::CpuTensor;
+    let mut attention = MultiheadAttention::new(4, 2);
+    let names: Vec<String> = attention.named_parameters().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, vec!["in_proj_weight", "in_proj_bias", "out_proj.weight", "out_proj.bias"]);
+    // With identity projections, head h attends over the features [2h, 2h + 2)
+    let identity: Vec<f32> = (0..16).map(|i| if i % 5 == 0 { 1. } else { 0. }).collect();
+    attention.in_proj_weight = Tensor::from_data_and_shape(identity.repeat(3), vec![12, 4]);
+    attention.out_proj.weight = Tensor::from_data_and_shape(identity, vec![4, 4]);
+    let input = CpuTensor::rand(vec![2, 3, 4]);
+    let output = attention.forward(&Tensor::from_data_and_shape(input.as_contiguous_vec(), vec![2, 3, 4])).to_vec();
+    let data = input.as_contiguous_vec();
+    for head in 0..2 {
+        let features: Vec<f32> = data.chunks(2).skip(head).step_by(2).flatten().copied().collect();
+        let features = CpuTensor::from_data_and_shape(features, vec![2, 3, 2]);
+        let expected = features.scaled_dot_product_attention(&features, &features, None, false).as_contiguous_vec();
+        for (row, expected) in expected.chunks(2).enumerate() {
+            let actual = &output[row * 4 + 2 * head..row * 4 + 2 * head + 2];
+            assert!((actual[0] - expected[0]).abs() < 1e-5 && (actual[1] - expected[1]).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn attention_grads_match_numerical() {
+    use crate::autograd::gradcheck;
+    use crate::nn::{scaled_dot_product_attention, MultiheadAttention};
+    use crate::RawTensor;
+    let key = Tensor::from_raw_tensor(RawTensor::rand(vec![2, 3, 2]));
+    let value = Tensor::from_raw_tensor(RawTensor::rand(vec![2, 3, 2]));
+    let scale = Tensor::from_raw_tensor(RawTensor::rand(vec![2, 3, 2]));
+    let query = RawTensor::rand(vec![2, 3, 2]).sub_scalar(0.5);
+    assert!(gradcheck(
+        |x| scaled_dot_product_attention(x, &key, &value, None, true).dot_mul(&scale).sum(),
+        &query,
+        1e-2,
+        1e-2
+    ));
+    let attention = MultiheadAttention::new(4, 2);
+    let scale = Tensor::from_raw_tensor(RawTensor::rand(vec![2, 3, 4]));
+    let input = RawTensor::rand(vec![2, 3, 4]).sub_scalar(0.5);
+    assert!(gradcheck(|x| attention.forward(x).dot_mul(&scale).sum(), &input, 1e-2, 1e-2));
+}
+
+#[test]
+fn state_dict_round_trips_parameters_and_buffers() {
+    use crate::nn::BatchNorm;
+    use crate::RawTensor;
+    let model = Sequential::new(vec![Box::new(Linear::new(2, 3)), Box::new(BatchNorm::new(3))]);
+    model.forward(&Tensor::from_data_and_shape(vec![1., 2., -1., 0.5, 3., 0.], vec![3, 2]));
+    let state_dict = model.state_dict();
+    let mut keys: Vec<&String> = state_dict.tensors.keys().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec!["0.bias", "0.weight", "1.bias", "1.running_mean", "1.running_var", "1.weight"]
+    );
+
+    let mut restored = Sequential::new(vec![Box::new(Linear::new(2, 3)), Box::new(BatchNorm::new(3))]);
+    // The parameters keep their identity, so optimizers holding them see the loaded values
+    let weight = restored.parameters()[0].shallow_clone();
+    assert!(restored.load_state_dict(&state_dict, true).is_clean());
+    assert_eq!(weight.to_vec(), state_dict.tensors["0.weight"].to_vec());
+    let buffers = restored.named_buffers();
+    assert_eq!(buffers[0].1.to_vec(), state_dict.tensors["1.running_mean"].to_vec());
+
+    // Non strict loading skips what doesn't match and reports it
+    let mut partial = model.state_dict();
+    partial.tensors.remove("0.bias");
+    partial.tensors.insert("0.weight".to_string(), RawTensor::zeros(vec![2, 3]));
+    partial.tensors.insert("2.weight".to_string(), RawTensor::zeros(vec![1]));
+    let report = restored.load_state_dict(&partial, false);
+    assert_eq!(report.missing_keys, vec!["0.bias"]);
+    assert_eq!(report.unexpected_keys, vec!["2.weight"]);
+    assert_eq!(report.shape_mismatches[0].key, "0.weight");
+    assert_eq!(weight.to_vec(), state_dict.tensors["0.weight"].to_vec());
+}
+
+#[test]
+#[should_panic(expected = "Error loading the state dict: missing keys [\"bias\"]")]
+fn strict_load_state_dict_panics_on_missing_keys() {
+    let model = Linear::new(2, 3);
+    let mut state_dict = model.state_dict();
+    state_dict.tensors.remove("bias");
+    Linear::new(2, 3).load_state_dict(&state_dict, true);
+}
+
+#[test]
+fn module_lists_nest() {
+    let mut inner = ModuleList::new();
+    inner.push(Linear::new(2, 3));
+    let mut outer = ModuleList::new();
+    outer.push(Linear::new(3, 1));
+    outer.push(inner);
+    outer.eval();
+    let names: Vec<String> = outer.state_dict().tensors.into_keys().collect();
+    assert!(names.contains(&"1.0.weight".to_string()), "{:?}", names);
+    assert_eq!(outer.parameters().len(), 4);
+    assert!(!outer.get(1).unwrap().is_training());
+}
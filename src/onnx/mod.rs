@@ -0,0 +1,283 @@
+//! Loading [ONNX](https://onnx.ai) models to run them for inference.
+//!
+//! The graph, its initializers and the attributes of its nodes are decoded from the protobuf
+//! file, and each node is mapped onto [`RawTensor`] operations. A practical subset of the
+//! default operator set is supported:
+//!
+//! - Arithmetic: `Add`, `Sub`, `Mul`, `Div` with broadcasting, `Neg`, `MatMul` and `Gemm`
+//! - Activations: `Relu`, `LeakyRelu`, `Sigmoid`, `Tanh`, `Exp` and `Softmax`
+//! - Convolutions and pooling: `Conv` in 1D and 2D, `MaxPool`, `AveragePool` and
+//!   `GlobalAveragePool`, with symmetric padding
+//! - Normalization: `BatchNormalization` for inference and `LayerNormalization`
+//! - Shapes: `Reshape`, `Flatten`, `Transpose`, `Squeeze`, `Unsqueeze`, `Concat`, `Gather`
+//!   and `Shape`
+//! - `Constant`, `Identity` and `Dropout`, which is the identity at inference
+//!
+//! Every Tensor is converted to `f32`, including integer Tensors such as the target shape of
+//! `Reshape`, so they must hold integers exactly representable as `f32`. Loading fails if the
+//! model uses other operators, and running panics if the attributes of a node are not
+//! supported, such as asymmetric padding.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::collections::HashMap;
+//! use tensor_compute::onnx::OnnxModel;
+//! use tensor_compute::RawTensor;
+//! let model = OnnxModel::load("mnist.onnx").unwrap();
+//! let mut inputs = HashMap::new();
+//! inputs.insert(model.input_names()[0].to_string(), RawTensor::zeros(vec![1, 1, 28, 28]));
+//! let outputs = model.run(inputs);
+//! println!("{:?}", outputs[model.output_names()[0]].to_vec());
+//! ```
+use crate::serialization::json::invalid_data;
+use crate::RawTensor;
+use proto::{AttributeProto, ModelProto, NodeProto, TensorProto};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+mod ops;
+mod proto;
+#[cfg(test)]
+mod tests;
+
+/// The operators of the default domain which can be run
+const SUPPORTED_OPERATORS: &[&str] = &[
+    "Add",
+    "AveragePool",
+    "BatchNormalization",
+    "Concat",
+    "Constant",
+    "Conv",
+    "Div",
+    "Dropout",
+    "Exp",
+    "Flatten",
+    "Gather",
+    "Gemm",
+    "GlobalAveragePool",
+    "Identity",
+    "LayerNormalization",
+    "LeakyRelu",
+    "MatMul",
+    "MaxPool",
+    "Mul",
+    "Neg",
+    "Relu",
+    "Reshape",
+    "Shape",
+    "Sigmoid",
+    "Softmax",
+    "Squeeze",
+    "Sub",
+    "Tanh",
+    "Transpose",
+    "Unsqueeze",
+];
+
+/// A value flowing through the graph. ONNX scalars have rank 0, which Tensors can't have, so
+/// they are stored with shape `[1]` and flagged.
+pub(crate) struct Value {
+    pub tensor: RawTensor,
+    pub scalar: bool,
+}
+
+impl Value {
+    pub fn new(tensor: RawTensor) -> Self {
+        Self { tensor, scalar: false }
+    }
+
+    pub fn from_proto(proto: &TensorProto) -> Value {
+        assert!(!proto.data.is_empty(), "The ONNX tensor {} is empty, which is not supported", proto.name);
+        let scalar = proto.dims.is_empty();
+        let shape = if scalar { vec![1] } else { proto.dims.clone() };
+        Value {
+            tensor: RawTensor::from_data_and_shape(proto.data.clone(), shape),
+            scalar,
+        }
+    }
+
+    /// The ONNX shape, empty for scalars
+    pub fn shape(&self) -> Vec<usize> {
+        if self.scalar {
+            vec![]
+        } else {
+            self.tensor.shape().iter().copied().collect()
+        }
+    }
+
+    /// Reads back the elements of an integer Tensor, such as a shape
+    pub fn ints(&self) -> Vec<i64> {
+        self.tensor.to_vec().into_iter().map(|value| value as i64).collect()
+    }
+}
+
+/// A node of the graph with its attributes by name
+pub(crate) struct Node {
+    pub name: String,
+    pub op_type: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    attributes: HashMap<String, AttributeProto>,
+}
+
+impl Node {
+    fn from_proto(proto: NodeProto) -> io::Result<Node> {
+        if !proto.domain.is_empty() && proto.domain != "ai.onnx" {
+            return invalid_data(format!(
+                "The ONNX operator {} of the domain {} is not supported",
+                proto.op_type, proto.domain
+            ));
+        }
+        if !SUPPORTED_OPERATORS.contains(&proto.op_type.as_str()) {
+            return invalid_data(format!("The ONNX operator {} is not supported", proto.op_type));
+        }
+        Ok(Node {
+            name: proto.name,
+            op_type: proto.op_type,
+            inputs: proto.inputs,
+            outputs: proto.outputs,
+            attributes: proto.attributes.into_iter().map(|attribute| (attribute.name.clone(), attribute)).collect(),
+        })
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.attributes.contains_key(name)
+    }
+
+    pub fn int(&self, name: &str, default: i64) -> i64 {
+        self.attributes.get(name).and_then(|attribute| attribute.i).unwrap_or(default)
+    }
+
+    pub fn float(&self, name: &str, default: f32) -> f32 {
+        self.attributes.get(name).and_then(|attribute| attribute.f).unwrap_or(default)
+    }
+
+    pub fn ints(&self, name: &str) -> Option<&[i64]> {
+        self.attributes.get(name).map(|attribute| attribute.ints.as_slice())
+    }
+
+    pub fn floats(&self, name: &str) -> Option<&[f32]> {
+        self.attributes.get(name).map(|attribute| attribute.floats.as_slice())
+    }
+
+    pub fn string(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).and_then(|attribute| attribute.s.as_deref())
+    }
+
+    pub fn tensor(&self, name: &str) -> Option<&TensorProto> {
+        self.attributes.get(name).and_then(|attribute| attribute.t.as_ref())
+    }
+}
+
+/// An ONNX model ready to run, with its initializers on the device
+pub struct OnnxModel {
+    opset_version: i64,
+    nodes: Vec<Node>,
+    initializers: HashMap<String, Value>,
+    input_names: Vec<String>,
+    output_names: Vec<String>,
+}
+
+impl OnnxModel {
+    /// Loads a model from an ONNX file, failing if it is malformed or uses operators which are
+    /// not supported
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<OnnxModel> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Loads a model from the bytes of an ONNX file, see [`OnnxModel::load`]
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<OnnxModel> {
+        let model = ModelProto::decode(bytes)?;
+        let graph = model.graph;
+        let nodes = graph.nodes.into_iter().map(Node::from_proto).collect::<io::Result<Vec<Node>>>()?;
+        if let Some(empty) = graph.initializers.iter().find(|initializer| initializer.data.is_empty()) {
+            return invalid_data(format!("The ONNX initializer {} is empty, which is not supported", empty.name));
+        }
+        let initializers: HashMap<String, Value> = graph
+            .initializers
+            .iter()
+            .map(|initializer| (initializer.name.clone(), Value::from_proto(initializer)))
+            .collect();
+        // Older exporters also list the initializers as inputs
+        let input_names = graph
+            .inputs
+            .into_iter()
+            .map(|input| input.name)
+            .filter(|name| !initializers.contains_key(name))
+            .collect();
+        Ok(OnnxModel {
+            opset_version: model.opset_version,
+            nodes,
+            initializers,
+            input_names,
+            output_names: graph.outputs.into_iter().map(|output| output.name).collect(),
+        })
+    }
+
+    /// The names of the inputs to give to [`OnnxModel::run`]
+    pub fn input_names(&self) -> Vec<&str> {
+        self.input_names.iter().map(String::as_str).collect()
+    }
+
+    /// The names of the outputs returned by [`OnnxModel::run`]
+    pub fn output_names(&self) -> Vec<&str> {
+        self.output_names.iter().map(String::as_str).collect()
+    }
+
+    /// The version of the default operator set the model was exported with
+    pub fn opset_version(&self) -> i64 {
+        self.opset_version
+    }
+
+    /// Runs the graph on the given inputs, by name, and returns its outputs. Scalar outputs
+    /// have shape `[1]`.
+    ///
+    /// Panics if an input is missing or if the shapes are not valid for the operators.
+    pub fn run(&self, inputs: HashMap<String, RawTensor>) -> HashMap<String, RawTensor> {
+        let mut values: HashMap<String, Value> = inputs.into_iter().map(|(name, tensor)| (name, Value::new(tensor))).collect();
+        for name in &self.input_names {
+            assert!(values.contains_key(name), "Missing the input {} of the ONNX model", name);
+        }
+        for node in &self.nodes {
+            let outputs = {
+                let inputs: Vec<Option<&Value>> = node
+                    .inputs
+                    .iter()
+                    .map(|name| match name.as_str() {
+                        // Optional inputs which are not given have an empty name
+                        "" => None,
+                        name => Some(self.value(&values, name).unwrap_or_else(|| {
+                            panic!("The ONNX node {} ({}) needs the missing value {}", node.name, node.op_type, name)
+                        })),
+                    })
+                    .collect();
+                ops::run_node(node, &inputs, self.opset_version)
+            };
+            for (name, output) in node.outputs.iter().zip(outputs) {
+                if !name.is_empty() {
+                    values.insert(name.clone(), output);
+                }
+            }
+        }
+        self.output_names
+            .iter()
+            .map(|name| {
+                let output = match values.remove(name) {
+                    Some(output) => output.tensor,
+                    None => match self.initializers.get(name) {
+                        Some(initializer) => initializer.tensor.clone(),
+                        None => panic!("The ONNX graph does not compute its output {}", name),
+                    },
+                };
+                (name.clone(), output)
+            })
+            .collect()
+    }
+
+    fn value<'a>(&'a self, values: &'a HashMap<String, Value>, name: &str) -> Option<&'a Value> {
+        values.get(name).or_else(|| self.initializers.get(name))
+    }
+}
@@ -0,0 +1,485 @@
+//! The operators of [`super::SUPPORTED_OPERATORS`], following the
+//! [ONNX operator specifications](https://github.com/onnx/onnx/blob/main/docs/Operators.md)
+use super::proto::TensorProto;
+use super::{Node, Value};
+use crate::{broadcast_shapes, Conv1dParams, Conv2dParams, RawTensor, Window2d};
+
+/// Runs `node` on its inputs, `None` for the optional ones which are not given, and returns
+/// its outputs
+pub(crate) fn run_node(node: &Node, inputs: &[Option<&Value>], opset_version: i64) -> Vec<Value> {
+    let input = |idx: usize| required(node, inputs, idx);
+    let optional = |idx: usize| inputs.get(idx).copied().flatten();
+    let output = match node.op_type.as_str() {
+        "Add" => broadcast_binary(input(0), input(1), RawTensor::add),
+        "Sub" => broadcast_binary(input(0), input(1), RawTensor::sub),
+        "Mul" => broadcast_binary(input(0), input(1), RawTensor::dot_mul),
+        "Div" => broadcast_binary(input(0), input(1), RawTensor::dot_div),
+        "Neg" => unary(input(0), |tensor| tensor.mul_scalar(-1.)),
+        "MatMul" => matmul(&input(0).tensor, &input(1).tensor),
+        "Gemm" => gemm(node, input(0), input(1), optional(2)),
+        "Relu" => unary(input(0), |tensor| tensor.relu(0.)),
+        "LeakyRelu" => unary(input(0), |tensor| tensor.relu(node.float("alpha", 0.01))),
+        "Sigmoid" => unary(input(0), RawTensor::sigmoid),
+        "Tanh" => unary(input(0), RawTensor::tanh),
+        "Exp" => unary(input(0), RawTensor::exp),
+        "Softmax" => softmax(node, input(0), opset_version),
+        "Conv" => conv(node, input(0), input(1), optional(2)),
+        "MaxPool" => {
+            assert!(
+                node.outputs.get(1).is_none_or(String::is_empty),
+                "The Indices output of MaxPool is not supported, node {}",
+                node.name
+            );
+            let window = pool_window(node, input(0));
+            Value::new(input(0).tensor.max_pool2d(&window).0)
+        }
+        "AveragePool" => {
+            let window = pool_window(node, input(0));
+            Value::new(input(0).tensor.avg_pool2d(&window, node.int("count_include_pad", 0) != 0))
+        }
+        "GlobalAveragePool" => {
+            assert_eq!(input(0).shape().len(), 4, "Only 2D GlobalAveragePool is supported, node {}", node.name);
+            Value::new(input(0).tensor.adaptive_avg_pool2d((1, 1)))
+        }
+        "BatchNormalization" => {
+            assert_eq!(node.int("training_mode", 0), 0, "BatchNormalization is only supported for inference");
+            let (mut mean, mut var) = (input(3).tensor.clone(), input(4).tensor.clone());
+            Value::new(input(0).tensor.batch_norm(
+                Some((&mut mean, &mut var)),
+                Some(&input(1).tensor),
+                Some(&input(2).tensor),
+                false,
+                0.,
+                node.float("epsilon", 1e-5),
+            ))
+        }
+        "LayerNormalization" => {
+            let shape = input(0).shape();
+            let axis = normalize_axis(node.int("axis", -1), shape.len());
+            Value::new(input(0).tensor.layer_norm(
+                &shape[axis..],
+                Some(&input(1).tensor),
+                optional(2).map(|bias| &bias.tensor),
+                node.float("epsilon", 1e-5),
+            ))
+        }
+        "Reshape" => {
+            let shape = match optional(1) {
+                Some(shape) => shape.ints(),
+                // Before opset 5 the shape was an attribute
+                None => node.ints("shape").expect("Reshape needs a shape").to_vec(),
+            };
+            reshape(input(0), &shape, node.int("allowzero", 0) != 0)
+        }
+        "Flatten" => {
+            let shape = input(0).shape();
+            let axis = normalize_split_axis(node.int("axis", 1), shape.len());
+            let outer = shape[..axis].iter().product();
+            reshaped(&input(0).tensor, vec![outer, shape[axis..].iter().product()])
+        }
+        "Transpose" => {
+            let rank = input(0).shape().len();
+            let dims: Vec<usize> = match node.ints("perm") {
+                Some(perm) => perm.iter().map(|&dim| normalize_axis(dim, rank)).collect(),
+                None => (0..rank).rev().collect(),
+            };
+            Value::new(input(0).tensor.permute(&dims))
+        }
+        "Squeeze" => squeeze(node, input(0), optional(1), opset_version),
+        "Unsqueeze" => unsqueeze(node, input(0), optional(1), opset_version),
+        "Concat" => {
+            let tensors: Vec<&RawTensor> = (0..inputs.len()).map(|idx| &input(idx).tensor).collect();
+            assert!(node.has("axis"), "Concat needs an axis, node {}", node.name);
+            Value::new(RawTensor::cat(&tensors, normalize_axis(node.int("axis", 0), input(0).shape().len())))
+        }
+        "Gather" => gather(node, input(0), input(1)),
+        "Shape" => {
+            let shape = input(0).shape();
+            assert!(!shape.is_empty(), "The Shape of a scalar is empty, which is not supported");
+            let start = clamp_split_axis(node.int("start", 0), shape.len());
+            let end = clamp_split_axis(node.int("end", shape.len() as i64), shape.len()).max(start);
+            let dims: Vec<f32> = shape[start..end].iter().map(|&dim| dim as f32).collect();
+            Value::new(RawTensor::from_data_1d(dims))
+        }
+        "Constant" => constant(node),
+        "Identity" | "Dropout" => Value {
+            tensor: input(0).tensor.clone(),
+            scalar: input(0).scalar,
+        },
+        op_type => panic!("The ONNX operator {} is not supported", op_type),
+    };
+    vec![output]
+}
+
+fn required<'a>(node: &Node, inputs: &[Option<&'a Value>], idx: usize) -> &'a Value {
+    match inputs.get(idx) {
+        Some(Some(input)) => input,
+        _ => panic!("The ONNX node {} ({}) needs an input {}", node.name, node.op_type, idx),
+    }
+}
+
+/// Converts a possibly negative axis, counted from the end, to an index in `0..rank`
+fn normalize_axis(axis: i64, rank: usize) -> usize {
+    let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+    assert!(
+        normalized >= 0 && (normalized as usize) < rank.max(1),
+        "The axis {} is out of range for rank {}",
+        axis,
+        rank
+    );
+    normalized as usize
+}
+
+/// Converts a possibly negative axis, counted from the end, to a position in `0..=rank`
+/// between two dimensions, such as the axis a Tensor is flattened at
+fn normalize_split_axis(axis: i64, rank: usize) -> usize {
+    let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+    assert!(
+        normalized >= 0 && normalized as usize <= rank,
+        "The axis {} is out of range for rank {}",
+        axis,
+        rank
+    );
+    normalized as usize
+}
+
+/// Same as [`normalize_split_axis`], but clamps the axes out of range
+fn clamp_split_axis(axis: i64, rank: usize) -> usize {
+    let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+    normalized.clamp(0, rank as i64) as usize
+}
+
+fn shape_of(tensor: &RawTensor) -> Vec<usize> {
+    tensor.shape().iter().copied().collect()
+}
+
+/// Returns a copy of the Tensor with a new shape
+fn reshaped(tensor: &RawTensor, shape: Vec<usize>) -> Value {
+    let scalar = shape.is_empty();
+    let mut tensor = tensor.clone();
+    tensor.reshape(if scalar { vec![1] } else { shape });
+    Value { tensor, scalar }
+}
+
+fn unary<F: Fn(&RawTensor) -> RawTensor>(input: &Value, op: F) -> Value {
+    Value {
+        tensor: op(&input.tensor),
+        scalar: input.scalar,
+    }
+}
+
+/// Applies an element wise operation after broadcasting both inputs to the same shape
+fn broadcast_binary(left: &Value, right: &Value, op: fn(&RawTensor, &RawTensor) -> RawTensor) -> Value {
+    let (left_shape, right_shape) = (shape_of(&left.tensor), shape_of(&right.tensor));
+    let shape: Vec<usize> = broadcast_shapes(&left_shape, &right_shape)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .into();
+    let broadcast = |tensor: &RawTensor, tensor_shape: &[usize]| {
+        if tensor_shape == shape.as_slice() {
+            None
+        } else {
+            Some(tensor.broadcast_to(shape.clone()))
+        }
+    };
+    let (left_broadcast, right_broadcast) = (broadcast(&left.tensor, &left_shape), broadcast(&right.tensor, &right_shape));
+    Value {
+        tensor: op(
+            left_broadcast.as_ref().unwrap_or(&left.tensor),
+            right_broadcast.as_ref().unwrap_or(&right.tensor),
+        ),
+        scalar: left.scalar && right.scalar,
+    }
+}
+
+/// Matrix product with the semantics of `numpy.matmul`: one dimensional inputs are promoted
+/// to matrices and the batch dimensions are broadcast
+fn matmul(left: &RawTensor, right: &RawTensor) -> Value {
+    let (mut left_shape, mut right_shape) = (shape_of(left), shape_of(right));
+    let (left_vector, right_vector) = (left_shape.len() == 1, right_shape.len() == 1);
+    if left_vector {
+        left_shape.insert(0, 1);
+    }
+    if right_vector {
+        right_shape.push(1);
+    }
+    let (left_rank, right_rank) = (left_shape.len(), right_shape.len());
+    let (m, k, n) = (left_shape[left_rank - 2], left_shape[left_rank - 1], right_shape[right_rank - 1]);
+    assert_eq!(
+        k,
+        right_shape[right_rank - 2],
+        "MatMul of incompatible shapes {:?} and {:?}",
+        left_shape,
+        right_shape
+    );
+    let batch: Vec<usize> = broadcast_shapes(&left_shape[..left_rank - 2], &right_shape[..right_rank - 2])
+        .unwrap_or_else(|err| panic!("{}", err))
+        .into();
+    let product = as_batch(left, &batch, (m, k)).matmul(&as_batch(right, &batch, (k, n)));
+    let mut shape = batch;
+    if !left_vector {
+        shape.push(m);
+    }
+    if !right_vector {
+        shape.push(n);
+    }
+    let scalar = shape.is_empty();
+    let mut tensor = product;
+    tensor.reshape(if scalar { vec![1] } else { shape });
+    Value { tensor, scalar }
+}
+
+/// Broadcasts a matrix or batch of matrices to `batch` and flattens it to the rank 3 Tensor
+/// expected by [`RawTensor::matmul`]
+fn as_batch(tensor: &RawTensor, batch: &[usize], matrix: (usize, usize)) -> RawTensor {
+    let shape: Vec<usize> = batch.iter().copied().chain(vec![matrix.0, matrix.1]).collect();
+    let mut batched = if tensor.numel() == shape.iter().product::<usize>() {
+        tensor.clone()
+    } else {
+        tensor.broadcast_to(shape)
+    };
+    batched.reshape(vec![batch.iter().product(), matrix.0, matrix.1]);
+    batched
+}
+
+/// `alpha * A' * B' + beta * C`, where the matrices are optionally transposed
+fn gemm(node: &Node, a: &Value, b: &Value, c: Option<&Value>) -> Value {
+    let transposed = |value: &Value, attribute: &str| {
+        assert_eq!(value.shape().len(), 2, "The inputs of Gemm must be matrices, node {}", node.name);
+        if node.int(attribute, 0) != 0 {
+            Some(value.tensor.transpose())
+        } else {
+            None
+        }
+    };
+    let (a_transposed, b_transposed) = (transposed(a, "transA"), transposed(b, "transB"));
+    let mut product = matmul(a_transposed.as_ref().unwrap_or(&a.tensor), b_transposed.as_ref().unwrap_or(&b.tensor));
+    let alpha = node.float("alpha", 1.);
+    if alpha != 1. {
+        product.tensor = product.tensor.mul_scalar(alpha);
+    }
+    let beta = node.float("beta", 1.);
+    match c {
+        Some(c) if beta == 1. => broadcast_binary(&product, c, RawTensor::add),
+        Some(c) => broadcast_binary(&product, &unary(c, |tensor| tensor.mul_scalar(beta)), RawTensor::add),
+        None => product,
+    }
+}
+
+fn softmax(node: &Node, input: &Value, opset_version: i64) -> Value {
+    let shape = input.shape();
+    let rank = shape.len();
+    // Before opset 13 the input was flattened to a matrix at `axis`, which defaulted to 1
+    if opset_version < 13 {
+        let axis = normalize_split_axis(node.int("axis", 1), rank);
+        let rows = shape[..axis].iter().product();
+        let mut softmax = reshaped(&input.tensor, vec![rows, shape[axis..].iter().product()]).tensor.softmax();
+        softmax.reshape(shape_of(&input.tensor));
+        return Value::new(softmax);
+    }
+    let axis = normalize_axis(node.int("axis", -1), rank);
+    if axis + 1 == rank.max(1) {
+        return unary(input, RawTensor::softmax);
+    }
+    // Moves the axis last, and back
+    let mut dims: Vec<usize> = (0..rank).filter(|&dim| dim != axis).collect();
+    dims.push(axis);
+    let mut inverse = vec![0; rank];
+    for (position, &dim) in dims.iter().enumerate() {
+        inverse[dim] = position;
+    }
+    Value::new(input.tensor.permute(&dims).softmax().permute(&inverse))
+}
+
+/// Returns the padding of each spatial dimension, which must be the same on both sides
+fn symmetric_padding(node: &Node, input: &[usize], kernel: &[usize], strides: &[usize], dilations: &[usize]) -> Vec<usize> {
+    let spatial = input.len();
+    let pads: Vec<usize> = match node.string("auto_pad").unwrap_or("NOTSET") {
+        "NOTSET" => match node.ints("pads") {
+            Some(pads) => pads.iter().map(|&pad| pad as usize).collect(),
+            None => vec![0; 2 * spatial],
+        },
+        "VALID" => vec![0; 2 * spatial],
+        auto_pad @ "SAME_UPPER" | auto_pad @ "SAME_LOWER" => {
+            // The output has ceil(input / stride) elements, the extra padding going at the end
+            // for SAME_UPPER and at the beginning for SAME_LOWER
+            let totals: Vec<usize> = (0..spatial)
+                .map(|dim| {
+                    let output = input[dim].div_ceil(strides[dim]);
+                    let needed = (output - 1) * strides[dim] + (kernel[dim] - 1) * dilations[dim] + 1;
+                    needed.saturating_sub(input[dim])
+                })
+                .collect();
+            let begins = totals.iter().map(|&total| if auto_pad == "SAME_UPPER" { total / 2 } else { total - total / 2 });
+            let ends = totals.iter().map(|&total| if auto_pad == "SAME_UPPER" { total - total / 2 } else { total / 2 });
+            begins.chain(ends).collect()
+        }
+        auto_pad => panic!("The auto_pad {} of node {} is not supported", auto_pad, node.name),
+    };
+    assert_eq!(pads.len(), 2 * spatial, "Node {} needs {} pads", node.name, 2 * spatial);
+    assert_eq!(
+        pads[..spatial],
+        pads[spatial..],
+        "Asymmetric padding is not supported, node {} has pads {:?}",
+        node.name,
+        pads
+    );
+    pads[..spatial].to_vec()
+}
+
+/// Reads a per spatial dimension attribute, such as the strides
+fn spatial_attribute(node: &Node, name: &str, spatial: usize) -> Vec<usize> {
+    match node.ints(name) {
+        Some(values) => {
+            assert_eq!(values.len(), spatial, "Node {} needs {} {}", node.name, spatial, name);
+            values.iter().map(|&value| value as usize).collect()
+        }
+        None => vec![1; spatial],
+    }
+}
+
+fn conv(node: &Node, input: &Value, weight: &Value, bias: Option<&Value>) -> Value {
+    let (shape, kernel) = (input.shape(), weight.shape());
+    let spatial = shape.len().saturating_sub(2);
+    assert!(spatial == 1 || spatial == 2, "Only 1D and 2D convolutions are supported, node {}", node.name);
+    let strides = spatial_attribute(node, "strides", spatial);
+    let dilations = spatial_attribute(node, "dilations", spatial);
+    let padding = symmetric_padding(node, &shape[2..], &kernel[2..], &strides, &dilations);
+    let groups = node.int("group", 1) as usize;
+    let bias = bias.map(|bias| &bias.tensor);
+    let output = if spatial == 2 {
+        let params = Conv2dParams {
+            stride: (strides[0], strides[1]),
+            padding: (padding[0], padding[1]),
+            dilation: (dilations[0], dilations[1]),
+            groups,
+        };
+        input.tensor.conv2d(&weight.tensor, bias, &params)
+    } else {
+        let params = Conv1dParams {
+            stride: strides[0],
+            padding: padding[0],
+            dilation: dilations[0],
+            groups,
+        };
+        input.tensor.conv1d(&weight.tensor, bias, &params)
+    };
+    Value::new(output)
+}
+
+/// The window of MaxPool and AveragePool, which only support images
+fn pool_window(node: &Node, input: &Value) -> Window2d {
+    let shape = input.shape();
+    assert_eq!(shape.len(), 4, "Only 2D pooling is supported, node {}", node.name);
+    assert_eq!(node.int("ceil_mode", 0), 0, "Pooling with ceil_mode is not supported, node {}", node.name);
+    let kernel: Vec<usize> = node
+        .ints("kernel_shape")
+        .unwrap_or_else(|| panic!("Node {} needs a kernel_shape", node.name))
+        .iter()
+        .map(|&dim| dim as usize)
+        .collect();
+    assert_eq!(kernel.len(), 2, "Node {} needs a 2D kernel_shape", node.name);
+    let strides = spatial_attribute(node, "strides", 2);
+    let dilations = spatial_attribute(node, "dilations", 2);
+    let padding = symmetric_padding(node, &shape[2..], &kernel, &strides, &dilations);
+    Window2d {
+        kernel_size: (kernel[0], kernel[1]),
+        stride: (strides[0], strides[1]),
+        padding: (padding[0], padding[1]),
+        dilation: (dilations[0], dilations[1]),
+    }
+}
+
+/// Reshapes to `target`, where -1 is inferred from the other dimensions and 0 copies the
+/// input dimension unless `allow_zero` is set
+fn reshape(input: &Value, target: &[i64], allow_zero: bool) -> Value {
+    let shape = input.shape();
+    let numel: usize = shape.iter().product();
+    let mut new_shape: Vec<usize> = target
+        .iter()
+        .enumerate()
+        .map(|(idx, &dim)| match dim {
+            0 if !allow_zero => shape[idx],
+            -1 => 1,
+            dim => dim as usize,
+        })
+        .collect();
+    if let Some(inferred) = target.iter().position(|&dim| dim == -1) {
+        let known: usize = new_shape.iter().product();
+        assert!(known > 0 && numel.is_multiple_of(known), "Can't reshape {:?} into {:?}", shape, target);
+        new_shape[inferred] = numel / known;
+    }
+    reshaped(&input.tensor, new_shape)
+}
+
+/// Reads the axes of Squeeze and Unsqueeze, an input since opset 13
+fn axes(node: &Node, axes: Option<&Value>, opset_version: i64) -> Option<Vec<i64>> {
+    if opset_version >= 13 {
+        axes.map(Value::ints)
+    } else {
+        node.ints("axes").map(<[i64]>::to_vec)
+    }
+}
+
+fn squeeze(node: &Node, input: &Value, axes_input: Option<&Value>, opset_version: i64) -> Value {
+    let shape = input.shape();
+    let squeezed: Vec<usize> = match axes(node, axes_input, opset_version) {
+        Some(axes) => axes.iter().map(|&axis| normalize_axis(axis, shape.len())).collect(),
+        None => (0..shape.len()).filter(|&dim| shape[dim] == 1).collect(),
+    };
+    for &dim in &squeezed {
+        assert_eq!(shape[dim], 1, "Can't squeeze the dimension {} of shape {:?}", dim, shape);
+    }
+    let new_shape = (0..shape.len()).filter(|dim| !squeezed.contains(dim)).map(|dim| shape[dim]).collect();
+    reshaped(&input.tensor, new_shape)
+}
+
+fn unsqueeze(node: &Node, input: &Value, axes_input: Option<&Value>, opset_version: i64) -> Value {
+    let axes = axes(node, axes_input, opset_version).unwrap_or_else(|| panic!("Node {} needs axes", node.name));
+    let mut shape = input.shape();
+    let rank = shape.len() + axes.len();
+    let mut axes: Vec<usize> = axes.iter().map(|&axis| normalize_axis(axis, rank)).collect();
+    axes.sort_unstable();
+    for axis in axes {
+        shape.insert(axis, 1);
+    }
+    reshaped(&input.tensor, shape)
+}
+
+/// Selects the entries of `data` along `axis`, the indices' dimensions replacing that axis
+fn gather(node: &Node, data: &Value, indices: &Value) -> Value {
+    let shape = data.shape();
+    let axis = normalize_axis(node.int("axis", 0), shape.len());
+    let positions: Vec<f32> = indices
+        .ints()
+        .into_iter()
+        .map(|index| normalize_axis(index, shape[axis]) as f32)
+        .collect();
+    let selected = data.tensor.index_select(axis, &RawTensor::from_data_1d(positions));
+    let new_shape = shape[..axis]
+        .iter()
+        .chain(indices.shape().iter())
+        .chain(shape[axis + 1..].iter())
+        .copied()
+        .collect();
+    reshaped(&selected, new_shape)
+}
+
+fn constant(node: &Node) -> Value {
+    let from_data = |data: Vec<f32>, dims: Vec<usize>| Value::from_proto(&TensorProto { name: node.name.clone(), dims, data });
+    if let Some(tensor) = node.tensor("value") {
+        return Value::from_proto(tensor);
+    }
+    if let Some(values) = node.floats("value_floats") {
+        return from_data(values.to_vec(), vec![values.len()]);
+    }
+    if let Some(values) = node.ints("value_ints") {
+        return from_data(values.iter().map(|&value| value as f32).collect(), vec![values.len()]);
+    }
+    if node.has("value_float") {
+        return from_data(vec![node.float("value_float", 0.)], vec![]);
+    }
+    if node.has("value_int") {
+        return from_data(vec![node.int("value_int", 0) as f32], vec![]);
+    }
+    panic!("The Constant node {} has no supported value", node.name)
+}
@@ -0,0 +1,411 @@
+//! A decoder for the subset of the ONNX protobuf messages needed for inference, following
+//! [onnx.proto](https://github.com/onnx/onnx/blob/main/onnx/onnx.proto). Unknown fields are
+//! skipped, as the protobuf specification requires.
+use crate::serialization::json::invalid_data;
+use crate::serialization::safetensors::f16_to_f32;
+use std::io;
+
+/// The value of a field, by wire type
+enum Field<'a> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Bytes(&'a [u8]),
+    Fixed32([u8; 4]),
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = match self.bytes.get(self.position) {
+                Some(&byte) => byte,
+                None => return invalid_data("Truncated protobuf varint".to_string()),
+            };
+            self.position += 1;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        invalid_data("Protobuf varint is too long".to_string())
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        match self.bytes.get(self.position..self.position.saturating_add(len)) {
+            Some(taken) => {
+                self.position += len;
+                Ok(taken)
+            }
+            None => invalid_data("Truncated protobuf message".to_string()),
+        }
+    }
+
+    /// Returns the number and value of the next field, or None at the end of the message
+    fn next_field(&mut self) -> io::Result<Option<(u64, Field<'a>)>> {
+        if self.position == self.bytes.len() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let field = match key & 7 {
+            0 => Field::Varint(self.varint()?),
+            1 => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(self.take(8)?);
+                Field::Fixed64(bytes)
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                Field::Bytes(self.take(len)?)
+            }
+            5 => {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(self.take(4)?);
+                Field::Fixed32(bytes)
+            }
+            wire_type => return invalid_data(format!("Unsupported protobuf wire type {}", wire_type)),
+        };
+        Ok(Some((key >> 3, field)))
+    }
+}
+
+fn string(field: Field) -> io::Result<String> {
+    match field {
+        Field::Bytes(bytes) => match String::from_utf8(bytes.to_vec()) {
+            Ok(string) => Ok(string),
+            Err(_) => invalid_data("Invalid UTF-8 in a protobuf string".to_string()),
+        },
+        _ => invalid_data("Expected a protobuf string".to_string()),
+    }
+}
+
+fn message(field: Field) -> io::Result<Reader> {
+    match field {
+        Field::Bytes(bytes) => Ok(Reader::new(bytes)),
+        _ => invalid_data("Expected a protobuf message".to_string()),
+    }
+}
+
+fn int(field: Field) -> io::Result<i64> {
+    match field {
+        Field::Varint(value) => Ok(value as i64),
+        _ => invalid_data("Expected a protobuf integer".to_string()),
+    }
+}
+
+/// Appends the values of a repeated integer field, which may be packed
+fn push_ints(field: Field, values: &mut Vec<i64>) -> io::Result<()> {
+    match field {
+        Field::Bytes(bytes) => {
+            let mut reader = Reader::new(bytes);
+            while reader.position < bytes.len() {
+                values.push(reader.varint()? as i64);
+            }
+            Ok(())
+        }
+        field => int(field).map(|value| values.push(value)),
+    }
+}
+
+/// Appends the values of a repeated float field, which may be packed
+fn push_floats(field: Field, values: &mut Vec<f32>) -> io::Result<()> {
+    match field {
+        Field::Fixed32(bytes) => values.push(f32::from_le_bytes(bytes)),
+        Field::Bytes(bytes) if bytes.len().is_multiple_of(4) => {
+            values.extend(bytes.chunks(4).map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]])))
+        }
+        _ => return invalid_data("Expected protobuf floats".to_string()),
+    }
+    Ok(())
+}
+
+/// Appends the values of a repeated double field, which may be packed
+fn push_doubles(field: Field, values: &mut Vec<f64>) -> io::Result<()> {
+    match field {
+        Field::Fixed64(bytes) => values.push(f64::from_le_bytes(bytes)),
+        Field::Bytes(bytes) if bytes.len().is_multiple_of(8) => {
+            for value in bytes.chunks(8) {
+                let mut value_bytes = [0; 8];
+                value_bytes.copy_from_slice(value);
+                values.push(f64::from_le_bytes(value_bytes));
+            }
+        }
+        _ => return invalid_data("Expected protobuf doubles".to_string()),
+    }
+    Ok(())
+}
+
+pub(crate) struct ModelProto {
+    /// The version of the default operator set
+    pub opset_version: i64,
+    pub graph: GraphProto,
+}
+
+pub(crate) struct GraphProto {
+    pub nodes: Vec<NodeProto>,
+    pub initializers: Vec<TensorProto>,
+    pub inputs: Vec<ValueInfoProto>,
+    pub outputs: Vec<ValueInfoProto>,
+}
+
+pub(crate) struct NodeProto {
+    pub name: String,
+    pub op_type: String,
+    pub domain: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub attributes: Vec<AttributeProto>,
+}
+
+#[derive(Default)]
+pub(crate) struct AttributeProto {
+    pub name: String,
+    pub f: Option<f32>,
+    pub i: Option<i64>,
+    pub s: Option<String>,
+    pub t: Option<TensorProto>,
+    pub floats: Vec<f32>,
+    pub ints: Vec<i64>,
+}
+
+/// A Tensor whose elements were converted to `f32`
+#[derive(Debug, PartialEq)]
+pub(crate) struct TensorProto {
+    pub name: String,
+    pub dims: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+pub(crate) struct ValueInfoProto {
+    pub name: String,
+}
+
+/// The element types of `TensorProto.DataType`
+mod data_type {
+    pub const FLOAT: i64 = 1;
+    pub const UINT8: i64 = 2;
+    pub const INT8: i64 = 3;
+    pub const UINT16: i64 = 4;
+    pub const INT16: i64 = 5;
+    pub const INT32: i64 = 6;
+    pub const INT64: i64 = 7;
+    pub const BOOL: i64 = 9;
+    pub const FLOAT16: i64 = 10;
+    pub const DOUBLE: i64 = 11;
+    pub const UINT32: i64 = 12;
+    pub const UINT64: i64 = 13;
+    pub const BFLOAT16: i64 = 16;
+}
+
+impl ModelProto {
+    pub fn decode(bytes: &[u8]) -> io::Result<ModelProto> {
+        let mut reader = Reader::new(bytes);
+        let mut opset_version = None;
+        let mut graph = None;
+        while let Some((number, field)) = reader.next_field()? {
+            match number {
+                7 => graph = Some(GraphProto::decode(message(field)?)?),
+                8 => {
+                    let mut opset = message(field)?;
+                    let (mut domain, mut version) = (String::new(), 0);
+                    while let Some((number, field)) = opset.next_field()? {
+                        match number {
+                            1 => domain = string(field)?,
+                            2 => version = int(field)?,
+                            _ => {}
+                        }
+                    }
+                    if domain.is_empty() || domain == "ai.onnx" {
+                        opset_version = Some(version);
+                    }
+                }
+                _ => {}
+            }
+        }
+        match graph {
+            // Models without operator set import are from before it was introduced
+            Some(graph) => Ok(ModelProto { opset_version: opset_version.unwrap_or(1), graph }),
+            None => invalid_data("The ONNX model has no graph".to_string()),
+        }
+    }
+}
+
+impl GraphProto {
+    fn decode(mut reader: Reader) -> io::Result<GraphProto> {
+        let mut graph = GraphProto { nodes: Vec::new(), initializers: Vec::new(), inputs: Vec::new(), outputs: Vec::new() };
+        while let Some((number, field)) = reader.next_field()? {
+            match number {
+                1 => graph.nodes.push(NodeProto::decode(message(field)?)?),
+                5 => graph.initializers.push(TensorProto::decode(message(field)?)?),
+                11 => graph.inputs.push(ValueInfoProto::decode(message(field)?)?),
+                12 => graph.outputs.push(ValueInfoProto::decode(message(field)?)?),
+                _ => {}
+            }
+        }
+        Ok(graph)
+    }
+}
+
+impl NodeProto {
+    fn decode(mut reader: Reader) -> io::Result<NodeProto> {
+        let mut node = NodeProto {
+            name: String::new(),
+            op_type: String::new(),
+            domain: String::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            attributes: Vec::new(),
+        };
+        while let Some((number, field)) = reader.next_field()? {
+            match number {
+                1 => node.inputs.push(string(field)?),
+                2 => node.outputs.push(string(field)?),
+                3 => node.name = string(field)?,
+                4 => node.op_type = string(field)?,
+                5 => node.attributes.push(AttributeProto::decode(message(field)?)?),
+                7 => node.domain = string(field)?,
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+}
+
+impl AttributeProto {
+    fn decode(mut reader: Reader) -> io::Result<AttributeProto> {
+        let mut attribute = AttributeProto::default();
+        while let Some((number, field)) = reader.next_field()? {
+            match number {
+                1 => attribute.name = string(field)?,
+                2 => match field {
+                    Field::Fixed32(bytes) => attribute.f = Some(f32::from_le_bytes(bytes)),
+                    _ => return invalid_data("Expected a protobuf float".to_string()),
+                },
+                3 => attribute.i = Some(int(field)?),
+                4 => attribute.s = Some(string(field)?),
+                5 => attribute.t = Some(TensorProto::decode(message(field)?)?),
+                7 => push_floats(field, &mut attribute.floats)?,
+                8 => push_ints(field, &mut attribute.ints)?,
+                _ => {}
+            }
+        }
+        Ok(attribute)
+    }
+}
+
+impl TensorProto {
+    fn decode(mut reader: Reader) -> io::Result<TensorProto> {
+        let (mut name, mut dims, mut data_type) = (String::new(), Vec::new(), data_type::FLOAT);
+        let (mut floats, mut ints, mut doubles, mut raw_data) = (Vec::new(), Vec::new(), Vec::new(), None);
+        while let Some((number, field)) = reader.next_field()? {
+            match number {
+                1 => push_ints(field, &mut dims)?,
+                2 => data_type = int(field)?,
+                4 => push_floats(field, &mut floats)?,
+                // int32_data, int64_data and uint64_data
+                5 | 7 | 11 => push_ints(field, &mut ints)?,
+                8 => name = string(field)?,
+                9 => match field {
+                    Field::Bytes(bytes) => raw_data = Some(bytes),
+                    _ => return invalid_data("Expected protobuf bytes".to_string()),
+                },
+                10 => push_doubles(field, &mut doubles)?,
+                14 if int(field)? != 0 => {
+                    return invalid_data(format!("The ONNX tensor {} uses external data, which is not supported", name));
+                }
+                _ => {}
+            }
+        }
+        if dims.iter().any(|&dim| dim < 0) {
+            return invalid_data(format!("The ONNX tensor {} has negative dimensions", name));
+        }
+        let dims: Vec<usize> = dims.into_iter().map(|dim| dim as usize).collect();
+        let data = match raw_data {
+            Some(raw_data) => decode_raw_data(raw_data, data_type, &name)?,
+            None => match data_type {
+                data_type::FLOAT => floats,
+                data_type::DOUBLE => doubles.into_iter().map(|value| value as f32).collect(),
+                // Stored in int32_data as their bits
+                data_type::FLOAT16 => ints.into_iter().map(|bits| f16_to_f32(bits as u16)).collect(),
+                data_type::BFLOAT16 => ints.into_iter().map(|bits| f32::from_bits((bits as u32) << 16)).collect(),
+                data_type::UINT64 => ints.into_iter().map(|value| value as u64 as f32).collect(),
+                data_type::UINT8
+                | data_type::INT8
+                | data_type::UINT16
+                | data_type::INT16
+                | data_type::INT32
+                | data_type::INT64
+                | data_type::BOOL
+                | data_type::UINT32 => ints.into_iter().map(|value| value as f32).collect(),
+                _ => return invalid_data(format!("The ONNX tensor {} has the unsupported type {}", name, data_type)),
+            },
+        };
+        let numel = match dims.iter().try_fold(1usize, |numel, &dim| numel.checked_mul(dim)) {
+            Some(numel) => numel,
+            None => return invalid_data(format!("The shape {:?} of the ONNX tensor {} is too large", dims, name)),
+        };
+        if data.len() != numel {
+            return invalid_data(format!(
+                "The ONNX tensor {} has {} elements instead of the {} of its shape {:?}",
+                name,
+                data.len(),
+                numel,
+                dims
+            ));
+        }
+        Ok(TensorProto { name, dims, data })
+    }
+}
+
+/// Converts the little endian elements of `raw_data` to `f32`
+fn decode_raw_data(raw_data: &[u8], data_type: i64, name: &str) -> io::Result<Vec<f32>> {
+    let size = match data_type {
+        data_type::UINT8 | data_type::INT8 | data_type::BOOL => 1,
+        data_type::UINT16 | data_type::INT16 | data_type::FLOAT16 | data_type::BFLOAT16 => 2,
+        data_type::FLOAT | data_type::INT32 | data_type::UINT32 => 4,
+        data_type::INT64 | data_type::DOUBLE | data_type::UINT64 => 8,
+        _ => return invalid_data(format!("The ONNX tensor {} has the unsupported type {}", name, data_type)),
+    };
+    if !raw_data.len().is_multiple_of(size) {
+        return invalid_data(format!("The raw data of the ONNX tensor {} is truncated", name));
+    }
+    let values = raw_data.chunks(size).map(|value| {
+        let mut bytes = [0; 8];
+        bytes[..size].copy_from_slice(value);
+        match data_type {
+            data_type::UINT8 | data_type::BOOL => value[0] as f32,
+            data_type::INT8 => value[0] as i8 as f32,
+            data_type::UINT16 => u16::from_le_bytes([value[0], value[1]]) as f32,
+            data_type::INT16 => i16::from_le_bytes([value[0], value[1]]) as f32,
+            data_type::FLOAT16 => f16_to_f32(u16::from_le_bytes([value[0], value[1]])),
+            data_type::BFLOAT16 => f32::from_bits((u16::from_le_bytes([value[0], value[1]]) as u32) << 16),
+            data_type::FLOAT => f32::from_le_bytes([value[0], value[1], value[2], value[3]]),
+            data_type::INT32 => i32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f32,
+            data_type::UINT32 => u32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f32,
+            data_type::INT64 => i64::from_le_bytes(bytes) as f32,
+            data_type::UINT64 => u64::from_le_bytes(bytes) as f32,
+            _ => f64::from_le_bytes(bytes) as f32,
+        }
+    });
+    Ok(values.collect())
+}
+
+impl ValueInfoProto {
+    fn decode(mut reader: Reader) -> io::Result<ValueInfoProto> {
+        let mut name = String::new();
+        while let Some((number, field)) = reader.next_field()? {
+            if number == 1 {
+                name = string(field)?;
+            }
+        }
+        Ok(ValueInfoProto { name })
+    }
+}
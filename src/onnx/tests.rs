@@ -0,0 +1,333 @@
+use super::proto::{ModelProto, TensorProto};
+use super::OnnxModel;
+use crate::{Conv2dParams, CpuTensor, RawTensor, ShapeStrideTrait, Window2d};
+use std::collections::HashMap;
+
+/// Encodes protobuf messages, to build ONNX models by hand
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn key(mut self, field: u64, wire_type: u64) -> Self {
+        self = self.raw_varint((field << 3) | wire_type);
+        self
+    }
+
+    fn raw_varint(mut self, mut value: u64) -> Self {
+        while value >= 0x80 {
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+        self
+    }
+
+    fn int(self, field: u64, value: i64) -> Self {
+        self.key(field, 0).raw_varint(value as u64)
+    }
+
+    fn float(mut self, field: u64, value: f32) -> Self {
+        self = self.key(field, 5);
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn bytes(mut self, field: u64, bytes: &[u8]) -> Self {
+        self = self.key(field, 2).raw_varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    fn string(self, field: u64, value: &str) -> Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    fn message(self, field: u64, message: Message) -> Self {
+        self.bytes(field, &message.0)
+    }
+}
+
+/// A float Tensor with packed `float_data`
+fn tensor(name: &str, dims: &[i64], data: &[f32]) -> Message {
+    let packed_dims = dims.iter().fold(Message::default(), |message, &dim| message.raw_varint(dim as u64));
+    let packed_data: Vec<u8> = data.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+    Message::default()
+        .bytes(1, &packed_dims.0)
+        .int(2, 1)
+        .bytes(4, &packed_data)
+        .string(8, name)
+}
+
+/// An INT64 Tensor with `raw_data` and unpacked dims
+fn int64_tensor(name: &str, dims: &[i64], data: &[i64]) -> Message {
+    let raw_data: Vec<u8> = data.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+    let message = dims.iter().fold(Message::default(), |message, &dim| message.int(1, dim));
+    message.int(2, 7).string(8, name).bytes(9, &raw_data)
+}
+
+fn int_attribute(name: &str, value: i64) -> Message {
+    Message::default().string(1, name).int(3, value).int(20, 2)
+}
+
+fn float_attribute(name: &str, value: f32) -> Message {
+    Message::default().string(1, name).float(2, value).int(20, 1)
+}
+
+fn ints_attribute(name: &str, values: &[i64]) -> Message {
+    let message = Message::default().string(1, name);
+    values.iter().fold(message, |message, &value| message.int(8, value)).int(20, 7)
+}
+
+fn node(op_type: &str, inputs: &[&str], outputs: &[&str], attributes: Vec<Message>) -> Message {
+    let mut message = Message::default();
+    for input in inputs {
+        message = message.string(1, input);
+    }
+    for output in outputs {
+        message = message.string(2, output);
+    }
+    message = message.string(3, &format!("{}_{}", op_type, outputs[0])).string(4, op_type);
+    attributes.into_iter().fold(message, |message, attribute| message.message(5, attribute))
+}
+
+fn model(opset_version: i64, nodes: Vec<Message>, initializers: Vec<Message>, inputs: &[&str], outputs: &[&str]) -> Vec<u8> {
+    let mut graph = Message::default().string(2, "graph");
+    for node in nodes {
+        graph = graph.message(1, node);
+    }
+    for initializer in initializers {
+        graph = graph.message(5, initializer);
+    }
+    for input in inputs {
+        graph = graph.message(11, Message::default().string(1, input));
+    }
+    for output in outputs {
+        graph = graph.message(12, Message::default().string(1, output));
+    }
+    let opset = Message::default().string(1, "").int(2, opset_version);
+    Message::default()
+        .int(1, 8)
+        .string(2, "hand-built")
+        .message(7, graph)
+        .message(8, opset)
+        .0
+}
+
+fn run(model: &OnnxModel, inputs: Vec<(&str, RawTensor)>) -> HashMap<String, RawTensor> {
+    model.run(inputs.into_iter().map(|(name, tensor)| (name.to_string(), tensor)).collect())
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn decodes_models_and_tensors() {
+    let bytes = model(
+        11,
+        vec![node("Relu", &["x"], &["y"], vec![int_attribute("axis", -1), ints_attribute("perm", &[1, 0])])],
+        vec![int64_tensor("shape", &[2], &[-1, 3]), tensor("weight", &[2, 1], &[0.5, -2.])],
+        &["x", "weight"],
+        &["y"],
+    );
+    let decoded = ModelProto::decode(&bytes).unwrap();
+    assert_eq!(decoded.opset_version, 11);
+    let graph = decoded.graph;
+    assert_eq!(graph.initializers[0], TensorProto { name: "shape".to_string(), dims: vec![2], data: vec![-1., 3.] });
+    assert_eq!(graph.initializers[1].dims, vec![2, 1]);
+    assert_eq!(graph.initializers[1].data, vec![0.5, -2.]);
+    let node = &graph.nodes[0];
+    assert_eq!((node.op_type.as_str(), &node.inputs, &node.outputs), ("Relu", &vec!["x".to_string()], &vec!["y".to_string()]));
+    assert_eq!(node.attributes[0].i, Some(-1));
+    assert_eq!(node.attributes[1].ints, vec![1, 0]);
+    assert_eq!(graph.inputs.len(), 2);
+
+    // A scalar of doubles, and float16 bits in int32_data
+    let doubles = Message::default().int(2, 11).bytes(10, &2.5f64.to_le_bytes());
+    let halves = Message::default().int(1, 2).int(2, 10).int(5, 0x3C00).int(5, 0xC000);
+    let graph = Message::default().message(5, doubles).message(5, halves);
+    let decoded = ModelProto::decode(&Message::default().message(7, graph).0).unwrap();
+    assert_eq!(decoded.opset_version, 1);
+    assert_eq!(decoded.graph.initializers[0].dims, Vec::<usize>::new());
+    assert_eq!(decoded.graph.initializers[0].data, vec![2.5]);
+    assert_eq!(decoded.graph.initializers[1].data, vec![1., -2.]);
+}
+
+#[test]
+fn rejects_malformed_and_unsupported_models() {
+    assert!(OnnxModel::from_bytes(b"\x0a\xff").is_err());
+    assert!(OnnxModel::from_bytes(&Message::default().int(1, 8).0).is_err());
+    let unsupported = model(13, vec![node("Resize", &["x"], &["y"], vec![])], vec![], &["x"], &["y"]);
+    let error = OnnxModel::from_bytes(&unsupported).err().unwrap();
+    assert!(error.to_string().contains("Resize"), "{}", error);
+    // The element count must match the shape
+    let truncated = model(13, vec![], vec![tensor("w", &[2, 2], &[1., 2., 3.])], &[], &[]);
+    assert!(OnnxModel::from_bytes(&truncated).is_err());
+    let huge = model(13, vec![], vec![tensor("w", &[i64::MAX, i64::MAX], &[1.])], &[], &[]);
+    let error = OnnxModel::from_bytes(&huge).err().unwrap();
+    assert!(error.to_string().contains("too large"), "{}", error);
+}
+
+#[test]
+fn runs_a_multilayer_perceptron() {
+    // softmax(relu(x @ w1 + b1) @ w2^T + b2)
+    let w1 = [1., -1., 0.5, 2., 0., 1.];
+    let w2 = [1., 0., 1., -1.];
+    let bytes = model(
+        13,
+        vec![
+            node("MatMul", &["x", "w1"], &["h"], vec![]),
+            node("Add", &["h", "b1"], &["h_bias"], vec![]),
+            node("Relu", &["h_bias"], &["h_relu"], vec![]),
+            node("Gemm", &["h_relu", "w2", "b2"], &["logits"], vec![int_attribute("transB", 1), float_attribute("alpha", 0.5)]),
+            node("Softmax", &["logits"], &["probabilities"], vec![]),
+        ],
+        vec![
+            tensor("w1", &[3, 2], &w1),
+            tensor("b1", &[2], &[0., -1.]),
+            tensor("w2", &[2, 2], &w2),
+            tensor("b2", &[2], &[0.25, 0.]),
+        ],
+        &["x"],
+        &["probabilities", "logits"],
+    );
+    let model = OnnxModel::from_bytes(&bytes).unwrap();
+    assert_eq!(model.input_names(), vec!["x"]);
+    assert_eq!(model.output_names(), vec!["probabilities", "logits"]);
+    let x = [1., 2., 3., -1., 0., 1.];
+    let outputs = run(&model, vec![("x", RawTensor::from_data_and_shape(x.to_vec(), vec![2, 3]))]);
+
+    let mut expected_logits = Vec::new();
+    for row in x.chunks(3) {
+        let hidden: Vec<f32> = (0..2)
+            .map(|j| (row[0] * w1[j] + row[1] * w1[2 + j] + row[2] * w1[4 + j] + [0., -1.][j]).max(0.))
+            .collect();
+        for i in 0..2 {
+            expected_logits.push(0.5 * (hidden[0] * w2[2 * i] + hidden[1] * w2[2 * i + 1]) + [0.25, 0.][i]);
+        }
+    }
+    assert_eq!(outputs["logits"].shape(), &[2, 2]);
+    assert_close(&outputs["logits"].to_vec(), &expected_logits);
+    let expected_probabilities: Vec<f32> = expected_logits
+        .chunks(2)
+        .flat_map(|logits| {
+            let total: f32 = logits.iter().map(|logit| logit.exp()).sum();
+            logits.iter().map(move |logit| logit.exp() / total).collect::<Vec<f32>>()
+        })
+        .collect();
+    assert_close(&outputs["probabilities"].to_vec(), &expected_probabilities);
+}
+
+#[test]
+fn runs_a_convolutional_network() {
+    let input = CpuTensor::from_data_and_shape((0..2 * 25).map(|x| (x as f32 * 0.37).sin()).collect(), vec![1, 2, 5, 5]);
+    let weight = CpuTensor::from_data_and_shape((0..3 * 2 * 9).map(|x| (x as f32 * 0.11).cos()).collect(), vec![3, 2, 3, 3]);
+    let bias = CpuTensor::from_data_and_shape(vec![0.1, -0.2, 0.3], vec![3]);
+    let bytes = model(
+        13,
+        vec![
+            node(
+                "Conv",
+                &["x", "w", "b"],
+                &["conv"],
+                vec![ints_attribute("pads", &[1, 1, 1, 1]), ints_attribute("kernel_shape", &[3, 3])],
+            ),
+            node("Relu", &["conv"], &["relu"], vec![]),
+            node(
+                "MaxPool",
+                &["relu"],
+                &["pool"],
+                vec![ints_attribute("kernel_shape", &[2, 2]), ints_attribute("strides", &[2, 2])],
+            ),
+            node("Flatten", &["pool"], &["y"], vec![]),
+        ],
+        vec![
+            tensor("w", &[3, 2, 3, 3], &weight.as_contiguous_vec()),
+            tensor("b", &[3], &bias.as_contiguous_vec()),
+        ],
+        &["x"],
+        &["y"],
+    );
+    let model = OnnxModel::from_bytes(&bytes).unwrap();
+    let outputs = run(&model, vec![("x", RawTensor::from_data_and_shape(input.as_contiguous_vec(), vec![1, 2, 5, 5]))]);
+
+    let params = Conv2dParams { padding: (1, 1), ..Conv2dParams::default() };
+    let conv = input.conv2d(&weight, Some(&bias), &params);
+    let relu = CpuTensor::from_data_and_shape(conv.as_contiguous_vec().iter().map(|x| x.max(0.)).collect(), vec![1, 3, 5, 5]);
+    let window = Window2d { stride: (2, 2), ..Window2d::new((2, 2)) };
+    let expected = relu.max_pool2d(&window);
+    assert_eq!(expected.shape(), &[1, 3, 2, 2]);
+    assert_eq!(outputs["y"].shape(), &[1, 12]);
+    assert_close(&outputs["y"].to_vec(), &expected.as_contiguous_vec());
+}
+
+#[test]
+fn runs_shape_operators() {
+    // The dynamic reshape exported by PyTorch for `x.view(x.size(0), -1)`, and a transpose
+    let bytes = model(
+        13,
+        vec![
+            node("Shape", &["x"], &["shape"], vec![]),
+            node("Gather", &["shape", "zero"], &["batch"], vec![int_attribute("axis", 0)]),
+            node("Unsqueeze", &["batch", "axes"], &["batch_1d"], vec![]),
+            node("Concat", &["batch_1d", "minus_one"], &["target"], vec![int_attribute("axis", 0)]),
+            node("Reshape", &["x", "target"], &["flat"], vec![]),
+            node("Transpose", &["x"], &["transposed"], vec![ints_attribute("perm", &[2, 0, 1])]),
+            node("Softmax", &["transposed"], &["softmax"], vec![int_attribute("axis", 1)]),
+            node("Squeeze", &["x", "axes"], &["squeezed"], vec![]),
+        ],
+        vec![
+            int64_tensor("zero", &[], &[0]),
+            int64_tensor("axes", &[1], &[0]),
+            int64_tensor("minus_one", &[1], &[-1]),
+        ],
+        &["x"],
+        &["flat", "transposed", "softmax", "squeezed"],
+    );
+    let model = OnnxModel::from_bytes(&bytes).unwrap();
+    let x: Vec<f32> = (0..6).map(|x| x as f32).collect();
+    let outputs = run(&model, vec![("x", RawTensor::from_data_and_shape(x.clone(), vec![1, 2, 3]))]);
+    assert_eq!(outputs["flat"].shape(), &[1, 6]);
+    assert_eq!(outputs["flat"].to_vec(), x);
+    assert_eq!(outputs["transposed"].shape(), &[3, 1, 2]);
+    assert_eq!(outputs["transposed"].to_vec(), &[0., 3., 1., 4., 2., 5.]);
+    // The softmax over the dimension of size 1 is 1 everywhere
+    assert_eq!(outputs["softmax"].to_vec(), vec![1.; 6]);
+    assert_eq!(outputs["squeezed"].shape(), &[2, 3]);
+}
+
+#[test]
+fn runs_operators_with_negative_axes() {
+    let bytes = model(
+        11,
+        vec![
+            node("Flatten", &["x"], &["flat"], vec![int_attribute("axis", -1)]),
+            node("Softmax", &["x"], &["softmax"], vec![int_attribute("axis", -1)]),
+            node("Shape", &["x"], &["tail"], vec![int_attribute("start", -2)]),
+            node("Shape", &["x"], &["clamped"], vec![int_attribute("start", -10), int_attribute("end", 10)]),
+        ],
+        vec![],
+        &["x"],
+        &["flat", "softmax", "tail", "clamped"],
+    );
+    let model = OnnxModel::from_bytes(&bytes).unwrap();
+    let x: Vec<f32> = (0..6).map(|x| x as f32).collect();
+    let outputs = run(&model, vec![("x", RawTensor::from_data_and_shape(x, vec![1, 2, 3]))]);
+    assert_eq!(outputs["flat"].shape(), &[2, 3]);
+    // Before opset 13 the softmax is over the dimensions from the axis on, here the last one
+    let denominator: f32 = [0f32, 1., 2.].iter().map(|x| x.exp()).sum();
+    let row: Vec<f32> = [0f32, 1., 2.].iter().map(|x| x.exp() / denominator).collect();
+    assert_close(&outputs["softmax"].to_vec(), &[row.clone(), row].concat());
+    assert_eq!(outputs["tail"].to_vec(), &[2., 3.]);
+    assert_eq!(outputs["clamped"].to_vec(), &[1., 2., 3.]);
+}
+
+#[test]
+#[should_panic(expected = "Missing the input x of the ONNX model")]
+fn running_without_inputs_panics() {
+    let bytes = model(13, vec![node("Relu", &["x"], &["y"], vec![])], vec![], &["x"], &["y"]);
+    OnnxModel::from_bytes(&bytes).unwrap().run(HashMap::new());
+}
@@ -0,0 +1,180 @@
+use crate::checkpoint::{LoadReport, StateDict};
+use crate::optim::{load_optimizer_state, save_optimizer_state, Optimizer, ParamGroup, ParamIdx, ParamState, SavedState};
+use crate::{AdamStep, RawTensor, Tensor};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+
+/// The Adam optimizer, from [Adam: A Method for Stochastic Optimization](https://arxiv.org/abs/1412.6980).
+/// The weight decay of the groups is added to the gradients, see [`AdamW`] for the decoupled
+/// version.
+#[derive(Debug)]
+pub struct Adam {
+    param_groups: Vec<ParamGroup>,
+    pub betas: (f32, f32),
+    pub eps: f32,
+    decoupled_weight_decay: bool,
+    state: HashMap<ParamIdx, AdamState>,
+}
+
+#[derive(Debug)]
+struct AdamState {
+    step: u32,
+    exp_avg: RawTensor,
+    exp_avg_sq: RawTensor,
+}
+
+impl ParamState for AdamState {
+    const TENSORS: &'static [&'static str] = &["exp_avg", "exp_avg_sq"];
+    const SCALARS: &'static [&'static str] = &["step"];
+
+    fn save(&self) -> SavedState {
+        let tensors = vec![("exp_avg", self.exp_avg.clone()), ("exp_avg_sq", self.exp_avg_sq.clone())];
+        (tensors, vec![("step", self.step as f64)])
+    }
+
+    fn load(tensors: &mut HashMap<&str, RawTensor>, scalars: &HashMap<&str, f64>) -> Result<Self, &'static str> {
+        Ok(Self {
+            step: *scalars.get("step").ok_or("step")? as u32,
+            exp_avg: tensors.remove("exp_avg").ok_or("exp_avg")?,
+            exp_avg_sq: tensors.remove("exp_avg_sq").ok_or("exp_avg_sq")?,
+        })
+    }
+}
+
+impl Adam {
+    /// Creates an Adam optimizer with a single group of parameters, betas `(0.9, 0.999)`
+    /// and eps `1e-8`
+    pub fn new(params: Vec<Tensor>, lr: f32) -> Self {
+        Self::with_param_groups(vec![ParamGroup::new(params, lr)])
+    }
+
+    pub fn with_param_groups(param_groups: Vec<ParamGroup>) -> Self {
+        Self {
+            param_groups,
+            betas: (0.9, 0.999),
+            eps: 1e-8,
+            decoupled_weight_decay: false,
+            state: HashMap::new(),
+        }
+    }
+}
+
+impl Optimizer for Adam {
+    fn step(&mut self) {
+        let (beta1, beta2) = self.betas;
+        let (eps, decoupled_weight_decay) = (self.eps, self.decoupled_weight_decay);
+        let state = &mut self.state;
+        for (group_idx, group) in self.param_groups.iter().enumerate() {
+            for (param_idx, param) in group.params.iter().enumerate() {
+                param.update_with_grad(|data, grad| {
+                    let state = state.entry((group_idx, param_idx)).or_insert_with(|| AdamState {
+                        step: 0,
+                        exp_avg: RawTensor::zeros_like(data),
+                        exp_avg_sq: RawTensor::zeros_like(data),
+                    });
+                    state.step += 1;
+                    let step = AdamStep {
+                        lr: group.lr,
+                        beta1,
+                        beta2,
+                        eps,
+                        weight_decay: group.weight_decay,
+                        decoupled_weight_decay,
+                        step: state.step,
+                    };
+                    data.adam_step(grad, &mut state.exp_avg, &mut state.exp_avg_sq, &step);
+                });
+            }
+        }
+    }
+
+    fn param_groups(&self) -> &[ParamGroup] {
+        &self.param_groups
+    }
+
+    fn param_groups_mut(&mut self) -> &mut Vec<ParamGroup> {
+        &mut self.param_groups
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let hyperparameters = [
+            ("betas.0", self.betas.0 as f64),
+            ("betas.1", self.betas.1 as f64),
+            ("eps", self.eps as f64),
+        ];
+        save_optimizer_state(&self.param_groups, &self.state, &hyperparameters)
+    }
+
+    fn load_state_dict(&mut self, state_dict: &StateDict, strict: bool) -> LoadReport {
+        let loaded = load_optimizer_state(&mut self.param_groups, state_dict, strict, &["betas.0", "betas.1", "eps"]);
+        for (name, value) in loaded.hyperparameters {
+            match name.as_str() {
+                "betas.0" => self.betas.0 = value as f32,
+                "betas.1" => self.betas.1 = value as f32,
+                _ => self.eps = value as f32,
+            }
+        }
+        self.state = loaded.state;
+        loaded.report
+    }
+}
+
+/// Adam with decoupled weight decay, from
+/// [Decoupled Weight Decay Regularization](https://arxiv.org/abs/1711.05101).
+/// The parameters are decayed directly instead of through the gradient. Its hyperparameters
+/// are the ones of [`Adam`], which it dereferences to.
+#[derive(Debug)]
+pub struct AdamW {
+    adam: Adam,
+}
+
+impl AdamW {
+    /// Creates an AdamW optimizer with a single group of parameters and weight decay `0.01`
+    pub fn new(params: Vec<Tensor>, lr: f32) -> Self {
+        let mut group = ParamGroup::new(params, lr);
+        group.weight_decay = 0.01;
+        Self::with_param_groups(vec![group])
+    }
+
+    pub fn with_param_groups(param_groups: Vec<ParamGroup>) -> Self {
+        let mut adam = Adam::with_param_groups(param_groups);
+        adam.decoupled_weight_decay = true;
+        Self { adam }
+    }
+}
+
+impl Deref for AdamW {
+    type Target = Adam;
+
+    fn deref(&self) -> &Adam {
+        &self.adam
+    }
+}
+
+impl DerefMut for AdamW {
+    fn deref_mut(&mut self) -> &mut Adam {
+        &mut self.adam
+    }
+}
+
+impl Optimizer for AdamW {
+    fn step(&mut self) {
+        self.adam.step();
+    }
+
+    fn param_groups(&self) -> &[ParamGroup] {
+        self.adam.param_groups()
+    }
+
+    fn param_groups_mut(&mut self) -> &mut Vec<ParamGroup> {
+        self.adam.param_groups_mut()
+    }
+
+    fn state_dict(&self) -> StateDict {
+        self.adam.state_dict()
+    }
+
+    fn load_state_dict(&mut self, state_dict: &StateDict, strict: bool) -> LoadReport {
+        self.adam.load_state_dict(state_dict, strict)
+    }
+}
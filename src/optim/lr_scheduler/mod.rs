@@ -0,0 +1,346 @@
+//! Learning rate schedulers, which change the learning rate of the parameter groups of an
+//! [`Optimizer`] as training progresses.
+//!
+//! Schedulers do not own the optimizer, it is given to every [`LrScheduler::step`] call,
+//! normally once per epoch after [`Optimizer::step`].
+//!
+//! # Examples
+//!
+//! ```
+//! use tensor_compute::optim::lr_scheduler::{LrScheduler, StepLr};
+//! use tensor_compute::optim::{Optimizer, Sgd};
+//! let mut optimizer = Sgd::new(vec![], 0.1);
+//! let mut scheduler = StepLr::new(&optimizer, 2, 0.5);
+//! for _epoch in 0..4 {
+//!     // train, calling optimizer.step() for each batch
+//!     scheduler.step(&mut optimizer);
+//! }
+//! assert_eq!(optimizer.param_groups()[0].lr, 0.025);
+//! ```
+use crate::optim::Optimizer;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+#[cfg(test)]
+mod tests;
+
+/// The progress of a scheduler, which can be saved alongside a model checkpoint and loaded
+/// into a scheduler created with the same hyperparameters in order to resume training.
+pub type SchedulerState = HashMap<String, f64>;
+
+pub trait LrScheduler {
+    /// Advances the schedule by one step and updates the learning rates of the optimizer
+    fn step(&mut self, optimizer: &mut dyn Optimizer);
+
+    /// The learning rates set by the last step, one per parameter group
+    fn last_lrs(&self) -> Vec<f32>;
+
+    fn state(&self) -> SchedulerState;
+
+    /// Restores the progress saved by [`LrScheduler::state`]. Panics if any entry is missing.
+    fn load_state(&mut self, state: &SchedulerState);
+}
+
+/// Progress shared by the schedulers whose learning rate is a function of the initial one and
+/// of the number of steps taken
+#[derive(Debug, Clone)]
+struct Progress {
+    base_lrs: Vec<f32>,
+    last_lrs: Vec<f32>,
+    last_epoch: usize,
+}
+
+impl Progress {
+    fn new(optimizer: &dyn Optimizer) -> Self {
+        let base_lrs: Vec<f32> = optimizer.param_groups().iter().map(|group| group.lr).collect();
+        Self {
+            last_lrs: base_lrs.clone(),
+            base_lrs,
+            last_epoch: 0,
+        }
+    }
+
+    /// Sets the learning rates of `optimizer` to `lr(base_lr, epoch)`
+    fn apply<F: Fn(f32, usize) -> f32>(&mut self, optimizer: &mut dyn Optimizer, lr: F) {
+        let groups = optimizer.param_groups_mut();
+        assert_eq!(
+            groups.len(),
+            self.base_lrs.len(),
+            "The optimizer parameter groups changed after the scheduler was created"
+        );
+        self.last_lrs = self
+            .base_lrs
+            .iter()
+            .map(|&base_lr| lr(base_lr, self.last_epoch))
+            .collect();
+        for (group, &lr) in groups.iter_mut().zip(&self.last_lrs) {
+            group.lr = lr;
+        }
+    }
+
+    /// Advances one step and applies the new learning rates
+    fn step<F: Fn(f32, usize) -> f32>(&mut self, optimizer: &mut dyn Optimizer, lr: F) {
+        self.last_epoch += 1;
+        self.apply(optimizer, lr);
+    }
+
+    fn state(&self) -> SchedulerState {
+        let mut state = SchedulerState::new();
+        state.insert("last_epoch".to_string(), self.last_epoch as f64);
+        for (idx, (base_lr, last_lr)) in self.base_lrs.iter().zip(&self.last_lrs).enumerate() {
+            state.insert(format!("base_lr.{}", idx), *base_lr as f64);
+            state.insert(format!("last_lr.{}", idx), *last_lr as f64);
+        }
+        state
+    }
+
+    fn load_state(&mut self, state: &SchedulerState) {
+        self.last_epoch = state_entry(state, "last_epoch") as usize;
+        for idx in 0..self.base_lrs.len() {
+            self.base_lrs[idx] = state_entry(state, &format!("base_lr.{}", idx)) as f32;
+            self.last_lrs[idx] = state_entry(state, &format!("last_lr.{}", idx)) as f32;
+        }
+    }
+}
+
+fn state_entry(state: &SchedulerState, key: &str) -> f64 {
+    *state
+        .get(key)
+        .unwrap_or_else(|| panic!("Scheduler state is missing the {} entry", key))
+}
+
+/// Implements [`LrScheduler`] for a scheduler with a `progress` field and a
+/// `fn lr(&self, base_lr: f32, epoch: usize) -> f32` method
+macro_rules! closed_form_scheduler {
+    ($scheduler:ty) => {
+        impl LrScheduler for $scheduler {
+            fn step(&mut self, optimizer: &mut dyn Optimizer) {
+                let schedule = self.clone();
+                self.progress.step(optimizer, |base_lr, epoch| schedule.lr(base_lr, epoch));
+            }
+
+            fn last_lrs(&self) -> Vec<f32> {
+                self.progress.last_lrs.clone()
+            }
+
+            fn state(&self) -> SchedulerState {
+                self.progress.state()
+            }
+
+            fn load_state(&mut self, state: &SchedulerState) {
+                self.progress.load_state(state);
+            }
+        }
+    };
+}
+
+/// Multiplies the learning rate by `gamma` every `step_size` steps
+#[derive(Debug, Clone)]
+pub struct StepLr {
+    progress: Progress,
+    step_size: usize,
+    gamma: f32,
+}
+
+impl StepLr {
+    pub fn new(optimizer: &dyn Optimizer, step_size: usize, gamma: f32) -> Self {
+        assert!(step_size > 0, "step_size must be positive");
+        Self {
+            progress: Progress::new(optimizer),
+            step_size,
+            gamma,
+        }
+    }
+
+    fn lr(&self, base_lr: f32, epoch: usize) -> f32 {
+        base_lr * self.gamma.powi((epoch / self.step_size) as i32)
+    }
+}
+
+closed_form_scheduler!(StepLr);
+
+/// Multiplies the learning rate by `gamma` every step
+#[derive(Debug, Clone)]
+pub struct ExponentialLr {
+    progress: Progress,
+    gamma: f32,
+}
+
+impl ExponentialLr {
+    pub fn new(optimizer: &dyn Optimizer, gamma: f32) -> Self {
+        Self {
+            progress: Progress::new(optimizer),
+            gamma,
+        }
+    }
+
+    fn lr(&self, base_lr: f32, epoch: usize) -> f32 {
+        base_lr * self.gamma.powi(epoch as i32)
+    }
+}
+
+closed_form_scheduler!(ExponentialLr);
+
+/// Anneals the learning rate from its initial value to `eta_min` following half a cosine
+/// period over `t_max` steps. After `t_max` steps the learning rate rises again, following the
+/// cosine.
+#[derive(Debug, Clone)]
+pub struct CosineAnnealingLr {
+    progress: Progress,
+    t_max: usize,
+    eta_min: f32,
+}
+
+impl CosineAnnealingLr {
+    pub fn new(optimizer: &dyn Optimizer, t_max: usize, eta_min: f32) -> Self {
+        assert!(t_max > 0, "t_max must be positive");
+        Self {
+            progress: Progress::new(optimizer),
+            t_max,
+            eta_min,
+        }
+    }
+
+    fn lr(&self, base_lr: f32, epoch: usize) -> f32 {
+        let cos = (PI * epoch as f32 / self.t_max as f32).cos();
+        self.eta_min + (base_lr - self.eta_min) * (1. + cos) / 2.
+    }
+}
+
+closed_form_scheduler!(CosineAnnealingLr);
+
+/// Increases the learning rate linearly from `start_factor` times its initial value up to the
+/// initial value over `warmup_steps` steps, keeping it constant afterwards
+#[derive(Debug, Clone)]
+pub struct LinearWarmupLr {
+    progress: Progress,
+    warmup_steps: usize,
+    start_factor: f32,
+}
+
+impl LinearWarmupLr {
+    /// The learning rates of the optimizer are set to the starting ones right away
+    pub fn new(optimizer: &mut dyn Optimizer, warmup_steps: usize, start_factor: f32) -> Self {
+        assert!(warmup_steps > 0, "warmup_steps must be positive");
+        let mut scheduler = Self {
+            progress: Progress::new(optimizer),
+            warmup_steps,
+            start_factor,
+        };
+        let schedule = scheduler.clone();
+        scheduler
+            .progress
+            .apply(optimizer, |base_lr, epoch| schedule.lr(base_lr, epoch));
+        scheduler
+    }
+
+    fn lr(&self, base_lr: f32, epoch: usize) -> f32 {
+        let progress = epoch.min(self.warmup_steps) as f32 / self.warmup_steps as f32;
+        base_lr * (self.start_factor + (1. - self.start_factor) * progress)
+    }
+}
+
+closed_form_scheduler!(LinearWarmupLr);
+
+/// Whether [`ReduceLrOnPlateau`] expects the metric to decrease, like a loss, or increase,
+/// like an accuracy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlateauMode {
+    Min,
+    Max,
+}
+
+/// Multiplies the learning rate by `factor` when the metric given to
+/// [`ReduceLrOnPlateau::step`] does not improve for more than `patience` steps.
+///
+/// A metric improves if it is better than the best one seen by more than `threshold`, relative
+/// to the best one. After a reduction, `cooldown` steps are ignored. Learning rates are never
+/// reduced below `min_lr`.
+#[derive(Debug, Clone)]
+pub struct ReduceLrOnPlateau {
+    pub mode: PlateauMode,
+    pub factor: f32,
+    pub patience: usize,
+    pub threshold: f32,
+    pub cooldown: usize,
+    pub min_lr: f32,
+    best: f64,
+    num_bad_epochs: usize,
+    cooldown_counter: usize,
+    last_epoch: usize,
+}
+
+impl ReduceLrOnPlateau {
+    /// Creates a scheduler with factor `0.1`, patience `10`, threshold `1e-4`, no cooldown and
+    /// no minimum learning rate
+    pub fn new(mode: PlateauMode) -> Self {
+        Self {
+            mode,
+            factor: 0.1,
+            patience: 10,
+            threshold: 1e-4,
+            cooldown: 0,
+            min_lr: 0.,
+            best: Self::worst(mode),
+            num_bad_epochs: 0,
+            cooldown_counter: 0,
+            last_epoch: 0,
+        }
+    }
+
+    fn worst(mode: PlateauMode) -> f64 {
+        match mode {
+            PlateauMode::Min => f64::INFINITY,
+            PlateauMode::Max => f64::NEG_INFINITY,
+        }
+    }
+
+    fn is_better(&self, metric: f64) -> bool {
+        let threshold = self.threshold as f64;
+        match self.mode {
+            PlateauMode::Min => metric < self.best * (1. - threshold),
+            PlateauMode::Max => metric > self.best * (1. + threshold),
+        }
+    }
+
+    /// Records the metric of the last epoch, reducing the learning rates if it has not
+    /// improved for too long
+    pub fn step(&mut self, optimizer: &mut dyn Optimizer, metric: f32) {
+        let metric = metric as f64;
+        self.last_epoch += 1;
+        if self.is_better(metric) {
+            self.best = metric;
+            self.num_bad_epochs = 0;
+        } else {
+            self.num_bad_epochs += 1;
+        }
+        if self.cooldown_counter > 0 {
+            self.cooldown_counter -= 1;
+            self.num_bad_epochs = 0;
+        }
+        if self.num_bad_epochs > self.patience {
+            for group in optimizer.param_groups_mut().iter_mut() {
+                group.lr = (group.lr * self.factor).max(self.min_lr);
+            }
+            self.cooldown_counter = self.cooldown;
+            self.num_bad_epochs = 0;
+        }
+    }
+
+    pub fn state(&self) -> SchedulerState {
+        let mut state = SchedulerState::new();
+        state.insert("best".to_string(), self.best);
+        state.insert("num_bad_epochs".to_string(), self.num_bad_epochs as f64);
+        state.insert("cooldown_counter".to_string(), self.cooldown_counter as f64);
+        state.insert("last_epoch".to_string(), self.last_epoch as f64);
+        state
+    }
+
+    /// Restores the progress saved by [`ReduceLrOnPlateau::state`]
+    pub fn load_state(&mut self, state: &SchedulerState) {
+        self.best = state_entry(state, "best");
+        self.num_bad_epochs = state_entry(state, "num_bad_epochs") as usize;
+        self.cooldown_counter = state_entry(state, "cooldown_counter") as usize;
+        self.last_epoch = state_entry(state, "last_epoch") as usize;
+    }
+}
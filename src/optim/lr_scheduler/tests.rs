@@ -0,0 +1,91 @@
+use crate::optim::lr_scheduler::{
+    CosineAnnealingLr, ExponentialLr, LinearWarmupLr, LrScheduler, PlateauMode, ReduceLrOnPlateau, StepLr,
+};
+use crate::optim::{Optimizer, ParamGroup, Sgd};
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+}
+
+fn lrs<S: LrScheduler>(scheduler: &mut S, optimizer: &mut Sgd, steps: usize) -> Vec<f32> {
+    (0..steps)
+        .map(|_| {
+            scheduler.step(optimizer);
+            optimizer.param_groups()[0].lr
+        })
+        .collect()
+}
+
+#[test]
+fn step_lr_decays_every_step_size() {
+    let mut optimizer = Sgd::new(vec![], 1.);
+    let mut scheduler = StepLr::new(&optimizer, 2, 0.1);
+    let lrs = lrs(&mut scheduler, &mut optimizer, 5);
+    for (actual, expected) in lrs.iter().zip(&[1., 0.1, 0.1, 0.01, 0.01]) {
+        assert_close(*actual, *expected);
+    }
+}
+
+#[test]
+fn exponential_lr_updates_all_groups() {
+    let mut optimizer = Sgd::with_param_groups(vec![ParamGroup::new(vec![], 1.), ParamGroup::new(vec![], 0.5)]);
+    let mut scheduler = ExponentialLr::new(&optimizer, 0.5);
+    scheduler.step(&mut optimizer);
+    scheduler.step(&mut optimizer);
+    assert_eq!(scheduler.last_lrs(), vec![0.25, 0.125]);
+    assert_eq!(optimizer.param_groups()[1].lr, 0.125);
+}
+
+#[test]
+fn cosine_annealing_reaches_eta_min() {
+    let mut optimizer = Sgd::new(vec![], 1.);
+    let mut scheduler = CosineAnnealingLr::new(&optimizer, 4, 0.1);
+    let lrs = lrs(&mut scheduler, &mut optimizer, 4);
+    assert_close(lrs[1], 0.55);
+    assert_close(lrs[3], 0.1);
+}
+
+#[test]
+fn linear_warmup_starts_low() {
+    let mut optimizer = Sgd::new(vec![], 1.);
+    let mut scheduler = LinearWarmupLr::new(&mut optimizer, 4, 0.2);
+    assert_close(optimizer.param_groups()[0].lr, 0.2);
+    let lrs = lrs(&mut scheduler, &mut optimizer, 6);
+    assert_close(lrs[0], 0.4);
+    assert_close(lrs[3], 1.);
+    assert_close(lrs[5], 1.);
+}
+
+#[test]
+fn reduce_on_plateau_waits_for_patience() {
+    let mut optimizer = Sgd::new(vec![], 1.);
+    let mut scheduler = ReduceLrOnPlateau::new(PlateauMode::Min);
+    scheduler.patience = 1;
+    scheduler.factor = 0.5;
+    scheduler.min_lr = 0.3;
+    for (loss, expected_lr) in [(1., 1.), (1., 1.), (1., 0.5), (0.5, 0.5), (0.6, 0.5), (0.6, 0.3)].iter() {
+        scheduler.step(&mut optimizer, *loss);
+        assert_close(optimizer.param_groups()[0].lr, *expected_lr);
+    }
+}
+
+#[test]
+fn state_round_trips() {
+    let mut optimizer = Sgd::new(vec![], 1.);
+    let mut scheduler = StepLr::new(&optimizer, 2, 0.1);
+    lrs(&mut scheduler, &mut optimizer, 3);
+    let state = scheduler.state();
+
+    let mut resumed_optimizer = Sgd::new(vec![], 1.);
+    let mut resumed = StepLr::new(&resumed_optimizer, 2, 0.1);
+    resumed.load_state(&state);
+    resumed.step(&mut resumed_optimizer);
+    scheduler.step(&mut optimizer);
+    assert_eq!(resumed_optimizer.param_groups()[0].lr, optimizer.param_groups()[0].lr);
+
+    let mut plateau = ReduceLrOnPlateau::new(PlateauMode::Max);
+    plateau.step(&mut optimizer, 0.9);
+    let mut resumed_plateau = ReduceLrOnPlateau::new(PlateauMode::Max);
+    resumed_plateau.load_state(&plateau.state());
+    assert_eq!(resumed_plateau.state(), plateau.state());
+}
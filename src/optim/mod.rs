@@ -0,0 +1,232 @@
+//! Optimizers update the parameters of a model using the gradients computed by
+//! [`Tensor::backward`]. The parameters and the optimizer state are updated in place, on the
+//! device holding them.
+//!
+//! # Examples
+//!
+//! ```
+//! use tensor_compute::optim::{Optimizer, Sgd};
+//! use tensor_compute::Tensor;
+//! let x = Tensor::from_data_and_shape(vec![1., 2.], vec![2]);
+//! let mut optimizer = Sgd::new(vec![x.shallow_clone()], 0.25);
+//! optimizer.zero_grad();
+//! x.dot_mul(&x).sum().backward();
+//! optimizer.step();
+//! assert_eq!(x.to_vec(), &[0.5, 1.]);
+//! ```
+use crate::checkpoint::{LoadReport, ShapeMismatch, StateDict};
+use crate::{RawTensor, Tensor};
+use std::collections::HashMap;
+
+pub mod lr_scheduler;
+mod adam;
+mod rmsprop;
+mod sgd;
+pub use adam::{Adam, AdamW};
+pub use rmsprop::RmsProp;
+pub use sgd::Sgd;
+
+#[cfg(test)]
+mod tests;
+
+/// A set of parameters sharing the same learning rate and weight decay
+#[derive(Debug)]
+pub struct ParamGroup {
+    pub params: Vec<Tensor>,
+    pub lr: f32,
+    pub weight_decay: f32,
+}
+
+impl ParamGroup {
+    /// Creates a group without weight decay
+    pub fn new(params: Vec<Tensor>, lr: f32) -> Self {
+        Self {
+            params,
+            lr,
+            weight_decay: 0.,
+        }
+    }
+}
+
+pub trait Optimizer {
+    /// Updates every parameter which has a gradient. Parameters without one are skipped.
+    fn step(&mut self);
+
+    fn param_groups(&self) -> &[ParamGroup];
+
+    fn param_groups_mut(&mut self) -> &mut Vec<ParamGroup>;
+
+    /// Adds a group of parameters, for example the parameters of a layer being unfrozen
+    fn add_param_group(&mut self, group: ParamGroup) {
+        self.param_groups_mut().push(group);
+    }
+
+    /// Clears the gradients of all parameters, see [`Tensor::zero_grad`]
+    fn zero_grad(&mut self) {
+        for group in self.param_groups() {
+            for param in &group.params {
+                param.zero_grad();
+            }
+        }
+    }
+
+    /// Returns the learning rate and weight decay of each group, as `param_groups.0.lr`, the
+    /// hyperparameters, such as `momentum`, and copies of the state of each parameter, such
+    /// as `state.0.1.momentum_buffer` for the second parameter of the first group. The
+    /// parameters themselves are saved with their Module.
+    fn state_dict(&self) -> StateDict;
+
+    /// Restores the state saved by [`Optimizer::state_dict`] into an optimizer with the same
+    /// parameter groups. Parameters without a saved state start from scratch.
+    ///
+    /// Returns the keys which are missing or unexpected and the shape mismatches. When
+    /// `strict` is set, panics if there are any, before changing anything. Otherwise the
+    /// matching entries are loaded.
+    fn load_state_dict(&mut self, state_dict: &StateDict, strict: bool) -> LoadReport;
+}
+
+/// Identifies a parameter by the index of its group and its index inside the group
+pub(crate) type ParamIdx = (usize, usize);
+
+/// The named Tensors and scalars of a [`ParamState`]
+pub(crate) type SavedState = (Vec<(&'static str, RawTensor)>, Vec<(&'static str, f64)>);
+
+/// The state an optimizer keeps for each parameter, saved under `state.{group}.{param}.{name}`
+pub(crate) trait ParamState: Sized {
+    /// The names of the Tensors of the state, which have the shape of the parameter
+    const TENSORS: &'static [&'static str];
+    /// The names of the scalars of the state
+    const SCALARS: &'static [&'static str];
+
+    fn save(&self) -> SavedState;
+
+    /// Rebuilds the state from its saved entries, or returns the name of a missing one
+    fn load(tensors: &mut HashMap<&str, RawTensor>, scalars: &HashMap<&str, f64>) -> Result<Self, &'static str>;
+}
+
+/// Builds the [`StateDict`] of an optimizer, see [`Optimizer::state_dict`]
+pub(crate) fn save_optimizer_state<S: ParamState>(
+    groups: &[ParamGroup],
+    state: &HashMap<ParamIdx, S>,
+    hyperparameters: &[(&str, f64)],
+) -> StateDict {
+    let mut state_dict = StateDict::new();
+    for (idx, group) in groups.iter().enumerate() {
+        state_dict.scalars.insert(format!("param_groups.{}.lr", idx), group.lr as f64);
+        state_dict.scalars.insert(format!("param_groups.{}.weight_decay", idx), group.weight_decay as f64);
+    }
+    for (name, value) in hyperparameters {
+        state_dict.scalars.insert(name.to_string(), *value);
+    }
+    for ((group_idx, param_idx), param_state) in state {
+        let (tensors, scalars) = param_state.save();
+        let key = |name: &str| format!("state.{}.{}.{}", group_idx, param_idx, name);
+        state_dict.tensors.extend(tensors.into_iter().map(|(name, tensor)| (key(name), tensor)));
+        state_dict.scalars.extend(scalars.into_iter().map(|(name, value)| (key(name), value)));
+    }
+    state_dict
+}
+
+/// An optimizer state loaded from a [`StateDict`], see [`load_optimizer_state`]
+pub(crate) struct LoadedState<S> {
+    pub state: HashMap<ParamIdx, S>,
+    /// The values of the hyperparameters found in the StateDict
+    pub hyperparameters: HashMap<String, f64>,
+    pub report: LoadReport,
+}
+
+/// Splits `state.{group}.{param}.{name}` keys, checking the parameter exists
+fn parse_state_key<'a>(groups: &[ParamGroup], key: &'a str) -> Option<(ParamIdx, &'a str)> {
+    let mut parts = key.strip_prefix("state.")?.splitn(3, '.');
+    let group_idx: usize = parts.next()?.parse().ok()?;
+    let param_idx: usize = parts.next()?.parse().ok()?;
+    let name = parts.next()?;
+    groups.get(group_idx)?.params.get(param_idx)?;
+    Some(((group_idx, param_idx), name))
+}
+
+/// Reads the state of an optimizer from `state_dict`, see [`Optimizer::load_state_dict`].
+/// Updates the learning rates and weight decays of the groups, the caller setting the
+/// hyperparameters and the state.
+pub(crate) fn load_optimizer_state<S: ParamState>(
+    groups: &mut [ParamGroup],
+    state_dict: &StateDict,
+    strict: bool,
+    hyperparameters: &[&str],
+) -> LoadedState<S> {
+    let mut report = LoadReport::default();
+    let group_keys: Vec<String> = (0..groups.len())
+        .flat_map(|idx| vec![format!("param_groups.{}.lr", idx), format!("param_groups.{}.weight_decay", idx)])
+        .collect();
+    let expected_scalars: Vec<&str> = group_keys.iter().map(String::as_str).chain(hyperparameters.iter().copied()).collect();
+    report.missing_keys = expected_scalars
+        .iter()
+        .filter(|key| !state_dict.scalars.contains_key(**key))
+        .map(|key| key.to_string())
+        .collect();
+
+    let mut tensors: HashMap<ParamIdx, HashMap<&str, RawTensor>> = HashMap::new();
+    let mut scalars: HashMap<ParamIdx, HashMap<&str, f64>> = HashMap::new();
+    for (key, tensor) in &state_dict.tensors {
+        match parse_state_key(groups, key) {
+            Some((idx, name)) if S::TENSORS.contains(&name) => {
+                let expected = groups[idx.0].params[idx.1].shape();
+                let found: Vec<usize> = tensor.shape().iter().copied().collect();
+                if found == expected {
+                    tensors.entry(idx).or_default().insert(name, tensor.clone());
+                } else {
+                    report.shape_mismatches.push(ShapeMismatch { key: key.clone(), expected, found });
+                }
+            }
+            _ => report.unexpected_keys.push(key.clone()),
+        }
+    }
+    for (key, value) in &state_dict.scalars {
+        if expected_scalars.contains(&key.as_str()) {
+            continue;
+        }
+        match parse_state_key(groups, key) {
+            Some((idx, name)) if S::SCALARS.contains(&name) => {
+                scalars.entry(idx).or_default().insert(name, *value);
+            }
+            _ => report.unexpected_keys.push(key.clone()),
+        }
+    }
+
+    let mut state = HashMap::new();
+    let no_scalars = HashMap::new();
+    let saved: Vec<ParamIdx> = tensors.keys().chain(scalars.keys()).copied().collect();
+    for idx in saved {
+        if state.contains_key(&idx) {
+            continue;
+        }
+        let mut param_tensors = tensors.remove(&idx).unwrap_or_default();
+        match S::load(&mut param_tensors, scalars.get(&idx).unwrap_or(&no_scalars)) {
+            Ok(param_state) => {
+                state.insert(idx, param_state);
+            }
+            Err(name) => {
+                let key = format!("state.{}.{}.{}", idx.0, idx.1, name);
+                if !report.shape_mismatches.iter().any(|mismatch| mismatch.key == key) {
+                    report.missing_keys.push(key);
+                }
+            }
+        }
+    }
+    report.sort();
+    report.check_strict(strict);
+
+    for (idx, group) in groups.iter_mut().enumerate() {
+        if let Some(&lr) = state_dict.scalars.get(&format!("param_groups.{}.lr", idx)) {
+            group.lr = lr as f32;
+        }
+        if let Some(&weight_decay) = state_dict.scalars.get(&format!("param_groups.{}.weight_decay", idx)) {
+            group.weight_decay = weight_decay as f32;
+        }
+    }
+    let hyperparameters = hyperparameters
+        .iter()
+        .filter_map(|name| state_dict.scalars.get(*name).map(|value| (name.to_string(), *value)))
+        .collect();
+    LoadedState { state, hyperparameters, report }
+}
@@ -0,0 +1,115 @@
+use crate::checkpoint::{LoadReport, StateDict};
+use crate::optim::{load_optimizer_state, save_optimizer_state, Optimizer, ParamGroup, ParamIdx, ParamState, SavedState};
+use crate::{RawTensor, RmsPropStep, Tensor};
+use std::collections::HashMap;
+
+/// The RMSprop optimizer, which divides the gradients by a running average of their squares
+#[derive(Debug)]
+pub struct RmsProp {
+    param_groups: Vec<ParamGroup>,
+    pub alpha: f32,
+    pub eps: f32,
+    pub momentum: f32,
+    state: HashMap<ParamIdx, RmsPropState>,
+}
+
+#[derive(Debug)]
+struct RmsPropState {
+    square_avg: RawTensor,
+    momentum_buffer: Option<RawTensor>,
+}
+
+impl ParamState for RmsPropState {
+    const TENSORS: &'static [&'static str] = &["square_avg", "momentum_buffer"];
+    const SCALARS: &'static [&'static str] = &[];
+
+    fn save(&self) -> SavedState {
+        let mut tensors = vec![("square_avg", self.square_avg.clone())];
+        tensors.extend(self.momentum_buffer.iter().map(|buffer| ("momentum_buffer", buffer.clone())));
+        (tensors, Vec::new())
+    }
+
+    fn load(tensors: &mut HashMap<&str, RawTensor>, _scalars: &HashMap<&str, f64>) -> Result<Self, &'static str> {
+        Ok(Self {
+            square_avg: tensors.remove("square_avg").ok_or("square_avg")?,
+            momentum_buffer: tensors.remove("momentum_buffer"),
+        })
+    }
+}
+
+impl RmsProp {
+    /// Creates a RMSprop optimizer with a single group of parameters, alpha `0.99`, eps `1e-8`
+    /// and no momentum
+    pub fn new(params: Vec<Tensor>, lr: f32) -> Self {
+        Self::with_param_groups(vec![ParamGroup::new(params, lr)])
+    }
+
+    pub fn with_param_groups(param_groups: Vec<ParamGroup>) -> Self {
+        Self {
+            param_groups,
+            alpha: 0.99,
+            eps: 1e-8,
+            momentum: 0.,
+            state: HashMap::new(),
+        }
+    }
+}
+
+impl Optimizer for RmsProp {
+    fn step(&mut self) {
+        let (alpha, eps, momentum) = (self.alpha, self.eps, self.momentum);
+        let state = &mut self.state;
+        for (group_idx, group) in self.param_groups.iter().enumerate() {
+            for (param_idx, param) in group.params.iter().enumerate() {
+                param.update_with_grad(|data, grad| {
+                    let state = state.entry((group_idx, param_idx)).or_insert_with(|| RmsPropState {
+                        square_avg: RawTensor::zeros_like(data),
+                        momentum_buffer: None,
+                    });
+                    if momentum != 0. && state.momentum_buffer.is_none() {
+                        state.momentum_buffer = Some(RawTensor::zeros_like(data));
+                    }
+                    let step = RmsPropStep {
+                        lr: group.lr,
+                        alpha,
+                        eps,
+                        weight_decay: group.weight_decay,
+                        momentum,
+                    };
+                    let momentum_buffer = if momentum != 0. { state.momentum_buffer.as_mut() } else { None };
+                    data.rmsprop_step(grad, &mut state.square_avg, momentum_buffer, &step);
+                });
+            }
+        }
+    }
+
+    fn param_groups(&self) -> &[ParamGroup] {
+        &self.param_groups
+    }
+
+    fn param_groups_mut(&mut self) -> &mut Vec<ParamGroup> {
+        &mut self.param_groups
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let hyperparameters = [
+            ("alpha", self.alpha as f64),
+            ("eps", self.eps as f64),
+            ("momentum", self.momentum as f64),
+        ];
+        save_optimizer_state(&self.param_groups, &self.state, &hyperparameters)
+    }
+
+    fn load_state_dict(&mut self, state_dict: &StateDict, strict: bool) -> LoadReport {
+        let loaded = load_optimizer_state(&mut self.param_groups, state_dict, strict, &["alpha", "eps", "momentum"]);
+        for (name, value) in loaded.hyperparameters {
+            match name.as_str() {
+                "alpha" => self.alpha = value as f32,
+                "eps" => self.eps = value as f32,
+                _ => self.momentum = value as f32,
+            }
+        }
+        self.state = loaded.state;
+        loaded.report
+    }
+}
@@ -0,0 +1,123 @@
+use crate::checkpoint::{LoadReport, StateDict};
+use crate::optim::{load_optimizer_state, save_optimizer_state, Optimizer, ParamGroup, ParamIdx, ParamState, SavedState};
+use crate::{RawTensor, SgdStep, Tensor};
+use std::collections::HashMap;
+
+/// Stochastic Gradient Descent, optionally with momentum and Nesterov momentum.
+///
+/// With momentum the update follows PyTorch: the momentum buffer is initialized with the
+/// gradient on the first step.
+#[derive(Debug)]
+pub struct Sgd {
+    param_groups: Vec<ParamGroup>,
+    pub momentum: f32,
+    pub dampening: f32,
+    pub nesterov: bool,
+    state: HashMap<ParamIdx, SgdState>,
+}
+
+#[derive(Debug)]
+struct SgdState {
+    step: u32,
+    momentum_buffer: Option<RawTensor>,
+}
+
+impl ParamState for SgdState {
+    const TENSORS: &'static [&'static str] = &["momentum_buffer"];
+    const SCALARS: &'static [&'static str] = &["step"];
+
+    fn save(&self) -> SavedState {
+        let tensors = self.momentum_buffer.iter().map(|buffer| ("momentum_buffer", buffer.clone())).collect();
+        (tensors, vec![("step", self.step as f64)])
+    }
+
+    fn load(tensors: &mut HashMap<&str, RawTensor>, scalars: &HashMap<&str, f64>) -> Result<Self, &'static str> {
+        Ok(Self {
+            step: *scalars.get("step").ok_or("step")? as u32,
+            // Without momentum there is no buffer
+            momentum_buffer: tensors.remove("momentum_buffer"),
+        })
+    }
+}
+
+impl Sgd {
+    /// Creates a plain SGD optimizer with a single group of parameters
+    pub fn new(params: Vec<Tensor>, lr: f32) -> Self {
+        Self::with_param_groups(vec![ParamGroup::new(params, lr)])
+    }
+
+    pub fn with_param_groups(param_groups: Vec<ParamGroup>) -> Self {
+        Self {
+            param_groups,
+            momentum: 0.,
+            dampening: 0.,
+            nesterov: false,
+            state: HashMap::new(),
+        }
+    }
+}
+
+impl Optimizer for Sgd {
+    fn step(&mut self) {
+        assert!(
+            !self.nesterov || (self.momentum > 0. && self.dampening == 0.),
+            "Nesterov momentum requires a momentum and zero dampening"
+        );
+        let (momentum, dampening, nesterov) = (self.momentum, self.dampening, self.nesterov);
+        let state = &mut self.state;
+        for (group_idx, group) in self.param_groups.iter().enumerate() {
+            for (param_idx, param) in group.params.iter().enumerate() {
+                param.update_with_grad(|data, grad| {
+                    let state = state.entry((group_idx, param_idx)).or_insert_with(|| SgdState {
+                        step: 0,
+                        momentum_buffer: None,
+                    });
+                    state.step += 1;
+                    if momentum != 0. && state.momentum_buffer.is_none() {
+                        state.momentum_buffer = Some(RawTensor::zeros_like(data));
+                    }
+                    let step = SgdStep {
+                        lr: group.lr,
+                        weight_decay: group.weight_decay,
+                        momentum,
+                        dampening,
+                        nesterov,
+                        step: state.step,
+                    };
+                    let momentum_buffer = if momentum != 0. { state.momentum_buffer.as_mut() } else { None };
+                    data.sgd_step(grad, momentum_buffer, &step);
+                });
+            }
+        }
+    }
+
+    fn param_groups(&self) -> &[ParamGroup] {
+        &self.param_groups
+    }
+
+    fn param_groups_mut(&mut self) -> &mut Vec<ParamGroup> {
+        &mut self.param_groups
+    }
+
+    fn state_dict(&self) -> StateDict {
+        let hyperparameters = [
+            ("momentum", self.momentum as f64),
+            ("dampening", self.dampening as f64),
+            ("nesterov", if self.nesterov { 1. } else { 0. }),
+        ];
+        save_optimizer_state(&self.param_groups, &self.state, &hyperparameters)
+    }
+
+    fn load_state_dict(&mut self, state_dict: &StateDict, strict: bool) -> LoadReport {
+        let loaded = load_optimizer_state(&mut self.param_groups, state_dict, strict, &["momentum", "dampening", "nesterov"]);
+        for (name, value) in loaded.hyperparameters {
+            match name.as_str() {
+                "momentum" => self.momentum = value as f32,
+                "dampening" => self.dampening = value as f32,
+                _ => self.nesterov = value != 0.,
+            }
+        }
+        self.state = loaded.state;
+        loaded.report
+    }
+}
@@ -0,0 +1,117 @@
+use crate::optim::{Adam, AdamW, Optimizer, ParamGroup, RmsProp, Sgd};
+use crate::Tensor;
+
+/// Minimizes `sum((x - target)^2)` and returns the final `x`
+fn minimize<O: Optimizer>(x: &Tensor, optimizer: &mut O, steps: usize) -> Vec<f32> {
+    let minus_target = Tensor::from_data_and_shape(vec![-3., 1.], vec![2]);
+    for _ in 0..steps {
+        optimizer.zero_grad();
+        let diff = x.add(&minus_target);
+        diff.dot_mul(&diff).sum().backward();
+        optimizer.step();
+    }
+    x.to_vec()
+}
+
+fn assert_converged(actual: Vec<f32>) {
+    assert!((actual[0] - 3.).abs() < 1e-2, "{:?}", actual);
+    assert!((actual[1] + 1.).abs() < 1e-2, "{:?}", actual);
+}
+
+#[test]
+fn sgd_converges() {
+    let x = Tensor::from_data_and_shape(vec![0., 0.], vec![2]);
+    let mut optimizer = Sgd::new(vec![x.shallow_clone()], 0.1);
+    assert_converged(minimize(&x, &mut optimizer, 100));
+}
+
+#[test]
+fn sgd_momentum_converges() {
+    let x = Tensor::from_data_and_shape(vec![0., 0.], vec![2]);
+    let mut optimizer = Sgd::new(vec![x.shallow_clone()], 0.05);
+    optimizer.momentum = 0.9;
+    optimizer.nesterov = true;
+    assert_converged(minimize(&x, &mut optimizer, 200));
+}
+
+#[test]
+fn adam_converges() {
+    let x = Tensor::from_data_and_shape(vec![0., 0.], vec![2]);
+    let mut optimizer = Adam::new(vec![x.shallow_clone()], 0.1);
+    assert_converged(minimize(&x, &mut optimizer, 500));
+}
+
+#[test]
+fn adamw_decays_weights() {
+    let x = Tensor::from_data_and_shape(vec![1., -1.], vec![2]);
+    let mut optimizer = AdamW::new(vec![x.shallow_clone()], 0.1);
+    optimizer.param_groups_mut()[0].weight_decay = 1.;
+    // A zero gradient leaves only the decoupled weight decay
+    optimizer.zero_grad();
+    x.dot_mul(&Tensor::from_data_and_shape(vec![0., 0.], vec![2])).sum().backward();
+    optimizer.step();
+    let x = x.to_vec();
+    assert!((x[0] - 0.9).abs() < 1e-5 && (x[1] + 0.9).abs() < 1e-5, "{:?}", x);
+}
+
+#[test]
+fn rmsprop_converges() {
+    let x = Tensor::from_data_and_shape(vec![0., 0.], vec![2]);
+    let mut optimizer = RmsProp::new(vec![x.shallow_clone()], 0.01);
+    optimizer.momentum = 0.5;
+    assert_converged(minimize(&x, &mut optimizer, 1000));
+}
+
+#[test]
+fn param_groups_have_own_lr() {
+    let fast = Tensor::from_data_and_shape(vec![1.], vec![1]);
+    let frozen = Tensor::from_data_and_shape(vec![1.], vec![1]);
+    let mut optimizer = Sgd::with_param_groups(vec![ParamGroup::new(vec![fast.shallow_clone()], 0.5)]);
+    optimizer.add_param_group(ParamGroup::new(vec![frozen.shallow_clone()], 0.));
+    fast.add(&frozen).sum().backward();
+    optimizer.step();
+    assert_eq!(fast.to_vec(), &[0.5]);
+    assert_eq!(frozen.to_vec(), &[1.]);
+    optimizer.zero_grad();
+    assert!(fast.grad().is_none() && frozen.grad().is_none());
+}
+
+#[test]
+fn state_dict_resumes_training() {
+    let x = Tensor::from_data_and_shape(vec![0., 0.], vec![2]);
+    let mut optimizer = Adam::new(vec![x.shallow_clone()], 0.1);
+    optimizer.betas = (0.8, 0.99);
+    minimize(&x, &mut optimizer, 5);
+    let state_dict = optimizer.state_dict();
+    assert_eq!(state_dict.scalars["state.0.0.step"], 5.);
+    assert_eq!(state_dict.scalars["betas.0"], 0.8f32 as f64);
+
+    let resumed_x = Tensor::from_data_and_shape(x.to_vec(), vec![2]);
+    let mut resumed = Adam::new(vec![resumed_x.shallow_clone()], 1.);
+    assert!(resumed.load_state_dict(&state_dict, true).is_clean());
+    assert_eq!(resumed.param_groups()[0].lr, 0.1);
+    assert_eq!(resumed.betas, (0.8, 0.99));
+    assert_eq!(minimize(&resumed_x, &mut resumed, 5), minimize(&x, &mut optimizer, 5));
+}
+
+#[test]
+fn load_state_dict_reports_mismatches() {
+    let x = Tensor::from_data_and_shape(vec![0., 0.], vec![2]);
+    let mut optimizer = Sgd::new(vec![x.shallow_clone()], 0.1);
+    optimizer.momentum = 0.9;
+    minimize(&x, &mut optimizer, 1);
+    let mut state_dict = optimizer.state_dict();
+    state_dict.scalars.remove("dampening");
+    state_dict.scalars.insert("state.1.0.step".to_string(), 1.);
+    state_dict
+        .tensors
+        .insert("state.0.0.momentum_buffer".to_string(), crate::RawTensor::zeros(vec![3]));
+
+    let mut other = Sgd::new(vec![Tensor::from_data_and_shape(vec![0., 0.], vec![2])], 0.5);
+    let report = other.load_state_dict(&state_dict, false);
+    assert_eq!(report.missing_keys, vec!["dampening"]);
+    assert_eq!(report.unexpected_keys, vec!["state.1.0.step"]);
+    assert_eq!(report.shape_mismatches[0].key, "state.0.0.momentum_buffer");
+    assert_eq!(other.momentum, 0.9);
+    assert_eq!(other.param_groups()[0].lr, 0.1);
+}
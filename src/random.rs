@@ -0,0 +1,280 @@
+//! Random number generation with the counter-based Philox 4x32-10 generator. Each value is a
+//! pure function of a 64 bit seed, a 64 bit offset and its index, so the GPU kernels generate
+//! every element independently and the results are reproducible given the seed.
+//!
+//! Each GPU keeps its own [`Generator`], as does the CPU, whose offset is advanced by every
+//! random Op, see [`manual_seed`]. Random constructors also accept an explicit [`Generator`].
+use crate::GpuStore;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+/// The seed used until [`manual_seed`] is called
+const DEFAULT_SEED: u64 = 67_280_421_310_721;
+
+/// Seeds the default random number generators of the CPU and of all GPUs, resetting their
+/// offsets, so the following random Ops produce the same values on every run
+///
+/// # Examples
+///
+/// ```
+/// use tensor_compute::{manual_seed, RawTensor};
+/// manual_seed(42);
+/// let first = RawTensor::rand(vec![4]);
+/// manual_seed(42);
+/// assert_eq!(RawTensor::rand(vec![4]).to_vec(), first.to_vec());
+/// ```
+pub fn manual_seed(seed: u64) {
+    CPU_GENERATOR.lock().unwrap().manual_seed(seed);
+    for gpu_info in GpuStore::list_gpus() {
+        GpuStore::get(gpu_info).manual_seed(seed);
+    }
+}
+
+/// A distribution sampled by the random kernels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Uniform over `[low, high)`
+    Uniform { low: f32, high: f32 },
+    /// Normal with the given mean and standard deviation
+    Normal { mean: f32, std: f32 },
+    /// One with probability `p`, zero otherwise
+    Bernoulli { p: f32 },
+    /// Uniform over the integers of `[low, high)`
+    RandInt { low: i32, high: i32 },
+}
+
+impl Distribution {
+    /// The kind and the two parameters passed to the random kernel
+    pub(crate) fn push_constants(&self) -> [u32; 3] {
+        let float_bits = |value: f32| u32::from_ne_bytes(value.to_ne_bytes());
+        match *self {
+            Distribution::Uniform { low, high } => [0, float_bits(low), float_bits(high)],
+            Distribution::Normal { mean, std } => [1, float_bits(mean), float_bits(std)],
+            Distribution::Bernoulli { p } => {
+                assert!((0. ..=1.).contains(&p), "The probability must be in [0, 1], got {}", p);
+                [2, float_bits(p), 0]
+            }
+            Distribution::RandInt { low, high } => {
+                assert!(low < high, "The range [{}, {}) is empty", low, high);
+                [3, float_bits(low as f32), float_bits(high as f32)]
+            }
+        }
+    }
+
+    /// Computes the element `index` of a random Op using the given seed and offset, exactly as
+    /// the random kernel does, up to the precision of `ln`, `sqrt` and `cos`
+    pub fn sample(&self, seed: u64, offset: u64, index: u32) -> f32 {
+        let bits = philox4x32_10(
+            [index, offset as u32, (offset >> 32) as u32, 0],
+            [seed as u32, (seed >> 32) as u32],
+        );
+        let uniform = to_unit_interval(bits[0]);
+        match *self {
+            Distribution::Uniform { low, high } => low + uniform * (high - low),
+            Distribution::Normal { mean, std } => {
+                // Box-Muller, with the first uniform moved into (0, 1] to avoid ln(0)
+                let radius = (-2. * (1. - uniform).ln()).sqrt();
+                let angle = 2. * std::f32::consts::PI * to_unit_interval(bits[1]);
+                mean + std * radius * angle.cos()
+            }
+            Distribution::Bernoulli { p } => (uniform < p) as u32 as f32,
+            Distribution::RandInt { low, high } => {
+                let value = (low as f32 + uniform * (high - low) as f32).floor();
+                value.min((high - 1) as f32)
+            }
+        }
+    }
+}
+
+/// Maps the 24 most significant bits to a float in `[0, 1)`
+fn to_unit_interval(bits: u32) -> f32 {
+    (bits >> 8) as f32 * (1. / (1u32 << 24) as f32)
+}
+
+/// The Philox 4x32 generator with 10 rounds, see "Parallel random numbers: as easy as 1, 2, 3"
+/// by Salmon et al.
+pub(crate) fn philox4x32_10(counter: [u32; 4], key: [u32; 2]) -> [u32; 4] {
+    let (mut counter, mut key) = (counter, key);
+    for round in 0..10 {
+        if round > 0 {
+            key = [key[0].wrapping_add(0x9E37_79B9), key[1].wrapping_add(0xBB67_AE85)];
+        }
+        let product_0 = 0xD251_1F53u64 * counter[0] as u64;
+        let product_1 = 0xCD9E_8D57u64 * counter[2] as u64;
+        counter = [
+            (product_1 >> 32) as u32 ^ counter[1] ^ key[0],
+            product_1 as u32,
+            (product_0 >> 32) as u32 ^ counter[3] ^ key[1],
+            product_0 as u32,
+        ];
+    }
+    counter
+}
+
+/// The seed and offset of a [`Generator`]. The next random Op uses this offset, then the
+/// offset is incremented, so restoring a state replays the following Ops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneratorState {
+    pub seed: u64,
+    pub offset: u64,
+}
+
+/// An explicit random number generator. Random constructors taking an optional Generator use
+/// the device generator when given `None`, and this one otherwise, which makes the result
+/// independent of the other random Ops of the program.
+///
+/// Since Philox is counter-based, the same state produces the same values on the CPU and on
+/// the GPU, up to the precision of the math functions.
+///
+/// # Examples
+///
+/// ```
+/// use tensor_compute::random::Generator;
+/// use tensor_compute::CpuTensor;
+/// let mut generator = Generator::new(7);
+/// let state = generator.get_state();
+/// let first = CpuTensor::uniform(vec![3], -1., 1., Some(&mut generator));
+/// let second = CpuTensor::uniform(vec![3], -1., 1., Some(&mut generator));
+/// assert_ne!(first, second);
+/// generator.set_state(state);
+/// assert_eq!(CpuTensor::uniform(vec![3], -1., 1., Some(&mut generator)), first);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generator {
+    state: GeneratorState,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: GeneratorState { seed, offset: 0 },
+        }
+    }
+
+    /// Reseeds the generator, resetting its offset
+    pub fn manual_seed(&mut self, seed: u64) {
+        self.state = GeneratorState { seed, offset: 0 };
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.state.seed
+    }
+
+    pub fn get_state(&self) -> GeneratorState {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: GeneratorState) {
+        self.state = state;
+    }
+
+    /// Returns the seed and offset to use for the next random Op, advancing the offset
+    pub(crate) fn advance(&mut self) -> (u64, u64) {
+        let current = (self.state.seed, self.state.offset);
+        self.state.offset += 1;
+        current
+    }
+}
+
+/// The generator used by the CPU random constructors when no [`Generator`] is given
+static CPU_GENERATOR: Lazy<Mutex<Generator>> = Lazy::new(|| Mutex::new(Generator::default()));
+
+/// Returns the seed and offset of the next random Op, taken from `generator` if given and
+/// from `default` otherwise
+pub(crate) fn next_state(generator: Option<&mut Generator>, default: impl FnOnce() -> (u64, u64)) -> (u64, u64) {
+    match generator {
+        Some(generator) => generator.advance(),
+        None => default(),
+    }
+}
+
+/// Same as [`next_state`], defaulting to the CPU generator
+pub(crate) fn next_cpu_state(generator: Option<&mut Generator>) -> (u64, u64) {
+    next_state(generator, || CPU_GENERATOR.lock().unwrap().advance())
+}
+
+/// Returns a random permutation of `0..n` using the given seed and offset. It is a
+/// Fisher-Yates shuffle whose swap `i` is drawn from the Philox output at index `i`.
+pub(crate) fn permutation(n: usize, seed: u64, offset: u64) -> Vec<usize> {
+    assert!(n <= u32::MAX as usize, "Permutations can have at most 2^32 elements");
+    let mut permutation: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        let bits = philox4x32_10(
+            [i as u32, offset as u32, (offset >> 32) as u32, 0],
+            [seed as u32, (seed >> 32) as u32],
+        );
+        // Lemire's multiply-shift maps 64 random bits into [0, i] with a negligible bias
+        let random = (bits[0] as u64) << 32 | bits[1] as u64;
+        let j = ((random as u128 * (i as u128 + 1)) >> 64) as usize;
+        permutation.swap(i, j);
+    }
+    permutation
+}
+
+#[test]
+fn philox_matches_known_answers() {
+    assert_eq!(philox4x32_10([0; 4], [0; 2]), [0x6627_e8d5, 0xe169_c58d, 0xbc57_ac4c, 0x9b00_dbd8]);
+    assert_eq!(
+        philox4x32_10([u32::MAX; 4], [u32::MAX; 2]),
+        [0x408f_276d, 0x41c8_3b0e, 0xa20b_c7c6, 0x6d54_51fd]
+    );
+    assert_eq!(
+        philox4x32_10([0x243f_6a88, 0x85a3_08d3, 0x1319_8a2e, 0x0370_7344], [0xa409_3822, 0x299f_31d0]),
+        [0xd16c_fe09, 0x94fd_cceb, 0x5001_e420, 0x2412_6ea1]
+    );
+}
+
+#[test]
+fn samples_are_in_range() {
+    for index in 0..1000 {
+        let uniform = Distribution::Uniform { low: -2., high: 3. }.sample(1, 2, index);
+        assert!((-2. ..3.).contains(&uniform));
+        let int = Distribution::RandInt { low: -1, high: 2 }.sample(1, 2, index);
+        assert!([-1., 0., 1.].contains(&int));
+        assert!(Distribution::Normal { mean: 0., std: 1. }.sample(1, 2, index).is_finite());
+    }
+}
+
+#[test]
+fn generator_state_replays_samples() {
+    let mut generator = Generator::new(11);
+    assert_eq!(generator.seed(), 11);
+    let (seed, offset) = generator.advance();
+    assert_eq!((seed, offset), (11, 0));
+    let state = generator.get_state();
+    assert_eq!(state, GeneratorState { seed: 11, offset: 1 });
+    let first = generator.advance();
+    generator.set_state(state);
+    assert_eq!(generator.advance(), first);
+    generator.manual_seed(3);
+    assert_eq!(generator.get_state(), GeneratorState { seed: 3, offset: 0 });
+}
+
+#[test]
+fn permutations_are_reproducible() {
+    let shuffled = permutation(50, 1, 2);
+    let mut sorted = shuffled.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..50).collect::<Vec<_>>());
+    assert_ne!(shuffled, sorted);
+    assert_eq!(shuffled, permutation(50, 1, 2));
+    assert_ne!(shuffled, permutation(50, 1, 3));
+    assert!(permutation(0, 1, 2).is_empty());
+}
+
+#[test]
+fn cpu_tensors_sampled_with_a_generator_are_reproducible() {
+    use crate::CpuTensor;
+    let mut generator = Generator::new(5);
+    let first = CpuTensor::randn_with(vec![2, 3], &mut generator);
+    generator.manual_seed(5);
+    assert_eq!(CpuTensor::randn_with(vec![2, 3], &mut generator).raw_data_slice(), first.raw_data_slice());
+    let uniform = CpuTensor::rand_with(vec![8], &mut generator);
+    assert!(uniform.raw_data_slice().iter().all(|value| (0. ..1.).contains(value)));
+}
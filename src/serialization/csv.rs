@@ -0,0 +1,83 @@
+//! Reading of numeric CSV files, such as the tabular datasets of scikit-learn or Kaggle.
+//!
+//! Fields are separated by commas and may be surrounded by whitespace or by double quotes.
+//! Blank lines are skipped, and every other line must have the same number of fields, each
+//! holding a number.
+use crate::serialization::json::invalid_data;
+use crate::CpuTensor;
+use std::io::{self, Read};
+
+impl CpuTensor {
+    /// Reads a numeric CSV file into a Tensor of shape `[rows, columns]`, skipping the first
+    /// line if `has_header`. Fails with the line number if a field is not a number or if a
+    /// row has a different number of fields.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::{CpuTensor, ShapeStrideTrait};
+    /// let csv = "sepal_length,sepal_width,species\n5.1,3.5,0\n7.0,3.2,1\n";
+    /// let tensor = CpuTensor::from_csv(csv.as_bytes(), true).unwrap();
+    /// assert_eq!(tensor.shape(), &[2, 3]);
+    /// assert_eq!(tensor.as_contiguous_vec(), vec![5.1, 3.5, 0., 7., 3.2, 1.]);
+    /// ```
+    pub fn from_csv<R: Read>(mut reader: R, has_header: bool) -> io::Result<CpuTensor> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut data = Vec::new();
+        let mut columns = None;
+        let mut rows = 0;
+        for (line_idx, line) in text.lines().enumerate().skip(has_header as usize) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut nb_fields = 0;
+            for field in line.split(',') {
+                let field = field.trim();
+                let field = field.strip_prefix('"').and_then(|field| field.strip_suffix('"')).unwrap_or(field);
+                match field.trim().parse::<f32>() {
+                    Ok(value) => data.push(value),
+                    Err(_) => return invalid_data(format!("Line {} of the CSV has the non numeric field {:?}", line_idx + 1, field)),
+                }
+                nb_fields += 1;
+            }
+            match columns {
+                None => columns = Some(nb_fields),
+                Some(columns) if columns != nb_fields => {
+                    return invalid_data(format!(
+                        "Line {} of the CSV has {} fields instead of {}",
+                        line_idx + 1,
+                        nb_fields,
+                        columns
+                    ))
+                }
+                Some(_) => {}
+            }
+            rows += 1;
+        }
+        match columns {
+            Some(columns) => Ok(CpuTensor::from_data_and_shape(data, vec![rows, columns])),
+            None => invalid_data("The CSV has no rows".to_string()),
+        }
+    }
+}
+
+#[test]
+fn reads_quoted_and_padded_fields() {
+    use crate::ShapeStrideTrait;
+    let csv = "\"a\", \"b\"\r\n 1, \"-2.5\"\r\n\r\n3e2,4\r\n";
+    let tensor = CpuTensor::from_csv(csv.as_bytes(), true).unwrap();
+    assert_eq!(tensor.shape(), &[2, 2]);
+    assert_eq!(tensor.as_contiguous_vec(), vec![1., -2.5, 300., 4.]);
+    let tensor = CpuTensor::from_csv("1\n2\n".as_bytes(), false).unwrap();
+    assert_eq!(tensor.shape(), &[2, 1]);
+}
+
+#[test]
+fn rejects_malformed_rows() {
+    let error = CpuTensor::from_csv("1,2\n3,x\n".as_bytes(), false).unwrap_err();
+    assert!(error.to_string().contains("Line 2"), "{}", error);
+    let error = CpuTensor::from_csv("a,b\n1,2\n3\n".as_bytes(), true).unwrap_err();
+    assert_eq!(error.to_string(), "Line 3 of the CSV has 1 fields instead of 2");
+    assert!(CpuTensor::from_csv("a,b\n".as_bytes(), true).is_err());
+}
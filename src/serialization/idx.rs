@@ -0,0 +1,119 @@
+//! Reading of the IDX format, in which the MNIST and Fashion-MNIST datasets are distributed.
+//!
+//! An IDX file starts with two zero bytes, a byte giving the type of the elements and a byte
+//! giving the number of dimensions, followed by each dimension as a big endian `u32` and by
+//! the big endian elements in row major order. The files must be decompressed first, for
+//! example with `gunzip`.
+use crate::serialization::json::invalid_data;
+use crate::CpuTensor;
+use std::io::{self, Read};
+
+/// The size in bytes of the elements of each IDX type
+fn element_size(data_type: u8) -> Option<usize> {
+    match data_type {
+        0x08 | 0x09 => Some(1),
+        0x0B => Some(2),
+        0x0C | 0x0D => Some(4),
+        0x0E => Some(8),
+        _ => None,
+    }
+}
+
+impl CpuTensor {
+    /// Reads an IDX file. Every element type is converted exactly except `i32` and `f64`,
+    /// which are rounded to the nearest `f32`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::{CpuTensor, ShapeStrideTrait};
+    /// // Two unsigned bytes in one dimension
+    /// let bytes = [0, 0, 0x08, 1, 0, 0, 0, 2, 7, 255];
+    /// let tensor = CpuTensor::from_idx(&bytes[..]).unwrap();
+    /// assert_eq!(tensor.shape(), &[2]);
+    /// assert_eq!(tensor.as_contiguous_vec(), vec![7., 255.]);
+    /// ```
+    pub fn from_idx<R: Read>(mut reader: R) -> io::Result<CpuTensor> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic[..2] != [0, 0] {
+            return invalid_data("Not an IDX file".to_string());
+        }
+        let size = match element_size(magic[2]) {
+            Some(size) => size,
+            None => return invalid_data(format!("Unsupported IDX element type {:#04x}", magic[2])),
+        };
+        if magic[3] == 0 {
+            return invalid_data("The IDX file has no dimensions".to_string());
+        }
+        let mut shape = Vec::with_capacity(magic[3] as usize);
+        for _ in 0..magic[3] {
+            let mut dim = [0; 4];
+            reader.read_exact(&mut dim)?;
+            shape.push(u32::from_be_bytes(dim) as usize);
+        }
+        let numel = shape.iter().try_fold(1usize, |numel, &dim| numel.checked_mul(dim));
+        let len = match numel.and_then(|numel| numel.checked_mul(size)) {
+            Some(len) => len,
+            None => return invalid_data(format!("The IDX shape {:?} is too large", shape)),
+        };
+        let mut data = vec![0; len];
+        reader.read_exact(&mut data)?;
+        let values = match magic[2] {
+            0x08 => data.iter().map(|&value| value as f32).collect(),
+            0x09 => data.iter().map(|&value| value as i8 as f32).collect(),
+            0x0B => data.chunks(2).map(|bytes| i16::from_be_bytes([bytes[0], bytes[1]]) as f32).collect(),
+            0x0C => data
+                .chunks(4)
+                .map(|bytes| i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32)
+                .collect(),
+            0x0D => data
+                .chunks(4)
+                .map(|bytes| f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect(),
+            _ => data
+                .chunks(8)
+                .map(|bytes| {
+                    let mut array = [0; 8];
+                    array.copy_from_slice(bytes);
+                    f64::from_be_bytes(array) as f32
+                })
+                .collect(),
+        };
+        Ok(CpuTensor::from_data_and_shape(values, shape))
+    }
+}
+
+#[cfg(test)]
+fn idx_bytes(data_type: u8, shape: &[u32], data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0, 0, data_type, shape.len() as u8];
+    for dim in shape {
+        bytes.extend_from_slice(&dim.to_be_bytes());
+    }
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+#[test]
+fn reads_every_element_type() {
+    use crate::ShapeStrideTrait;
+    let read = |data_type: u8, shape: &[u32], data: &[u8]| CpuTensor::from_idx(idx_bytes(data_type, shape, data).as_slice()).unwrap();
+    let images = read(0x08, &[2, 1, 2], &[0, 1, 128, 255]);
+    assert_eq!(images.shape(), &[2, 1, 2]);
+    assert_eq!(images.as_contiguous_vec(), vec![0., 1., 128., 255.]);
+    assert_eq!(read(0x09, &[2], &[0x7F, 0x80]).as_contiguous_vec(), vec![127., -128.]);
+    assert_eq!(read(0x0B, &[2], &[0xFF, 0xFE, 0x01, 0x00]).as_contiguous_vec(), vec![-2., 256.]);
+    assert_eq!(read(0x0C, &[1], &(-70_000i32).to_be_bytes()).as_contiguous_vec(), vec![-70_000.]);
+    assert_eq!(read(0x0D, &[1], &1.5f32.to_be_bytes()).as_contiguous_vec(), vec![1.5]);
+    assert_eq!(read(0x0E, &[1], &(-0.25f64).to_be_bytes()).as_contiguous_vec(), vec![-0.25]);
+}
+
+#[test]
+fn rejects_malformed_files() {
+    let read = |bytes: Vec<u8>| CpuTensor::from_idx(bytes.as_slice()).map_err(|error| error.kind());
+    assert_eq!(read(vec![1, 0, 0x08, 1, 0, 0, 0, 1, 0]), Err(io::ErrorKind::InvalidData));
+    assert_eq!(read(idx_bytes(0x0A, &[1], &[0])), Err(io::ErrorKind::InvalidData));
+    assert_eq!(read(idx_bytes(0x08, &[], &[])), Err(io::ErrorKind::InvalidData));
+    assert_eq!(read(idx_bytes(0x0B, &[2], &[0, 1, 2])), Err(io::ErrorKind::UnexpectedEof));
+    assert_eq!(read(idx_bytes(0x0E, &[u32::MAX, u32::MAX, u32::MAX], &[])), Err(io::ErrorKind::InvalidData));
+}
@@ -0,0 +1,300 @@
+//! A minimal JSON reader and writer, enough for the headers of the serialization formats.
+//! Numbers are kept as `f64`, which represents the offsets and shapes of any file that fits
+//! in memory exactly.
+use std::collections::BTreeMap;
+use std::io;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(BTreeMap<String, JsonValue>),
+}
+
+pub(crate) fn invalid_data<T>(message: String) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+impl JsonValue {
+    pub(crate) fn parse(text: &str) -> io::Result<JsonValue> {
+        let mut parser = Parser { bytes: text.as_bytes(), position: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != parser.bytes.len() {
+            return parser.error("trailing characters");
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_object(&self) -> Option<&BTreeMap<String, JsonValue>> {
+        match self {
+            JsonValue::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of an array of non negative integers
+    pub(crate) fn as_usize_array(&self) -> Option<Vec<usize>> {
+        match self {
+            JsonValue::Array(values) => values
+                .iter()
+                .map(|value| match value {
+                    JsonValue::Number(n) if *n >= 0. && n.fract() == 0. => Some(*n as usize),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+
+    /// Writes the value without whitespace
+    pub(crate) fn write(&self, out: &mut String) {
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            JsonValue::Number(value) => out.push_str(&value.to_string()),
+            JsonValue::String(value) => write_string(value, out),
+            JsonValue::Array(values) => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    value.write(out);
+                }
+                out.push(']');
+            }
+            JsonValue::Object(object) => {
+                out.push('{');
+                for (i, (key, value)) in object.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_string(key, out);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn write_string(value: &str, out: &mut String) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: &str) -> io::Result<T> {
+        invalid_data(format!("Invalid JSON at byte {}: {}", self.position, message))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.position < self.bytes.len() && self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.position).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> io::Result<()> {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", byte as char))
+        }
+    }
+
+    fn literal(&mut self, literal: &str, value: JsonValue) -> io::Result<JsonValue> {
+        if self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(value)
+        } else {
+            self.error("unknown literal")
+        }
+    }
+
+    fn value(&mut self) -> io::Result<JsonValue> {
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Ok(JsonValue::String(self.string()?)),
+            Some(b't') => self.literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.literal("false", JsonValue::Bool(false)),
+            Some(b'n') => self.literal("null", JsonValue::Null),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => self.error("expected a value"),
+        }
+    }
+
+    fn object(&mut self) -> io::Result<JsonValue> {
+        self.expect(b'{')?;
+        let mut object = BTreeMap::new();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(JsonValue::Object(object));
+        }
+        loop {
+            if self.peek() != Some(b'"') {
+                return self.error("expected a key");
+            }
+            let key = self.string()?;
+            self.expect(b':')?;
+            let value = self.value()?;
+            if object.insert(key, value).is_some() {
+                return self.error("duplicated key");
+            }
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Object(object));
+                }
+                _ => return self.error("expected ',' or '}'"),
+            }
+        }
+    }
+
+    fn array(&mut self) -> io::Result<JsonValue> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(values));
+                }
+                _ => return self.error("expected ',' or ']'"),
+            }
+        }
+    }
+
+    fn number(&mut self) -> io::Result<JsonValue> {
+        let start = self.position;
+        while self.position < self.bytes.len()
+            && matches!(self.bytes[self.position], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        {
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).unwrap();
+        match text.parse() {
+            Ok(number) => Ok(JsonValue::Number(number)),
+            Err(_) => self.error("invalid number"),
+        }
+    }
+
+    fn hex_escape(&mut self) -> io::Result<u32> {
+        let digits = self.bytes.get(self.position..self.position + 4);
+        let code = digits
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok());
+        match code {
+            Some(code) => {
+                self.position += 4;
+                Ok(code)
+            }
+            None => self.error("invalid unicode escape"),
+        }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = match self.bytes.get(self.position) {
+                Some(&byte) => byte,
+                None => return self.error("unterminated string"),
+            };
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self.bytes.get(self.position).copied();
+                    self.position += 1;
+                    let c = match escaped {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            let mut code = self.hex_escape()?;
+                            // Characters outside the basic plane are escaped as surrogate pairs
+                            if (0xD800..0xDC00).contains(&code) && self.bytes[self.position..].starts_with(b"\\u") {
+                                self.position += 2;
+                                let low = self.hex_escape()?;
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                            }
+                            match std::char::from_u32(code) {
+                                Some(c) => c,
+                                None => return self.error("invalid unicode escape"),
+                            }
+                        }
+                        _ => return self.error("invalid escape"),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                byte => bytes.push(byte),
+            }
+        }
+        match String::from_utf8(bytes) {
+            Ok(string) => Ok(string),
+            Err(_) => self.error("invalid UTF-8"),
+        }
+    }
+}
+
+#[test]
+fn json_round_trip() {
+    let text = r#" {"a": [1, 2.5, -3e2], "b\né": {"c": null, "d": true}, "e": "😀"} "#;
+    let value = JsonValue::parse(text).unwrap();
+    let object = value.as_object().unwrap();
+    assert_eq!(object["b\né"].as_object().unwrap()["d"], JsonValue::Bool(true));
+    assert_eq!(object["e"].as_str(), Some("😀"));
+    let mut written = String::new();
+    value.write(&mut written);
+    assert_eq!(JsonValue::parse(&written).unwrap(), value);
+    assert!(JsonValue::parse("{\"a\": 1,}").is_err());
+    assert!(JsonValue::parse("[1] 2").is_err());
+}
@@ -0,0 +1,9 @@
+//! Saving and loading Tensors in the file formats of other tools
+pub mod csv;
+pub mod idx;
+pub(crate) mod json;
+pub mod npy;
+pub mod safetensors;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod zip;
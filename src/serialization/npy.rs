@@ -0,0 +1,392 @@
+//! Reading and writing of the NumPy `.npy` format, and of `.npz` archives of `.npy` files.
+//!
+//! A `.npy` file starts with a magic string and a version, followed by a Python dict literal
+//! holding the `descr` (dtype), the `fortran_order` and the `shape` of the array, and then the
+//! raw data. Fortran ordered arrays are mapped onto column major strides, without copying.
+//!
+//! Tensors are written as little endian `f4`. When reading, `f2`, `f4`, `i1`, `i2`, `u1`,
+//! `u2` and `b1` are always converted exactly, and `f8` is rounded to the nearest `f32`.
+//! `i4`, `i8`, `u4` and `u8` are converted too, but reading fails if a value can't be
+//! represented exactly by a `f32`.
+use crate::serialization::json::invalid_data;
+use crate::serialization::safetensors::f16_to_f32;
+use crate::serialization::zip;
+use crate::utils::strides_from_deque_shape;
+use crate::{CpuTensor, ShapeStrideTrait};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// The header and the data are aligned to this many bytes, as done by NumPy
+const ALIGNMENT: usize = 64;
+
+impl CpuTensor {
+    /// Reads a `.npy` array, see [`crate::serialization::npy`] for the supported dtypes.
+    /// Scalars, which have an empty shape, are read with shape `[1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::{CpuTensor, ShapeStrideTrait};
+    /// let tensor = CpuTensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+    /// let mut bytes = Vec::new();
+    /// tensor.to_npy(&mut bytes).unwrap();
+    /// let read = CpuTensor::from_npy(bytes.as_slice()).unwrap();
+    /// assert_eq!(read, tensor);
+    /// ```
+    pub fn from_npy<R: Read>(mut reader: R) -> io::Result<CpuTensor> {
+        let mut preamble = [0; 8];
+        reader.read_exact(&mut preamble)?;
+        if &preamble[..6] != MAGIC {
+            return invalid_data("Not a .npy file".to_string());
+        }
+        let header_len = match preamble[6] {
+            1 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0; 4];
+                reader.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            }
+            version => return invalid_data(format!("Unsupported .npy version {}", version)),
+        };
+        let mut header = vec![0; header_len];
+        reader.read_exact(&mut header)?;
+        let header = match String::from_utf8(header) {
+            Ok(header) => NpyHeader::parse(&header)?,
+            Err(_) => return invalid_data("The .npy header is not valid UTF-8".to_string()),
+        };
+        let numel = header.shape.iter().try_fold(1usize, |numel, &dim| numel.checked_mul(dim));
+        let len = match numel.and_then(|numel| numel.checked_mul(header.dtype.size)) {
+            Some(len) => len,
+            None => return invalid_data(format!("The .npy shape {:?} is too large", header.shape)),
+        };
+        // The buffer grows with the data actually read, so a truncated file doesn't allocate
+        // the size claimed by its header
+        let mut data = Vec::new();
+        reader.take(len as u64).read_to_end(&mut data)?;
+        if data.len() != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("The .npy data has {} bytes instead of {}", data.len(), len),
+            ));
+        }
+        let values = header.dtype.to_f32(&data)?;
+        let shape = if header.shape.is_empty() { VecDeque::from(vec![1]) } else { VecDeque::from(header.shape) };
+        if header.fortran_order {
+            // Column major strides are the row major ones of the reversed shape, reversed
+            let reversed: VecDeque<usize> = shape.iter().rev().copied().collect();
+            let strides = strides_from_deque_shape(&reversed).into_iter().rev().collect();
+            Ok(CpuTensor::new_with_strides_and_offset(values, shape, strides, 0))
+        } else {
+            Ok(CpuTensor::from_data_and_shape(values, shape.into_iter().collect()))
+        }
+    }
+
+    /// Writes this Tensor as a C ordered `.npy` array of little endian `f4`
+    pub fn to_npy<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let shape: Vec<String> = self.shape().iter().map(|d| d.to_string()).collect();
+        // One dimensional tuples need a trailing comma
+        let shape = if shape.len() == 1 { format!("{},", shape[0]) } else { shape.join(", ") };
+        let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({}), }}", shape);
+        // Version 2 only differs by its 4 bytes header length, for headers longer than 64 KiB
+        let version: u8 = if header.len() + ALIGNMENT < u16::MAX as usize { 1 } else { 2 };
+        let len_size = if version == 1 { 2 } else { 4 };
+        // Padded with spaces and a final newline, so the data is aligned
+        let unpadded = MAGIC.len() + 2 + len_size + header.len() + 1;
+        header.push_str(&" ".repeat((ALIGNMENT - unpadded % ALIGNMENT) % ALIGNMENT));
+        header.push('\n');
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[version, 0])?;
+        if version == 1 {
+            writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        } else {
+            writer.write_all(&(header.len() as u32).to_le_bytes())?;
+        }
+        writer.write_all(header.as_bytes())?;
+        let mut data = Vec::with_capacity(self.numel() * 4);
+        for value in self.as_contiguous_vec() {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        writer.write_all(&data)
+    }
+}
+
+/// Reads the arrays of an uncompressed `.npz` archive, named after their file names without
+/// the `.npy` extension, as done by `numpy.savez`
+pub fn read_npz<R: Read>(mut reader: R) -> io::Result<HashMap<String, CpuTensor>> {
+    let mut archive = Vec::new();
+    reader.read_to_end(&mut archive)?;
+    let mut tensors = HashMap::new();
+    for (name, data) in zip::read_stored_entries(&archive)? {
+        let name = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+        tensors.insert(name, CpuTensor::from_npy(data)?);
+    }
+    Ok(tensors)
+}
+
+/// Writes the Tensors to an uncompressed `.npz` archive, which can be read by `numpy.load`.
+/// They are written in the order of their names.
+pub fn write_npz<W: Write>(writer: W, tensors: &HashMap<String, CpuTensor>) -> io::Result<()> {
+    let mut names: Vec<&String> = tensors.keys().collect();
+    names.sort();
+    let mut entries = Vec::with_capacity(names.len());
+    for name in names {
+        let mut data = Vec::new();
+        tensors[name].to_npy(&mut data)?;
+        entries.push((format!("{}.npy", name), data));
+    }
+    zip::write_stored_entries(writer, &entries)
+}
+
+/// The dtypes of the `descr` field which can be read
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Bool,
+    Int,
+    Uint,
+    Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NpyDtype {
+    kind: Kind,
+    size: usize,
+    big_endian: bool,
+}
+
+impl NpyDtype {
+    fn parse(descr: &str) -> io::Result<NpyDtype> {
+        let unsupported = || invalid_data(format!("Unsupported .npy dtype {}", descr));
+        let mut chars = descr.chars();
+        let big_endian = match chars.next() {
+            Some('<') | Some('|') | Some('=') => cfg!(target_endian = "big") && descr.starts_with('='),
+            Some('>') => true,
+            _ => return unsupported(),
+        };
+        let kind = match chars.next() {
+            Some('b') => Kind::Bool,
+            Some('i') => Kind::Int,
+            Some('u') => Kind::Uint,
+            Some('f') => Kind::Float,
+            _ => return unsupported(),
+        };
+        let size = match chars.as_str().parse() {
+            Ok(size) => size,
+            Err(_) => return unsupported(),
+        };
+        let supported = match kind {
+            Kind::Bool => size == 1,
+            Kind::Int | Kind::Uint => [1, 2, 4, 8].contains(&size),
+            Kind::Float => [2, 4, 8].contains(&size),
+        };
+        if !supported {
+            return unsupported();
+        }
+        Ok(NpyDtype { kind, size, big_endian })
+    }
+
+    /// Converts the values, failing if one of the integers can't be represented exactly
+    fn to_f32(self, bytes: &[u8]) -> io::Result<Vec<f32>> {
+        bytes
+            .chunks(self.size)
+            .map(|value| {
+                let mut buffer = [0; 8];
+                if self.big_endian {
+                    value.iter().rev().enumerate().for_each(|(i, &byte)| buffer[i] = byte);
+                } else {
+                    buffer[..value.len()].copy_from_slice(value);
+                }
+                let [b0, b1, b2, b3, ..] = buffer;
+                let (converted, exact) = match (self.kind, self.size) {
+                    (Kind::Bool, _) | (Kind::Uint, 1) => (b0 as f32, true),
+                    (Kind::Int, 1) => (b0 as i8 as f32, true),
+                    (Kind::Int, 2) => (i16::from_le_bytes([b0, b1]) as f32, true),
+                    (Kind::Uint, 2) => (u16::from_le_bytes([b0, b1]) as f32, true),
+                    (Kind::Int, 4) => {
+                        let value = i32::from_le_bytes([b0, b1, b2, b3]);
+                        (value as f32, value as f32 as i64 == value as i64)
+                    }
+                    (Kind::Uint, 4) => {
+                        let value = u32::from_le_bytes([b0, b1, b2, b3]);
+                        (value as f32, value as f32 as u64 == value as u64)
+                    }
+                    (Kind::Int, _) => {
+                        let value = i64::from_le_bytes(buffer);
+                        (value as f32, value as f32 as i128 == value as i128)
+                    }
+                    (Kind::Uint, _) => {
+                        let value = u64::from_le_bytes(buffer);
+                        (value as f32, value as f32 as u128 == value as u128)
+                    }
+                    (Kind::Float, 2) => (f16_to_f32(u16::from_le_bytes([b0, b1])), true),
+                    (Kind::Float, 4) => (f32::from_le_bytes([b0, b1, b2, b3]), true),
+                    (Kind::Float, _) => (f64::from_le_bytes(buffer) as f32, true),
+                };
+                if exact {
+                    Ok(converted)
+                } else {
+                    invalid_data("The .npy array has values which can't be converted to f32 exactly".to_string())
+                }
+            })
+            .collect()
+    }
+}
+
+struct NpyHeader {
+    dtype: NpyDtype,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+impl NpyHeader {
+    /// Parses the Python dict literal of the header, such as
+    /// `{'descr': '<f4', 'fortran_order': False, 'shape': (3, 4), }`
+    fn parse(header: &str) -> io::Result<NpyHeader> {
+        let invalid = || invalid_data(format!("Invalid .npy header {}", header.trim_end()));
+        let body = header.trim();
+        let body = match body.strip_prefix('{').and_then(|body| body.strip_suffix('}')) {
+            Some(body) => body,
+            None => return invalid(),
+        };
+        let (mut descr, mut fortran_order, mut shape) = (None, None, None);
+        let mut rest = body.trim_start();
+        while !rest.is_empty() {
+            let (key, after_key) = match quoted(rest) {
+                Some(parsed) => parsed,
+                None => return invalid(),
+            };
+            let value = match after_key.trim_start().strip_prefix(':') {
+                Some(value) => value.trim_start(),
+                None => return invalid(),
+            };
+            let after_value = match key {
+                "descr" => quoted(value).map(|(dtype, after)| {
+                    descr = Some(dtype.to_string());
+                    after
+                }),
+                "fortran_order" => {
+                    if let Some(after) = value.strip_prefix("True") {
+                        fortran_order = Some(true);
+                        Some(after)
+                    } else if let Some(after) = value.strip_prefix("False") {
+                        fortran_order = Some(false);
+                        Some(after)
+                    } else {
+                        None
+                    }
+                }
+                "shape" => tuple(value).map(|(dims, after)| {
+                    shape = Some(dims);
+                    after
+                }),
+                _ => None,
+            };
+            rest = match after_value {
+                Some(after) => {
+                    let after = after.trim_start();
+                    after.strip_prefix(',').unwrap_or(after).trim_start()
+                }
+                None => return invalid(),
+            };
+        }
+        match (descr, fortran_order, shape) {
+            (Some(descr), Some(fortran_order), Some(shape)) => Ok(NpyHeader {
+                dtype: NpyDtype::parse(&descr)?,
+                fortran_order,
+                shape,
+            }),
+            _ => invalid(),
+        }
+    }
+}
+
+/// Parses a single or double quoted string, returning it and the text after it
+fn quoted(text: &str) -> Option<(&str, &str)> {
+    let quote = text.chars().next().filter(|&c| c == '\'' || c == '"')?;
+    let end = text[1..].find(quote)? + 1;
+    Some((&text[1..end], &text[end + 1..]))
+}
+
+/// Parses a tuple of integers, returning them and the text after it
+fn tuple(text: &str) -> Option<(Vec<usize>, &str)> {
+    let end = text.find(')')?;
+    let dims = text.strip_prefix('(')?[..end - 1]
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        // Python 2 wrote long integers with a trailing L
+        .map(|dim| dim.trim_end_matches('L').parse().ok())
+        .collect::<Option<Vec<usize>>>()?;
+    Some((dims, &text[end + 1..]))
+}
+
+#[test]
+fn reads_numpy_headers_and_dtypes() {
+    // As written by numpy.save(np.arange(6, dtype='>i2').reshape(2, 3, order='F'))
+    let header = "{'descr': '>i2', 'fortran_order': True, 'shape': (2, 3), }";
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for value in &[0i16, 3, 1, 4, 2, 5] {
+        bytes.extend_from_slice(&value.to_be_bytes());
+    }
+    let tensor = CpuTensor::from_npy(bytes.as_slice()).unwrap();
+    assert_eq!(tensor.shape(), &[2, 3]);
+    assert_eq!(tensor.strides(), &[1, 2]);
+    assert_eq!(tensor.as_contiguous_vec(), vec![0., 1., 2., 3., 4., 5.]);
+
+    let header = "{'descr': '<f8', 'fortran_order': False, 'shape': (), }";
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(&0.5f64.to_le_bytes());
+    assert_eq!(CpuTensor::from_npy(bytes.as_slice()).unwrap().as_contiguous_vec(), vec![0.5]);
+    // 0.1 is not exactly representable as a f32, so it is rounded
+    let len = bytes.len();
+    bytes[len - 8..].copy_from_slice(&0.1f64.to_le_bytes());
+    assert_eq!(CpuTensor::from_npy(bytes.as_slice()).unwrap().as_contiguous_vec(), vec![0.1f32]);
+    assert!(NpyDtype::parse("<c8").is_err());
+}
+
+#[test]
+fn rejects_truncated_and_oversized_arrays() {
+    let npy = |shape: &str, data: &[u8]| {
+        let header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {}, }}", shape);
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    };
+    let read = |bytes: Vec<u8>| CpuTensor::from_npy(bytes.as_slice()).map_err(|error| error.kind());
+    assert_eq!(read(npy("(2,)", &[0; 8])).map(|tensor| tensor.numel()), Ok(2));
+    assert_eq!(read(npy("(2,)", &[0; 6])), Err(io::ErrorKind::UnexpectedEof));
+    assert_eq!(read(npy("(4294967296, 4294967296)", &[])), Err(io::ErrorKind::InvalidData));
+    assert_eq!(read(npy("(1000000000000,)", &[0; 4])), Err(io::ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn writes_aligned_npy_and_npz() {
+    let tensor = CpuTensor::from_data_and_shape(vec![1., 2., 3.], vec![3]);
+    let mut bytes = Vec::new();
+    tensor.to_npy(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 2 * ALIGNMENT + 12);
+    let header = std::str::from_utf8(&bytes[10..2 * ALIGNMENT]).unwrap();
+    assert!(header.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (3,), }"));
+    assert!(header.ends_with(" \n"));
+
+    let mut tensors = HashMap::new();
+    tensors.insert("x".to_string(), tensor);
+    tensors.insert("y".to_string(), CpuTensor::from_data_and_shape(vec![4., 5., 6., 7.], vec![2, 2]));
+    let mut archive = Vec::new();
+    write_npz(&mut archive, &tensors).unwrap();
+    assert_eq!(read_npz(archive.as_slice()).unwrap(), tensors);
+}
@@ -0,0 +1,338 @@
+//! Reading and writing of the [safetensors](https://github.com/huggingface/safetensors) format,
+//! so weights can be exchanged with other tools.
+//!
+//! A file starts with the size of its header as a little endian `u64`, followed by the JSON
+//! header and the data of all the Tensors. The header maps each name to the `dtype`, the
+//! `shape` and the `data_offsets` of its Tensor, which are relative to the end of the header.
+//! The Tensors are written in `F32`, and the other numeric dtypes are converted to `f32` when
+//! loading, which is exact for all of them except `F64`, `I32` and `I64`. Scalars, which have an
+//! empty shape, are loaded with shape `[1]` since Tensors have at least one dimension.
+use crate::serialization::json::{invalid_data, JsonValue};
+use crate::{CpuTensor, RawTensor, ShapeStrideTrait};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Key of the optional string to string map of the header, which isn't a Tensor
+const METADATA_KEY: &str = "__metadata__";
+
+/// Larger headers are rejected, so corrupted files don't allocate huge buffers
+const MAX_HEADER_SIZE: u64 = 100_000_000;
+
+/// Saves the Tensors to a safetensors file, see [`serialize`]
+pub fn save<P: AsRef<Path>>(path: P, tensors: &HashMap<String, RawTensor>) -> io::Result<()> {
+    let tensors = tensors.iter().map(|(name, tensor)| (name.clone(), tensor.to_cpu())).collect();
+    fs::write(path, serialize(&tensors))
+}
+
+/// Loads the Tensors of a safetensors file into the GPU, see [`deserialize`]
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<HashMap<String, RawTensor>> {
+    let tensors = deserialize(&fs::read(path)?)?;
+    Ok(tensors
+        .into_iter()
+        .map(|(name, tensor)| {
+            let shape = tensor.shape().iter().copied().collect();
+            (name, RawTensor::from_data_and_shape(tensor.as_contiguous_vec(), shape))
+        })
+        .collect())
+}
+
+/// Returns the safetensors representation of the Tensors. They are written in the order of
+/// their names, so the output doesn't depend on the iteration order of the map.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use tensor_compute::serialization::safetensors::{deserialize, serialize};
+/// use tensor_compute::CpuTensor;
+/// let mut tensors = HashMap::new();
+/// tensors.insert("weight".to_string(), CpuTensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![2, 2]));
+/// let bytes = serialize(&tensors);
+/// assert_eq!(deserialize(&bytes).unwrap(), tensors);
+/// ```
+pub fn serialize(tensors: &HashMap<String, CpuTensor>) -> Vec<u8> {
+    serialize_with_metadata(tensors, &HashMap::new())
+}
+
+/// Same as [`serialize`], also writing the string to string `metadata` map of the header
+pub fn serialize_with_metadata(tensors: &HashMap<String, CpuTensor>, metadata: &HashMap<String, String>) -> Vec<u8> {
+    let mut names: Vec<&String> = tensors.keys().collect();
+    names.sort();
+    let mut header = BTreeMap::new();
+    if !metadata.is_empty() {
+        let metadata = metadata
+            .iter()
+            .map(|(key, value)| (key.clone(), JsonValue::String(value.clone())))
+            .collect();
+        header.insert(METADATA_KEY.to_string(), JsonValue::Object(metadata));
+    }
+    let mut data = Vec::new();
+    for name in names {
+        assert_ne!(name, METADATA_KEY, "{} is reserved for the metadata of the file", METADATA_KEY);
+        let tensor = &tensors[name];
+        let begin = data.len();
+        for value in tensor.as_contiguous_vec() {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        let number = |n: usize| JsonValue::Number(n as f64);
+        let mut entry = BTreeMap::new();
+        entry.insert("dtype".to_string(), JsonValue::String("F32".to_string()));
+        entry.insert("shape".to_string(), JsonValue::Array(tensor.shape().iter().map(|&d| number(d)).collect()));
+        entry.insert("data_offsets".to_string(), JsonValue::Array(vec![number(begin), number(data.len())]));
+        header.insert(name.clone(), JsonValue::Object(entry));
+    }
+    let mut header_text = String::new();
+    JsonValue::Object(header).write(&mut header_text);
+    // The header is padded with spaces, so the data is aligned to 8 bytes
+    while !header_text.len().is_multiple_of(8) {
+        header_text.push(' ');
+    }
+    let mut bytes = Vec::with_capacity(8 + header_text.len() + data.len());
+    bytes.extend_from_slice(&(header_text.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(header_text.as_bytes());
+    bytes.extend_from_slice(&data);
+    bytes
+}
+
+/// Parses a safetensors file, converting the Tensors to `f32`. Returns an
+/// [`io::ErrorKind::InvalidData`] error if the file is malformed, for example if the data of
+/// the Tensors overlap or don't cover the whole file.
+pub fn deserialize(bytes: &[u8]) -> io::Result<HashMap<String, CpuTensor>> {
+    deserialize_with_metadata(bytes).map(|(tensors, _metadata)| tensors)
+}
+
+/// Same as [`deserialize`], also returning the metadata map of the header, which is empty if
+/// there is none
+pub fn deserialize_with_metadata(bytes: &[u8]) -> io::Result<(HashMap<String, CpuTensor>, HashMap<String, String>)> {
+    if bytes.len() < 8 {
+        return invalid_data("The file is too short to hold the header size".to_string());
+    }
+    let mut size_bytes = [0; 8];
+    size_bytes.copy_from_slice(&bytes[..8]);
+    let header_size = u64::from_le_bytes(size_bytes);
+    if header_size > MAX_HEADER_SIZE || header_size > (bytes.len() - 8) as u64 {
+        return invalid_data(format!("Invalid header size {}", header_size));
+    }
+    let data = &bytes[8 + header_size as usize..];
+    let header_text = match std::str::from_utf8(&bytes[8..8 + header_size as usize]) {
+        Ok(text) => text,
+        Err(_) => return invalid_data("The header is not valid UTF-8".to_string()),
+    };
+    let header = JsonValue::parse(header_text)?;
+    let header = match header.as_object() {
+        Some(header) => header,
+        None => return invalid_data("The header must be a JSON object".to_string()),
+    };
+    let mut entries = Vec::with_capacity(header.len());
+    let mut metadata = HashMap::new();
+    for (name, entry) in header {
+        if name != METADATA_KEY {
+            entries.push((name, TensorEntry::parse(name, entry)?));
+            continue;
+        }
+        let values = entry.as_object().map(|values| {
+            values
+                .iter()
+                .map(|(key, value)| value.as_str().map(|value| (key.clone(), value.to_string())))
+                .collect::<Option<HashMap<String, String>>>()
+        });
+        metadata = match values {
+            Some(Some(values)) => values,
+            _ => return invalid_data("The metadata must map strings to strings".to_string()),
+        };
+    }
+    // The data of the Tensors must be contiguous and cover the whole buffer
+    entries.sort_by_key(|(_, entry)| entry.offsets);
+    let mut end = 0;
+    for (name, entry) in &entries {
+        if entry.offsets.0 != end {
+            return invalid_data(format!("The data of {} doesn't start where the previous Tensor ends", name));
+        }
+        end = entry.offsets.1;
+    }
+    if end != data.len() {
+        return invalid_data(format!("The Tensors hold {} bytes but the file has {}", end, data.len()));
+    }
+    let tensors = entries
+        .into_iter()
+        .map(|(name, entry)| {
+            let values = entry.dtype.to_f32(&data[entry.offsets.0..entry.offsets.1]);
+            let shape = if entry.shape.is_empty() { vec![1] } else { entry.shape };
+            (name.clone(), CpuTensor::from_data_and_shape(values, shape))
+        })
+        .collect();
+    Ok((tensors, metadata))
+}
+
+/// The numeric dtypes of safetensors
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dtype {
+    Bool,
+    U8,
+    I8,
+    I16,
+    I32,
+    I64,
+    F16,
+    BF16,
+    F32,
+    F64,
+}
+
+impl Dtype {
+    fn parse(name: &str) -> Option<Dtype> {
+        Some(match name {
+            "BOOL" => Dtype::Bool,
+            "U8" => Dtype::U8,
+            "I8" => Dtype::I8,
+            "I16" => Dtype::I16,
+            "I32" => Dtype::I32,
+            "I64" => Dtype::I64,
+            "F16" => Dtype::F16,
+            "BF16" => Dtype::BF16,
+            "F32" => Dtype::F32,
+            "F64" => Dtype::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Dtype::Bool | Dtype::U8 | Dtype::I8 => 1,
+            Dtype::I16 | Dtype::F16 | Dtype::BF16 => 2,
+            Dtype::I32 | Dtype::F32 => 4,
+            Dtype::I64 | Dtype::F64 => 8,
+        }
+    }
+
+    /// Converts little endian values of this dtype
+    fn to_f32(self, bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks(self.size())
+            .map(|value| {
+                let mut buffer = [0; 8];
+                buffer[..value.len()].copy_from_slice(value);
+                let [b0, b1, b2, b3, ..] = buffer;
+                match self {
+                    Dtype::Bool | Dtype::U8 => value[0] as f32,
+                    Dtype::I8 => value[0] as i8 as f32,
+                    Dtype::I16 => i16::from_le_bytes([b0, b1]) as f32,
+                    Dtype::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f32,
+                    Dtype::I64 => i64::from_le_bytes(buffer) as f32,
+                    Dtype::F16 => f16_to_f32(u16::from_le_bytes([b0, b1])),
+                    // bfloat16 is the upper half of a f32
+                    Dtype::BF16 => f32::from_bits((u16::from_le_bytes([b0, b1]) as u32) << 16),
+                    Dtype::F32 => f32::from_le_bytes([b0, b1, b2, b3]),
+                    Dtype::F64 => f64::from_le_bytes(buffer) as f32,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Converts an IEEE half precision float, which is exact
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits >> 15) as u32) << 31;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    let magnitude = match exponent {
+        // Subnormal values are mantissa * 2^-24
+        0 => {
+            let value = mantissa as f32 * 2f32.powi(-24);
+            return if sign == 0 { value } else { -value };
+        }
+        0x1f => 0xff << 23 | mantissa << 13,
+        _ => (exponent + 127 - 15) << 23 | mantissa << 13,
+    };
+    f32::from_bits(sign | magnitude)
+}
+
+struct TensorEntry {
+    dtype: Dtype,
+    shape: Vec<usize>,
+    offsets: (usize, usize),
+}
+
+impl TensorEntry {
+    fn parse(name: &str, entry: &JsonValue) -> io::Result<TensorEntry> {
+        let invalid = |what: &str| invalid_data(format!("Invalid {} for the Tensor {}", what, name));
+        let entry = match entry.as_object() {
+            Some(entry) => entry,
+            None => return invalid("entry"),
+        };
+        let dtype = match entry.get("dtype").and_then(JsonValue::as_str) {
+            Some(dtype) => match Dtype::parse(dtype) {
+                Some(dtype) => dtype,
+                None => return invalid_data(format!("Unsupported dtype {} for the Tensor {}", dtype, name)),
+            },
+            None => return invalid("dtype"),
+        };
+        let shape = match entry.get("shape").and_then(JsonValue::as_usize_array) {
+            Some(shape) => shape,
+            None => return invalid("shape"),
+        };
+        let offsets = match entry.get("data_offsets").and_then(JsonValue::as_usize_array) {
+            Some(offsets) if offsets.len() == 2 && offsets[0] <= offsets[1] => (offsets[0], offsets[1]),
+            _ => return invalid("data_offsets"),
+        };
+        let numel = shape.iter().try_fold(1usize, |numel, &d| numel.checked_mul(d));
+        if numel.and_then(|numel| numel.checked_mul(dtype.size())) != Some(offsets.1 - offsets.0) {
+            return invalid_data(format!("The data_offsets of {} don't match its shape and dtype", name));
+        }
+        Ok(TensorEntry { dtype, shape, offsets })
+    }
+}
+
+#[test]
+fn round_trip_and_layout() {
+    use std::collections::VecDeque;
+    let mut tensors = HashMap::new();
+    tensors.insert("b".to_string(), CpuTensor::from_data_and_shape(vec![1.5, -2.], vec![2]));
+    // Non contiguous Tensors are written in their logical order
+    let transposed = CpuTensor::new_with_strides_and_offset(vec![1., 2., 3., 4., 5., 6.], VecDeque::from(vec![3, 2]), VecDeque::from(vec![1, 3]), 0);
+    tensors.insert("a".to_string(), transposed);
+    let bytes = serialize(&tensors);
+    let header_size = u64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    assert_eq!(header_size % 8, 0);
+    let header = std::str::from_utf8(&bytes[8..8 + header_size]).unwrap().trim_end();
+    assert_eq!(
+        header,
+        r#"{"a":{"data_offsets":[0,24],"dtype":"F32","shape":[3,2]},"b":{"data_offsets":[24,32],"dtype":"F32","shape":[2]}}"#
+    );
+    assert_eq!(&bytes[8 + header_size..8 + header_size + 8], &[0, 0, 128, 63, 0, 0, 128, 64][..]);
+    let loaded = deserialize(&bytes).unwrap();
+    assert_eq!(loaded["a"].as_contiguous_vec(), vec![1., 4., 2., 5., 3., 6.]);
+    assert_eq!(loaded["b"], tensors["b"]);
+}
+
+#[test]
+fn converts_other_dtypes() {
+    let header = r#"{"__metadata__":{"format":"pt"},"h":{"dtype":"F16","shape":[3],"data_offsets":[0,6]},"i":{"dtype":"I64","shape":[1,1],"data_offsets":[6,14]},"s":{"dtype":"F64","shape":[],"data_offsets":[14,22]}}"#;
+    let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+    bytes.extend_from_slice(header.as_bytes());
+    // 1.0, -2.5 and the smallest subnormal in half precision
+    bytes.extend_from_slice(&[0x00, 0x3c, 0x00, 0xc1, 0x01, 0x00]);
+    bytes.extend_from_slice(&(-7i64).to_le_bytes());
+    bytes.extend_from_slice(&0.25f64.to_le_bytes());
+    let (tensors, metadata) = deserialize_with_metadata(&bytes).unwrap();
+    assert_eq!(metadata["format"], "pt");
+    assert_eq!(tensors.len(), 3);
+    assert_eq!(tensors["h"].as_contiguous_vec(), vec![1., -2.5, 2f32.powi(-24)]);
+    assert_eq!(tensors["i"].as_contiguous_vec(), vec![-7.]);
+    assert_eq!(tensors["s"].as_contiguous_vec(), vec![0.25]);
+    assert_eq!(tensors["s"].shape(), &[1]);
+}
+
+#[test]
+fn rejects_malformed_files() {
+    let mut tensors = HashMap::new();
+    tensors.insert("a".to_string(), CpuTensor::from_data_and_shape(vec![1., 2.], vec![2]));
+    let bytes = serialize(&tensors);
+    assert!(deserialize(&bytes[..bytes.len() - 1]).is_err());
+    let mut extra = bytes.clone();
+    extra.push(0);
+    assert!(deserialize(&extra).is_err());
+    assert!(deserialize(&[255; 16]).is_err());
+}
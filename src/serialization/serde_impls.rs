@@ -0,0 +1,276 @@
+//! `serde` support for [`CpuTensor`] and [`ShapeStrides`], enabled by the `serde` feature.
+//!
+//! Both serialize as a struct with their `shape`, `strides` and `offset`. The elements of a
+//! CpuTensor are stored as they are in memory, as little endian `f32` bytes: a base64 string in
+//! human readable formats such as JSON and a byte array in binary formats such as bincode.
+//! A sequence of numbers is also accepted when deserializing, which is handy for hand-written
+//! configurations. Deserializing fails if the strides and offset reach outside of the data.
+//!
+//! # Examples
+//!
+//! ```
+//! use tensor_compute::CpuTensor;
+//! let tensor = CpuTensor::from_data_and_shape(vec![1., 2.], vec![2]);
+//! let json = serde_json::to_string(&tensor).unwrap();
+//! assert_eq!(json, r#"{"shape":[2],"strides":[1],"offset":0,"data":"AACAPwAAAEA="}"#);
+//! assert_eq!(serde_json::from_str::<CpuTensor>(&json).unwrap(), tensor);
+//! ```
+use crate::{CpuTensor, ShapeStrideTrait, ShapeStrides};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::{self, Formatter};
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 encoding, with padding
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |acc, (idx, &byte)| acc | (byte as u32) << (16 - 8 * idx));
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(group >> (18 - 6 * idx) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn base64_decode(encoded: &str) -> Result<Vec<u8>, String> {
+    let encoded = encoded.as_bytes();
+    if !encoded.len().is_multiple_of(4) {
+        return Err("the length of base64 data must be a multiple of 4".to_string());
+    }
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    for (chunk_idx, chunk) in encoded.chunks(4).enumerate() {
+        let is_last = chunk_idx + 1 == encoded.len() / 4;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return Err("invalid base64 padding".to_string());
+        }
+        let mut group = 0u32;
+        for (idx, &c) in chunk[..4 - padding].iter().enumerate() {
+            let value = match BASE64_ALPHABET.iter().position(|&a| a == c) {
+                Some(value) => value as u32,
+                None => return Err(format!("invalid base64 character {:?}", c as char)),
+            };
+            group |= value << (18 - 6 * idx);
+        }
+        bytes.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(bytes)
+}
+
+/// Checks that the elements of a Tensor with this layout are inside `data_len` elements
+fn check_layout(shape: &VecDeque<usize>, strides: &VecDeque<usize>, offset: usize, data_len: usize) -> Result<(), String> {
+    if shape.len() != strides.len() {
+        return Err(format!("the shape {:?} and strides {:?} have different ranks", shape, strides));
+    }
+    if shape.contains(&0) {
+        return Ok(());
+    }
+    let last = shape.iter().zip(strides).try_fold(offset, |last, (&dim, &stride)| {
+        (dim - 1).checked_mul(stride).and_then(|reach| last.checked_add(reach))
+    });
+    match last {
+        Some(last) if last < data_len => Ok(()),
+        _ => Err(format!(
+            "the shape {:?}, strides {:?} and offset {} reach outside of the {} elements of data",
+            shape, strides, offset, data_len
+        )),
+    }
+}
+
+/// The elements of a CpuTensor, see the module documentation for their format
+struct Data(Vec<f32>);
+
+impl Serialize for Data {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes: Vec<u8> = self.0.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&base64_encode(&bytes))
+        } else {
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+}
+
+struct DataVisitor;
+
+impl<'de> Visitor<'de> for DataVisitor {
+    type Value = Data;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("little endian f32 bytes, as base64 or a byte array, or a sequence of numbers")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Data, E> {
+        self.visit_byte_buf(base64_decode(value).map_err(E::custom)?)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Data, E> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err(E::invalid_length(bytes.len(), &"a multiple of 4 bytes"));
+        }
+        let values = bytes
+            .chunks(4)
+            .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+            .collect();
+        Ok(Data(values))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Data, E> {
+        self.visit_bytes(&bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Data, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Data(values))
+    }
+}
+
+impl<'de> Deserialize<'de> for Data {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Data, D::Error> {
+        // Binary formats are not self describing
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(DataVisitor)
+        } else {
+            deserializer.deserialize_byte_buf(DataVisitor)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "ShapeStrides")]
+struct ShapeStridesRepr {
+    shape: VecDeque<usize>,
+    strides: VecDeque<usize>,
+    offset: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "CpuTensor")]
+struct CpuTensorRepr {
+    shape: VecDeque<usize>,
+    strides: VecDeque<usize>,
+    offset: usize,
+    data: Data,
+}
+
+impl Serialize for ShapeStrides {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ShapeStridesRepr {
+            shape: self.shape().clone(),
+            strides: self.strides().clone(),
+            offset: self.offset(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ShapeStrides {
+    /// Fails if the shape and strides have different ranks. Whether the offset is valid
+    /// depends on the buffer the layout is used with, so it is not checked.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ShapeStrides, D::Error> {
+        let repr = ShapeStridesRepr::deserialize(deserializer)?;
+        if repr.shape.len() != repr.strides.len() {
+            return Err(de::Error::custom(format!(
+                "the shape {:?} and strides {:?} have different ranks",
+                repr.shape, repr.strides
+            )));
+        }
+        Ok(ShapeStrides::from_shape_and_strides_and_offset(repr.shape, repr.strides, repr.offset))
+    }
+}
+
+impl Serialize for CpuTensor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CpuTensorRepr {
+            shape: self.shape().clone(),
+            strides: self.strides().clone(),
+            offset: self.offset(),
+            data: Data(self.raw_data_slice().to_vec()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CpuTensor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<CpuTensor, D::Error> {
+        let repr = CpuTensorRepr::deserialize(deserializer)?;
+        check_layout(&repr.shape, &repr.strides, repr.offset, repr.data.0.len()).map_err(de::Error::custom)?;
+        Ok(CpuTensor::new_with_strides_and_offset(
+            repr.data.0,
+            repr.shape,
+            repr.strides,
+            repr.offset,
+        ))
+    }
+}
+
+#[test]
+fn base64_round_trip() {
+    for (bytes, encoded) in &[(&b""[..], ""), (b"f", "Zg=="), (b"fo", "Zm8="), (b"foo", "Zm9v"), (b"foob", "Zm9vYg==")] {
+        assert_eq!(base64_encode(bytes), *encoded);
+        assert_eq!(base64_decode(encoded).unwrap(), *bytes);
+    }
+    assert!(base64_decode("Zm9").is_err());
+    assert!(base64_decode("Zg==Zm8=").is_err());
+    assert!(base64_decode("Z!==").is_err());
+}
+
+#[test]
+fn tensors_round_trip_in_json_and_bincode() {
+    // A transposed view, which keeps its layout
+    let tensor = CpuTensor::new_with_strides_and_offset(
+        vec![0., 1., 2., 3., 4., 5., -1.5],
+        VecDeque::from(vec![3, 2]),
+        VecDeque::from(vec![1, 3]),
+        1,
+    );
+    let json = serde_json::to_string(&tensor).unwrap();
+    assert!(json.starts_with(r#"{"shape":[3,2],"strides":[1,3],"offset":1,"data":""#));
+    let from_json: CpuTensor = serde_json::from_str(&json).unwrap();
+    assert_eq!(from_json.raw_data_slice(), tensor.raw_data_slice());
+    assert_eq!(from_json.strides(), tensor.strides());
+    assert_eq!(from_json, tensor);
+
+    let encoded = bincode::serialize(&tensor).unwrap();
+    // The shape, strides, offset, byte length and data
+    assert_eq!(encoded.len(), 8 * (1 + 2 + 1 + 2 + 1 + 1) + 4 * 7);
+    let from_bincode: CpuTensor = bincode::deserialize(&encoded).unwrap();
+    assert_eq!(from_bincode.offset(), 1);
+    assert_eq!(from_bincode, tensor);
+
+    let layout = ShapeStrides::from_shape_and_strides_and_offset(VecDeque::from(vec![2, 2]), VecDeque::from(vec![0, 1]), 3);
+    let json = serde_json::to_string(&layout).unwrap();
+    assert_eq!(json, r#"{"shape":[2,2],"strides":[0,1],"offset":3}"#);
+    let from_json: ShapeStrides = serde_json::from_str(&json).unwrap();
+    assert_eq!(from_json.strides(), layout.strides());
+    let from_bincode: ShapeStrides = bincode::deserialize(&bincode::serialize(&layout).unwrap()).unwrap();
+    assert_eq!(from_bincode.offset(), 3);
+}
+
+#[test]
+fn deserializing_validates_the_layout() {
+    let from_numbers: CpuTensor = serde_json::from_str(r#"{"shape":[2],"strides":[1],"offset":0,"data":[1,2.5]}"#).unwrap();
+    assert_eq!(from_numbers.as_contiguous_vec(), vec![1., 2.5]);
+
+    let error = |json: &str| serde_json::from_str::<CpuTensor>(json).unwrap_err().to_string();
+    assert!(error(r#"{"shape":[2,2],"strides":[2,1],"offset":1,"data":[0,1,2,3]}"#).contains("reach outside"));
+    assert!(error(r#"{"shape":[2],"strides":[1,1],"offset":0,"data":[0,1]}"#).contains("different ranks"));
+    assert!(error(r#"{"shape":[2],"strides":[1],"offset":0,"data":"AACAPw="}"#).contains("base64"));
+    assert!(error(r#"{"shape":[2],"strides":[1],"offset":0,"data":"AACAPwAA"}"#).contains("multiple of 4"));
+    assert!(error(r#"{"shape":[2],"strides":[1],"offset":0}"#).contains("missing field `data`"));
+    assert!(serde_json::from_str::<ShapeStrides>(r#"{"shape":[2],"strides":[],"offset":0}"#).is_err());
+    // Overflowing strides are rejected instead of wrapping
+    let huge = format!(r#"{{"shape":[3],"strides":[{}],"offset":0,"data":[0]}}"#, usize::MAX);
+    assert!(error(&huge).contains("reach outside"));
+}
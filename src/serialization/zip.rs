@@ -0,0 +1,194 @@
+//! A minimal reader and writer of zip archives whose entries are stored without compression,
+//! as written by `numpy.savez`. The sizes and offsets are read from the central directory,
+//! including its zip64 extension, and the checksums of the entries are verified.
+use crate::serialization::json::invalid_data;
+use std::io::{self, Write};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+/// Version 2.0, the first one supporting directories and stored entries
+const VERSION: u16 = 20;
+/// The first day of the MS-DOS dates, 1980-01-01
+const DOS_DATE: u16 = 0x21;
+
+/// The CRC-32 of zip archives, with the reflected polynomial 0xEDB88320
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Little endian reads which fail if the archive is truncated
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        match self.bytes.get(self.position..self.position.saturating_add(len)) {
+            Some(taken) => {
+                self.position += len;
+                Ok(taken)
+            }
+            None => invalid_data("Truncated zip archive".to_string()),
+        }
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+}
+
+/// Returns the names and the data of the entries of the archive, failing if one of them is
+/// compressed
+pub(crate) fn read_stored_entries(archive: &[u8]) -> io::Result<Vec<(String, &[u8])>> {
+    // The end of central directory record is followed by a comment of at most 64 KiB
+    let last_start = archive.len().checked_sub(END_OF_CENTRAL_DIRECTORY_LEN);
+    let end_record = last_start.and_then(|last_start| {
+        (last_start.saturating_sub(u16::MAX as usize)..=last_start)
+            .rev()
+            .find(|&start| archive[start..start + 4] == END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())
+    });
+    let mut cursor = match end_record {
+        // Skips the signature and the disk numbers
+        Some(start) => Cursor { bytes: archive, position: start + 8 },
+        None => return invalid_data("Not a zip archive".to_string()),
+    };
+    let entries = cursor.u16()?;
+    cursor.u16()?;
+    cursor.u32()?;
+    let mut central = Cursor { bytes: archive, position: cursor.u32()? as usize };
+    let mut result = Vec::with_capacity(entries as usize);
+    for _ in 0..entries {
+        if central.u32()? != CENTRAL_HEADER_SIGNATURE {
+            return invalid_data("Invalid zip central directory".to_string());
+        }
+        // Skips the versions and the flags
+        central.take(6)?;
+        let method = central.u16()?;
+        central.take(4)?;
+        let crc = central.u32()?;
+        let mut compressed_size = central.u32()? as u64;
+        let mut size = central.u32()? as u64;
+        let name_len = central.u16()? as usize;
+        let extra_len = central.u16()? as usize;
+        let comment_len = central.u16()? as usize;
+        central.take(8)?;
+        let mut local_offset = central.u32()? as u64;
+        let name = String::from_utf8_lossy(central.take(name_len)?).into_owned();
+        // The zip64 extra field holds the values which didn't fit, in this order
+        let mut extra = Cursor { bytes: central.take(extra_len)?, position: 0 };
+        while extra.position + 4 <= extra.bytes.len() {
+            let (id, len) = (extra.u16()?, extra.u16()? as usize);
+            let mut field = Cursor { bytes: extra.take(len)?, position: 0 };
+            if id == ZIP64_EXTRA_ID {
+                for value in &mut [&mut size, &mut compressed_size, &mut local_offset] {
+                    if **value == u32::MAX as u64 {
+                        **value = field.u64()?;
+                    }
+                }
+            }
+        }
+        central.take(comment_len)?;
+        if method != 0 {
+            return invalid_data(format!("The zip entry {} is compressed, which is not supported", name));
+        }
+        let mut local = Cursor { bytes: archive, position: local_offset as usize };
+        if local.u32()? != LOCAL_HEADER_SIGNATURE {
+            return invalid_data(format!("Invalid zip local header for {}", name));
+        }
+        local.take(22)?;
+        let local_name_len = local.u16()? as usize;
+        let local_extra_len = local.u16()? as usize;
+        local.take(local_name_len + local_extra_len)?;
+        let data = local.take(size.min(compressed_size) as usize)?;
+        if size != compressed_size || crc32(data) != crc {
+            return invalid_data(format!("The zip entry {} is corrupted", name));
+        }
+        result.push((name, data));
+    }
+    Ok(result)
+}
+
+/// Writes the entries to a zip archive, without compression
+pub(crate) fn write_stored_entries<W: Write>(mut writer: W, entries: &[(String, Vec<u8>)]) -> io::Result<()> {
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "Archives larger than 4 GiB are not supported");
+    let mut central = Vec::new();
+    let mut offset = 0usize;
+    for (name, data) in entries {
+        let size = data.len() as u32;
+        if data.len() > u32::MAX as usize || offset > u32::MAX as usize {
+            return Err(too_large());
+        }
+        // The common part of the local and central headers, from the version needed
+        let mut common = Vec::with_capacity(26);
+        for value in &[VERSION, 0, 0, 0, DOS_DATE] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in &[crc32(data), size, size] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        let mut local = LOCAL_HEADER_SIGNATURE.to_le_bytes().to_vec();
+        local.extend_from_slice(&common);
+        local.extend_from_slice(name.as_bytes());
+        writer.write_all(&local)?;
+        writer.write_all(data)?;
+
+        central.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        central.extend_from_slice(&VERSION.to_le_bytes());
+        central.extend_from_slice(&common);
+        // Comment length, disk number, internal and external attributes
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&(offset as u32).to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+        offset += local.len() + data.len();
+    }
+    if offset > u32::MAX as usize || entries.len() > u16::MAX as usize {
+        return Err(too_large());
+    }
+    let mut end = END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes().to_vec();
+    end.extend_from_slice(&[0; 4]);
+    end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    end.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    end.extend_from_slice(&(offset as u32).to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes());
+    writer.write_all(&central)?;
+    writer.write_all(&end)
+}
+
+#[test]
+fn zip_round_trip() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    let entries = vec![("a.npy".to_string(), vec![1, 2, 3]), ("b".to_string(), Vec::new())];
+    let mut archive = Vec::new();
+    write_stored_entries(&mut archive, &entries).unwrap();
+    let read = read_stored_entries(&archive).unwrap();
+    assert_eq!(read, vec![("a.npy".to_string(), &[1u8, 2, 3][..]), ("b".to_string(), &[][..])]);
+    // Corrupting the data fails the checksum
+    archive[35] ^= 1;
+    assert!(read_stored_entries(&archive).is_err());
+    assert!(read_stored_entries(b"not a zip").is_err());
+}
@@ -0,0 +1,304 @@
+//! Straightforward CPU implementations of the window, normalization and attention Ops. They are slow, but simple enough to
+//! be obviously correct, so they serve as references when testing the GPU kernels.
+use crate::{Conv2dParams, ConvTranspose2dParams, CpuTensor, ShapeStrideTrait, Window2d};
+
+/// Returns the contiguous data and the `[batch, channels, height, width]` dims of `tensor`
+fn nchw(tensor: &CpuTensor) -> (Vec<f32>, [usize; 4]) {
+    let shape = tensor.shape();
+    assert_eq!(shape.len(), 4, "Expected a [batch, channels, height, width] Tensor");
+    (tensor.as_contiguous_vec(), [shape[0], shape[1], shape[2], shape[3]])
+}
+
+/// Calls `f(h, w)` for each position of the input inside the window of output `(oh, ow)`,
+/// skipping the padding
+fn for_each_in_window<F: FnMut(usize, usize)>(window: &Window2d, dims: [usize; 4], oh: usize, ow: usize, mut f: F) {
+    for ki in 0..window.kernel_size.0 {
+        let h = (oh * window.stride.0 + ki * window.dilation.0) as isize - window.padding.0 as isize;
+        for kj in 0..window.kernel_size.1 {
+            let w = (ow * window.stride.1 + kj * window.dilation.1) as isize - window.padding.1 as isize;
+            if h >= 0 && w >= 0 && (h as usize) < dims[2] && (w as usize) < dims[3] {
+                f(h as usize, w as usize);
+            }
+        }
+    }
+}
+
+impl CpuTensor {
+    /// Reference for [`crate::GpuTensor::conv2d`]
+    pub fn conv2d(&self, weight: &CpuTensor, bias: Option<&CpuTensor>, params: &Conv2dParams) -> CpuTensor {
+        let (input, [batch, in_channels, height, width]) = nchw(self);
+        let (weight, [out_channels, group_in_channels, kernel_h, kernel_w]) = nchw(weight);
+        let bias = bias.map(|bias| bias.as_contiguous_vec());
+        let window = params.window((kernel_h, kernel_w));
+        let (out_h, out_w) = window.output_size(height, width);
+        let group_out_channels = out_channels / params.groups;
+        let mut output = Vec::with_capacity(batch * out_channels * out_h * out_w);
+        for n in 0..batch {
+            for oc in 0..out_channels {
+                let group = oc / group_out_channels;
+                for oh in 0..out_h {
+                    for ow in 0..out_w {
+                        let mut acc = bias.as_ref().map_or(0., |bias| bias[oc]);
+                        for gc in 0..group_in_channels {
+                            let ic = group * group_in_channels + gc;
+                            for ki in 0..kernel_h {
+                                for kj in 0..kernel_w {
+                                    let h = (oh * window.stride.0 + ki * window.dilation.0) as isize
+                                        - window.padding.0 as isize;
+                                    let w = (ow * window.stride.1 + kj * window.dilation.1) as isize
+                                        - window.padding.1 as isize;
+                                    if h < 0 || w < 0 || h as usize >= height || w as usize >= width {
+                                        continue;
+                                    }
+                                    let input_idx = ((n * in_channels + ic) * height + h as usize) * width + w as usize;
+                                    let weight_idx = ((oc * group_in_channels + gc) * kernel_h + ki) * kernel_w + kj;
+                                    acc += input[input_idx] * weight[weight_idx];
+                                }
+                            }
+                        }
+                        output.push(acc);
+                    }
+                }
+            }
+        }
+        CpuTensor::from_data_and_shape(output, vec![batch, out_channels, out_h, out_w])
+    }
+
+    /// Reference for [`crate::GpuTensor::conv_transpose2d`], scattering each input element
+    /// times the kernel into the output
+    pub fn conv_transpose2d(&self, weight: &CpuTensor, bias: Option<&CpuTensor>, params: &ConvTranspose2dParams) -> CpuTensor {
+        let (input, [batch, in_channels, height, width]) = nchw(self);
+        let (weight, [_, group_out_channels, kernel_h, kernel_w]) = nchw(weight);
+        let (out_h, out_w) = params.output_size((kernel_h, kernel_w), height, width);
+        let out_channels = group_out_channels * params.groups;
+        let group_in_channels = in_channels / params.groups;
+        let mut output = vec![0.; batch * out_channels * out_h * out_w];
+        for n in 0..batch {
+            for ic in 0..in_channels {
+                let group = ic / group_in_channels;
+                for h in 0..height {
+                    for w in 0..width {
+                        let value = input[((n * in_channels + ic) * height + h) * width + w];
+                        for gc in 0..group_out_channels {
+                            let oc = group * group_out_channels + gc;
+                            for ki in 0..kernel_h {
+                                for kj in 0..kernel_w {
+                                    let oh = (h * params.stride.0 + ki * params.dilation.0) as isize - params.padding.0 as isize;
+                                    let ow = (w * params.stride.1 + kj * params.dilation.1) as isize - params.padding.1 as isize;
+                                    if oh < 0 || ow < 0 || oh as usize >= out_h || ow as usize >= out_w {
+                                        continue;
+                                    }
+                                    let output_idx = ((n * out_channels + oc) * out_h + oh as usize) * out_w + ow as usize;
+                                    let weight_idx = ((ic * group_out_channels + gc) * kernel_h + ki) * kernel_w + kj;
+                                    output[output_idx] += value * weight[weight_idx];
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(bias) = bias {
+            let bias = bias.as_contiguous_vec();
+            for (plane_idx, plane) in output.chunks_mut(out_h * out_w).enumerate() {
+                plane.iter_mut().for_each(|e| *e += bias[plane_idx % out_channels]);
+            }
+        }
+        CpuTensor::from_data_and_shape(output, vec![batch, out_channels, out_h, out_w])
+    }
+
+    /// Reference for [`crate::GpuTensor::max_pool2d`], without the indices
+    pub fn max_pool2d(&self, window: &Window2d) -> CpuTensor {
+        let (input, dims) = nchw(self);
+        let (out_h, out_w) = window.output_size(dims[2], dims[3]);
+        let mut output = Vec::with_capacity(dims[0] * dims[1] * out_h * out_w);
+        for plane in input.chunks(dims[2] * dims[3]) {
+            for oh in 0..out_h {
+                for ow in 0..out_w {
+                    let mut max = f32::NEG_INFINITY;
+                    for_each_in_window(window, dims, oh, ow, |h, w| max = max.max(plane[h * dims[3] + w]));
+                    output.push(max);
+                }
+            }
+        }
+        CpuTensor::from_data_and_shape(output, vec![dims[0], dims[1], out_h, out_w])
+    }
+
+    /// Reference for [`crate::GpuTensor::avg_pool2d`]
+    pub fn avg_pool2d(&self, window: &Window2d, count_include_pad: bool) -> CpuTensor {
+        let (input, dims) = nchw(self);
+        let (out_h, out_w) = window.output_size(dims[2], dims[3]);
+        let mut output = Vec::with_capacity(dims[0] * dims[1] * out_h * out_w);
+        for plane in input.chunks(dims[2] * dims[3]) {
+            for oh in 0..out_h {
+                for ow in 0..out_w {
+                    let (mut sum, mut count) = (0., 0);
+                    for_each_in_window(window, dims, oh, ow, |h, w| {
+                        sum += plane[h * dims[3] + w];
+                        count += 1;
+                    });
+                    if count_include_pad {
+                        // The window is clipped to the padded input
+                        let padded_end = |start: usize, kernel: usize, size: usize, padding: usize| {
+                            (start + kernel).min(size + 2 * padding) - start
+                        };
+                        count = padded_end(oh * window.stride.0, window.kernel_size.0, dims[2], window.padding.0)
+                            * padded_end(ow * window.stride.1, window.kernel_size.1, dims[3], window.padding.1);
+                    }
+                    output.push(sum / count as f32);
+                }
+            }
+        }
+        CpuTensor::from_data_and_shape(output, vec![dims[0], dims[1], out_h, out_w])
+    }
+
+    /// Reference for [`crate::GpuTensor::adaptive_avg_pool2d`]
+    pub fn adaptive_avg_pool2d(&self, output_size: (usize, usize)) -> CpuTensor {
+        let (input, dims) = nchw(self);
+        let (out_h, out_w) = output_size;
+        let range = |o: usize, input: usize, output: usize| (o * input / output)..((o + 1) * input).div_ceil(output);
+        let mut output = Vec::with_capacity(dims[0] * dims[1] * out_h * out_w);
+        for plane in input.chunks(dims[2] * dims[3]) {
+            for oh in 0..out_h {
+                for ow in 0..out_w {
+                    let (mut sum, mut count) = (0., 0);
+                    for h in range(oh, dims[2], out_h) {
+                        for w in range(ow, dims[3], out_w) {
+                            sum += plane[h * dims[3] + w];
+                            count += 1;
+                        }
+                    }
+                    output.push(sum / count as f32);
+                }
+            }
+        }
+        CpuTensor::from_data_and_shape(output, vec![dims[0], dims[1], out_h, out_w])
+    }
+}
+
+/// Normalizes `values` in place with their mean and biased variance
+fn normalize_slice(values: &mut [f32], eps: f32) {
+    let len = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / len;
+    let var = values.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / len;
+    values.iter_mut().for_each(|x| *x = (*x - mean) / (var + eps).sqrt());
+}
+
+impl CpuTensor {
+    /// Reference for [`crate::RawTensor::layer_norm`] over the trailing dimensions with
+    /// `normalized_len` elements, without the affine transformation
+    pub fn layer_norm(&self, normalized_len: usize, eps: f32) -> CpuTensor {
+        let mut data = self.as_contiguous_vec();
+        data.chunks_mut(normalized_len).for_each(|row| normalize_slice(row, eps));
+        CpuTensor::from_data_and_shape(data, self.shape().iter().copied().collect())
+    }
+
+    /// Reference for [`crate::RawTensor::group_norm`], without the affine transformation
+    pub fn group_norm(&self, num_groups: usize, eps: f32) -> CpuTensor {
+        let channels_len = self.numel() / self.shape()[0];
+        self.layer_norm(channels_len / num_groups, eps)
+    }
+
+    /// Reference for [`crate::RawTensor::batch_norm`] in training mode, without the affine
+    /// transformation
+    pub fn batch_norm(&self, eps: f32) -> CpuTensor {
+        let shape: Vec<usize> = self.shape().iter().copied().collect();
+        let (batch, channels) = (shape[0], shape[1]);
+        let inner = self.numel() / (batch * channels);
+        let mut data = self.as_contiguous_vec();
+        for channel in 0..channels {
+            let indices: Vec<usize> = (0..batch)
+                .flat_map(|n| (0..inner).map(move |i| (n * channels + channel) * inner + i))
+                .collect();
+            let mut values: Vec<f32> = indices.iter().map(|&i| data[i]).collect();
+            normalize_slice(&mut values, eps);
+            indices.iter().zip(values).for_each(|(&i, value)| data[i] = value);
+        }
+        CpuTensor::from_data_and_shape(data, shape)
+    }
+}
+
+/// Returns the contiguous data and the `[batch, len, dim]` dims of `tensor`
+fn sequences(tensor: &CpuTensor) -> (Vec<f32>, [usize; 3]) {
+    let shape = tensor.shape();
+    assert_eq!(shape.len(), 3, "Expected a [batch, len, dim] Tensor");
+    (tensor.as_contiguous_vec(), [shape[0], shape[1], shape[2]])
+}
+
+impl CpuTensor {
+    /// Reference for [`crate::nn::scaled_dot_product_attention`] of `[batch, len, dim]`
+    /// queries, keys and values. The mask has shape `[queries, keys]` or `[batch, queries, keys]`.
+    pub fn scaled_dot_product_attention(&self, key: &CpuTensor, value: &CpuTensor, mask: Option<&CpuTensor>, causal: bool) -> CpuTensor {
+        let (query, [batch, queries, dim]) = sequences(self);
+        let (key, [_, keys, _]) = sequences(key);
+        let (value, [_, _, value_dim]) = sequences(value);
+        let mask = mask.map(|mask| mask.as_contiguous_vec());
+        let scale = 1. / (dim as f32).sqrt();
+        let mut output = vec![0.; batch * queries * value_dim];
+        for n in 0..batch {
+            for i in 0..queries {
+                let mut scores: Vec<f32> = (0..keys)
+                    .map(|j| {
+                        let dot: f32 = (0..dim)
+                            .map(|d| query[(n * queries + i) * dim + d] * key[(n * keys + j) * dim + d])
+                            .sum();
+                        let masked = mask.as_ref().map_or(0., |mask| mask[((n * queries + i) * keys + j) % mask.len()]);
+                        if causal && j > i { f32::NEG_INFINITY } else { scale * dot + masked }
+                    })
+                    .collect();
+                let max = scores.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+                if max == f32::NEG_INFINITY {
+                    continue;
+                }
+                scores.iter_mut().for_each(|score| *score = (*score - max).exp());
+                let sum: f32 = scores.iter().sum();
+                for (j, score) in scores.iter().enumerate() {
+                    for d in 0..value_dim {
+                        output[(n * queries + i) * value_dim + d] += score / sum * value[(n * keys + j) * value_dim + d];
+                    }
+                }
+            }
+        }
+        CpuTensor::from_data_and_shape(output, vec![batch, queries, value_dim])
+    }
+}
+
+#[test]
+fn causal_attention_of_the_first_query_is_the_first_value() {
+    let query = CpuTensor::rand(vec![2, 3, 4]);
+    let key = CpuTensor::rand(vec![2, 3, 4]);
+    let value = CpuTensor::rand(vec![2, 3, 5]);
+    let output = query.scaled_dot_product_attention(&key, &value, None, true).as_contiguous_vec();
+    let value = value.as_contiguous_vec();
+    for n in 0..2 {
+        assert_eq!(&output[n * 15..n * 15 + 5], &value[n * 15..n * 15 + 5]);
+    }
+}
+
+#[test]
+fn conv_transpose2d_is_the_adjoint_of_conv2d() {
+    // <conv2d(x, w), y> == <x, conv_transpose2d(y, w)>
+    let params = ConvTranspose2dParams {
+        stride: (2, 3),
+        padding: (1, 0),
+        output_padding: (1, 2),
+        dilation: (2, 1),
+        groups: 2,
+    };
+    let conv_params = Conv2dParams {
+        stride: params.stride,
+        padding: params.padding,
+        dilation: params.dilation,
+        groups: params.groups,
+    };
+    let weight = CpuTensor::rand(vec![4, 3, 2, 3]);
+    let y = CpuTensor::rand(vec![2, 4, 3, 2]);
+    let transposed = y.conv_transpose2d(&weight, None, &params);
+    assert_eq!(transposed.shape(), &[2, 6, 6, 8]);
+    let x = CpuTensor::rand(vec![2, 6, 6, 8]);
+    let convolved = x.conv2d(&weight, None, &conv_params);
+    let dot = |a: &CpuTensor, b: &CpuTensor| -> f32 {
+        a.as_contiguous_vec().iter().zip(b.as_contiguous_vec()).map(|(a, b)| a * b).sum()
+    };
+    assert!((dot(&convolved, &y) - dot(&x, &transposed)).abs() < 1e-3);
+}
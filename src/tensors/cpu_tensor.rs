@@ -1,5 +1,6 @@
 use crate::utils::strides_from_deque_shape;
-use crate::{GpuStore, GpuTensor, ShapeStrideTrait};
+use crate::random::{self, Generator};
+use crate::{Distribution, GpuStore, GpuTensor, ShapeStrideTrait};
 use std::collections::VecDeque;
 use std::fmt::{Debug, Display, Formatter};
 
@@ -95,22 +96,54 @@ impl CpuTensor {
         }
     }
 
+    /// Returns a Tensor with values sampled uniformly from `[0, 1)` by the CPU random number
+    /// generator, see [`crate::manual_seed`]
     pub fn rand(shape: Vec<usize>) -> Self {
-        use rand::Rng;
-        let shape = VecDeque::from(shape);
-        let numel = GpuTensor::numel_from_shape(&shape);
-        let mut rng = rand::thread_rng();
-        let mut data = Vec::with_capacity(numel);
-        for _i in 0..numel {
-            data.push(rng.gen());
-        }
-        let strides = strides_from_deque_shape(&shape);
-        Self {
-            data,
-            shape,
-            strides,
-            offset: 0,
-        }
+        Self::uniform(shape, 0., 1., None)
+    }
+
+    /// Same as [`CpuTensor::rand`], sampling with `generator`
+    pub fn rand_with(shape: Vec<usize>, generator: &mut Generator) -> Self {
+        Self::uniform(shape, 0., 1., Some(generator))
+    }
+
+    /// Returns a Tensor with values sampled from the standard normal distribution by the CPU
+    /// random number generator
+    pub fn randn(shape: Vec<usize>) -> Self {
+        Self::normal(shape, 0., 1., None)
+    }
+
+    /// Same as [`CpuTensor::randn`], sampling with `generator`
+    pub fn randn_with(shape: Vec<usize>, generator: &mut Generator) -> Self {
+        Self::normal(shape, 0., 1., Some(generator))
+    }
+
+    pub fn uniform(shape: Vec<usize>, low: f32, high: f32, generator: Option<&mut Generator>) -> Self {
+        Self::random(shape, &Distribution::Uniform { low, high }, generator)
+    }
+
+    pub fn normal(shape: Vec<usize>, mean: f32, std: f32, generator: Option<&mut Generator>) -> Self {
+        Self::random(shape, &Distribution::Normal { mean, std }, generator)
+    }
+
+    /// Returns a one dimensional Tensor holding a random permutation of `0..n`
+    pub fn randperm(n: usize, generator: Option<&mut Generator>) -> Self {
+        let (seed, offset) = random::next_cpu_state(generator);
+        let permutation = random::permutation(n, seed, offset).into_iter().map(|i| i as f32).collect();
+        Self::from_data_and_shape(permutation, vec![n])
+    }
+
+    /// Returns a Tensor sampled from `distribution` using `generator` or, if `None`, the CPU
+    /// random number generator. Given the same generator state, the values are the ones
+    /// computed by [`crate::RawTensor::random`].
+    pub fn random(shape: Vec<usize>, distribution: &Distribution, generator: Option<&mut Generator>) -> Self {
+        let numel = GpuTensor::numel_from_shape(&VecDeque::from(shape.clone()));
+        assert!(numel <= u32::MAX as usize, "Random Tensors can have at most 2^32 elements");
+        let (seed, offset) = random::next_cpu_state(generator);
+        let data = (0..numel as u32)
+            .map(|index| distribution.sample(seed, offset, index))
+            .collect();
+        Self::from_data_and_shape(data, shape)
     }
 
     pub fn new_with_strides_and_offset(
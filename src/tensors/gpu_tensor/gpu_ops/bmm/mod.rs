@@ -3,7 +3,7 @@ mod tests;
 
 use crate::gpu_internals::shader_runner::{ThreadGroup};
 use crate::gpu_internals::GpuInstance;
-use crate::{GpuTensor, ShapeStrideTrait, AsShaderInput};
+use crate::{broadcast_shape_and_stride, AsShaderInput, GpuTensor, ShapeStrideTrait, ShapeStrides};
 use std::collections::VecDeque;
 use zerocopy::{AsBytes, FromBytes};
 
@@ -13,6 +13,9 @@ struct Shapes {
     batch_size: u32,
     stride_batch_size_a: u32,
     stride_batch_size_b: u32,
+    inner_batch_size: u32,
+    stride_inner_batch_a: u32,
+    stride_inner_batch_b: u32,
     rows_a: u32,
     stride_rows_a: u32,
     cols_a: u32,
@@ -24,7 +27,10 @@ struct Shapes {
 }
 
 /// Performs Batch Matrix Multiplication of the input Tensors.
-/// Expects inputs to be of Rank 3, have same batch size and compatible dimensions
+/// Expects inputs of rank 3 or 4 with compatible matrix dimensions. Their leading (batch)
+/// dimensions are broadcast together, the broadcasted ones being read with a stride of 0, so
+/// a `[groups, m, k]` Tensor can be multiplied by a `[batch, groups, k, n]` one without
+/// repeating it for every sample.
 pub async fn bmm_kernel(
     gpu: &GpuInstance,
     left: &GpuTensor,
@@ -34,40 +40,55 @@ pub async fn bmm_kernel(
         panic!("Tried to matmul with at least one empty Tensor")
     }
     assert!(
-        left.shape().len() == 3 && right.shape().len() == 3,
-        "Cant matmul tensor of rank different than 3"
+        (3..=4).contains(&left.shape().len()) && (3..=4).contains(&right.shape().len()),
+        "Cant matmul tensors of rank different than 3 or 4, got shapes {:?} and {:?}",
+        left.shape(),
+        right.shape()
     );
-    // the batch sizes need to be the same -> [2x3x4] x [2x4x6] -> [3x4] [4x6]
-    assert_eq!(left.shape()[0], right.shape()[0], "matmul batches must be the same");
     assert_eq!(
-        left.shape()[2],
-        right.shape()[1],
+        left.shape()[left.shape().len() - 1],
+        right.shape()[right.shape().len() - 2],
         "Shapes do not match for matrix multiply: {:?} and {:?}",
         left.shape(),
         right.shape()
     );
-    assert_eq!(left.shape().len(), 3, "Input to matmul must be of rank 3");
+    // the batch dimensions are broadcast -> [2x3x4] x [5x2x4x6] -> [3x4] [4x6] for each of the 5x2 batches
+    let (left_view, right_view) = broadcast_shape_and_stride(left.dim_strides(), right.dim_strides(), Some(2))
+        .unwrap_or_else(|err| panic!("matmul batches must be broadcastable: {}", err));
+    let rank = left_view.rank();
+    // Rank 3 views only have the inner batch dimension, the outer one has a single entry
+    let outer_batch = |view: &ShapeStrides| -> (u32, u32) {
+        if rank == 4 {
+            (view.shape[0] as u32, view.strides[0] as u32)
+        } else {
+            (1, 0)
+        }
+    };
+    let (batch_size, stride_batch_size_a) = outer_batch(&left_view);
+    let (_, stride_batch_size_b) = outer_batch(&right_view);
+    let (inner_batch_size, stride_inner_batch_a) = (left_view.shape[rank - 3] as u32, left_view.strides[rank - 3] as u32);
+    let stride_inner_batch_b = right_view.strides[rank - 3] as u32;
 
     let mut shader_inputs = left.to_shader_inputs().with_tensor(&right);
     shader_inputs.push_constants.data.clear();
-    shader_inputs.push_constants.data.push(left.shape()[0] as u32); // batch_size
-    shader_inputs.push_constants.data.push(left.strides()[0] as u32); // stride_batch_size_a
-    shader_inputs.push_constants.data.push(right.strides()[0] as u32); // stride_batch_size_b
-    shader_inputs.push_constants.data.push(left.shape()[1] as u32); // rows_a
-    shader_inputs.push_constants.data.push(left.strides()[1] as u32); // stride_rows_a
-    shader_inputs.push_constants.data.push(left.shape()[2] as u32); // cols_a
-    shader_inputs.push_constants.data.push(left.strides()[2] as u32); // stride_cols_a
-    shader_inputs.push_constants.data.push(right.shape()[1] as u32); // rows_b
-    shader_inputs.push_constants.data.push(right.strides()[1] as u32); // stride_rows_b
-    shader_inputs.push_constants.data.push(right.shape()[2] as u32); // cols_b
-    shader_inputs.push_constants.data.push(right.strides()[2] as u32); // stride_cols_b
+    shader_inputs.push_constants.data.push(batch_size); // batch_size
+    shader_inputs.push_constants.data.push(stride_batch_size_a); // stride_batch_size_a
+    shader_inputs.push_constants.data.push(stride_batch_size_b); // stride_batch_size_b
+    shader_inputs.push_constants.data.push(inner_batch_size); // inner_batch_size
+    shader_inputs.push_constants.data.push(stride_inner_batch_a); // stride_inner_batch_a
+    shader_inputs.push_constants.data.push(stride_inner_batch_b); // stride_inner_batch_b
+    shader_inputs.push_constants.data.push(left_view.shape[rank - 2] as u32); // rows_a
+    shader_inputs.push_constants.data.push(left_view.strides[rank - 2] as u32); // stride_rows_a
+    shader_inputs.push_constants.data.push(left_view.shape[rank - 1] as u32); // cols_a
+    shader_inputs.push_constants.data.push(left_view.strides[rank - 1] as u32); // stride_cols_a
+    shader_inputs.push_constants.data.push(right_view.shape[rank - 2] as u32); // rows_b
+    shader_inputs.push_constants.data.push(right_view.strides[rank - 2] as u32); // stride_rows_b
+    shader_inputs.push_constants.data.push(right_view.shape[rank - 1] as u32); // cols_b
+    shader_inputs.push_constants.data.push(right_view.strides[rank - 1] as u32); // stride_cols_b
 
     let cs_module = gpu.shader_from_file_bytes(wgpu::include_spirv!("bmm.spv"));
-    let output_shape = vec![
-        left.shape()[0],
-        left.shape()[1],
-        right.shape()[2],
-    ];
+    let mut output_shape: Vec<usize> = left_view.shape.iter().copied().collect();
+    output_shape[rank - 1] = right_view.shape[rank - 1];
     let nb_output_numbers = GpuTensor::numel_from_shape(&VecDeque::from(output_shape.clone()));
     let out_buffer_store = gpu.empty_gpu_buffer(std::mem::size_of::<f32>() * nb_output_numbers);
     shader_inputs.append_buffer(&out_buffer_store);
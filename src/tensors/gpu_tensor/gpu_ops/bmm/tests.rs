@@ -1,4 +1,5 @@
-use crate::{GpuTensor, CpuTransferable};
+use crate::{CpuTransferable, GpuStore, GpuTensor, ShapeStrideTrait};
+use std::collections::VecDeque;
 
 #[test]
 fn simple_rank_2_mm() {
@@ -57,6 +58,38 @@ fn big_rank_2_mm_different_dims() {
     futures::executor::block_on(async_block);
 }
 
+#[test]
+fn rectangular_mm_with_strided_rhs() {
+    // The output has more columns than rows, and the right hand side is read through the
+    // swapped strides of a `[1, 4, 3]` buffer
+    let async_block = async {
+        let lhs = vec![1., 2., 3., 4., 5., 6.];
+        let rhs_transposed = vec![1., 0., 3., 0., 1., 1., 2., 1., 0., 1., 2., 1.];
+        let mut expected = vec![0.; 8];
+        for row in 0..2 {
+            for col in 0..4 {
+                for k in 0..3 {
+                    expected[row * 4 + col] += lhs[row * 3 + k] * rhs_transposed[col * 3 + k];
+                }
+            }
+        }
+
+        let ma = GpuTensor::from(lhs, vec![1, 2, 3]);
+        let buffer = GpuStore::get_default().gpu_buffer_from_data(bytemuck::cast_slice(&rhs_transposed));
+        let mb = GpuTensor::from_buffer_with_strides_and_offset(
+            buffer,
+            VecDeque::from(vec![1, 3, 4]),
+            VecDeque::from(vec![12, 1, 3]),
+            0,
+        );
+        assert!(!mb.is_contiguous());
+        let result = ma.matmul(&mb).await;
+        assert_eq!(result.shape(), &[1, 2, 4]);
+        assert_eq!(result.to_cpu_async().await.raw_data_slice(), expected.as_slice());
+    };
+    futures::executor::block_on(async_block);
+}
+
 // #[test]
 // fn mm_with_broadcasting() {
 //     let async_block = async {
@@ -85,3 +118,26 @@ fn big_rank_2_mm_different_dims() {
 //     };
 //     futures::executor::block_on(async_block);
 // }
+
+#[test]
+fn broadcast_batches_mm() {
+    let async_block = async {
+        // The same 2 kernels multiply the 2 groups of each of the 3 samples
+        let kernels = GpuTensor::from(vec![1., 2., 3., 4., 5., 6., 7., 8.], vec![2, 2, 2]);
+        let samples = GpuTensor::from((1..=12).map(|e| e as f32).collect(), vec![3, 2, 2, 1]);
+        let result = kernels.matmul(&samples).await;
+        assert_eq!(result.shape(), &[3, 2, 2, 1]);
+        assert_eq!(
+            result.to_cpu_async().await.raw_data_slice(),
+            &[5., 11., 39., 53., 17., 39., 83., 113., 29., 67., 127., 173.]
+        );
+        let single = GpuTensor::from(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+        let result = single.matmul(&kernels).await;
+        assert_eq!(result.shape(), &[2, 2, 2]);
+        assert_eq!(
+            result.to_cpu_async().await.raw_data_slice(),
+            &[7., 10., 15., 22., 19., 22., 43., 50.]
+        );
+    };
+    futures::executor::block_on(async_block);
+}
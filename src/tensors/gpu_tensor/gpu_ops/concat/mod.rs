@@ -0,0 +1,76 @@
+//! Concatenation along a dimension and its adjoint, narrowing. Each concatenated Tensor is
+//! copied in place into its slice of the output, viewed as `[outer, out_len, inner]`.
+use crate::tensors::gpu_tensor::gpu_ops::conv2d::run_window_kernel;
+use crate::{GpuAllocated, GpuTensor, ShapeStrideTrait, ShapeStrides};
+use std::collections::VecDeque;
+
+#[cfg(test)]
+mod tests;
+
+impl GpuTensor {
+    /// Concatenates the Tensors along `dim`. They must have the same shape, except along `dim`.
+    pub async fn cat(tensors: &[&GpuTensor], dim: usize) -> GpuTensor {
+        assert!(!tensors.is_empty(), "Cannot concatenate an empty list of Tensors");
+        let first_shape = tensors[0].shape();
+        assert!(dim < first_shape.len(), "Dimension {} is out of range for shape {:?}", dim, first_shape);
+        for tensor in tensors {
+            let matches = tensor.shape().len() == first_shape.len()
+                && tensor.shape().iter().zip(first_shape).enumerate().all(|(d, (a, b))| d == dim || a == b);
+            assert!(
+                matches,
+                "Cannot concatenate shapes {:?} and {:?} along dimension {}",
+                first_shape,
+                tensor.shape(),
+                dim
+            );
+        }
+        let mut output_shape = first_shape.clone();
+        output_shape[dim] = tensors.iter().map(|tensor| tensor.shape()[dim]).sum();
+        let inner: usize = first_shape.iter().skip(dim + 1).product();
+        let gpu = tensors[0].gpu();
+        let numel = GpuTensor::numel_from_shape(&output_shape);
+        let output_buffer = gpu.empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        let mut start = 0;
+        for tensor in tensors {
+            let len = tensor.shape()[dim];
+            let push_constants = vec![len as u32, inner as u32, output_shape[dim] as u32, start as u32];
+            let contiguous;
+            let source = if tensor.is_contiguous() {
+                *tensor
+            } else {
+                contiguous = tensor.contiguous().await;
+                &contiguous
+            };
+            run_window_kernel(
+                wgpu::include_spirv!("copy_into.spv"),
+                &[source],
+                &[&output_buffer],
+                push_constants,
+                tensor.numel(),
+            ).await;
+            start += len;
+        }
+        GpuTensor::from_buffer(output_buffer, output_shape)
+    }
+
+    /// Returns a contiguous copy of the entries `[start, start + len)` along `dim`
+    pub async fn narrow(&self, dim: usize, start: usize, len: usize) -> GpuTensor {
+        assert!(dim < self.rank(), "Dimension {} is out of range for shape {:?}", dim, self.shape());
+        assert!(
+            start + len <= self.shape()[dim],
+            "Cannot take {} entries from {} along dimension {} of shape {:?}",
+            len,
+            start,
+            dim,
+            self.shape()
+        );
+        let mut shape: VecDeque<usize> = self.shape().clone();
+        shape[dim] = len;
+        let view = ShapeStrides::from_shape_and_strides_and_offset(
+            shape,
+            self.strides().clone(),
+            self.offset() + start * self.strides()[dim],
+        );
+        self.strided_copy(&view).await
+    }
+}
@@ -0,0 +1,21 @@
+use crate::prelude::*;
+use crate::GpuTensor;
+
+#[test]
+fn cat_and_narrow() {
+    let async_block = async {
+        let left = GpuTensor::from(vec![1., 2., 3., 4.], vec![2, 2]);
+        let right = GpuTensor::from(vec![5., 6.], vec![2, 1]);
+        let columns = GpuTensor::cat(&[&left, &right], 1).await;
+        assert_eq!(columns.shape(), &[2, 3]);
+        assert_eq!(columns.to_cpu().as_contiguous_vec(), vec![1., 2., 5., 3., 4., 6.]);
+        let rows = GpuTensor::cat(&[&left, &left.transpose().await], 0).await;
+        assert_eq!(rows.to_cpu().as_contiguous_vec(), vec![1., 2., 3., 4., 1., 3., 2., 4.]);
+
+        let narrowed = columns.narrow(1, 1, 2).await;
+        assert_eq!(narrowed.shape(), &[2, 2]);
+        assert_eq!(narrowed.to_cpu().as_contiguous_vec(), vec![2., 5., 4., 6.]);
+        assert_eq!(rows.narrow(0, 2, 1).await.to_cpu().as_contiguous_vec(), vec![1., 3.]);
+    };
+    futures::executor::block_on(async_block);
+}
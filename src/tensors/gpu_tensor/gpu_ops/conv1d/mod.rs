@@ -0,0 +1,126 @@
+//! 1D convolutions over `[batch, channels, length]` Tensors, computed as 2D convolutions of
+//! images with a single row.
+use super::conv2d::Conv2dParams;
+use super::conv_transpose2d::ConvTranspose2dParams;
+use crate::{GpuTensor, ShapeStrides, ShapeStrideTrait};
+
+#[cfg(test)]
+mod tests;
+
+/// Options of a 1D convolution. The kernel size is given by the weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conv1dParams {
+    pub stride: usize,
+    pub padding: usize,
+    pub dilation: usize,
+    pub groups: usize,
+}
+
+impl Default for Conv1dParams {
+    fn default() -> Self {
+        Self {
+            stride: 1,
+            padding: 0,
+            dilation: 1,
+            groups: 1,
+        }
+    }
+}
+
+impl Conv1dParams {
+    /// The parameters of the equivalent convolution over images with a single row
+    pub fn to_2d(&self) -> Conv2dParams {
+        Conv2dParams {
+            stride: (1, self.stride),
+            padding: (0, self.padding),
+            dilation: (1, self.dilation),
+            groups: self.groups,
+        }
+    }
+}
+
+/// Options of a transposed 1D convolution, see [`ConvTranspose2dParams`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvTranspose1dParams {
+    pub stride: usize,
+    pub padding: usize,
+    pub output_padding: usize,
+    pub dilation: usize,
+    pub groups: usize,
+}
+
+impl Default for ConvTranspose1dParams {
+    fn default() -> Self {
+        Self {
+            stride: 1,
+            padding: 0,
+            output_padding: 0,
+            dilation: 1,
+            groups: 1,
+        }
+    }
+}
+
+impl ConvTranspose1dParams {
+    /// The parameters of the equivalent transposed convolution over images with a single row
+    pub fn to_2d(&self) -> ConvTranspose2dParams {
+        ConvTranspose2dParams {
+            stride: (1, self.stride),
+            padding: (0, self.padding),
+            output_padding: (0, self.output_padding),
+            dilation: (1, self.dilation),
+            groups: self.groups,
+        }
+    }
+}
+
+impl GpuTensor {
+    /// Copies this `[batch, channels, length]` Tensor into a `[batch, channels, 1, length]` one
+    async fn with_single_row(&self) -> GpuTensor {
+        assert_eq!(
+            self.shape().len(),
+            3,
+            "Expected a [batch, channels, length] Tensor, got shape {:?}",
+            self.shape()
+        );
+        let shape = self.shape();
+        let strides = self.strides();
+        let view = ShapeStrides::from_shape_and_strides_and_offset(
+            vec![shape[0], shape[1], 1, shape[2]].into(),
+            vec![strides[0], strides[1], 0, strides[2]].into(),
+            self.offset(),
+        );
+        self.strided_copy(&view).await
+    }
+
+    /// Removes the single row of a `[batch, channels, 1, length]` Tensor
+    fn without_row(mut self) -> GpuTensor {
+        let shape = self.shape();
+        let shape = vec![shape[0], shape[1], shape[3]];
+        self.reshape(shape);
+        self
+    }
+
+    /// 1D convolution of this `[batch, in_channels, length]` Tensor with a weight of shape
+    /// `[out_channels, in_channels / groups, kernel]` and an optional bias of shape
+    /// `[out_channels]`, see [`GpuTensor::conv2d`]
+    pub async fn conv1d(&self, weight: &GpuTensor, bias: Option<&GpuTensor>, params: &Conv1dParams) -> GpuTensor {
+        let input = self.with_single_row().await;
+        let weight = weight.with_single_row().await;
+        input.conv2d(&weight, bias, &params.to_2d()).await.without_row()
+    }
+
+    /// Transposed 1D convolution of this `[batch, in_channels, length]` Tensor with a weight of
+    /// shape `[in_channels, out_channels / groups, kernel]` and an optional bias of shape
+    /// `[out_channels]`, see [`GpuTensor::conv_transpose2d`]
+    pub async fn conv_transpose1d(
+        &self,
+        weight: &GpuTensor,
+        bias: Option<&GpuTensor>,
+        params: &ConvTranspose1dParams,
+    ) -> GpuTensor {
+        let input = self.with_single_row().await;
+        let weight = weight.with_single_row().await;
+        input.conv_transpose2d(&weight, bias, &params.to_2d()).await.without_row()
+    }
+}
@@ -0,0 +1,56 @@
+use crate::prelude::*;
+use crate::{Conv1dParams, ConvTranspose1dParams, CpuTensor, GpuTensor};
+
+/// Compares a 1D result with the 2D CPU reference over images with a single row
+fn assert_close_to_2d(actual: &GpuTensor, expected: &CpuTensor) {
+    let expected_shape = expected.shape();
+    assert_eq!(actual.shape(), &[expected_shape[0], expected_shape[1], expected_shape[3]]);
+    for (actual, expected) in actual.to_cpu().as_contiguous_vec().iter().zip(expected.as_contiguous_vec()) {
+        assert!((actual - expected).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+fn with_single_row(tensor: &CpuTensor) -> CpuTensor {
+    let shape = tensor.shape();
+    CpuTensor::from_data_and_shape(tensor.as_contiguous_vec(), vec![shape[0], shape[1], 1, shape[2]])
+}
+
+#[test]
+fn conv1d_matches_reference() {
+    let async_block = async {
+        let input = CpuTensor::rand(vec![2, 4, 11]);
+        let weight = CpuTensor::rand(vec![6, 2, 3]);
+        let bias = CpuTensor::rand(vec![6]);
+        let params = Conv1dParams {
+            stride: 2,
+            padding: 1,
+            dilation: 2,
+            groups: 2,
+        };
+        let output = input
+            .to_gpu()
+            .conv1d(&weight.to_gpu(), Some(&bias.to_gpu()), &params)
+            .await;
+        let expected = with_single_row(&input).conv2d(&with_single_row(&weight), Some(&bias), &params.to_2d());
+        assert_close_to_2d(&output, &expected);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn conv_transpose1d_matches_reference() {
+    let async_block = async {
+        let input = CpuTensor::rand(vec![2, 3, 5]);
+        let weight = CpuTensor::rand(vec![3, 2, 4]);
+        let params = ConvTranspose1dParams {
+            stride: 3,
+            padding: 1,
+            output_padding: 2,
+            ..ConvTranspose1dParams::default()
+        };
+        let output = input.to_gpu().conv_transpose1d(&weight.to_gpu(), None, &params).await;
+        let expected = with_single_row(&input).conv_transpose2d(&with_single_row(&weight), None, &params.to_2d());
+        assert_close_to_2d(&output, &expected);
+    };
+    futures::executor::block_on(async_block);
+}
@@ -0,0 +1,323 @@
+//! 2D convolutions, implemented by copying the input windows into columns (im2col) and
+//! multiplying them by the kernels using [`GpuTensor::matmul`]. [`GpuTensor::col2im`] does the
+//! opposite, adding the columns back into an image, which is used by the backward pass.
+use crate::gpu_internals::gpu_buffers::GpuBuffer;
+use crate::gpu_internals::shader_runner::{ShaderInputs, ThreadGroup};
+use crate::{GpuAllocated, GpuTensor, ShapeStrideTrait, ShapeStrides};
+use std::collections::VecDeque;
+use wgpu::ShaderModuleSource;
+
+#[cfg(test)]
+mod tests;
+
+/// Geometry of a window sliding over the last two dimensions of a Tensor, used by
+/// convolutions and pooling. Each field holds the `(height, width)` values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window2d {
+    pub kernel_size: (usize, usize),
+    pub stride: (usize, usize),
+    /// Implicit zeros added to both sides of the input
+    pub padding: (usize, usize),
+    /// Spacing between the elements of the kernel
+    pub dilation: (usize, usize),
+}
+
+impl Window2d {
+    /// A window with stride 1, no padding and no dilation
+    pub fn new(kernel_size: (usize, usize)) -> Self {
+        Self {
+            kernel_size,
+            stride: (1, 1),
+            padding: (0, 0),
+            dilation: (1, 1),
+        }
+    }
+
+    /// Returns the `(height, width)` of the output for an input of the given size
+    pub fn output_size(&self, height: usize, width: usize) -> (usize, usize) {
+        let output_len = |input: usize, kernel: usize, stride: usize, padding: usize, dilation: usize| {
+            assert!(kernel > 0 && stride > 0 && dilation > 0, "Invalid window {:?}", self);
+            let span = dilation * (kernel - 1) + 1;
+            assert!(
+                input + 2 * padding >= span,
+                "Window {:?} is larger than the padded input of size {}x{}",
+                self,
+                height,
+                width
+            );
+            (input + 2 * padding - span) / stride + 1
+        };
+        (
+            output_len(height, self.kernel_size.0, self.stride.0, self.padding.0, self.dilation.0),
+            output_len(width, self.kernel_size.1, self.stride.1, self.padding.1, self.dilation.1),
+        )
+    }
+
+    /// The push constants describing this window over an input of shape
+    /// `[batch, channels, height, width]`, as expected by the window shaders
+    pub(crate) fn push_constants(&self, input_shape: &VecDeque<usize>) -> Vec<u32> {
+        let (out_h, out_w) = self.output_size(input_shape[2], input_shape[3]);
+        let mut data: Vec<u32> = input_shape.iter().map(|&dim| dim as u32).collect();
+        data.extend(
+            [
+                out_h,
+                out_w,
+                self.kernel_size.0,
+                self.kernel_size.1,
+                self.stride.0,
+                self.stride.1,
+                self.padding.0,
+                self.padding.1,
+                self.dilation.0,
+                self.dilation.1,
+            ]
+            .iter()
+            .map(|&value| value as u32),
+        );
+        data
+    }
+}
+
+/// Options of a 2D convolution. The kernel size is given by the weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conv2dParams {
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+    pub dilation: (usize, usize),
+    /// The input and output channels are split into this many groups, each output group
+    /// only seeing its input group
+    pub groups: usize,
+}
+
+impl Default for Conv2dParams {
+    fn default() -> Self {
+        Self {
+            stride: (1, 1),
+            padding: (0, 0),
+            dilation: (1, 1),
+            groups: 1,
+        }
+    }
+}
+
+impl Conv2dParams {
+    pub fn window(&self, kernel_size: (usize, usize)) -> Window2d {
+        Window2d {
+            kernel_size,
+            stride: self.stride,
+            padding: self.padding,
+            dilation: self.dilation,
+        }
+    }
+}
+
+/// Checks `shape` is a `[batch, channels, height, width]` shape
+pub(crate) fn assert_nchw(shape: &VecDeque<usize>) {
+    assert_eq!(
+        shape.len(),
+        4,
+        "Expected a [batch, channels, height, width] Tensor, got shape {:?}",
+        shape
+    );
+}
+
+/// Runs a shader binding the `inputs` followed by the `outputs`. The inputs which are not
+/// contiguous are copied first.
+pub(crate) async fn run_window_kernel(
+    shader: ShaderModuleSource<'_>,
+    inputs: &[&GpuTensor],
+    outputs: &[&GpuBuffer],
+    push_constants: Vec<u32>,
+    threads: usize,
+) {
+    if threads == 0 {
+        return;
+    }
+    let mut copies = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        copies.push(if input.is_contiguous() { None } else { Some(input.contiguous().await) });
+    }
+    let inputs: Vec<&GpuTensor> = inputs
+        .iter()
+        .zip(copies.iter())
+        .map(|(&input, copy)| copy.as_ref().unwrap_or(input))
+        .collect();
+    let gpu = inputs[0].gpu();
+    let cs_module = gpu.shader_from_file_bytes(shader);
+    let mut shader_inputs = ShaderInputs::default();
+    for input in &inputs {
+        shader_inputs.append_buffer(input.buffer());
+    }
+    for output in outputs {
+        shader_inputs.append_buffer(output);
+    }
+    shader_inputs.push_constants.data = push_constants;
+    gpu.run_shader(
+        &cs_module,
+        &shader_inputs,
+        ThreadGroup {
+            x: threads,
+            y: 1,
+            z: 1,
+        },
+    );
+}
+
+/// Copies `view`, which describes a view over the contiguous version of `tensor`
+pub(super) async fn copy_view(tensor: &GpuTensor, view: &ShapeStrides) -> GpuTensor {
+    if tensor.is_contiguous() {
+        tensor.strided_copy(view).await
+    } else {
+        tensor.contiguous().await.strided_copy(view).await
+    }
+}
+
+impl GpuTensor {
+    /// Copies each window of this `[batch, channels, height, width]` Tensor into a column,
+    /// returning a Tensor of shape `[batch, channels * kernel_h * kernel_w, out_h * out_w]`.
+    /// Elements of the padding are zeros.
+    pub async fn im2col(&self, window: &Window2d) -> GpuTensor {
+        assert_nchw(self.shape());
+        let (batch, channels) = (self.shape()[0], self.shape()[1]);
+        let (out_h, out_w) = window.output_size(self.shape()[2], self.shape()[3]);
+        let output_shape = VecDeque::from(vec![
+            batch,
+            channels * window.kernel_size.0 * window.kernel_size.1,
+            out_h * out_w,
+        ]);
+        let numel = GpuTensor::numel_from_shape(&output_shape);
+        let output_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        run_window_kernel(
+            wgpu::include_spirv!("im2col.spv"),
+            &[self],
+            &[&output_buffer],
+            window.push_constants(self.shape()),
+            numel,
+        ).await;
+        GpuTensor::from_buffer(output_buffer, output_shape)
+    }
+
+    /// The adjoint of [`GpuTensor::im2col`]: adds the columns of this
+    /// `[batch, channels * kernel_h * kernel_w, out_h * out_w]` Tensor back into an image of
+    /// shape `[batch, channels, height, width]`, where `image_size` is `(channels, height, width)`.
+    pub async fn col2im(&self, window: &Window2d, image_size: (usize, usize, usize)) -> GpuTensor {
+        let (channels, height, width) = image_size;
+        let batch = self.shape()[0];
+        let image_shape = VecDeque::from(vec![batch, channels, height, width]);
+        let (out_h, out_w) = window.output_size(height, width);
+        let expected_shape = [
+            batch,
+            channels * window.kernel_size.0 * window.kernel_size.1,
+            out_h * out_w,
+        ];
+        assert_eq!(
+            self.shape(),
+            &expected_shape,
+            "Columns don't match the window {:?} over an image of size {:?}",
+            window,
+            image_size
+        );
+        let numel = GpuTensor::numel_from_shape(&image_shape);
+        let output_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        run_window_kernel(
+            wgpu::include_spirv!("col2im.spv"),
+            &[self],
+            &[&output_buffer],
+            window.push_constants(&image_shape),
+            numel,
+        ).await;
+        GpuTensor::from_buffer(output_buffer, image_shape)
+    }
+
+    /// 2D convolution (actually cross-correlation, as usual) of this
+    /// `[batch, in_channels, height, width]` Tensor with a weight of shape
+    /// `[out_channels, in_channels / groups, kernel_h, kernel_w]` and an optional bias of shape
+    /// `[out_channels]`. The output has shape `[batch, out_channels, out_h, out_w]`.
+    pub async fn conv2d(&self, weight: &GpuTensor, bias: Option<&GpuTensor>, params: &Conv2dParams) -> GpuTensor {
+        let geometry = Conv2dGeometry::new(self.shape(), weight.shape(), params);
+        let mut columns = self.im2col(&geometry.window).await;
+        columns.reshape(geometry.grouped_columns_shape());
+        // The kernels of each group are broadcast over the samples by the bmm, without copies
+        let weight = copy_view(weight, &ShapeStrides::from_shape_vec(geometry.grouped_weight_shape())).await;
+        let mut output = weight.matmul(&columns).await;
+        output.reshape(geometry.output_shape());
+        match bias {
+            Some(bias) => {
+                assert_eq!(bias.shape(), &[geometry.out_channels], "The bias must have shape [out_channels]");
+                let bias = copy_view(bias, &geometry.bias_view()).await;
+                output.add(&bias).await
+            }
+            None => output,
+        }
+    }
+}
+
+/// Shapes involved in a grouped convolution computed as a batch matrix multiplication
+#[derive(Debug, Clone)]
+pub(crate) struct Conv2dGeometry {
+    pub batch: usize,
+    pub groups: usize,
+    pub out_channels: usize,
+    /// `in_channels / groups * kernel_h * kernel_w`
+    pub group_rows: usize,
+    pub out_h: usize,
+    pub out_w: usize,
+    pub window: Window2d,
+}
+
+impl Conv2dGeometry {
+    pub fn new(input_shape: &VecDeque<usize>, weight_shape: &VecDeque<usize>, params: &Conv2dParams) -> Self {
+        assert_nchw(input_shape);
+        assert_eq!(
+            weight_shape.len(),
+            4,
+            "The weight must have shape [out_channels, in_channels / groups, kernel_h, kernel_w]"
+        );
+        let groups = params.groups;
+        let (in_channels, out_channels) = (input_shape[1], weight_shape[0]);
+        assert!(
+            groups > 0 && in_channels.is_multiple_of(groups) && out_channels.is_multiple_of(groups),
+            "The {} input and {} output channels must be divisible by the {} groups",
+            in_channels,
+            out_channels,
+            groups
+        );
+        assert_eq!(
+            weight_shape[1] * groups,
+            in_channels,
+            "The weight expects {} input channels, but the input has {}",
+            weight_shape[1] * groups,
+            in_channels
+        );
+        let window = params.window((weight_shape[2], weight_shape[3]));
+        let (out_h, out_w) = window.output_size(input_shape[2], input_shape[3]);
+        Self {
+            batch: input_shape[0],
+            groups,
+            out_channels,
+            group_rows: weight_shape[1] * weight_shape[2] * weight_shape[3],
+            out_h,
+            out_w,
+            window,
+        }
+    }
+
+    /// The im2col columns, with each group in its own batch dimension
+    pub fn grouped_columns_shape(&self) -> Vec<usize> {
+        vec![self.batch, self.groups, self.group_rows, self.out_h * self.out_w]
+    }
+
+    /// The weight as the kernels of each group, which the matmul broadcasts over the samples
+    pub fn grouped_weight_shape(&self) -> Vec<usize> {
+        vec![self.groups, self.out_channels / self.groups, self.group_rows]
+    }
+
+    pub fn output_shape(&self) -> Vec<usize> {
+        vec![self.batch, self.out_channels, self.out_h, self.out_w]
+    }
+
+    /// View of a `[out_channels]` bias broadcasted over the output
+    pub fn bias_view(&self) -> ShapeStrides {
+        ShapeStrides::from_shape_and_strides_vec(self.output_shape(), vec![0, 1, 0, 0])
+    }
+}
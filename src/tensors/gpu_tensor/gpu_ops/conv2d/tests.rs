@@ -0,0 +1,83 @@
+use crate::prelude::*;
+use crate::{Conv2dParams, CpuTensor, GpuStore, GpuTensor, Window2d};
+use std::collections::VecDeque;
+
+fn assert_close(actual: &CpuTensor, expected: &CpuTensor) {
+    assert_eq!(actual.shape(), expected.shape());
+    for (actual, expected) in actual.as_contiguous_vec().iter().zip(expected.as_contiguous_vec()) {
+        assert!((actual - expected).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn im2col_col2im_test() {
+    let async_block = async {
+        let image = GpuTensor::from((1..=9).map(|x| x as f32).collect(), vec![1, 1, 3, 3]);
+        let window = Window2d::new((2, 2));
+        let columns = image.im2col(&window).await;
+        assert_eq!(columns.shape(), &[1, 4, 4]);
+        assert_eq!(
+            columns.to_cpu().raw_data_slice(),
+            &[1., 2., 4., 5., 2., 3., 5., 6., 4., 5., 7., 8., 5., 6., 8., 9.]
+        );
+        // Each element is added once per window containing it
+        let ones = GpuTensor::from(vec![1.; 16], vec![1, 4, 4]);
+        let counts = ones.col2im(&window, (1, 3, 3)).await;
+        assert_eq!(counts.to_cpu().raw_data_slice(), &[1., 2., 1., 2., 4., 2., 1., 2., 1.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn im2col_of_strided_input() {
+    let async_block = async {
+        // The image is the transpose of [[1, 2, 3], [4, 5, 6], [7, 8, 9]]
+        let data: Vec<f32> = (1..=9).map(|x| x as f32).collect();
+        let buffer = GpuStore::get_default().gpu_buffer_from_data(bytemuck::cast_slice(&data));
+        let image = GpuTensor::from_buffer_with_strides_and_offset(
+            buffer,
+            VecDeque::from(vec![1, 1, 3, 3]),
+            VecDeque::from(vec![9, 9, 1, 3]),
+            0,
+        );
+        let columns = image.im2col(&Window2d::new((2, 2))).await;
+        assert_eq!(
+            columns.to_cpu().raw_data_slice(),
+            &[1., 4., 2., 5., 4., 7., 5., 8., 2., 5., 3., 6., 5., 8., 6., 9.]
+        );
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn conv2d_matches_reference() {
+    let async_block = async {
+        let input = CpuTensor::rand(vec![2, 4, 7, 6]);
+        let weight = CpuTensor::rand(vec![6, 2, 3, 2]);
+        let bias = CpuTensor::rand(vec![6]);
+        let params = Conv2dParams {
+            stride: (2, 1),
+            padding: (1, 2),
+            dilation: (1, 2),
+            groups: 2,
+        };
+        let output = input
+            .to_gpu()
+            .conv2d(&weight.to_gpu(), Some(&bias.to_gpu()), &params)
+            .await;
+        assert_close(&output.to_cpu(), &input.conv2d(&weight, Some(&bias), &params));
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn conv2d_without_bias() {
+    let async_block = async {
+        let input = GpuTensor::from((1..=9).map(|x| x as f32).collect(), vec![1, 1, 3, 3]);
+        let weight = GpuTensor::from(vec![1., 0., 0., -1.], vec![1, 1, 2, 2]);
+        let output = input.conv2d(&weight, None, &Conv2dParams::default()).await;
+        assert_eq!(output.shape(), &[1, 1, 2, 2]);
+        assert_eq!(output.to_cpu().raw_data_slice(), &[-4., -4., -4., -4.]);
+    };
+    futures::executor::block_on(async_block);
+}
@@ -0,0 +1,187 @@
+//! Transposed 2D convolutions, the adjoint of [`GpuTensor::conv2d`] with respect to its input.
+//! They multiply the input by the transposed kernels, producing columns which
+//! [`GpuTensor::col2im`] adds back into the (larger) output image.
+use super::conv2d::{assert_nchw, copy_view, Window2d};
+use crate::{GpuTensor, ShapeStrides, ShapeStrideTrait};
+use std::collections::VecDeque;
+
+#[cfg(test)]
+mod tests;
+
+/// Options of a transposed 2D convolution. The kernel size is given by the weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvTranspose2dParams {
+    pub stride: (usize, usize),
+    /// Padding of the equivalent [`GpuTensor::conv2d`], removed from both sides of the output
+    pub padding: (usize, usize),
+    /// Extra rows and columns added to one side of the output, to pick between the output
+    /// sizes which a strided convolution maps to the same size. Must be smaller than the stride.
+    pub output_padding: (usize, usize),
+    pub dilation: (usize, usize),
+    pub groups: usize,
+}
+
+impl Default for ConvTranspose2dParams {
+    fn default() -> Self {
+        Self {
+            stride: (1, 1),
+            padding: (0, 0),
+            output_padding: (0, 0),
+            dilation: (1, 1),
+            groups: 1,
+        }
+    }
+}
+
+impl ConvTranspose2dParams {
+    /// The window of the equivalent [`GpuTensor::conv2d`]
+    pub fn window(&self, kernel_size: (usize, usize)) -> Window2d {
+        Window2d {
+            kernel_size,
+            stride: self.stride,
+            padding: self.padding,
+            dilation: self.dilation,
+        }
+    }
+
+    /// Returns the `(height, width)` of the output for an input of the given size
+    pub fn output_size(&self, kernel_size: (usize, usize), height: usize, width: usize) -> (usize, usize) {
+        let output_len = |input: usize, kernel: usize, stride: usize, padding: usize, output_padding: usize, dilation: usize| {
+            assert!(
+                input > 0 && kernel > 0 && stride > 0 && dilation > 0,
+                "Invalid transposed convolution {:?} of a {}x{} input",
+                self,
+                height,
+                width
+            );
+            assert!(
+                output_padding < stride,
+                "The output padding must be smaller than the stride, got {:?}",
+                self
+            );
+            let full = (input - 1) * stride + dilation * (kernel - 1) + output_padding + 1;
+            assert!(
+                full > 2 * padding,
+                "The padding of {:?} removes the whole output of a {}x{} input",
+                self,
+                height,
+                width
+            );
+            full - 2 * padding
+        };
+        (
+            output_len(height, kernel_size.0, self.stride.0, self.padding.0, self.output_padding.0, self.dilation.0),
+            output_len(width, kernel_size.1, self.stride.1, self.padding.1, self.output_padding.1, self.dilation.1),
+        )
+    }
+}
+
+impl GpuTensor {
+    /// Transposed 2D convolution of this `[batch, in_channels, height, width]` Tensor with a
+    /// weight of shape `[in_channels, out_channels / groups, kernel_h, kernel_w]` and an optional
+    /// bias of shape `[out_channels]`. The output has shape `[batch, out_channels, out_h, out_w]`,
+    /// see [`ConvTranspose2dParams::output_size`].
+    pub async fn conv_transpose2d(
+        &self,
+        weight: &GpuTensor,
+        bias: Option<&GpuTensor>,
+        params: &ConvTranspose2dParams,
+    ) -> GpuTensor {
+        let geometry = ConvTranspose2dGeometry::new(self.shape(), weight.shape(), params);
+        let input = copy_view(self, &ShapeStrides::from_shape_vec(geometry.grouped_input_shape())).await;
+        let weight = copy_view(weight, &geometry.grouped_weight_view()).await;
+        let mut columns = weight.matmul(&input).await;
+        columns.reshape(geometry.columns_shape());
+        let output = columns.col2im(&geometry.window, geometry.image_size()).await;
+        match bias {
+            Some(bias) => {
+                assert_eq!(bias.shape(), &[geometry.out_channels], "The bias must have shape [out_channels]");
+                let bias_view = ShapeStrides::from_shape_and_strides_vec(output.shape().iter().copied().collect(), vec![0, 1, 0, 0]);
+                let bias = copy_view(bias, &bias_view).await;
+                output.add(&bias).await
+            }
+            None => output,
+        }
+    }
+}
+
+/// Shapes involved in a grouped transposed convolution computed as a batch matrix
+/// multiplication followed by col2im
+#[derive(Debug, Clone)]
+pub(crate) struct ConvTranspose2dGeometry {
+    pub batch: usize,
+    pub groups: usize,
+    pub in_channels: usize,
+    pub out_channels: usize,
+    /// `out_channels / groups * kernel_h * kernel_w`
+    pub group_rows: usize,
+    pub height: usize,
+    pub width: usize,
+    pub out_h: usize,
+    pub out_w: usize,
+    pub window: Window2d,
+}
+
+impl ConvTranspose2dGeometry {
+    pub fn new(input_shape: &VecDeque<usize>, weight_shape: &VecDeque<usize>, params: &ConvTranspose2dParams) -> Self {
+        assert_nchw(input_shape);
+        assert_eq!(
+            weight_shape.len(),
+            4,
+            "The weight must have shape [in_channels, out_channels / groups, kernel_h, kernel_w]"
+        );
+        let groups = params.groups;
+        let in_channels = input_shape[1];
+        let out_channels = weight_shape[1] * groups;
+        assert!(
+            groups > 0 && in_channels.is_multiple_of(groups),
+            "The {} input channels must be divisible by the {} groups",
+            in_channels,
+            groups
+        );
+        assert_eq!(
+            weight_shape[0], in_channels,
+            "The weight expects {} input channels, but the input has {}",
+            weight_shape[0], in_channels
+        );
+        let kernel_size = (weight_shape[2], weight_shape[3]);
+        let (out_h, out_w) = params.output_size(kernel_size, input_shape[2], input_shape[3]);
+        Self {
+            batch: input_shape[0],
+            groups,
+            in_channels,
+            out_channels,
+            group_rows: weight_shape[1] * weight_shape[2] * weight_shape[3],
+            height: input_shape[2],
+            width: input_shape[3],
+            out_h,
+            out_w,
+            window: params.window(kernel_size),
+        }
+    }
+
+    /// The input, with each group in its own batch dimension
+    pub fn grouped_input_shape(&self) -> Vec<usize> {
+        vec![self.batch, self.groups, self.in_channels / self.groups, self.height * self.width]
+    }
+
+    /// View of a contiguous weight as `[groups, group_rows, in_channels / groups]`, transposing
+    /// the kernels of each group. The matmul broadcasts them over the samples.
+    pub fn grouped_weight_view(&self) -> ShapeStrides {
+        let group_in_channels = self.in_channels / self.groups;
+        ShapeStrides::from_shape_and_strides_vec(
+            vec![self.groups, self.group_rows, group_in_channels],
+            vec![group_in_channels * self.group_rows, 1, self.group_rows],
+        )
+    }
+
+    /// The columns which [`GpuTensor::col2im`] turns into the output
+    pub fn columns_shape(&self) -> Vec<usize> {
+        vec![self.batch, self.groups * self.group_rows, self.height * self.width]
+    }
+
+    /// The `(channels, height, width)` of the output
+    pub fn image_size(&self) -> (usize, usize, usize) {
+        (self.out_channels, self.out_h, self.out_w)
+    }
+}
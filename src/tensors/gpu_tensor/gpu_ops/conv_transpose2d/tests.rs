@@ -0,0 +1,55 @@
+use crate::prelude::*;
+use crate::{Conv2dParams, ConvTranspose2dParams, CpuTensor};
+
+fn assert_close(actual: &CpuTensor, expected: &CpuTensor) {
+    assert_eq!(actual.shape(), expected.shape());
+    for (actual, expected) in actual.as_contiguous_vec().iter().zip(expected.as_contiguous_vec()) {
+        assert!((actual - expected).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn conv_transpose2d_matches_reference() {
+    let async_block = async {
+        let input = CpuTensor::rand(vec![2, 4, 3, 5]);
+        let weight = CpuTensor::rand(vec![4, 3, 3, 2]);
+        let bias = CpuTensor::rand(vec![6]);
+        let params = ConvTranspose2dParams {
+            stride: (2, 3),
+            padding: (1, 1),
+            output_padding: (1, 2),
+            dilation: (1, 2),
+            groups: 2,
+        };
+        let output = input
+            .to_gpu()
+            .conv_transpose2d(&weight.to_gpu(), Some(&bias.to_gpu()), &params)
+            .await;
+        assert_close(&output.to_cpu(), &input.conv_transpose2d(&weight, Some(&bias), &params));
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn conv_transpose2d_undoes_the_shape_change_of_conv2d() {
+    let async_block = async {
+        let params = ConvTranspose2dParams {
+            stride: (2, 2),
+            padding: (1, 1),
+            output_padding: (1, 0),
+            ..ConvTranspose2dParams::default()
+        };
+        let conv_params = Conv2dParams {
+            stride: params.stride,
+            padding: params.padding,
+            ..Conv2dParams::default()
+        };
+        let input = CpuTensor::rand(vec![1, 2, 8, 7]).to_gpu();
+        let weight = CpuTensor::rand(vec![3, 2, 3, 3]).to_gpu();
+        let downsampled = input.conv2d(&weight, None, &conv_params).await;
+        assert_eq!(downsampled.shape(), &[1, 3, 4, 4]);
+        let upsampled = downsampled.conv_transpose2d(&weight, None, &params).await;
+        assert_eq!(upsampled.shape(), &[1, 2, 8, 7]);
+    };
+    futures::executor::block_on(async_block);
+}
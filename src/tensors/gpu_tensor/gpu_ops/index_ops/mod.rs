@@ -0,0 +1,228 @@
+//! Integer indexed Ops: index_select, gather, scatter and embedding. Indices are stored as
+//! floats, like the classes of the losses, so they are exact up to 2^24. Out of range indices
+//! read zero and are ignored when scattering.
+//!
+//! Tensors are viewed as `[outer, dim_len, inner]` around the indexed dimension. Scattering
+//! is computed by the destination elements, each one looking for the sources written to it,
+//! so the results are deterministic: the last source wins for scatter, sources are summed in
+//! order for scatter_add.
+use crate::tensors::gpu_tensor::gpu_ops::conv2d::run_window_kernel;
+use crate::{GpuAllocated, GpuTensor, ShapeStrideTrait};
+use std::collections::VecDeque;
+
+#[cfg(test)]
+mod tests;
+
+/// Returns `(outer, shape[dim], inner)`
+fn split_at_dim(shape: &VecDeque<usize>, dim: usize) -> (usize, usize, usize) {
+    assert!(dim < shape.len(), "Dimension {} is out of range for shape {:?}", dim, shape);
+    let outer = shape.iter().take(dim).product();
+    let inner = shape.iter().skip(dim + 1).product();
+    (outer, shape[dim], inner)
+}
+
+/// Checks `index` has the shape of `tensor`, except along `dim`
+fn assert_index_shape(tensor: &VecDeque<usize>, index: &VecDeque<usize>, dim: usize) {
+    let matches = tensor.len() == index.len()
+        && tensor.iter().zip(index).enumerate().all(|(d, (a, b))| d == dim || a == b);
+    assert!(
+        matches,
+        "The index shape {:?} must match the shape {:?} except along dimension {}",
+        index,
+        tensor,
+        dim
+    );
+}
+
+fn assert_indices_1d(indices: &GpuTensor) {
+    assert_eq!(indices.rank(), 1, "The indices must be one dimensional, got shape {:?}", indices.shape());
+}
+
+/// Strides used to read the index of element `(o, i, j)`, see `gather.comp`
+#[derive(Debug, Clone, Copy)]
+struct IndexStrides([usize; 3]);
+
+impl IndexStrides {
+    /// An index with the shape of the output, as for gather and scatter
+    fn full(index_len: usize, inner: usize) -> Self {
+        Self([index_len * inner, inner, 1])
+    }
+
+    /// The same one dimensional index for all the outer and inner positions
+    fn one_dimensional() -> Self {
+        Self([0, 1, 0])
+    }
+}
+
+/// Geometry of an indexing kernel, the indexed Tensor being `[outer, dim_len, inner]` and the
+/// gathered or scattered one `[outer, index_len, inner]`
+struct IndexGeometry {
+    dim_len: usize,
+    index_len: usize,
+    inner: usize,
+    strides: IndexStrides,
+}
+
+impl IndexGeometry {
+    fn push_constants(&self) -> Vec<u32> {
+        let mut push_constants = vec![self.dim_len as u32, self.index_len as u32, self.inner as u32];
+        push_constants.extend(self.strides.0.iter().map(|&stride| stride as u32));
+        push_constants
+    }
+}
+
+/// Runs `shader` binding the inputs, copied first if they are not contiguous, then `output`
+async fn run_index_kernel(
+    shader: wgpu::ShaderModuleSource<'_>,
+    inputs: &[&GpuTensor],
+    output_shape: VecDeque<usize>,
+    push_constants: Vec<u32>,
+) -> GpuTensor {
+    let mut copies = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        copies.push(if input.is_contiguous() { None } else { Some(input.contiguous().await) });
+    }
+    let inputs: Vec<&GpuTensor> = inputs
+        .iter()
+        .zip(&copies)
+        .map(|(&input, copy)| copy.as_ref().unwrap_or(input))
+        .collect();
+    let numel = GpuTensor::numel_from_shape(&output_shape);
+    let output_buffer = inputs[0].gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+    run_window_kernel(shader, &inputs, &[&output_buffer], push_constants, numel).await;
+    GpuTensor::from_buffer(output_buffer, output_shape)
+}
+
+async fn gather_kernel(input: &GpuTensor, index: &GpuTensor, geometry: IndexGeometry, output_shape: VecDeque<usize>) -> GpuTensor {
+    run_index_kernel(
+        wgpu::include_spirv!("gather.spv"),
+        &[input, index],
+        output_shape,
+        geometry.push_constants(),
+    )
+    .await
+}
+
+async fn scatter_kernel(base: &GpuTensor, index: &GpuTensor, source: &GpuTensor, geometry: IndexGeometry, add: bool) -> GpuTensor {
+    let mut push_constants = geometry.push_constants();
+    push_constants.push(add as u32);
+    run_index_kernel(
+        wgpu::include_spirv!("scatter.spv"),
+        &[base, index, source],
+        base.shape().clone(),
+        push_constants,
+    )
+    .await
+}
+
+impl GpuTensor {
+    /// Selects the entries of dimension `dim` given by the one dimensional `indices`, so the
+    /// output has the shape of this Tensor with `indices.len()` entries along `dim`
+    pub async fn index_select(&self, dim: usize, indices: &GpuTensor) -> GpuTensor {
+        assert_indices_1d(indices);
+        let (_, dim_len, inner) = split_at_dim(self.shape(), dim);
+        let mut output_shape = self.shape().clone();
+        output_shape[dim] = indices.numel();
+        let geometry = IndexGeometry {
+            dim_len,
+            index_len: indices.numel(),
+            inner,
+            strides: IndexStrides::one_dimensional(),
+        };
+        gather_kernel(self, indices, geometry, output_shape).await
+    }
+
+    /// Adds the entries of `source` along `dim` to the entries of this Tensor given by the one
+    /// dimensional `indices`, summing the duplicates. This is the adjoint of
+    /// [`GpuTensor::index_select`], computed by the scatter_add kernel.
+    pub async fn index_add(&self, dim: usize, indices: &GpuTensor, source: &GpuTensor) -> GpuTensor {
+        assert_indices_1d(indices);
+        let (_, dim_len, inner) = split_at_dim(self.shape(), dim);
+        let mut expected_shape = self.shape().clone();
+        expected_shape[dim] = indices.numel();
+        assert_eq!(source.shape(), &expected_shape, "The source must have one entry per index along dimension {}", dim);
+        let geometry = IndexGeometry {
+            dim_len,
+            index_len: indices.numel(),
+            inner,
+            strides: IndexStrides::one_dimensional(),
+        };
+        scatter_kernel(self, indices, source, geometry, true).await
+    }
+
+    /// Returns the Tensor of the shape of `index` whose element at position `p` is the one of
+    /// this Tensor at `p` with coordinate `dim` replaced by `index[p]`. The index must have
+    /// the shape of this Tensor, except along `dim`.
+    pub async fn gather(&self, dim: usize, index: &GpuTensor) -> GpuTensor {
+        assert_index_shape(self.shape(), index.shape(), dim);
+        let (_, dim_len, inner) = split_at_dim(self.shape(), dim);
+        let index_len = index.shape()[dim];
+        let geometry = IndexGeometry {
+            dim_len,
+            index_len,
+            inner,
+            strides: IndexStrides::full(index_len, inner),
+        };
+        gather_kernel(self, index, geometry, index.shape().clone()).await
+    }
+
+    /// Returns a copy of this Tensor where the element at position `p` of `source` is written
+    /// at `p` with coordinate `dim` replaced by `index[p]`. When several sources are written to
+    /// the same element, the last one along `dim` wins. This is the inverse of
+    /// [`GpuTensor::gather`].
+    pub async fn scatter(&self, dim: usize, index: &GpuTensor, source: &GpuTensor) -> GpuTensor {
+        self.scatter_impl(dim, index, source, false).await
+    }
+
+    /// Same as [`GpuTensor::scatter`], but summing the sources into this Tensor. This is the
+    /// adjoint of [`GpuTensor::gather`].
+    pub async fn scatter_add(&self, dim: usize, index: &GpuTensor, source: &GpuTensor) -> GpuTensor {
+        self.scatter_impl(dim, index, source, true).await
+    }
+
+    async fn scatter_impl(&self, dim: usize, index: &GpuTensor, source: &GpuTensor, add: bool) -> GpuTensor {
+        assert_index_shape(self.shape(), index.shape(), dim);
+        assert_eq!(index.shape(), source.shape(), "The index and the source must have the same shape");
+        let (_, dim_len, inner) = split_at_dim(self.shape(), dim);
+        let index_len = index.shape()[dim];
+        let geometry = IndexGeometry {
+            dim_len,
+            index_len,
+            inner,
+            strides: IndexStrides::full(index_len, inner),
+        };
+        scatter_kernel(self, index, source, geometry, add).await
+    }
+
+    /// Looks up the rows of `weight`, with shape `[num_embeddings, embedding_dim]`, given by
+    /// the indices in this Tensor. The output has shape `[*indices_shape, embedding_dim]`.
+    pub async fn embedding(&self, weight: &GpuTensor) -> GpuTensor {
+        assert_eq!(weight.rank(), 2, "The embedding weight must have shape [num_embeddings, embedding_dim]");
+        let mut output_shape = self.shape().clone();
+        output_shape.push_back(weight.shape()[1]);
+        let geometry = IndexGeometry {
+            dim_len: weight.shape()[0],
+            index_len: self.numel(),
+            inner: weight.shape()[1],
+            strides: IndexStrides::one_dimensional(),
+        };
+        gather_kernel(weight, self, geometry, output_shape).await
+    }
+
+    /// Given the gradient of the output of [`GpuTensor::embedding`] for the indices in this
+    /// Tensor, returns the gradient of the weight, which has `num_embeddings` rows
+    pub async fn embedding_backward(&self, grad: &GpuTensor, num_embeddings: usize) -> GpuTensor {
+        let embedding_dim = *grad.shape().back().unwrap();
+        let mut expected_shape = self.shape().clone();
+        expected_shape.push_back(embedding_dim);
+        assert_eq!(grad.shape(), &expected_shape, "The gradient must have the shape of the embeddings");
+        let zeros = GpuTensor::new_filled(vec![num_embeddings, embedding_dim], 0.).await;
+        let geometry = IndexGeometry {
+            dim_len: num_embeddings,
+            index_len: self.numel(),
+            inner: embedding_dim,
+            strides: IndexStrides::one_dimensional(),
+        };
+        scatter_kernel(&zeros, self, grad, geometry, true).await
+    }
+}
@@ -0,0 +1,75 @@
+use crate::prelude::*;
+use crate::GpuTensor;
+
+async fn values(tensor: GpuTensor) -> Vec<f32> {
+    tensor.to_cpu().as_contiguous_vec()
+}
+
+#[test]
+fn index_select_and_index_add() {
+    let async_block = async {
+        let input = GpuTensor::from(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+        let indices = GpuTensor::from(vec![2., 0., 2.], vec![3]);
+        let selected = input.index_select(1, &indices).await;
+        assert_eq!(selected.shape(), &[2, 3]);
+        assert_eq!(values(selected).await, vec![3., 1., 3., 6., 4., 6.]);
+        let rows = input.index_select(0, &GpuTensor::from(vec![1.], vec![1])).await;
+        assert_eq!(values(rows).await, vec![4., 5., 6.]);
+
+        let zeros = GpuTensor::new_filled(vec![2, 3], 0.).await;
+        let added = zeros.index_add(1, &indices, &input).await;
+        assert_eq!(values(added).await, vec![2., 0., 4., 5., 0., 10.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn gather_test() {
+    let async_block = async {
+        let input = GpuTensor::from(vec![1., 2., 3., 4.], vec![2, 2]);
+        let index = GpuTensor::from(vec![0., 0., 1., 0.], vec![2, 2]);
+        assert_eq!(values(input.gather(1, &index).await).await, vec![1., 1., 4., 3.]);
+        let index = GpuTensor::from(vec![1., 0.], vec![1, 2]);
+        assert_eq!(values(input.gather(0, &index).await).await, vec![3., 2.]);
+        // Out of range indices read zero
+        let index = GpuTensor::from(vec![5., -1.], vec![2, 1]);
+        assert_eq!(values(input.gather(1, &index).await).await, vec![0., 0.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn scatter_test() {
+    let async_block = async {
+        let base = GpuTensor::new_filled(vec![2, 3], 0.).await;
+        let index = GpuTensor::from(vec![0., 2., 1., 1.], vec![2, 2]);
+        let source = GpuTensor::from(vec![1., 2., 3., 4.], vec![2, 2]);
+        let scattered = base.scatter(1, &index, &source).await;
+        assert_eq!(values(scattered).await, vec![1., 0., 2., 0., 4., 0.]);
+        let added = base.scatter_add(1, &index, &source).await;
+        assert_eq!(values(added).await, vec![1., 0., 2., 0., 7., 0.]);
+        // Scattering what was gathered restores it
+        let input = GpuTensor::from(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+        let index = GpuTensor::from(vec![2., 0., 1., 2.], vec![2, 2]);
+        let gathered = input.gather(1, &index).await;
+        let restored = base.scatter(1, &index, &gathered).await;
+        assert_eq!(values(restored).await, vec![1., 0., 3., 0., 5., 6.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn embedding_test() {
+    let async_block = async {
+        let weight = GpuTensor::from(vec![0., 1., 10., 11., 20., 21.], vec![3, 2]);
+        let indices = GpuTensor::from(vec![2., 0., 2., 1.], vec![2, 2]);
+        let embeddings = indices.embedding(&weight).await;
+        assert_eq!(embeddings.shape(), &[2, 2, 2]);
+        assert_eq!(values(embeddings).await, vec![20., 21., 0., 1., 20., 21., 10., 11.]);
+        let grad = GpuTensor::from((1..=8).map(|x| x as f32).collect(), vec![2, 2, 2]);
+        let weight_grad = indices.embedding_backward(&grad, 3).await;
+        assert_eq!(weight_grad.shape(), &[3, 2]);
+        assert_eq!(values(weight_grad).await, vec![3., 4., 7., 8., 6., 8.]);
+    };
+    futures::executor::block_on(async_block);
+}
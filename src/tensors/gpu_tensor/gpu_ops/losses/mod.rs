@@ -0,0 +1,142 @@
+//! Fused loss kernels. Each loss is computed per element, or per sample for the
+//! classification losses, and its gradient is computed directly from the inputs instead of
+//! differentiating the individual operations.
+use crate::gpu_internals::gpu_buffers::GpuBuffer;
+use crate::gpu_internals::shader_runner::{ShaderInputs, ThreadGroup};
+use crate::{GpuAllocated, GpuTensor, ShapeStrideTrait};
+use std::collections::VecDeque;
+use wgpu::ShaderModuleSource;
+
+#[cfg(test)]
+mod tests;
+
+/// Losses computed independently for each element of the input, given the target element
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointwiseLoss {
+    /// `(x - t)^2`
+    SquaredError,
+    /// `|x - t|`
+    AbsoluteError,
+    /// `0.5 (x - t)^2` if `|x - t| <= delta`, `delta (|x - t| - 0.5 delta)` otherwise
+    Huber { delta: f32 },
+    /// `-(t log(sigmoid(x)) + (1 - t) log(1 - sigmoid(x)))`, computed in a numerically stable way
+    BinaryCrossEntropyWithLogits,
+}
+
+impl PointwiseLoss {
+    fn push_constants(&self) -> Vec<u32> {
+        let (kind, delta) = match self {
+            PointwiseLoss::SquaredError => (0, 0.),
+            PointwiseLoss::AbsoluteError => (1, 0.),
+            PointwiseLoss::Huber { delta } => (2, *delta),
+            PointwiseLoss::BinaryCrossEntropyWithLogits => (3, 0.),
+        };
+        vec![kind, u32::from_ne_bytes(delta.to_ne_bytes())]
+    }
+}
+
+/// Runs a loss kernel binding the `inputs`, in order, followed by the output buffer. The
+/// inputs which are not contiguous are copied first.
+async fn run_loss_kernel(
+    shader: ShaderModuleSource<'_>,
+    inputs: &[&GpuTensor],
+    output_shape: VecDeque<usize>,
+    push_constants: Vec<u32>,
+) -> GpuTensor {
+    let mut copies = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        copies.push(if input.is_contiguous() { None } else { Some(input.contiguous().await) });
+    }
+    let inputs: Vec<&GpuTensor> = inputs
+        .iter()
+        .zip(copies.iter())
+        .map(|(&input, copy)| copy.as_ref().unwrap_or(input))
+        .collect();
+    let gpu = inputs[0].gpu();
+    let numel = GpuTensor::numel_from_shape(&output_shape);
+    let output_buffer: GpuBuffer = gpu.empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+    if numel != 0 {
+        let cs_module = gpu.shader_from_file_bytes(shader);
+        let mut shader_inputs = ShaderInputs::default();
+        for input in &inputs {
+            shader_inputs.append_buffer(input.buffer());
+        }
+        shader_inputs.append_buffer(&output_buffer);
+        shader_inputs.push_constants.data = push_constants;
+        gpu.run_shader(
+            &cs_module,
+            &shader_inputs,
+            ThreadGroup {
+                x: numel,
+                y: 1,
+                z: 1,
+            },
+        );
+    }
+    GpuTensor::from_buffer(output_buffer, output_shape)
+}
+
+/// Checks the scores have shape `[samples, classes]` and the targets `[samples]`, returning
+/// the number of classes
+fn classes(scores: &GpuTensor, targets: &GpuTensor) -> usize {
+    assert_eq!(scores.rank(), 2, "Scores must have shape [samples, classes]");
+    assert_eq!(
+        targets.shape(),
+        &[scores.shape()[0]],
+        "There must be one target class per sample"
+    );
+    scores.shape()[1]
+}
+
+impl GpuTensor {
+    /// Returns the loss of each element of this Tensor, given the target of same shape
+    pub async fn pointwise_loss(&self, target: &GpuTensor, loss: PointwiseLoss) -> GpuTensor {
+        assert_eq!(self.shape(), target.shape(), "The target must have the same shape as the input");
+        run_loss_kernel(
+            wgpu::include_spirv!("pointwise_loss.spv"),
+            &[self, target],
+            self.shape().clone(),
+            loss.push_constants(),
+        ).await
+    }
+
+    /// Returns the gradient of [`GpuTensor::pointwise_loss`] with respect to this Tensor,
+    /// given the gradient of the loss of each element
+    pub async fn pointwise_loss_backward(&self, target: &GpuTensor, grad: &GpuTensor, loss: PointwiseLoss) -> GpuTensor {
+        assert_eq!(self.shape(), target.shape(), "The target must have the same shape as the input");
+        assert_eq!(self.shape(), grad.shape(), "The gradient must have the same shape as the input");
+        run_loss_kernel(
+            wgpu::include_spirv!("pointwise_loss_backward.spv"),
+            &[self, target, grad],
+            self.shape().clone(),
+            loss.push_constants(),
+        ).await
+    }
+
+    /// Returns the negative log likelihood of the target class of each sample. This Tensor
+    /// holds the scores with shape `[samples, classes]` and `targets` the class index of each
+    /// sample. If `log_softmax` is set, the scores are logits and the log softmax is applied to
+    /// them first, otherwise they must be log probabilities.
+    pub async fn class_loss(&self, targets: &GpuTensor, log_softmax: bool) -> GpuTensor {
+        let classes = classes(self, targets);
+        run_loss_kernel(
+            wgpu::include_spirv!("class_loss.spv"),
+            &[self, targets],
+            targets.shape().clone(),
+            vec![classes as u32, log_softmax as u32],
+        ).await
+    }
+
+    /// Returns the gradient of [`GpuTensor::class_loss`] with respect to the scores, given the
+    /// gradient of the loss of each sample
+    pub async fn class_loss_backward(&self, targets: &GpuTensor, grad: &GpuTensor, log_softmax: bool) -> GpuTensor {
+        let classes = classes(self, targets);
+        assert_eq!(grad.shape(), targets.shape(), "There must be one gradient per sample");
+        run_loss_kernel(
+            wgpu::include_spirv!("class_loss_backward.spv"),
+            &[self, targets, grad],
+            self.shape().clone(),
+            vec![classes as u32, log_softmax as u32],
+        ).await
+    }
+}
@@ -0,0 +1,74 @@
+use crate::prelude::*;
+use crate::{GpuStore, GpuTensor, PointwiseLoss};
+use std::collections::VecDeque;
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn pointwise_loss_test() {
+    let async_block = async {
+        let input = GpuTensor::from(vec![0., 1., 4.], vec![3]);
+        let target = GpuTensor::from(vec![1., 1., 1.], vec![3]);
+        let grad = GpuTensor::from(vec![1., 1., 2.], vec![3]);
+        let mse = input.pointwise_loss(&target, PointwiseLoss::SquaredError).await;
+        assert_close(mse.to_cpu().raw_data_slice(), &[1., 0., 9.]);
+        let huber = PointwiseLoss::Huber { delta: 1. };
+        let loss = input.pointwise_loss(&target, huber).await;
+        assert_close(loss.to_cpu().raw_data_slice(), &[0.5, 0., 2.5]);
+        let loss_grad = input.pointwise_loss_backward(&target, &grad, huber).await;
+        assert_close(loss_grad.to_cpu().raw_data_slice(), &[-1., 0., 2.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn pointwise_loss_of_strided_input() {
+    let async_block = async {
+        // The input is read column by column from its buffer, so it is [[0, 2], [1, 3]]
+        let buffer = GpuStore::get_default().gpu_buffer_from_data(bytemuck::cast_slice(&[0f32, 1., 2., 3.]));
+        let input = GpuTensor::from_buffer_with_strides_and_offset(
+            buffer,
+            VecDeque::from(vec![2, 2]),
+            VecDeque::from(vec![1, 2]),
+            0,
+        );
+        let target = GpuTensor::from(vec![1., 1., 1., 1.], vec![2, 2]);
+        let loss = input.pointwise_loss(&target, PointwiseLoss::SquaredError).await;
+        assert_close(loss.to_cpu().raw_data_slice(), &[1., 1., 0., 4.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn binary_cross_entropy_with_logits_test() {
+    let async_block = async {
+        let input = GpuTensor::from(vec![0., 100., -100.], vec![3]);
+        let target = GpuTensor::from(vec![1., 1., 1.], vec![3]);
+        let loss = input
+            .pointwise_loss(&target, PointwiseLoss::BinaryCrossEntropyWithLogits)
+            .await;
+        assert_close(loss.to_cpu().raw_data_slice(), &[2f32.ln(), 0., 100.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn class_loss_test() {
+    let async_block = async {
+        let logits = GpuTensor::from(vec![0., 0., 1000., 0.], vec![2, 2]);
+        let targets = GpuTensor::from(vec![1., 0.], vec![2]);
+        let loss = logits.class_loss(&targets, true).await;
+        assert_close(loss.to_cpu().raw_data_slice(), &[2f32.ln(), 0.]);
+        let grad = GpuTensor::from(vec![1., 1.], vec![2]);
+        let logits_grad = logits.class_loss_backward(&targets, &grad, true).await;
+        assert_close(logits_grad.to_cpu().raw_data_slice(), &[0.5, -0.5, 0., 0.]);
+        let nll = logits.class_loss(&targets, false).await;
+        assert_close(nll.to_cpu().raw_data_slice(), &[-0., -1000.]);
+    };
+    futures::executor::block_on(async_block);
+}
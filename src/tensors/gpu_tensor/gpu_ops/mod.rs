@@ -8,6 +8,27 @@ mod compare;
 mod log_soft_max;
 mod binary_ops;
 mod unary_ops;
+mod strided_copy;
+mod sum_dim;
+mod optim_steps;
+mod losses;
+mod conv2d;
+mod pool2d;
+mod conv_transpose2d;
+mod conv1d;
+mod normalization;
+mod random;
+mod index_ops;
+mod concat;
+mod softmax;
+pub use optim_steps::{AdamStep, RmsPropStep, SgdStep};
+pub use losses::PointwiseLoss;
+pub use conv2d::{Conv2dParams, Window2d};
+pub use conv_transpose2d::ConvTranspose2dParams;
+pub use conv1d::{Conv1dParams, ConvTranspose1dParams};
+pub use normalization::NormGroups;
+pub(crate) use conv2d::Conv2dGeometry;
+pub(crate) use conv_transpose2d::ConvTranspose2dGeometry;
 use crate::{GpuTensor, GpuAllocated};
 
 impl GpuTensor {
@@ -0,0 +1,233 @@
+//! Fused kernels of the normalization layers. The input is viewed as `[outer, groups, inner]`
+//! and each group is normalized using the statistics of its elements, over the outer and inner
+//! dimensions. Batch, layer and group normalization only differ in how they are split into
+//! groups, see [`NormGroups`].
+use crate::tensors::gpu_tensor::gpu_ops::conv2d::run_window_kernel;
+use crate::{GpuAllocated, GpuTensor, ShapeStrideTrait};
+use std::collections::VecDeque;
+
+#[cfg(test)]
+mod tests;
+
+/// Splits a contiguous Tensor, viewed as `[outer, groups, inner]`, into `groups` sets of
+/// elements. Element `i` belongs to group `(i / inner) % groups`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormGroups {
+    pub groups: usize,
+    pub inner: usize,
+}
+
+impl NormGroups {
+    /// One group per channel of a `[batch, channels, *]` Tensor, as used by batch
+    /// normalization and by the affine parameters of batch and group normalization
+    pub fn channels(shape: &VecDeque<usize>) -> Self {
+        assert!(
+            shape.len() >= 2,
+            "Expected a [batch, channels, *] Tensor, got shape {:?}",
+            shape
+        );
+        Self {
+            groups: shape[1],
+            inner: shape.iter().skip(2).product(),
+        }
+    }
+
+    /// One group per contiguous row of `row_len` elements, as used by layer and group
+    /// normalization
+    pub fn rows(shape: &VecDeque<usize>, row_len: usize) -> Self {
+        let numel: usize = shape.iter().product();
+        assert!(
+            row_len > 0 && numel.is_multiple_of(row_len),
+            "Rows of {} elements don't split a Tensor of shape {:?}",
+            row_len,
+            shape
+        );
+        Self {
+            groups: numel / row_len,
+            inner: row_len,
+        }
+    }
+
+    /// One group per position of rows of `row_len` elements, as used by the affine parameters
+    /// of layer normalization
+    pub fn columns(row_len: usize) -> Self {
+        Self {
+            groups: row_len,
+            inner: 1,
+        }
+    }
+
+    /// The groups of the statistics and of the affine parameters of the batch normalization
+    /// of a `[batch, channels, *]` Tensor, which are both per channel
+    pub fn batch_norm(shape: &VecDeque<usize>) -> Self {
+        Self::channels(shape)
+    }
+
+    /// The groups of the statistics and of the affine parameters of the layer normalization
+    /// of a Tensor over its trailing `normalized_shape` dimensions
+    pub fn layer_norm(shape: &VecDeque<usize>, normalized_shape: &[usize]) -> (Self, Self) {
+        assert!(
+            !normalized_shape.is_empty()
+                && normalized_shape.len() <= shape.len()
+                && shape.iter().skip(shape.len() - normalized_shape.len()).eq(normalized_shape.iter()),
+            "The normalized shape {:?} must match the trailing dimensions of {:?}",
+            normalized_shape,
+            shape
+        );
+        let row_len = normalized_shape.iter().product();
+        (Self::rows(shape, row_len), Self::columns(row_len))
+    }
+
+    /// The groups of the statistics and of the affine parameters of the group normalization
+    /// of a `[batch, channels, *]` Tensor, with the channels split into `num_groups` groups.
+    /// The affine parameters are per channel.
+    pub fn group_norm(shape: &VecDeque<usize>, num_groups: usize) -> (Self, Self) {
+        let channels = Self::channels(shape);
+        assert!(
+            num_groups > 0 && channels.groups.is_multiple_of(num_groups),
+            "The {} channels must be divisible by the {} groups",
+            channels.groups,
+            num_groups
+        );
+        let row_len = channels.groups / num_groups * channels.inner;
+        (Self::rows(shape, row_len), channels)
+    }
+
+    /// Number of elements of each group of a Tensor with `numel` elements
+    pub fn len(&self, numel: usize) -> usize {
+        self.assert_splits(numel);
+        numel / self.groups
+    }
+
+    fn assert_splits(&self, numel: usize) {
+        assert_eq!(
+            numel % (self.groups * self.inner),
+            0,
+            "{:?} don't split a Tensor of {} elements",
+            self,
+            numel
+        );
+    }
+
+    fn push_constants(&self) -> Vec<u32> {
+        vec![self.groups as u32, self.inner as u32]
+    }
+}
+
+fn float_bits(value: f32) -> u32 {
+    u32::from_ne_bytes(value.to_ne_bytes())
+}
+
+impl GpuTensor {
+    fn empty_with_shape(&self, shape: VecDeque<usize>) -> GpuTensor {
+        let numel = GpuTensor::numel_from_shape(&shape);
+        GpuTensor::from_buffer(self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>()), shape)
+    }
+
+    /// Returns the mean and the biased variance of each group, both with shape `[groups]`
+    pub async fn norm_stats(&self, groups: &NormGroups) -> (GpuTensor, GpuTensor) {
+        let len = groups.len(self.numel());
+        assert!(len > 0, "Can't compute the statistics of empty groups");
+        let mean = self.empty_with_shape(VecDeque::from(vec![groups.groups]));
+        let var = self.empty_with_shape(VecDeque::from(vec![groups.groups]));
+        let mut push_constants = groups.push_constants();
+        push_constants.push(len as u32);
+        run_window_kernel(
+            wgpu::include_spirv!("norm_stats.spv"),
+            &[self],
+            &[mean.buffer(), var.buffer()],
+            push_constants,
+            groups.groups,
+        ).await;
+        (mean, var)
+    }
+
+    /// Returns `(x - mean) / sqrt(var + eps)`, using the statistics of the group of each element
+    pub async fn normalize(&self, mean: &GpuTensor, var: &GpuTensor, groups: &NormGroups, eps: f32) -> GpuTensor {
+        groups.assert_splits(self.numel());
+        assert_eq!(mean.shape(), &[groups.groups], "There must be one mean per group");
+        assert_eq!(var.shape(), &[groups.groups], "There must be one variance per group");
+        let output = self.empty_with_shape(self.shape().clone());
+        let mut push_constants = groups.push_constants();
+        push_constants.push(float_bits(eps));
+        run_window_kernel(
+            wgpu::include_spirv!("normalize.spv"),
+            &[self, mean, var],
+            &[output.buffer()],
+            push_constants,
+            self.numel(),
+        ).await;
+        output
+    }
+
+    /// Returns the gradient of [`GpuTensor::normalize`] with respect to its input. This Tensor
+    /// is the normalized output and `grad` its gradient. If `batch_stats` is set, the statistics
+    /// were computed from the input by [`GpuTensor::norm_stats`], otherwise they are treated as
+    /// constants, as the running statistics of batch normalization in evaluation mode.
+    pub async fn normalize_backward(
+        &self,
+        grad: &GpuTensor,
+        var: &GpuTensor,
+        groups: &NormGroups,
+        eps: f32,
+        batch_stats: bool,
+    ) -> GpuTensor {
+        let len = groups.len(self.numel());
+        assert_eq!(grad.shape(), self.shape(), "The gradient must have the shape of the output");
+        assert_eq!(var.shape(), &[groups.groups], "There must be one variance per group");
+        let sums = self.empty_with_shape(VecDeque::from(vec![3, groups.groups]));
+        let mut push_constants = groups.push_constants();
+        push_constants.extend_from_slice(&[len as u32, float_bits(eps), batch_stats as u32]);
+        run_window_kernel(
+            wgpu::include_spirv!("normalize_backward_sums.spv"),
+            &[grad, self, var],
+            &[sums.buffer()],
+            push_constants,
+            groups.groups,
+        ).await;
+        let input_grad = self.empty_with_shape(self.shape().clone());
+        run_window_kernel(
+            wgpu::include_spirv!("normalize_backward.spv"),
+            &[grad, self, &sums],
+            &[input_grad.buffer()],
+            groups.push_constants(),
+            self.numel(),
+        ).await;
+        input_grad
+    }
+
+    /// Returns `x * weight + bias`, using the weight and bias of the group of each element
+    pub async fn affine(&self, weight: &GpuTensor, bias: &GpuTensor, groups: &NormGroups) -> GpuTensor {
+        groups.assert_splits(self.numel());
+        assert_eq!(weight.numel(), groups.groups, "There must be one weight per group");
+        assert_eq!(bias.numel(), groups.groups, "There must be one bias per group");
+        let output = self.empty_with_shape(self.shape().clone());
+        run_window_kernel(
+            wgpu::include_spirv!("affine.spv"),
+            &[self, weight, bias],
+            &[output.buffer()],
+            groups.push_constants(),
+            self.numel(),
+        ).await;
+        output
+    }
+
+    /// Returns the gradients of the weight and bias of [`GpuTensor::affine`], where this Tensor
+    /// is its input and `grad` the gradient of its output
+    pub async fn affine_backward(&self, grad: &GpuTensor, groups: &NormGroups) -> (GpuTensor, GpuTensor) {
+        let len = groups.len(self.numel());
+        assert_eq!(grad.shape(), self.shape(), "The gradient must have the shape of the output");
+        let weight_grad = self.empty_with_shape(VecDeque::from(vec![groups.groups]));
+        let bias_grad = self.empty_with_shape(VecDeque::from(vec![groups.groups]));
+        let mut push_constants = groups.push_constants();
+        push_constants.push(len as u32);
+        run_window_kernel(
+            wgpu::include_spirv!("affine_backward.spv"),
+            &[self, grad],
+            &[weight_grad.buffer(), bias_grad.buffer()],
+            push_constants,
+            groups.groups,
+        ).await;
+        (weight_grad, bias_grad)
+    }
+}
@@ -0,0 +1,77 @@
+use crate::prelude::*;
+use crate::{CpuTensor, RawTensor};
+
+fn assert_close(actual: &RawTensor, expected: &CpuTensor) {
+    assert_eq!(actual.shape(), expected.shape());
+    for (actual, expected) in actual.to_vec().iter().zip(expected.as_contiguous_vec()) {
+        assert!((actual - expected).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+fn raw(tensor: &CpuTensor) -> RawTensor {
+    RawTensor::from_data_and_shape(tensor.as_contiguous_vec(), tensor.shape().iter().copied().collect())
+}
+
+#[test]
+fn layer_norm_matches_reference() {
+    let input = CpuTensor::rand(vec![3, 4, 5]);
+    let output = raw(&input).layer_norm(&[4, 5], None, None, 1e-5);
+    assert_close(&output, &input.layer_norm(20, 1e-5));
+}
+
+#[test]
+fn group_norm_matches_reference() {
+    let input = CpuTensor::rand(vec![2, 6, 3, 2]);
+    let output = raw(&input).group_norm(3, None, None, 1e-5);
+    assert_close(&output, &input.group_norm(3, 1e-5));
+}
+
+#[test]
+fn batch_norm_matches_reference_and_updates_running_stats() {
+    let input = CpuTensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6., 7., 8.], vec![2, 2, 2]);
+    let mut running_mean = RawTensor::zeros(vec![2]);
+    let mut running_var = RawTensor::filled(vec![2], 1.);
+    let output = raw(&input).batch_norm(
+        Some((&mut running_mean, &mut running_var)),
+        None,
+        None,
+        true,
+        0.5,
+        1e-5,
+    );
+    assert_close(&output, &input.batch_norm(1e-5));
+    // The first channel has values 1, 2, 5, 6, with mean 3.5 and unbiased variance 17 / 3
+    let mean_and_var = [running_mean.to_vec(), running_var.to_vec()];
+    let expected = [[1.75, 2.75], [0.5 + 17. / 6., 0.5 + 17. / 6.]];
+    for (actual, expected) in mean_and_var.iter().flatten().zip(expected.iter().flatten()) {
+        assert!((actual - expected).abs() < 1e-5, "{} != {}", actual, expected);
+    }
+}
+
+#[test]
+fn batch_norm_eval_uses_running_stats_and_affine() {
+    let input = RawTensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![1, 2, 2]);
+    let mut running_mean = RawTensor::from_data_1d(vec![1., 2.]);
+    let mut running_var = RawTensor::from_data_1d(vec![4., 1.]);
+    let weight = RawTensor::from_data_1d(vec![2., 1.]);
+    let bias = RawTensor::from_data_1d(vec![0., 10.]);
+    let output = input.batch_norm(
+        Some((&mut running_mean, &mut running_var)),
+        Some(&weight),
+        Some(&bias),
+        false,
+        0.1,
+        0.,
+    );
+    assert_eq!(output.to_vec(), &[0., 1., 11., 12.]);
+    assert_eq!(running_mean.to_vec(), &[1., 2.]);
+}
+
+#[test]
+fn affine_backward_sums_over_each_group() {
+    let input = RawTensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![3, 2]);
+    let grad = RawTensor::from_data_and_shape(vec![1., 1., 2., 2., 1., 0.], vec![3, 2]);
+    let (weight_grad, bias_grad) = input.affine_backward(&grad, &crate::NormGroups::columns(2));
+    assert_eq!(weight_grad.to_vec(), &[12., 10.]);
+    assert_eq!(bias_grad.to_vec(), &[4., 3.]);
+}
@@ -0,0 +1,193 @@
+//! In place parameter updates used by the optimizers. The parameter, its gradient and the
+//! optimizer state are all updated on the device by a single kernel, without allocating.
+use crate::gpu_internals::shader_runner::{ShaderInputs, ThreadGroup};
+use crate::{GpuAllocated, GpuTensor, ShapeStrideTrait};
+use wgpu::ShaderModuleSource;
+
+#[cfg(test)]
+mod tests;
+
+/// Hyperparameters of a single SGD update
+#[derive(Debug, Clone, Copy)]
+pub struct SgdStep {
+    pub lr: f32,
+    pub weight_decay: f32,
+    pub momentum: f32,
+    pub dampening: f32,
+    pub nesterov: bool,
+    /// Number of the step being taken, starting at 1. The momentum buffer is initialized with
+    /// the gradient on the first step.
+    pub step: u32,
+}
+
+/// Hyperparameters of a single Adam update
+#[derive(Debug, Clone, Copy)]
+pub struct AdamStep {
+    pub lr: f32,
+    pub beta1: f32,
+    pub beta2: f32,
+    pub eps: f32,
+    pub weight_decay: f32,
+    /// Applies the weight decay directly to the parameter, as in AdamW, instead of adding it
+    /// to the gradient
+    pub decoupled_weight_decay: bool,
+    /// Number of the step being taken, starting at 1. Used for the bias correction.
+    pub step: u32,
+}
+
+/// Hyperparameters of a single RMSprop update
+#[derive(Debug, Clone, Copy)]
+pub struct RmsPropStep {
+    pub lr: f32,
+    pub alpha: f32,
+    pub eps: f32,
+    pub weight_decay: f32,
+    pub momentum: f32,
+}
+
+fn f32_bits(value: f32) -> u32 {
+    u32::from_ne_bytes(value.to_ne_bytes())
+}
+
+impl GpuTensor {
+    /// Updates this Tensor in place using SGD. The momentum buffer must be given if, and only
+    /// if, `step.momentum` is not 0. It has the same shape as this Tensor and its contents
+    /// are only read after the first step.
+    pub async fn sgd_step(&mut self, grad: &GpuTensor, momentum_buffer: Option<&mut GpuTensor>, step: &SgdStep) {
+        let mut push_constants = vec![f32_bits(step.lr), f32_bits(step.weight_decay)];
+        match momentum_buffer {
+            Some(momentum_buffer) => {
+                assert_ne!(step.momentum, 0., "A momentum buffer was given but momentum is 0");
+                push_constants.push(f32_bits(step.momentum));
+                push_constants.push(f32_bits(step.dampening));
+                push_constants.push(step.nesterov as u32);
+                push_constants.push((step.step <= 1) as u32);
+                self.run_step_kernel(
+                    wgpu::include_spirv!("sgd_momentum_step.spv"),
+                    grad,
+                    &mut [momentum_buffer],
+                    push_constants,
+                ).await;
+            }
+            None => {
+                assert_eq!(step.momentum, 0., "SGD with momentum requires a momentum buffer");
+                self.run_step_kernel(wgpu::include_spirv!("sgd_step.spv"), grad, &mut [], push_constants).await;
+            }
+        }
+    }
+
+    /// Updates this Tensor in place using Adam, or AdamW if `step.decoupled_weight_decay` is
+    /// set. The first and second moment estimates have the same shape as this Tensor and
+    /// start as zeros.
+    pub async fn adam_step(&mut self, grad: &GpuTensor, exp_avg: &mut GpuTensor, exp_avg_sq: &mut GpuTensor, step: &AdamStep) {
+        assert!(step.step >= 1, "Adam steps are numbered starting at 1");
+        let bias_correction1 = 1. - step.beta1.powi(step.step as i32);
+        let bias_correction2 = 1. - step.beta2.powi(step.step as i32);
+        let push_constants = vec![
+            f32_bits(step.lr),
+            f32_bits(step.beta1),
+            f32_bits(step.beta2),
+            f32_bits(step.eps),
+            f32_bits(step.weight_decay),
+            f32_bits(bias_correction1),
+            f32_bits(bias_correction2),
+            step.decoupled_weight_decay as u32,
+        ];
+        self.run_step_kernel(
+            wgpu::include_spirv!("adam_step.spv"),
+            grad,
+            &mut [exp_avg, exp_avg_sq],
+            push_constants,
+        ).await;
+    }
+
+    /// Updates this Tensor in place using RMSprop. The square average starts as zeros and the
+    /// momentum buffer must be given if, and only if, `step.momentum` is not 0.
+    pub async fn rmsprop_step(
+        &mut self,
+        grad: &GpuTensor,
+        square_avg: &mut GpuTensor,
+        momentum_buffer: Option<&mut GpuTensor>,
+        step: &RmsPropStep,
+    ) {
+        let mut push_constants = vec![
+            f32_bits(step.lr),
+            f32_bits(step.alpha),
+            f32_bits(step.eps),
+            f32_bits(step.weight_decay),
+        ];
+        match momentum_buffer {
+            Some(momentum_buffer) => {
+                assert_ne!(step.momentum, 0., "A momentum buffer was given but momentum is 0");
+                push_constants.push(f32_bits(step.momentum));
+                self.run_step_kernel(
+                    wgpu::include_spirv!("rmsprop_momentum_step.spv"),
+                    grad,
+                    &mut [square_avg, momentum_buffer],
+                    push_constants,
+                ).await;
+            }
+            None => {
+                assert_eq!(step.momentum, 0., "RMSprop with momentum requires a momentum buffer");
+                self.run_step_kernel(
+                    wgpu::include_spirv!("rmsprop_step.spv"),
+                    grad,
+                    &mut [square_avg],
+                    push_constants,
+                ).await;
+            }
+        }
+    }
+
+    /// Runs an update kernel binding, in order, this Tensor, its gradient and the state
+    /// Tensors, which must all have the same shape. This Tensor and the state are updated in
+    /// place, so the ones which are not contiguous are replaced by a contiguous copy first.
+    async fn run_step_kernel(
+        &mut self,
+        shader: ShaderModuleSource<'_>,
+        grad: &GpuTensor,
+        state: &mut [&mut GpuTensor],
+        push_constants: Vec<u32>,
+    ) {
+        assert_eq!(self.shape(), grad.shape(), "The gradient must have the same shape as the parameter");
+        for tensor in state.iter() {
+            assert_eq!(self.shape(), tensor.shape(), "The optimizer state must have the same shape as the parameter");
+        }
+        if self.is_empty() {
+            return;
+        }
+        if !self.is_contiguous() {
+            *self = self.contiguous().await;
+        }
+        for tensor in state.iter_mut() {
+            if !tensor.is_contiguous() {
+                **tensor = tensor.contiguous().await;
+            }
+        }
+        let grad_copy;
+        let grad = if grad.is_contiguous() {
+            grad
+        } else {
+            grad_copy = grad.contiguous().await;
+            &grad_copy
+        };
+        let gpu = self.gpu();
+        let cs_module = gpu.shader_from_file_bytes(shader);
+        let mut shader_inputs = ShaderInputs::default();
+        shader_inputs.append_buffer(self.buffer());
+        shader_inputs.append_buffer(grad.buffer());
+        for tensor in state.iter() {
+            shader_inputs.append_buffer(tensor.buffer());
+        }
+        shader_inputs.push_constants.data = push_constants;
+        gpu.run_shader(
+            &cs_module,
+            &shader_inputs,
+            ThreadGroup {
+                x: self.numel(),
+                y: 1,
+                z: 1,
+            },
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use crate::prelude::*;
+use crate::{AdamStep, GpuStore, GpuTensor, RmsPropStep, SgdStep};
+use std::collections::VecDeque;
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn sgd_step_test() {
+    let async_block = async {
+        let mut param = GpuTensor::from(vec![1., 2.], vec![2]);
+        let grad = GpuTensor::from(vec![0.5, -1.], vec![2]);
+        let step = SgdStep {
+            lr: 0.1,
+            weight_decay: 0.,
+            momentum: 0.,
+            dampening: 0.,
+            nesterov: false,
+            step: 1,
+        };
+        param.sgd_step(&grad, None, &step).await;
+        assert_close(param.to_cpu().raw_data_slice(), &[0.95, 2.1]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn sgd_step_with_strided_tensors() {
+    let async_block = async {
+        // Both are read column by column from their buffers
+        let gpu = GpuStore::get_default();
+        let strided = |data: [f32; 4]| {
+            GpuTensor::from_buffer_with_strides_and_offset(
+                gpu.gpu_buffer_from_data(bytemuck::cast_slice(&data)),
+                VecDeque::from(vec![2, 2]),
+                VecDeque::from(vec![1, 2]),
+                0,
+            )
+        };
+        let mut param = strided([1., 2., 3., 4.]);
+        let grad = strided([1., 0., -1., 2.]);
+        let step = SgdStep {
+            lr: 0.5,
+            weight_decay: 0.,
+            momentum: 0.,
+            dampening: 0.,
+            nesterov: false,
+            step: 1,
+        };
+        param.sgd_step(&grad, None, &step).await;
+        assert_close(param.to_cpu().as_contiguous_vec().as_slice(), &[0.5, 3.5, 2., 3.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn sgd_momentum_step_test() {
+    let async_block = async {
+        let mut param = GpuTensor::from(vec![1., 2.], vec![2]);
+        let mut buf = GpuTensor::from(vec![0., 0.], vec![2]);
+        let grad = GpuTensor::from(vec![1., -1.], vec![2]);
+        let mut step = SgdStep {
+            lr: 0.1,
+            weight_decay: 0.,
+            momentum: 0.9,
+            dampening: 0.,
+            nesterov: false,
+            step: 1,
+        };
+        param.sgd_step(&grad, Some(&mut buf), &step).await;
+        assert_close(buf.to_cpu().raw_data_slice(), &[1., -1.]);
+        step.step = 2;
+        param.sgd_step(&grad, Some(&mut buf), &step).await;
+        assert_close(buf.to_cpu().raw_data_slice(), &[1.9, -1.9]);
+        assert_close(param.to_cpu().raw_data_slice(), &[0.71, 2.29]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn adam_step_test() {
+    let async_block = async {
+        let mut param = GpuTensor::from(vec![1., 2.], vec![2]);
+        let mut exp_avg = GpuTensor::from(vec![0., 0.], vec![2]);
+        let mut exp_avg_sq = GpuTensor::from(vec![0., 0.], vec![2]);
+        let grad = GpuTensor::from(vec![0.5, -2.], vec![2]);
+        let step = AdamStep {
+            lr: 0.1,
+            beta1: 0.9,
+            beta2: 0.999,
+            eps: 1e-8,
+            weight_decay: 0.,
+            decoupled_weight_decay: false,
+            step: 1,
+        };
+        param.adam_step(&grad, &mut exp_avg, &mut exp_avg_sq, &step).await;
+        // The first bias corrected Adam step moves each parameter by lr against its gradient
+        assert_close(param.to_cpu().raw_data_slice(), &[0.9, 2.1]);
+        assert_close(exp_avg.to_cpu().raw_data_slice(), &[0.05, -0.2]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn rmsprop_step_test() {
+    let async_block = async {
+        let mut param = GpuTensor::from(vec![1.], vec![1]);
+        let mut square_avg = GpuTensor::from(vec![0.], vec![1]);
+        let grad = GpuTensor::from(vec![2.], vec![1]);
+        let step = RmsPropStep {
+            lr: 0.01,
+            alpha: 0.99,
+            eps: 0.,
+            weight_decay: 0.,
+            momentum: 0.,
+        };
+        param.rmsprop_step(&grad, &mut square_avg, None, &step).await;
+        // square_avg = 0.01 * 4, so the update is 0.01 * 2 / 0.2
+        assert_close(square_avg.to_cpu().raw_data_slice(), &[0.04]);
+        assert_close(param.to_cpu().raw_data_slice(), &[0.9]);
+    };
+    futures::executor::block_on(async_block);
+}
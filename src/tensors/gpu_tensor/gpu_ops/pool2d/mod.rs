@@ -0,0 +1,165 @@
+//! Pooling over the last two dimensions of `[batch, channels, height, width]` Tensors.
+//! The backward kernels compute, for each input element, the sum of the gradients of the
+//! windows containing it, so no atomics are needed.
+use crate::tensors::gpu_tensor::gpu_ops::conv2d::{assert_nchw, run_window_kernel};
+use crate::{GpuAllocated, GpuTensor, ShapeStrideTrait, Window2d};
+use std::collections::VecDeque;
+
+#[cfg(test)]
+mod tests;
+
+/// Shape of the pooled output and of the gradient of the input, given the input shape
+fn pooled_shape(input_shape: &VecDeque<usize>, out_h: usize, out_w: usize) -> VecDeque<usize> {
+    VecDeque::from(vec![input_shape[0], input_shape[1], out_h, out_w])
+}
+
+fn input_shape_of(grad: &GpuTensor, input_size: (usize, usize)) -> VecDeque<usize> {
+    assert_nchw(grad.shape());
+    VecDeque::from(vec![grad.shape()[0], grad.shape()[1], input_size.0, input_size.1])
+}
+
+fn assert_grad_shape(grad: &GpuTensor, input_shape: &VecDeque<usize>, out_h: usize, out_w: usize) {
+    assert_eq!(
+        grad.shape(),
+        &pooled_shape(input_shape, out_h, out_w),
+        "The gradient must have the shape of the pooled output"
+    );
+}
+
+fn avg_pool_push_constants(window: &Window2d, input_shape: &VecDeque<usize>, count_include_pad: bool) -> Vec<u32> {
+    assert_eq!(window.dilation, (1, 1), "Average pooling does not support dilation");
+    let mut push_constants = window.push_constants(input_shape);
+    push_constants.push(count_include_pad as u32);
+    push_constants
+}
+
+fn adaptive_push_constants(input_shape: &VecDeque<usize>, output_size: (usize, usize)) -> Vec<u32> {
+    assert!(
+        output_size.0 > 0 && output_size.1 > 0,
+        "The output size must be positive, got {:?}",
+        output_size
+    );
+    vec![
+        input_shape[2] as u32,
+        input_shape[3] as u32,
+        output_size.0 as u32,
+        output_size.1 as u32,
+    ]
+}
+
+impl GpuTensor {
+    /// Returns the maximum of each window, ignoring the padding, and its index inside its
+    /// `height * width` plane. The indices are used by [`GpuTensor::max_pool2d_backward`].
+    pub async fn max_pool2d(&self, window: &Window2d) -> (GpuTensor, GpuTensor) {
+        assert_nchw(self.shape());
+        let (out_h, out_w) = window.output_size(self.shape()[2], self.shape()[3]);
+        let output_shape = pooled_shape(self.shape(), out_h, out_w);
+        let numel = GpuTensor::numel_from_shape(&output_shape);
+        let output_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        let indices_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        let mut push_constants = window.push_constants(self.shape());
+        push_constants.push(0);
+        run_window_kernel(
+            wgpu::include_spirv!("max_pool2d.spv"),
+            &[self],
+            &[&output_buffer, &indices_buffer],
+            push_constants,
+            numel,
+        ).await;
+        (
+            GpuTensor::from_buffer(output_buffer, output_shape.clone()),
+            GpuTensor::from_buffer(indices_buffer, output_shape),
+        )
+    }
+
+    /// Given the gradient of the output of [`GpuTensor::max_pool2d`] and its indices, returns
+    /// the gradient of its input, whose `(height, width)` is `input_size`
+    pub async fn max_pool2d_backward(&self, indices: &GpuTensor, window: &Window2d, input_size: (usize, usize)) -> GpuTensor {
+        let input_shape = input_shape_of(self, input_size);
+        let (out_h, out_w) = window.output_size(input_size.0, input_size.1);
+        assert_grad_shape(self, &input_shape, out_h, out_w);
+        let numel = GpuTensor::numel_from_shape(&input_shape);
+        let output_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        let mut push_constants = window.push_constants(&input_shape);
+        push_constants.push(0);
+        run_window_kernel(
+            wgpu::include_spirv!("max_pool2d_backward.spv"),
+            &[self, indices],
+            &[&output_buffer],
+            push_constants,
+            numel,
+        ).await;
+        GpuTensor::from_buffer(output_buffer, input_shape)
+    }
+
+    /// Returns the average of each window. If `count_include_pad` is set, the padding zeros
+    /// are counted in the average.
+    pub async fn avg_pool2d(&self, window: &Window2d, count_include_pad: bool) -> GpuTensor {
+        assert_nchw(self.shape());
+        let (out_h, out_w) = window.output_size(self.shape()[2], self.shape()[3]);
+        let output_shape = pooled_shape(self.shape(), out_h, out_w);
+        let numel = GpuTensor::numel_from_shape(&output_shape);
+        let output_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        run_window_kernel(
+            wgpu::include_spirv!("avg_pool2d.spv"),
+            &[self],
+            &[&output_buffer],
+            avg_pool_push_constants(window, self.shape(), count_include_pad),
+            numel,
+        ).await;
+        GpuTensor::from_buffer(output_buffer, output_shape)
+    }
+
+    /// Given the gradient of the output of [`GpuTensor::avg_pool2d`], returns the gradient of
+    /// its input, whose `(height, width)` is `input_size`
+    pub async fn avg_pool2d_backward(&self, window: &Window2d, count_include_pad: bool, input_size: (usize, usize)) -> GpuTensor {
+        let input_shape = input_shape_of(self, input_size);
+        let (out_h, out_w) = window.output_size(input_size.0, input_size.1);
+        assert_grad_shape(self, &input_shape, out_h, out_w);
+        let numel = GpuTensor::numel_from_shape(&input_shape);
+        let output_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        run_window_kernel(
+            wgpu::include_spirv!("avg_pool2d_backward.spv"),
+            &[self],
+            &[&output_buffer],
+            avg_pool_push_constants(window, &input_shape, count_include_pad),
+            numel,
+        ).await;
+        GpuTensor::from_buffer(output_buffer, input_shape)
+    }
+
+    /// Averages windows chosen so the output has size `output_size`. The window of the
+    /// output row `i` spans the input rows `floor(i * height / out_h)` to
+    /// `ceil((i + 1) * height / out_h)`, and the same for the columns.
+    pub async fn adaptive_avg_pool2d(&self, output_size: (usize, usize)) -> GpuTensor {
+        assert_nchw(self.shape());
+        let output_shape = pooled_shape(self.shape(), output_size.0, output_size.1);
+        let numel = GpuTensor::numel_from_shape(&output_shape);
+        let output_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        run_window_kernel(
+            wgpu::include_spirv!("adaptive_avg_pool2d.spv"),
+            &[self],
+            &[&output_buffer],
+            adaptive_push_constants(self.shape(), output_size),
+            numel,
+        ).await;
+        GpuTensor::from_buffer(output_buffer, output_shape)
+    }
+
+    /// Given the gradient of the output of [`GpuTensor::adaptive_avg_pool2d`], returns the
+    /// gradient of its input, whose `(height, width)` is `input_size`
+    pub async fn adaptive_avg_pool2d_backward(&self, input_size: (usize, usize)) -> GpuTensor {
+        let input_shape = input_shape_of(self, input_size);
+        let output_size = (self.shape()[2], self.shape()[3]);
+        let numel = GpuTensor::numel_from_shape(&input_shape);
+        let output_buffer = self.gpu().empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        run_window_kernel(
+            wgpu::include_spirv!("adaptive_avg_pool2d_backward.spv"),
+            &[self],
+            &[&output_buffer],
+            adaptive_push_constants(&input_shape, output_size),
+            numel,
+        ).await;
+        GpuTensor::from_buffer(output_buffer, input_shape)
+    }
+}
@@ -0,0 +1,70 @@
+use crate::prelude::*;
+use crate::{CpuTensor, GpuTensor, Window2d};
+
+fn assert_close(actual: &CpuTensor, expected: &CpuTensor) {
+    assert_eq!(actual.shape(), expected.shape());
+    for (actual, expected) in actual.as_contiguous_vec().iter().zip(expected.as_contiguous_vec()) {
+        assert!((actual - expected).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+}
+
+fn window() -> Window2d {
+    Window2d {
+        kernel_size: (3, 2),
+        stride: (2, 2),
+        padding: (1, 1),
+        dilation: (1, 1),
+    }
+}
+
+#[test]
+fn max_pool2d_test() {
+    let async_block = async {
+        let input = CpuTensor::rand(vec![2, 3, 7, 5]);
+        let dilated = Window2d {
+            dilation: (2, 1),
+            ..window()
+        };
+        let (output, _indices) = input.to_gpu().max_pool2d(&dilated).await;
+        assert_close(&output.to_cpu(), &input.max_pool2d(&dilated));
+
+        let input = GpuTensor::from(vec![1., 4., 3., 2.], vec![1, 1, 2, 2]);
+        let window = Window2d::new((2, 2));
+        let (output, indices) = input.max_pool2d(&window).await;
+        assert_eq!(output.to_cpu().raw_data_slice(), &[4.]);
+        assert_eq!(indices.to_cpu().raw_data_slice(), &[1.]);
+        let grad = GpuTensor::from(vec![2.], vec![1, 1, 1, 1]);
+        let input_grad = grad.max_pool2d_backward(&indices, &window, (2, 2)).await;
+        assert_eq!(input_grad.to_cpu().raw_data_slice(), &[0., 2., 0., 0.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn avg_pool2d_test() {
+    let async_block = async {
+        let input = CpuTensor::rand(vec![2, 3, 7, 5]);
+        for &count_include_pad in &[true, false] {
+            let output = input.to_gpu().avg_pool2d(&window(), count_include_pad).await;
+            assert_close(&output.to_cpu(), &input.avg_pool2d(&window(), count_include_pad));
+        }
+        let grad = GpuTensor::from(vec![4.], vec![1, 1, 1, 1]);
+        let input_grad = grad.avg_pool2d_backward(&Window2d::new((2, 2)), true, (2, 2)).await;
+        assert_eq!(input_grad.to_cpu().raw_data_slice(), &[1., 1., 1., 1.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn adaptive_avg_pool2d_test() {
+    let async_block = async {
+        let input = CpuTensor::rand(vec![2, 3, 7, 5]);
+        let output = input.to_gpu().adaptive_avg_pool2d((3, 2)).await;
+        assert_close(&output.to_cpu(), &input.adaptive_avg_pool2d((3, 2)));
+        // Overlapping windows: both outputs average the middle element
+        let grad = GpuTensor::from(vec![2., 2.], vec![1, 1, 1, 2]);
+        let input_grad = grad.adaptive_avg_pool2d_backward((1, 3)).await;
+        assert_eq!(input_grad.to_cpu().raw_data_slice(), &[1., 2., 1.]);
+    };
+    futures::executor::block_on(async_block);
+}
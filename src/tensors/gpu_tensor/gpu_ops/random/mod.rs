@@ -0,0 +1,60 @@
+//! Random Tensors generated on the GPU by a counter-based Philox generator, see
+//! [`crate::random`]
+use crate::gpu_internals::shader_runner::{ShaderInputs, ThreadGroup};
+use crate::gpu_internals::GpuInstance;
+use crate::random::{next_state, Generator};
+use crate::{Distribution, GpuStore, GpuTensor, ShapeStrideTrait};
+use std::collections::VecDeque;
+
+#[cfg(test)]
+mod tests;
+
+impl GpuTensor {
+    /// Returns a Tensor of the given shape sampled from `distribution` on the default GPU,
+    /// advancing the offset of `generator`, or of the GPU random number generator if `None`
+    pub async fn random(shape: Vec<usize>, distribution: &Distribution, generator: Option<&mut Generator>) -> GpuTensor {
+        let gpu = GpuStore::get_default();
+        let (seed, offset) = next_state(generator, || gpu.next_rng_state());
+        GpuTensor::random_with_state(gpu, shape, distribution, seed, offset).await
+    }
+
+    /// Returns a Tensor of the given shape sampled from `distribution` using the given seed and
+    /// offset. Element `i` only depends on them and on `i`.
+    pub async fn random_with_state(
+        gpu: &GpuInstance,
+        shape: Vec<usize>,
+        distribution: &Distribution,
+        seed: u64,
+        offset: u64,
+    ) -> GpuTensor {
+        let shape = VecDeque::from(shape);
+        let numel = GpuTensor::numel_from_shape(&shape);
+        assert!(numel <= u32::MAX as usize, "Random Tensors can have at most 2^32 elements");
+        let output_buffer = gpu.empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        if numel != 0 {
+            let cs_module = gpu.shader_from_file_bytes(wgpu::include_spirv!("random.spv"));
+            let mut shader_inputs = ShaderInputs::default();
+            shader_inputs.append_buffer(&output_buffer);
+            shader_inputs.push_constants.data = vec![
+                seed as u32,
+                (seed >> 32) as u32,
+                offset as u32,
+                (offset >> 32) as u32,
+            ];
+            shader_inputs
+                .push_constants
+                .data
+                .extend_from_slice(&distribution.push_constants());
+            gpu.run_shader(
+                &cs_module,
+                &shader_inputs,
+                ThreadGroup {
+                    x: numel,
+                    y: 1,
+                    z: 1,
+                },
+            );
+        }
+        GpuTensor::from_buffer(output_buffer, shape)
+    }
+}
@@ -0,0 +1,73 @@
+use crate::prelude::*;
+use crate::random::Generator;
+use crate::{CpuTensor, Distribution, GpuStore, GpuTensor};
+
+async fn sample_with_state(distribution: &Distribution, seed: u64, offset: u64) -> Vec<f32> {
+    GpuTensor::random_with_state(GpuStore::get_default(), vec![3, 100], distribution, seed, offset)
+        .await
+        .to_cpu()
+        .as_contiguous_vec()
+}
+
+#[test]
+fn kernel_matches_cpu_philox() {
+    let async_block = async {
+        let seed = 0x1234_5678_9abc_def0;
+        let offset = (1 << 32) + 7;
+        let uniform = Distribution::Uniform { low: -1., high: 1. };
+        let normal = Distribution::Normal { mean: 1., std: 2. };
+        let uniform_values = sample_with_state(&uniform, seed, offset).await;
+        let normal_values = sample_with_state(&normal, seed, offset).await;
+        for index in 0..300 {
+            assert_eq!(uniform_values[index], uniform.sample(seed, offset, index as u32));
+            let expected = normal.sample(seed, offset, index as u32);
+            assert!((normal_values[index] - expected).abs() < 1e-3, "{} != {}", normal_values[index], expected);
+        }
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn offsets_give_different_values() {
+    let async_block = async {
+        let uniform = Distribution::Uniform { low: 0., high: 1. };
+        let first = sample_with_state(&uniform, 3, 0).await;
+        assert_eq!(first, sample_with_state(&uniform, 3, 0).await);
+        assert_ne!(first, sample_with_state(&uniform, 3, 1).await);
+        assert_ne!(first, sample_with_state(&uniform, 4, 0).await);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn discrete_distributions() {
+    let async_block = async {
+        let bernoulli = sample_with_state(&Distribution::Bernoulli { p: 0.25 }, 5, 0).await;
+        assert!(bernoulli.iter().all(|&value| value == 0. || value == 1.));
+        let ones = bernoulli.iter().sum::<f32>();
+        assert!((40. ..110.).contains(&ones), "{} ones out of 300", ones);
+        let ints = sample_with_state(&Distribution::RandInt { low: -2, high: 3 }, 5, 1).await;
+        for int in -2..3 {
+            assert!(ints.contains(&(int as f32)));
+        }
+        assert!(ints.iter().all(|&value| (-2. ..3.).contains(&value) && value.fract() == 0.));
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn generators_give_the_same_values_on_cpu_and_gpu() {
+    let async_block = async {
+        let mut gpu_generator = Generator::new(9);
+        let mut cpu_generator = gpu_generator.clone();
+        let uniform = Distribution::Uniform { low: 2., high: 5. };
+        for _ in 0..2 {
+            let gpu_values = GpuTensor::random(vec![4, 25], &uniform, Some(&mut gpu_generator))
+                .await
+                .to_cpu();
+            assert_eq!(gpu_values, CpuTensor::random(vec![4, 25], &uniform, Some(&mut cpu_generator)));
+        }
+        assert_eq!(gpu_generator.get_state().offset, 2);
+    };
+    futures::executor::block_on(async_block);
+}
@@ -0,0 +1,103 @@
+//! Fused softmax over the last dimension, with the scaling and the masks of attention scores.
+//! Each row is handled by one thread which subtracts its maximum before exponentiating, so
+//! large scores don't overflow.
+use crate::tensors::gpu_tensor::gpu_ops::conv2d::run_window_kernel;
+use crate::{GpuAllocated, GpuTensor, ShapeStrideTrait};
+
+#[cfg(test)]
+mod tests;
+
+fn float_bits(value: f32) -> u32 {
+    u32::from_ne_bytes(value.to_ne_bytes())
+}
+
+impl GpuTensor {
+    /// Softmax over the last dimension
+    pub async fn softmax(&self) -> GpuTensor {
+        self.masked_softmax(None, false, 1.).await
+    }
+
+    /// Softmax over the last dimension of `scale * self + mask`. The mask is added to the
+    /// scaled scores, so entries are masked out with `-inf`, and its shape must be a suffix of
+    /// the shape of this Tensor, the mask being broadcast over the leading dimensions. When
+    /// `causal` is set, the entries above the diagonal of the last two dimensions are masked
+    /// out too. Fully masked rows are set to zero.
+    pub async fn masked_softmax(&self, mask: Option<&GpuTensor>, causal: bool, scale: f32) -> GpuTensor {
+        assert!(self.rank() >= 1, "Can't take the softmax of a scalar");
+        let shape = self.shape();
+        let cols = shape[shape.len() - 1];
+        let queries = if shape.len() >= 2 { shape[shape.len() - 2] } else { 1 };
+        if let Some(mask) = mask {
+            let is_suffix = mask.rank() >= 1
+                && mask.rank() <= shape.len()
+                && shape.iter().skip(shape.len() - mask.rank()).eq(mask.shape().iter());
+            assert!(
+                is_suffix,
+                "The mask shape {:?} must match the trailing dimensions of {:?}",
+                mask.shape(),
+                shape
+            );
+        }
+        let no_mask;
+        let mask_copy;
+        let mask_tensor = match mask {
+            Some(mask) if mask.is_contiguous() => mask,
+            Some(mask) => {
+                mask_copy = mask.contiguous().await;
+                &mask_copy
+            }
+            None => {
+                no_mask = GpuTensor::new_filled(vec![1], 0.).await;
+                &no_mask
+            }
+        };
+        let mask_rows = mask.map_or(1, |mask| mask.numel() / cols.max(1));
+        let input_copy;
+        let input = if self.is_contiguous() {
+            self
+        } else {
+            input_copy = self.contiguous().await;
+            &input_copy
+        };
+        let push_constants = vec![
+            cols as u32,
+            mask_rows as u32,
+            queries as u32,
+            mask.is_some() as u32,
+            causal as u32,
+            float_bits(scale),
+        ];
+        let output_buffer = self.gpu().empty_gpu_buffer(self.numel() * std::mem::size_of::<f32>());
+        run_window_kernel(
+            wgpu::include_spirv!("masked_softmax.spv"),
+            &[input, mask_tensor],
+            &[&output_buffer],
+            push_constants,
+            self.numel() / cols.max(1),
+        ).await;
+        GpuTensor::from_buffer(output_buffer, shape.clone())
+    }
+
+    /// Given the output of [`GpuTensor::masked_softmax`] in this Tensor and the gradient of
+    /// that output, returns the gradient of the scores `self` was computed from
+    pub async fn masked_softmax_backward(&self, grad: &GpuTensor, scale: f32) -> GpuTensor {
+        assert_eq!(self.shape(), grad.shape(), "The gradient must have the shape of the softmax output");
+        let cols = self.shape()[self.rank() - 1];
+        let grad_copy;
+        let grad = if grad.is_contiguous() {
+            grad
+        } else {
+            grad_copy = grad.contiguous().await;
+            &grad_copy
+        };
+        let output_buffer = self.gpu().empty_gpu_buffer(self.numel() * std::mem::size_of::<f32>());
+        run_window_kernel(
+            wgpu::include_spirv!("softmax_backward.spv"),
+            &[self, grad],
+            &[&output_buffer],
+            vec![cols as u32, float_bits(scale)],
+            self.numel() / cols.max(1),
+        ).await;
+        GpuTensor::from_buffer(output_buffer, self.shape().clone())
+    }
+}
@@ -0,0 +1,62 @@
+use crate::prelude::*;
+use crate::{CpuTensor, GpuTensor};
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn softmax_is_stable_and_masks_out_entries() {
+    let async_block = async {
+        let e = std::f32::consts::E;
+        let scores = GpuTensor::from(vec![1000., 1001., 0., 1.], vec![2, 2]);
+        let expected = [1. / (1. + e), e / (1. + e)];
+        let output = scores.softmax().await.to_cpu().as_contiguous_vec();
+        assert_close(&output, &[expected, expected].concat());
+
+        // The mask is broadcast over the rows, and fully masked rows are zeros
+        let scores = GpuTensor::from(vec![0., 2., 4., 0., 2., 4.], vec![2, 3]);
+        let mask = GpuTensor::from(vec![0., f32::NEG_INFINITY, 0.], vec![3]);
+        let output = scores.masked_softmax(Some(&mask), false, 0.5).await.to_cpu().as_contiguous_vec();
+        let expected = [1. / (1. + e * e), 0., e * e / (1. + e * e)];
+        assert_close(&output, &[expected, expected].concat());
+        let mask = GpuTensor::from(vec![0.; 3], vec![1, 3]);
+        let all_masked = GpuTensor::from(vec![f32::NEG_INFINITY; 3], vec![1, 3]);
+        let mask = GpuTensor::cat(&[&mask, &all_masked], 0).await;
+        let output = scores.masked_softmax(Some(&mask), false, 1.).await.to_cpu().as_contiguous_vec();
+        assert_eq!(&output[3..], &[0., 0., 0.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn causal_softmax_masks_the_future() {
+    let async_block = async {
+        let scores = GpuTensor::from(CpuTensor::rand(vec![2, 3, 3]).as_contiguous_vec(), vec![2, 3, 3]);
+        let output = scores.masked_softmax(None, true, 1.).await.to_cpu().as_contiguous_vec();
+        for matrix in output.chunks(9) {
+            assert_eq!(matrix[0], 1.);
+            assert_eq!(&matrix[1..3], &[0., 0.]);
+            assert_eq!(matrix[5], 0.);
+            for row in matrix.chunks(3) {
+                assert!((row.iter().sum::<f32>() - 1.).abs() < 1e-5);
+            }
+        }
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn softmax_backward() {
+    let async_block = async {
+        // The gradient of a constant times the output is zero, since the output sums to one
+        let output = GpuTensor::from(vec![0.25, 0.75, 0.5, 0.5], vec![2, 2]);
+        let grad = GpuTensor::from(vec![3., 3., 1., -1.], vec![2, 2]);
+        let scores_grad = output.masked_softmax_backward(&grad, 2.).await.to_cpu().as_contiguous_vec();
+        assert_close(&scores_grad, &[0., 0., 1., -1.]);
+    };
+    futures::executor::block_on(async_block);
+}
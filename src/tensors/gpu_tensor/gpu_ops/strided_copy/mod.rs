@@ -0,0 +1,51 @@
+use crate::gpu_internals::shader_runner::{ThreadGroup};
+use crate::{GpuTensor, ShapeStrides, ShapeStrideTrait, GpuAllocated, AsShaderInput};
+use std::collections::VecDeque;
+
+#[cfg(test)]
+mod tests;
+
+impl GpuTensor {
+    /// Copies the elements of the view described by `view` over this Tensor buffer into a new
+    /// contiguous Tensor. Used to materialize broadcasts, slices and permutations.
+    pub async fn strided_copy(&self, view: &ShapeStrides) -> GpuTensor {
+        let numel = GpuTensor::numel_from_shape(&view.shape);
+        let gpu = self.gpu();
+        if numel == 0 {
+            return GpuTensor::from_buffer(gpu.empty_gpu_buffer(0), VecDeque::new());
+        }
+        let cs_module = gpu.shader_from_file_bytes(wgpu::include_spirv!("strided_copy.spv"));
+        let output_buffer = gpu.empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        let mut shader_inputs = self.to_shader_inputs().with_buffer(&output_buffer);
+        shader_inputs.push_constants.data.clear();
+        push_shape_strides(&mut shader_inputs.push_constants.data, view);
+        shader_inputs.push_constants.data.push(view.offset as u32);
+        gpu.run_shader(
+            &cs_module,
+            &shader_inputs,
+            ThreadGroup {
+                x: numel,
+                y: 1,
+                z: 1,
+            },
+        );
+        GpuTensor::from_buffer(output_buffer, view.shape.clone())
+    }
+
+    /// Returns a contiguous copy of this Tensor
+    pub async fn contiguous(&self) -> GpuTensor {
+        self.strided_copy(self.dim_strides()).await
+    }
+}
+
+/// Pushes the shape and strides using the same layout as [`AsShaderInput::to_shader_inputs`]
+pub fn push_shape_strides(data: &mut Vec<u32>, shape_strides: &ShapeStrides) {
+    assert!(shape_strides.rank() <= 8, "Tensors can have at most 8 dimensions");
+    let mut shape: Vec<u32> = shape_strides.shape.iter().map(|&e| e as u32).collect();
+    let mut strides: Vec<u32> = shape_strides.strides.iter().map(|&e| e as u32).collect();
+    shape.resize(8, 0);
+    strides.resize(8, 0);
+    data.push(shape_strides.rank() as u32);
+    data.extend_from_slice(shape.as_slice());
+    data.extend_from_slice(strides.as_slice());
+}
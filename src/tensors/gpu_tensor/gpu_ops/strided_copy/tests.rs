@@ -0,0 +1,25 @@
+use crate::prelude::*;
+use crate::{GpuTensor, ShapeStrides};
+
+#[test]
+fn strided_copy_of_transposed_view() {
+    let async_block = async {
+        let tensor = GpuTensor::from(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+        let view = ShapeStrides::from_shape_and_strides_vec(vec![3, 2], vec![1, 3]);
+        let copy = tensor.strided_copy(&view).await;
+        assert_eq!(copy.shape(), &[3, 2]);
+        assert_eq!(copy.to_cpu().raw_data_slice(), &[1., 4., 2., 5., 3., 6.]);
+    };
+    futures::executor::block_on(async_block);
+}
+
+#[test]
+fn strided_copy_with_zero_strides() {
+    let async_block = async {
+        let tensor = GpuTensor::from(vec![1., 2., 3.], vec![3]);
+        let view = ShapeStrides::from_shape_and_strides_vec(vec![2, 3], vec![0, 1]);
+        let copy = tensor.strided_copy(&view).await;
+        assert_eq!(copy.to_cpu().raw_data_slice(), &[1., 2., 3., 1., 2., 3.]);
+    };
+    futures::executor::block_on(async_block);
+}
@@ -0,0 +1,34 @@
+use crate::gpu_internals::shader_runner::{ThreadGroup};
+use crate::tensors::gpu_tensor::gpu_ops::strided_copy::push_shape_strides;
+use crate::{GpuTensor, ShapeStrideTrait, GpuAllocated, AsShaderInput};
+
+#[cfg(test)]
+mod tests;
+
+impl GpuTensor {
+    /// Sums the elements along the dimension `dim`. The output has the same rank as the
+    /// input, with the `dim` dimension of size 1.
+    pub async fn sum_dim(&self, dim: usize) -> GpuTensor {
+        assert!(dim < self.rank(), "Tried to sum over dimension {} of a rank {} Tensor", dim, self.rank());
+        let gpu = self.gpu();
+        let cs_module = gpu.shader_from_file_bytes(wgpu::include_spirv!("sum_dim.spv"));
+        let mut output_shape = self.shape().clone();
+        output_shape[dim] = 1;
+        let numel = GpuTensor::numel_from_shape(&output_shape);
+        let output_buffer = gpu.empty_gpu_buffer(numel * std::mem::size_of::<f32>());
+        let mut shader_inputs = self.to_shader_inputs().with_buffer(&output_buffer);
+        shader_inputs.push_constants.data.clear();
+        push_shape_strides(&mut shader_inputs.push_constants.data, self.dim_strides());
+        shader_inputs.push_constants.data.push(dim as u32);
+        gpu.run_shader(
+            &cs_module,
+            &shader_inputs,
+            ThreadGroup {
+                x: numel,
+                y: 1,
+                z: 1,
+            },
+        );
+        GpuTensor::from_buffer(output_buffer, output_shape)
+    }
+}
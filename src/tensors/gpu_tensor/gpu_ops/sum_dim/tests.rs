@@ -0,0 +1,16 @@
+use crate::prelude::*;
+use crate::GpuTensor;
+
+#[test]
+fn sum_dim_test() {
+    let async_block = async {
+        let tensor = GpuTensor::from(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+        let rows = tensor.sum_dim(0).await;
+        assert_eq!(rows.shape(), &[1, 3]);
+        assert_eq!(rows.to_cpu().raw_data_slice(), &[5., 7., 9.]);
+        let cols = tensor.sum_dim(1).await;
+        assert_eq!(cols.shape(), &[2, 1]);
+        assert_eq!(cols.to_cpu().raw_data_slice(), &[6., 15.]);
+    };
+    futures::executor::block_on(async_block);
+}
@@ -30,6 +30,8 @@ macro_rules! bin_element_wise_unary_op {
 bin_element_wise_unary_op!("exp", exp, "exp.spv");
 bin_element_wise_unary_op!("ln", ln, "ln.spv");
 bin_element_wise_unary_op!("clone", clone, "clone.spv");
+bin_element_wise_unary_op!("sigmoid", sigmoid, "sigmoid.spv");
+bin_element_wise_unary_op!("tanh", tanh, "tanh.spv");
 
 mod test {
     use crate::{GpuTensor, CpuTransferable};
@@ -84,5 +86,18 @@ mod test {
         futures::executor::block_on(async_block);
     }
 
-}
-
+    #[test]
+    fn sigmoid_and_tanh_test() {
+        let async_block = async {
+            let data = vec![-100., -2., 0., 0.5, 3., 100.];
+            let tensor = GpuTensor::from(data.clone(), vec![2, 3]);
+            let sigmoid = tensor.sigmoid().await.to_cpu();
+            let tanh = tensor.tanh().await.to_cpu();
+            for (i, x) in data.iter().enumerate() {
+                assert!((sigmoid.raw_data_slice()[i] - 1. / (1. + (-x).exp())).abs() < 1e-6);
+                assert!((tanh.raw_data_slice()[i] - x.tanh()).abs() < 1e-6);
+            }
+        };
+        futures::executor::block_on(async_block);
+    }
+}
\ No newline at end of file
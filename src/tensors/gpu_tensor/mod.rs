@@ -1,14 +1,21 @@
 mod accessors_contructors;
 use crate::gpu_internals::gpu_buffers::GpuBuffer;
 use crate::tensors::gpu_tensor::utils::strides_from_deque_shape;
+use crate::ShapeStrideTrait;
 pub use indexing::SliceRangeInfo;
 use std::collections::VecDeque;
 
 pub mod traits;
 pub use traits::*;
 mod gpu_ops;
+pub use gpu_ops::{
+    AdamStep, Conv1dParams, Conv2dParams, ConvTranspose1dParams, ConvTranspose2dParams, NormGroups, PointwiseLoss,
+    RmsPropStep, SgdStep, Window2d,
+};
+pub(crate) use gpu_ops::{Conv2dGeometry, ConvTranspose2dGeometry};
 mod indexing;
 mod shape_changing;
+pub use shape_changing::{broadcast_shape_and_stride, broadcast_shapes};
 pub mod utils;
 
 pub struct GpuTensor {
@@ -88,5 +95,19 @@ impl ShapeStrides {
     }
 }
 
+impl ShapeStrideTrait for ShapeStrides {
+    fn shape(&self) -> &VecDeque<usize> {
+        &self.shape
+    }
+
+    fn strides(&self) -> &VecDeque<usize> {
+        &self.strides
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
 #[cfg(test)]
 mod tests;
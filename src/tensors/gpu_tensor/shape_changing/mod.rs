@@ -1,7 +1,10 @@
 use crate::{GpuTensor, ShapeStrides, ShapeStrideTrait};
+use std::collections::VecDeque;
 #[cfg(test)]
 mod broadcast_tests;
 #[cfg(test)]
+mod permute_tests;
+#[cfg(test)]
 mod slicing_tests;
 
 impl GpuTensor {
@@ -22,3 +25,108 @@ impl GpuTensor {
     }
 
 }
+
+impl ShapeStrides {
+    /// Returns the shape and strides of a view of this Tensor broadcasted into `shape`.
+    /// Dimensions are aligned from the right, new leading dimensions and dimensions of size 1
+    /// which are broadcasted get a stride of 0, so no data is copied.
+    pub fn broadcast_to(&self, shape: &VecDeque<usize>) -> Result<ShapeStrides, String> {
+        if shape.len() < self.rank() {
+            return Err(format!("Can't broadcast shape {:?} into smaller rank shape {:?}", self.shape, shape));
+        }
+        let new_dims = shape.len() - self.rank();
+        let mut strides = VecDeque::with_capacity(shape.len());
+        for (i, &target) in shape.iter().enumerate() {
+            if i < new_dims {
+                strides.push_back(0);
+                continue;
+            }
+            let original = self.shape[i - new_dims];
+            if original == target {
+                strides.push_back(self.strides[i - new_dims]);
+            } else if original == 1 {
+                strides.push_back(0);
+            } else {
+                return Err(format!("Can't broadcast shape {:?} into {:?}", self.shape, shape));
+            }
+        }
+        Ok(ShapeStrides::from_shape_and_strides_and_offset(shape.clone(), strides, self.offset))
+    }
+}
+
+impl ShapeStrides {
+    /// Returns the shape and strides of a view of this Tensor with its dimensions reordered,
+    /// dimension `i` of the view being dimension `dims[i]` of this one. No data is copied.
+    pub fn permute(&self, dims: &[usize]) -> Result<ShapeStrides, String> {
+        let mut sorted = dims.to_vec();
+        sorted.sort_unstable();
+        if sorted != (0..self.rank()).collect::<Vec<usize>>() {
+            return Err(format!("{:?} is not a permutation of the dimensions of shape {:?}", dims, self.shape));
+        }
+        let shape = dims.iter().map(|&dim| self.shape[dim]).collect();
+        let strides = dims.iter().map(|&dim| self.strides[dim]).collect();
+        Ok(ShapeStrides::from_shape_and_strides_and_offset(shape, strides, self.offset))
+    }
+}
+
+/// Returns the shape both shapes broadcast to, following the NumPy rules: dimensions are
+/// compared from the right, and must either be equal or one of them be 1.
+pub fn broadcast_shapes(a: &[usize], b: &[usize]) -> Result<VecDeque<usize>, String> {
+    let rank = a.len().max(b.len());
+    let mut shape = VecDeque::with_capacity(rank);
+    for i in 0..rank {
+        // missing leading dimensions behave as 1
+        let a_dim = if i < rank - a.len() { 1 } else { a[i - (rank - a.len())] };
+        let b_dim = if i < rank - b.len() { 1 } else { b[i - (rank - b.len())] };
+        if a_dim == b_dim || b_dim == 1 {
+            shape.push_back(a_dim);
+        } else if a_dim == 1 {
+            shape.push_back(b_dim);
+        } else {
+            return Err(format!("Shapes {:?} and {:?} can not be broadcast together", a, b));
+        }
+    }
+    Ok(shape)
+}
+
+/// Broadcasts both shapes and strides against each other. If `skip_last` is given, that
+/// many trailing dimensions are left untouched, which is useful when they are the matrix
+/// dimensions of a batch matrix multiplication.
+pub fn broadcast_shape_and_stride(
+    a: &ShapeStrides,
+    b: &ShapeStrides,
+    skip_last: Option<usize>,
+) -> Result<(ShapeStrides, ShapeStrides), String> {
+    let skip = skip_last.unwrap_or(0);
+    if a.rank() < skip || b.rank() < skip {
+        return Err(format!("Can't skip {} dimensions of shapes {:?} and {:?}", skip, a.shape, b.shape));
+    }
+    let a_shape = Vec::from(a.shape.clone());
+    let b_shape = Vec::from(b.shape.clone());
+    let leading = broadcast_shapes(&a_shape[..a.rank() - skip], &b_shape[..b.rank() - skip])?;
+    let target = |own_shape: &[usize]| -> VecDeque<usize> {
+        let mut target = leading.clone();
+        target.extend(own_shape[own_shape.len() - skip..].iter());
+        target
+    };
+    Ok((a.broadcast_to(&target(&a_shape))?, b.broadcast_to(&target(&b_shape))?))
+}
+
+impl GpuTensor {
+    /// Returns a new contiguous Tensor with this one broadcasted into `shape`,
+    /// see [`ShapeStrides::broadcast_to`]. Panics if the shapes are not compatible.
+    pub async fn broadcast_to(&self, shape: Vec<usize>) -> GpuTensor {
+        let view = self
+            .shape_strides
+            .broadcast_to(&VecDeque::from(shape))
+            .unwrap_or_else(|err| panic!("{}", err));
+        self.strided_copy(&view).await
+    }
+
+    /// Returns a new contiguous Tensor with the dimensions reordered, see
+    /// [`ShapeStrides::permute`]. Panics if `dims` is not a permutation of the dimensions.
+    pub async fn permute(&self, dims: &[usize]) -> GpuTensor {
+        let view = self.shape_strides.permute(dims).unwrap_or_else(|err| panic!("{}", err));
+        self.strided_copy(&view).await
+    }
+}
@@ -0,0 +1,10 @@
+use crate::ShapeStrides;
+
+#[test]
+pub fn permute_reorders_shape_and_strides() {
+    let view = ShapeStrides::from_shape_vec(vec![2, 3, 4]).permute(&[2, 0, 1]).unwrap();
+    assert_eq!(view.shape, [4, 2, 3]);
+    assert_eq!(view.strides, [1, 12, 4]);
+    assert!(ShapeStrides::from_shape_vec(vec![2, 3]).permute(&[0, 0]).is_err());
+    assert!(ShapeStrides::from_shape_vec(vec![2, 3]).permute(&[1]).is_err());
+}
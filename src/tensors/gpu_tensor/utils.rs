@@ -18,7 +18,7 @@ pub fn strides_from_deque_shape(shape: &VecDeque<usize>) -> VecDeque<usize> {
 pub fn can_calc_strides_from_shape() {
     assert_eq!(
         strides_from_deque_shape(&VecDeque::new()),
-        []
+        [0usize; 0]
     );
     assert_eq!(
         strides_from_deque_shape(&VecDeque::from(vec![2, 2])),
@@ -3,7 +3,12 @@
 
 mod cpu_tensor;
 mod gpu_tensor;
+mod cpu_reference;
+#[cfg(feature = "ndarray")]
+mod ndarray_impls;
 use blocking::block_on;
+use crate::random::{self, next_state, Generator};
+use crate::{Distribution, GpuStore};
 pub use cpu_tensor::*;
 pub use gpu_tensor::*;
 pub mod traits;
@@ -116,7 +121,7 @@ impl RawTensor {
     }
 
     /// Returns a N dimensional [`Tensor`] with the given shape filled with random values
-    /// between 0 and 1.
+    /// between 0 and 1. Same as [`RawTensor::uniform`] from 0 to 1 without a generator.
     ///
     /// # Examples
     ///
@@ -132,9 +137,116 @@ impl RawTensor {
     ///
     /// ```
     pub fn rand(shape: Vec<usize>) -> Self {
+        Self::uniform(shape, 0., 1., None)
+    }
+
+    /// Same as [`RawTensor::rand`], sampling with `generator`
+    pub fn rand_with(shape: Vec<usize>, generator: &mut Generator) -> Self {
+        Self::uniform(shape, 0., 1., Some(generator))
+    }
+
+    /// Returns a N dimensional [`Tensor`] with the given shape filled with values sampled from
+    /// the standard normal distribution. Same as [`RawTensor::normal`] with mean 0 and
+    /// standard deviation 1 without a generator.
+    pub fn randn(shape: Vec<usize>) -> Self {
+        Self::normal(shape, 0., 1., None)
+    }
+
+    /// Same as [`RawTensor::randn`], sampling with `generator`
+    pub fn randn_with(shape: Vec<usize>, generator: &mut Generator) -> Self {
+        Self::normal(shape, 0., 1., Some(generator))
+    }
+
+    /// Returns a N dimensional [`Tensor`] with the given shape filled with values sampled
+    /// uniformly from `[low, high)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::random::Generator;
+    /// use tensor_compute::RawTensor;
+    /// let mut generator = Generator::new(42);
+    /// let first = RawTensor::uniform(vec![4], -1., 1., Some(&mut generator));
+    /// generator.manual_seed(42);
+    /// let second = RawTensor::uniform(vec![4], -1., 1., Some(&mut generator));
+    /// assert_eq!(first.to_vec(), second.to_vec());
+    /// ```
+    pub fn uniform(shape: Vec<usize>, low: f32, high: f32, generator: Option<&mut Generator>) -> Self {
+        Self::random(shape, &Distribution::Uniform { low, high }, generator)
+    }
+
+    /// Returns a N dimensional [`Tensor`] with the given shape filled with values sampled from
+    /// the normal distribution with the given mean and standard deviation.
+    pub fn normal(shape: Vec<usize>, mean: f32, std: f32, generator: Option<&mut Generator>) -> Self {
+        Self::random(shape, &Distribution::Normal { mean, std }, generator)
+    }
+
+    /// Returns a N dimensional [`Tensor`] with the given shape filled with ones with
+    /// probability `p` and zeros otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let tensor = RawTensor::bernoulli(vec![2, 3], 1.);
+    /// assert_eq!(tensor.to_vec(), &[1.; 6]);
+    /// ```
+    pub fn bernoulli(shape: Vec<usize>, p: f32) -> Self {
+        Self::random(shape, &Distribution::Bernoulli { p }, None)
+    }
+
+    /// Same as [`RawTensor::bernoulli`], sampling with `generator`
+    pub fn bernoulli_with(shape: Vec<usize>, p: f32, generator: &mut Generator) -> Self {
+        Self::random(shape, &Distribution::Bernoulli { p }, Some(generator))
+    }
+
+    /// Returns a N dimensional [`Tensor`] with the given shape filled with integers sampled
+    /// uniformly from `[low, high)`.
+    pub fn randint(shape: Vec<usize>, low: i32, high: i32) -> Self {
+        Self::random(shape, &Distribution::RandInt { low, high }, None)
+    }
+
+    /// Same as [`RawTensor::randint`], sampling with `generator`
+    pub fn randint_with(shape: Vec<usize>, low: i32, high: i32, generator: &mut Generator) -> Self {
+        Self::random(shape, &Distribution::RandInt { low, high }, Some(generator))
+    }
+
+    /// Returns a one dimensional [`Tensor`] holding a random permutation of `0..n`. The
+    /// permutation is drawn on the CPU, see [`CpuTensor::randperm`], using the GPU random
+    /// number generator if `generator` is `None`.
+    pub fn randperm(n: usize, generator: Option<&mut Generator>) -> Self {
+        let (seed, offset) = next_state(generator, || GpuStore::get_default().next_rng_state());
+        let permutation = random::permutation(n, seed, offset).into_iter().map(|i| i as f32).collect();
+        Self::from_data_and_shape(permutation, vec![n])
+    }
+
+    /// Returns a N dimensional [`Tensor`] with the given shape sampled from `distribution`,
+    /// using `generator` or, if `None`, the default GPU random number generator, see
+    /// [`crate::manual_seed`].
+    pub fn random(shape: Vec<usize>, distribution: &Distribution, generator: Option<&mut Generator>) -> Self {
+        assert!(!shape.is_empty(), "Shape cant be empty!");
         Self {
-            actual_tensor: CpuTensor::rand(shape).to_gpu(),
+            actual_tensor: block_on(GpuTensor::random(shape, distribution, generator)),
+        }
+    }
+
+    /// Returns the scaled mask applied by dropout: each element is zero with probability `p`
+    /// and `1 / (1 - p)` otherwise, so the expected value of the masked input is unchanged.
+    pub fn dropout_mask(shape: Vec<usize>, p: f32) -> Self {
+        assert!((0. ..=1.).contains(&p), "The dropout probability must be in [0, 1], got {}", p);
+        if p == 1. {
+            return Self::zeros(shape);
+        }
+        Self::bernoulli(shape, 1. - p).mul_scalar(1. / (1. - p))
+    }
+
+    /// Zeroes each element with probability `p` and scales the others by `1 / (1 - p)` if
+    /// `training` is set, otherwise returns a copy of this Tensor.
+    pub fn dropout(&self, p: f32, training: bool) -> RawTensor {
+        if !training || p == 0. {
+            return self.clone();
         }
+        self.dot_mul(&Self::dropout_mask(Vec::from(self.shape().clone()), p))
     }
 
     /// Returns a [`Tensor`] filled with zeros with same shape as the input [`Tensor`]
@@ -340,6 +452,18 @@ impl RawTensor {
         }
     }
 
+    pub fn mul_scalar(&self, scalar: f32) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.mul_scalar(scalar))
+        }
+    }
+
+    pub fn div_scalar(&self, scalar: f32) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.div_scalar(scalar))
+        }
+    }
+
 
     pub fn sum(&self) -> RawTensor {
         RawTensor {
@@ -365,6 +489,498 @@ impl RawTensor {
         }
     }
 
+    /// Applies the logistic function `1 / (1 + e^-x)` to each element
+    pub fn sigmoid(&self) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.sigmoid())
+        }
+    }
+
+    /// Applies the hyperbolic tangent to each element
+    pub fn tanh(&self) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.tanh())
+        }
+    }
+
+    /// Concatenates the [`Tensor`]s along `dim`, see [`GpuTensor::cat`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let left = RawTensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![2, 2]);
+    /// let right = RawTensor::from_data_and_shape(vec![5., 6.], vec![2, 1]);
+    /// let result = RawTensor::cat(&[&left, &right], 1);
+    /// assert_eq!(result.to_vec(), &[1., 2., 5., 3., 4., 6.]);
+    /// assert_eq!(result.narrow(1, 2, 1).to_vec(), &[5., 6.]);
+    /// ```
+    pub fn cat(tensors: &[&RawTensor], dim: usize) -> RawTensor {
+        let tensors: Vec<&GpuTensor> = tensors.iter().map(|tensor| &tensor.actual_tensor).collect();
+        RawTensor {
+            actual_tensor: block_on(GpuTensor::cat(&tensors, dim))
+        }
+    }
+
+    /// Returns a copy of the entries `[start, start + len)` along `dim`
+    pub fn narrow(&self, dim: usize, start: usize, len: usize) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.narrow(dim, start, len))
+        }
+    }
+
+    /// Softmax over the last dimension
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let scores = RawTensor::from_data_and_shape(vec![0., 0., 1000., 1000.], vec![2, 2]);
+    /// assert_eq!(scores.softmax().to_vec(), &[0.5, 0.5, 0.5, 0.5]);
+    /// ```
+    pub fn softmax(&self) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.softmax())
+        }
+    }
+
+    /// Softmax of scaled and masked scores, see [`GpuTensor::masked_softmax`]
+    pub fn masked_softmax(&self, mask: Option<&RawTensor>, causal: bool, scale: f32) -> RawTensor {
+        let mask = mask.map(|mask| &mask.actual_tensor);
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.masked_softmax(mask, causal, scale))
+        }
+    }
+
+    /// See [`GpuTensor::masked_softmax_backward`]
+    pub fn masked_softmax_backward(&self, grad: &RawTensor, scale: f32) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.masked_softmax_backward(&grad.actual_tensor, scale))
+        }
+    }
+
+    /// Sums the elements along the dimension `dim`, which is kept with size 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let tensor = RawTensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+    /// let sum = tensor.sum_dim(0);
+    /// assert_eq!(sum.shape(), &[1, 3]);
+    /// assert_eq!(sum.to_vec(), &[5., 7., 9.]);
+    /// ```
+    pub fn sum_dim(&self, dim: usize) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.sum_dim(dim))
+        }
+    }
+
+    /// Returns a new [`Tensor`] with `self` broadcasted into the given shape. Dimensions are
+    /// aligned from the right and must either match or be 1 in `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let tensor = RawTensor::from_data_1d(vec![1., 2., 3.]);
+    /// let broadcasted = tensor.broadcast_to(vec![2, 3]);
+    /// assert_eq!(broadcasted.to_vec(), &[1., 2., 3., 1., 2., 3.]);
+    /// ```
+    pub fn broadcast_to(&self, shape: Vec<usize>) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.broadcast_to(shape))
+        }
+    }
+
+    /// Returns the loss of each element given the target, see [`GpuTensor::pointwise_loss`]
+    pub fn pointwise_loss(&self, target: &RawTensor, loss: PointwiseLoss) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.pointwise_loss(&target.actual_tensor, loss))
+        }
+    }
+
+    /// See [`GpuTensor::pointwise_loss_backward`]
+    pub fn pointwise_loss_backward(&self, target: &RawTensor, grad: &RawTensor, loss: PointwiseLoss) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.pointwise_loss_backward(
+                &target.actual_tensor,
+                &grad.actual_tensor,
+                loss,
+            ))
+        }
+    }
+
+    /// Returns the negative log likelihood of the target class of each sample, see
+    /// [`GpuTensor::class_loss`]
+    pub fn class_loss(&self, targets: &RawTensor, log_softmax: bool) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.class_loss(&targets.actual_tensor, log_softmax))
+        }
+    }
+
+    /// See [`GpuTensor::class_loss_backward`]
+    pub fn class_loss_backward(&self, targets: &RawTensor, grad: &RawTensor, log_softmax: bool) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.class_loss_backward(
+                &targets.actual_tensor,
+                &grad.actual_tensor,
+                log_softmax,
+            ))
+        }
+    }
+
+    /// Copies each window into a column, see [`GpuTensor::im2col`]
+    pub fn im2col(&self, window: &Window2d) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.im2col(window))
+        }
+    }
+
+    /// Adds the columns back into an image, see [`GpuTensor::col2im`]
+    pub fn col2im(&self, window: &Window2d, image_size: (usize, usize, usize)) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.col2im(window, image_size))
+        }
+    }
+
+    /// 2D convolution of a `[batch, channels, height, width]` Tensor, see [`GpuTensor::conv2d`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::{Conv2dParams, RawTensor};
+    /// let image = RawTensor::from_data_and_shape((1..=9).map(|x| x as f32).collect(), vec![1, 1, 3, 3]);
+    /// let kernel = RawTensor::from_data_and_shape(vec![1., 0., 0., -1.], vec![1, 1, 2, 2]);
+    /// let output = image.conv2d(&kernel, None, &Conv2dParams::default());
+    /// assert_eq!(output.to_vec(), &[-4., -4., -4., -4.]);
+    /// ```
+    pub fn conv2d(&self, weight: &RawTensor, bias: Option<&RawTensor>, params: &Conv2dParams) -> RawTensor {
+        let bias = bias.map(|bias| &bias.actual_tensor);
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.conv2d(&weight.actual_tensor, bias, params))
+        }
+    }
+
+    /// 1D convolution of a `[batch, channels, length]` Tensor, see [`GpuTensor::conv1d`]
+    pub fn conv1d(&self, weight: &RawTensor, bias: Option<&RawTensor>, params: &Conv1dParams) -> RawTensor {
+        let bias = bias.map(|bias| &bias.actual_tensor);
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.conv1d(&weight.actual_tensor, bias, params))
+        }
+    }
+
+    /// Transposed 2D convolution of a `[batch, channels, height, width]` Tensor, see
+    /// [`GpuTensor::conv_transpose2d`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::{ConvTranspose2dParams, RawTensor};
+    /// let image = RawTensor::from_data_and_shape(vec![1., 2.], vec![1, 1, 1, 2]);
+    /// let kernel = RawTensor::from_data_and_shape(vec![1., 1.], vec![1, 1, 1, 2]);
+    /// let params = ConvTranspose2dParams {
+    ///     stride: (1, 2),
+    ///     output_padding: (0, 1),
+    ///     ..ConvTranspose2dParams::default()
+    /// };
+    /// let output = image.conv_transpose2d(&kernel, None, &params);
+    /// assert_eq!(output.shape(), &[1, 1, 1, 5]);
+    /// assert_eq!(output.to_vec(), &[1., 1., 2., 2., 0.]);
+    /// ```
+    pub fn conv_transpose2d(&self, weight: &RawTensor, bias: Option<&RawTensor>, params: &ConvTranspose2dParams) -> RawTensor {
+        let bias = bias.map(|bias| &bias.actual_tensor);
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.conv_transpose2d(&weight.actual_tensor, bias, params))
+        }
+    }
+
+    /// Transposed 1D convolution of a `[batch, channels, length]` Tensor, see
+    /// [`GpuTensor::conv_transpose1d`]
+    pub fn conv_transpose1d(&self, weight: &RawTensor, bias: Option<&RawTensor>, params: &ConvTranspose1dParams) -> RawTensor {
+        let bias = bias.map(|bias| &bias.actual_tensor);
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.conv_transpose1d(&weight.actual_tensor, bias, params))
+        }
+    }
+
+    /// Returns the maximum of each window and its index, see [`GpuTensor::max_pool2d`]
+    pub fn max_pool2d(&self, window: &Window2d) -> (RawTensor, RawTensor) {
+        let (output, indices) = block_on(self.actual_tensor.max_pool2d(window));
+        (RawTensor { actual_tensor: output }, RawTensor { actual_tensor: indices })
+    }
+
+    /// See [`GpuTensor::max_pool2d_backward`]
+    pub fn max_pool2d_backward(&self, indices: &RawTensor, window: &Window2d, input_size: (usize, usize)) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.max_pool2d_backward(&indices.actual_tensor, window, input_size))
+        }
+    }
+
+    /// Returns the average of each window, see [`GpuTensor::avg_pool2d`]
+    pub fn avg_pool2d(&self, window: &Window2d, count_include_pad: bool) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.avg_pool2d(window, count_include_pad))
+        }
+    }
+
+    /// See [`GpuTensor::avg_pool2d_backward`]
+    pub fn avg_pool2d_backward(&self, window: &Window2d, count_include_pad: bool, input_size: (usize, usize)) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.avg_pool2d_backward(window, count_include_pad, input_size))
+        }
+    }
+
+    /// Averages windows chosen to produce the given output size, see
+    /// [`GpuTensor::adaptive_avg_pool2d`]
+    pub fn adaptive_avg_pool2d(&self, output_size: (usize, usize)) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.adaptive_avg_pool2d(output_size))
+        }
+    }
+
+    /// See [`GpuTensor::adaptive_avg_pool2d_backward`]
+    pub fn adaptive_avg_pool2d_backward(&self, input_size: (usize, usize)) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.adaptive_avg_pool2d_backward(input_size))
+        }
+    }
+
+    /// Returns the mean and the biased variance of each group, see [`GpuTensor::norm_stats`]
+    pub fn norm_stats(&self, groups: &NormGroups) -> (RawTensor, RawTensor) {
+        let (mean, var) = block_on(self.actual_tensor.norm_stats(groups));
+        (RawTensor { actual_tensor: mean }, RawTensor { actual_tensor: var })
+    }
+
+    /// Normalizes each group using the given statistics, see [`GpuTensor::normalize`]
+    pub fn normalize(&self, mean: &RawTensor, var: &RawTensor, groups: &NormGroups, eps: f32) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.normalize(&mean.actual_tensor, &var.actual_tensor, groups, eps))
+        }
+    }
+
+    /// See [`GpuTensor::normalize_backward`]
+    pub fn normalize_backward(&self, grad: &RawTensor, var: &RawTensor, groups: &NormGroups, eps: f32, batch_stats: bool) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.normalize_backward(
+                &grad.actual_tensor,
+                &var.actual_tensor,
+                groups,
+                eps,
+                batch_stats,
+            ))
+        }
+    }
+
+    /// Scales and shifts each group, see [`GpuTensor::affine`]
+    pub fn affine(&self, weight: &RawTensor, bias: &RawTensor, groups: &NormGroups) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.affine(&weight.actual_tensor, &bias.actual_tensor, groups))
+        }
+    }
+
+    /// See [`GpuTensor::affine_backward`]
+    pub fn affine_backward(&self, grad: &RawTensor, groups: &NormGroups) -> (RawTensor, RawTensor) {
+        let (weight_grad, bias_grad) = block_on(self.actual_tensor.affine_backward(&grad.actual_tensor, groups));
+        (RawTensor { actual_tensor: weight_grad }, RawTensor { actual_tensor: bias_grad })
+    }
+
+    /// Applies the optional affine parameters of a normalization, if any
+    fn optional_affine(self, weight: Option<&RawTensor>, bias: Option<&RawTensor>, groups: &NormGroups) -> RawTensor {
+        if weight.is_none() && bias.is_none() {
+            return self;
+        }
+        self.affine_with_defaults(weight, bias, groups)
+    }
+
+    /// Same as [`RawTensor::affine`], a missing weight being ones and a missing bias zeros
+    pub(crate) fn affine_with_defaults(&self, weight: Option<&RawTensor>, bias: Option<&RawTensor>, groups: &NormGroups) -> RawTensor {
+        let ones;
+        let weight = match weight {
+            Some(weight) => weight,
+            None => {
+                ones = RawTensor::filled(vec![groups.groups], 1.);
+                &ones
+            }
+        };
+        let zeros;
+        let bias = match bias {
+            Some(bias) => bias,
+            None => {
+                zeros = RawTensor::zeros(vec![groups.groups]);
+                &zeros
+            }
+        };
+        self.affine(weight, bias, groups)
+    }
+
+    /// A Tensor of the given shape with all elements equal to `value`
+    pub(crate) fn filled(shape: Vec<usize>, value: f32) -> RawTensor {
+        let mut tensor = RawTensor::zeros(shape);
+        tensor.fill_with(value);
+        tensor
+    }
+
+    /// Layer normalization over the trailing `normalized_shape` dimensions, followed by the
+    /// optional element-wise affine transformation, whose parameters have shape
+    /// `normalized_shape`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let tensor = RawTensor::from_data_and_shape(vec![1., 3., 2., 6.], vec![2, 2]);
+    /// let normalized = tensor.layer_norm(&[2], None, None, 0.);
+    /// assert_eq!(normalized.to_vec(), &[-1., 1., -1., 1.]);
+    /// ```
+    pub fn layer_norm(&self, normalized_shape: &[usize], weight: Option<&RawTensor>, bias: Option<&RawTensor>, eps: f32) -> RawTensor {
+        let (stats_groups, affine_groups) = NormGroups::layer_norm(self.shape(), normalized_shape);
+        let (mean, var) = self.norm_stats(&stats_groups);
+        self.normalize(&mean, &var, &stats_groups, eps)
+            .optional_affine(weight, bias, &affine_groups)
+    }
+
+    /// Group normalization of a `[batch, channels, *]` Tensor, with the channels split into
+    /// `num_groups` groups, followed by the optional per channel affine transformation
+    pub fn group_norm(&self, num_groups: usize, weight: Option<&RawTensor>, bias: Option<&RawTensor>, eps: f32) -> RawTensor {
+        let (stats_groups, affine_groups) = NormGroups::group_norm(self.shape(), num_groups);
+        let (mean, var) = self.norm_stats(&stats_groups);
+        self.normalize(&mean, &var, &stats_groups, eps)
+            .optional_affine(weight, bias, &affine_groups)
+    }
+
+    /// Batch normalization of a `[batch, channels, *]` Tensor, followed by the optional per
+    /// channel affine transformation.
+    ///
+    /// In training mode the statistics of each channel are computed from the batch and, if
+    /// given, the running statistics are updated as
+    /// `running = (1 - momentum) * running + momentum * batch_statistic`, using the unbiased
+    /// variance. Otherwise the running statistics are used to normalize.
+    pub fn batch_norm(
+        &self,
+        running_stats: Option<(&mut RawTensor, &mut RawTensor)>,
+        weight: Option<&RawTensor>,
+        bias: Option<&RawTensor>,
+        training: bool,
+        momentum: f32,
+        eps: f32,
+    ) -> RawTensor {
+        let groups = NormGroups::batch_norm(self.shape());
+        let normalized = if training {
+            let (mean, var) = self.norm_stats(&groups);
+            if let Some((running_mean, running_var)) = running_stats {
+                update_running_stats(running_mean, running_var, &mean, &var, momentum, groups.len(self.numel()));
+            }
+            self.normalize(&mean, &var, &groups, eps)
+        } else {
+            let (running_mean, running_var) = running_stats.expect("Batch normalization needs the running statistics in evaluation mode");
+            self.normalize(running_mean, running_var, &groups, eps)
+        };
+        normalized.optional_affine(weight, bias, &groups)
+    }
+
+    /// Selects entries along `dim` given one dimensional indices, see [`GpuTensor::index_select`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let tensor = RawTensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+    /// let indices = RawTensor::from_data_1d(vec![2., 0.]);
+    /// assert_eq!(tensor.index_select(1, &indices).to_vec(), &[3., 1., 6., 4.]);
+    /// ```
+    pub fn index_select(&self, dim: usize, indices: &RawTensor) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.index_select(dim, &indices.actual_tensor))
+        }
+    }
+
+    /// Adds `source` to the entries along `dim` given by `indices`, see [`GpuTensor::index_add`]
+    pub fn index_add(&self, dim: usize, indices: &RawTensor, source: &RawTensor) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.index_add(dim, &indices.actual_tensor, &source.actual_tensor))
+        }
+    }
+
+    /// Gathers values along `dim`, see [`GpuTensor::gather`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let tensor = RawTensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![2, 2]);
+    /// let index = RawTensor::from_data_and_shape(vec![0., 0., 1., 0.], vec![2, 2]);
+    /// assert_eq!(tensor.gather(1, &index).to_vec(), &[1., 1., 4., 3.]);
+    /// ```
+    pub fn gather(&self, dim: usize, index: &RawTensor) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.gather(dim, &index.actual_tensor))
+        }
+    }
+
+    /// Writes `source` along `dim` at the positions given by `index`, see [`GpuTensor::scatter`]
+    pub fn scatter(&self, dim: usize, index: &RawTensor, source: &RawTensor) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.scatter(dim, &index.actual_tensor, &source.actual_tensor))
+        }
+    }
+
+    /// Adds `source` along `dim` at the positions given by `index`, see
+    /// [`GpuTensor::scatter_add`]
+    pub fn scatter_add(&self, dim: usize, index: &RawTensor, source: &RawTensor) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.scatter_add(dim, &index.actual_tensor, &source.actual_tensor))
+        }
+    }
+
+    /// Looks up the rows of `weight` given by the indices in this [`Tensor`], see
+    /// [`GpuTensor::embedding`]
+    pub fn embedding(&self, weight: &RawTensor) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.embedding(&weight.actual_tensor))
+        }
+    }
+
+    /// See [`GpuTensor::embedding_backward`]
+    pub fn embedding_backward(&self, grad: &RawTensor, num_embeddings: usize) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.embedding_backward(&grad.actual_tensor, num_embeddings))
+        }
+    }
+
+    /// Updates this [`Tensor`] in place using SGD, see [`GpuTensor::sgd_step`]
+    pub fn sgd_step(&mut self, grad: &RawTensor, momentum_buffer: Option<&mut RawTensor>, step: &SgdStep) {
+        let momentum_buffer = momentum_buffer.map(|buffer| &mut buffer.actual_tensor);
+        block_on(self.actual_tensor.sgd_step(&grad.actual_tensor, momentum_buffer, step));
+    }
+
+    /// Updates this [`Tensor`] in place using Adam or AdamW, see [`GpuTensor::adam_step`]
+    pub fn adam_step(&mut self, grad: &RawTensor, exp_avg: &mut RawTensor, exp_avg_sq: &mut RawTensor, step: &AdamStep) {
+        block_on(self.actual_tensor.adam_step(
+            &grad.actual_tensor,
+            &mut exp_avg.actual_tensor,
+            &mut exp_avg_sq.actual_tensor,
+            step,
+        ));
+    }
+
+    /// Updates this [`Tensor`] in place using RMSprop, see [`GpuTensor::rmsprop_step`]
+    pub fn rmsprop_step(
+        &mut self,
+        grad: &RawTensor,
+        square_avg: &mut RawTensor,
+        momentum_buffer: Option<&mut RawTensor>,
+        step: &RmsPropStep,
+    ) {
+        let momentum_buffer = momentum_buffer.map(|buffer| &mut buffer.actual_tensor);
+        block_on(self.actual_tensor.rmsprop_step(
+            &grad.actual_tensor,
+            &mut square_avg.actual_tensor,
+            momentum_buffer,
+            step,
+        ));
+    }
+
 
 
     // /// Same as [`TensorView::add`] but async
@@ -381,10 +997,13 @@ impl RawTensor {
         }
     }
 
-    /// Does a batch 2D matrix multiplication of `self` and the `other`. Inputs must be of rank 3.
+    /// Does a batch 2D matrix multiplication of `self` and the `other`. Inputs must be of rank 3
+    /// or 4.
     ///
-    /// The first two dimensions must be compatible with Matrix Multiplication, that is:
+    /// The last two dimensions must be compatible with Matrix Multiplication, that is:
     /// if `self` has dimensions `[1, 2, 3]`, other must have `[1, 3, M]`, where M is any number.
+    /// The leading (batch) dimensions are broadcast without copying, so `[2, 2, 3]` can be
+    /// multiplied by `[5, 2, 3, M]`, giving `[5, 2, 2, M]`.
     ///
     /// # Examples
     ///
@@ -475,6 +1094,25 @@ impl RawTensor {
 
     /*******  Conversions  *******/
 
+    /// Copies a [`CpuTensor`] to GPU memory, keeping its strides and offset.
+    /// Panics if the CpuTensor is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::{CpuTensor, RawTensor};
+    /// let cpu_tensor = CpuTensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![2, 2]);
+    /// let tensor = RawTensor::from_cpu(&cpu_tensor);
+    /// assert_eq!(tensor.shape(), &[2, 2]);
+    /// assert_eq!(tensor.to_cpu(), cpu_tensor);
+    /// ```
+    pub fn from_cpu(tensor: &CpuTensor) -> RawTensor {
+        assert!(tensor.numel() > 0, "Data cant be empty!");
+        RawTensor {
+            actual_tensor: tensor.to_gpu(),
+        }
+    }
+
     /// Same as [Tensor::to_cpu], but async.
     pub async fn to_cpu_async(&self) -> CpuTensor {
         self.actual_tensor.to_cpu_async().await
@@ -534,6 +1172,24 @@ impl RawTensor {
         block_on(self.transpose_async())
     }
 
+    /// Returns a contiguous copy of the [`Tensor`] with its dimensions reordered, dimension `i`
+    /// of the result being dimension `dims[i]` of `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::RawTensor;
+    /// let tensor = RawTensor::from_data_and_shape(vec![1., 2., 3., 4., 5., 6.], vec![1, 2, 3]);
+    /// let permuted = tensor.permute(&[2, 0, 1]);
+    /// assert_eq!(permuted.shape(), &[3, 1, 2]);
+    /// assert_eq!(permuted.to_vec(), &[1., 4., 2., 5., 3., 6.]);
+    /// ```
+    pub fn permute(&self, dims: &[usize]) -> RawTensor {
+        RawTensor {
+            actual_tensor: block_on(self.actual_tensor.permute(dims)),
+        }
+    }
+
     /// Reshapes a [`Tensor`] to the given shape. The only restriction is having the same number
     /// of elements as the original shape.
     ///
@@ -570,3 +1226,22 @@ impl RawTensor {
 
 
 }
+
+/// Updates the running statistics of batch normalization with the statistics of a batch whose
+/// channels have `len` elements each
+pub(crate) fn update_running_stats(
+    running_mean: &mut RawTensor,
+    running_var: &mut RawTensor,
+    mean: &RawTensor,
+    var: &RawTensor,
+    momentum: f32,
+    len: usize,
+) {
+    let unbiased_var = if len > 1 {
+        var.mul_scalar(len as f32 / (len - 1) as f32)
+    } else {
+        var.clone()
+    };
+    *running_mean = running_mean.mul_scalar(1. - momentum).add(&mean.mul_scalar(momentum));
+    *running_var = running_var.mul_scalar(1. - momentum).add(&unbiased_var.mul_scalar(momentum));
+}
@@ -0,0 +1,150 @@
+//! Conversions between [`CpuTensor`] and [`ndarray`], enabled by the `ndarray` feature.
+//!
+//! Both describe an array with a shape, strides and an offset into a buffer, so converting
+//! keeps the memory layout: an [`ArrayD`] becomes a CpuTensor owning the same `Vec`, and
+//! [`CpuTensor::to_ndarray`] is a strided view over the data of the CpuTensor. The only copy
+//! happens for arrays with negative strides, for example after
+//! [`ArrayBase::invert_axis`](ndarray::ArrayBase::invert_axis), which a CpuTensor can't
+//! represent.
+//!
+//! # Examples
+//!
+//! ```
+//! use ndarray::{ArrayD, IxDyn};
+//! use tensor_compute::CpuTensor;
+//! let array = ArrayD::from_shape_vec(IxDyn(&[2, 3]), vec![1., 2., 3., 4., 5., 6.]).unwrap();
+//! let tensor = CpuTensor::from(array.t().to_owned());
+//! assert_eq!(tensor.as_contiguous_vec(), vec![1., 4., 2., 5., 3., 6.]);
+//! assert_eq!(tensor.to_ndarray(), array.t());
+//! ```
+use crate::{CpuTensor, RawTensor, ShapeStrideTrait};
+use ndarray::{ArrayD, ArrayViewD, IxDyn, ShapeBuilder};
+use std::collections::VecDeque;
+
+impl From<ArrayD<f32>> for CpuTensor {
+    /// Takes the buffer of the array, keeping its strides and offset. Panics for arrays of
+    /// rank 0, which a CpuTensor can't have.
+    fn from(array: ArrayD<f32>) -> Self {
+        assert!(array.ndim() > 0, "Arrays of rank 0 can't be converted to a CpuTensor");
+        let array = if array.strides().iter().any(|&stride| stride < 0) {
+            array.as_standard_layout().into_owned()
+        } else {
+            array
+        };
+        let shape: VecDeque<usize> = array.shape().iter().copied().collect();
+        let strides: VecDeque<usize> = array.strides().iter().map(|&stride| stride as usize).collect();
+        // The offset is only unknown for arrays without elements
+        let (data, offset) = array.into_raw_vec_and_offset();
+        CpuTensor::new_with_strides_and_offset(data, shape, strides, offset.unwrap_or(0))
+    }
+}
+
+impl CpuTensor {
+    /// Returns a view of this Tensor as an [`ndarray`] array, without copying or reordering
+    /// its data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tensor_compute::CpuTensor;
+    /// let tensor = CpuTensor::from_data_and_shape(vec![1., 2., 3., 4.], vec![2, 2]);
+    /// let view = tensor.to_ndarray();
+    /// assert_eq!(view.shape(), &[2, 2]);
+    /// assert_eq!(view[[1, 0]], 3.);
+    /// assert_eq!(view.sum(), 10.);
+    /// ```
+    pub fn to_ndarray(&self) -> ArrayViewD<'_, f32> {
+        let shape: Vec<usize> = self.shape().iter().copied().collect();
+        let strides: Vec<usize> = self.strides().iter().copied().collect();
+        let data = self.raw_data_slice().get(self.offset()..).unwrap_or(&[]);
+        ArrayViewD::from_shape(IxDyn(&shape).strides(IxDyn(&strides)), data)
+            .expect("The strides and offset of the CpuTensor reach outside of its data")
+    }
+}
+
+impl RawTensor {
+    /// Uploads an [`ndarray`] array to a new contiguous Tensor. Unlike [`CpuTensor::from`],
+    /// the data is copied in standard layout, since the GPU kernels don't support offsets.
+    /// Panics if the array is empty or has rank 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ndarray::{ArrayD, IxDyn};
+    /// use tensor_compute::RawTensor;
+    /// let array = ArrayD::from_shape_vec(IxDyn(&[2, 2]), vec![1., 2., 3., 4.]).unwrap();
+    /// let tensor = RawTensor::from_ndarray(array.clone());
+    /// assert_eq!(tensor.shape(), &[2, 2]);
+    /// assert_eq!(tensor.to_ndarray(), array);
+    /// ```
+    pub fn from_ndarray(array: ArrayD<f32>) -> RawTensor {
+        assert!(array.ndim() > 0, "Arrays of rank 0 can't be converted to a RawTensor");
+        let shape = array.shape().to_vec();
+        RawTensor::from_data_and_shape(array.as_standard_layout().iter().copied().collect(), shape)
+    }
+
+    /// Downloads the Tensor into an owned [`ndarray`] array in standard layout.
+    pub fn to_ndarray(&self) -> ArrayD<f32> {
+        self.to_cpu().to_ndarray().to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CpuTensor, RawTensor, ShapeStrideTrait};
+    use ndarray::{s, ArrayD, Axis, IxDyn};
+
+    fn arange(shape: &[usize]) -> ArrayD<f32> {
+        let numel = shape.iter().product();
+        ArrayD::from_shape_vec(IxDyn(shape), (0..numel).map(|x| x as f32).collect()).unwrap()
+    }
+
+    #[test]
+    fn converting_arrays_keeps_their_layout() {
+        let sliced = arange(&[3, 4]).slice_move(s![1.., 1..;2]).into_dyn();
+        let expected = sliced.clone();
+        let tensor = CpuTensor::from(sliced);
+        assert_eq!(tensor.raw_data_slice().len(), 12);
+        assert_eq!(tensor.shape(), &[2, 2]);
+        assert_eq!(tensor.strides(), &[4, 2]);
+        assert_eq!(tensor.offset(), 5);
+        assert_eq!(tensor.as_contiguous_vec(), vec![5., 7., 9., 11.]);
+        assert_eq!(tensor.to_ndarray(), expected);
+
+        let mut transposed = arange(&[2, 3]);
+        transposed.swap_axes(0, 1);
+        let tensor = CpuTensor::from(transposed);
+        assert_eq!(tensor.strides(), &[1, 3]);
+        assert_eq!(tensor.as_contiguous_vec(), vec![0., 3., 1., 4., 2., 5.]);
+    }
+
+    #[test]
+    fn arrays_are_uploaded_in_standard_layout() {
+        // Owns the whole buffer, with an offset of 5 and strides [2, 4]
+        let mut array = arange(&[3, 4]).slice_move(s![1.., 1..;2]).into_dyn();
+        array.swap_axes(0, 1);
+        let tensor = RawTensor::from_ndarray(array);
+        assert_eq!(tensor.shape(), &[2, 2]);
+        assert_eq!(tensor.to_vec(), vec![5., 9., 7., 11.]);
+    }
+
+    #[test]
+    fn negative_strides_are_copied() {
+        let mut inverted = arange(&[2, 3]);
+        inverted.invert_axis(Axis(1));
+        let tensor = CpuTensor::from(inverted.clone());
+        assert_eq!(tensor.strides(), &[3, 1]);
+        assert_eq!(tensor.as_contiguous_vec(), vec![2., 1., 0., 5., 4., 3.]);
+        assert_eq!(tensor.to_ndarray(), inverted);
+    }
+
+    #[test]
+    fn views_share_the_data_of_broadcast_tensors() {
+        // The rows repeat the elements 1 to 3 through a stride of 0
+        let data = vec![9., 1., 2., 3., 9., 9.];
+        let tensor = CpuTensor::new_with_strides_and_offset(data, vec![2, 3].into(), vec![0, 1].into(), 1);
+        let view = tensor.to_ndarray();
+        assert_eq!(view.as_ptr(), tensor.raw_data_slice()[1..].as_ptr());
+        assert_eq!(view, arange(&[1, 3]).broadcast(IxDyn(&[2, 3])).unwrap().mapv(|x| x + 1.));
+    }
+}